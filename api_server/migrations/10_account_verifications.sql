@@ -0,0 +1,9 @@
+-- 0 - Email verification, 1 - Password reset
+ALTER TABLE user_verifications
+    ADD COLUMN kind     smallint                 DEFAULT 0                 NOT NULL,
+    ADD COLUMN creation timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+
+-- Accounts from before email verification are trusted as they are
+UPDATE users
+SET verification_level = 1
+WHERE verification_level = 0;
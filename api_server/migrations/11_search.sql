@@ -0,0 +1,21 @@
+CREATE EXTENSION IF NOT EXISTS unaccent;
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Folds case and accents ("São João" -> "sao joao")
+-- unaccent() is not immutable (its dictionary can change), so it cannot be indexed directly
+CREATE FUNCTION search_fold(text) RETURNS text
+    LANGUAGE sql
+    IMMUTABLE PARALLEL SAFE STRICT
+AS
+$$
+SELECT lower(public.unaccent('public.unaccent'::regdictionary, $1))
+$$;
+
+CREATE INDEX stops_name_search_idx ON stops USING gin (search_fold(name) gin_trgm_ops);
+CREATE INDEX stops_short_name_search_idx ON stops USING gin (search_fold(short_name) gin_trgm_ops);
+CREATE INDEX stops_locality_search_idx ON stops USING gin (search_fold(locality) gin_trgm_ops);
+CREATE INDEX stops_street_search_idx ON stops USING gin (search_fold(street) gin_trgm_ops);
+CREATE INDEX routes_code_search_idx ON routes USING gin (search_fold(code) gin_trgm_ops);
+CREATE INDEX routes_name_search_idx ON routes USING gin (search_fold(name) gin_trgm_ops);
+CREATE INDEX operators_name_search_idx ON operators USING gin (search_fold(name) gin_trgm_ops);
+CREATE INDEX news_items_title_search_idx ON news_items USING gin (search_fold(title) gin_trgm_ops);
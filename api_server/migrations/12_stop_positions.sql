@@ -0,0 +1,5 @@
+-- Great-circle distances and a spatial index over stop positions
+CREATE EXTENSION IF NOT EXISTS cube;
+CREATE EXTENSION IF NOT EXISTS earthdistance;
+
+CREATE INDEX stops_position_idx ON stops USING gist (ll_to_earth(lat, lon));
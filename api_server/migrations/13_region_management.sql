@@ -0,0 +1,54 @@
+-- Regions and parishes can be deleted through the API.
+-- Their memberships go along with them; stops merely lose their parish.
+ALTER TABLE region_parishes
+    DROP CONSTRAINT region_parishes_region_id_fkey,
+    ADD CONSTRAINT region_parishes_region_id_fkey
+        FOREIGN KEY (region_id) REFERENCES regions (id) ON DELETE CASCADE,
+    DROP CONSTRAINT region_parishes_parish_id_fkey,
+    ADD CONSTRAINT region_parishes_parish_id_fkey
+        FOREIGN KEY (parish_id) REFERENCES parishes (id) ON DELETE CASCADE;
+
+ALTER TABLE region_municipalities
+    DROP CONSTRAINT region_municipalities_region_id_fkey,
+    ADD CONSTRAINT region_municipalities_region_id_fkey
+        FOREIGN KEY (region_id) REFERENCES regions (id) ON DELETE CASCADE;
+
+ALTER TABLE region_operators
+    DROP CONSTRAINT region_operators_region_id_fkey,
+    ADD CONSTRAINT region_operators_region_id_fkey
+        FOREIGN KEY (region_id) REFERENCES regions (id) ON DELETE CASCADE;
+
+ALTER TABLE region_routes
+    DROP CONSTRAINT region_routes_region_id_fkey,
+    ADD CONSTRAINT region_routes_region_id_fkey
+        FOREIGN KEY (region_id) REFERENCES regions (id) ON DELETE CASCADE;
+
+ALTER TABLE region_stops
+    DROP CONSTRAINT region_stops_region_id_fkey,
+    ADD CONSTRAINT region_stops_region_id_fkey
+        FOREIGN KEY (region_id) REFERENCES regions (id) ON DELETE CASCADE;
+
+ALTER TABLE issue_regions
+    DROP CONSTRAINT issue_regions_region_id_fkey,
+    ADD CONSTRAINT issue_regions_region_id_fkey
+        FOREIGN KEY (region_id) REFERENCES regions (id) ON DELETE CASCADE;
+
+ALTER TABLE abnormality_regions
+    DROP CONSTRAINT abnormality_regions_region_id_fkey,
+    ADD CONSTRAINT abnormality_regions_region_id_fkey
+        FOREIGN KEY (region_id) REFERENCES regions (id) ON DELETE CASCADE;
+
+ALTER TABLE news_items_regions
+    DROP CONSTRAINT news_items_regions_region_id_fkey,
+    ADD CONSTRAINT news_items_regions_region_id_fkey
+        FOREIGN KEY (region_id) REFERENCES regions (id) ON DELETE CASCADE;
+
+ALTER TABLE external_news_items_regions
+    DROP CONSTRAINT external_news_items_regions_region_id_fkey,
+    ADD CONSTRAINT external_news_items_regions_region_id_fkey
+        FOREIGN KEY (region_id) REFERENCES regions (id) ON DELETE CASCADE;
+
+ALTER TABLE stops
+    DROP CONSTRAINT stops_parish_fkey,
+    ADD CONSTRAINT stops_parish_fkey
+        FOREIGN KEY (parish) REFERENCES parishes (id) ON DELETE SET NULL;
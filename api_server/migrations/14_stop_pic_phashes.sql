@@ -0,0 +1,4 @@
+-- Perceptual hashes (64 bit dHash) of the stop pictures, to spot near-duplicates.
+-- Pictures uploaded before this was introduced are left without one.
+ALTER TABLE stop_pics
+    ADD COLUMN phash bigint;
@@ -0,0 +1,4 @@
+-- Areas (faces, licence plates, ...) blurred out of the published versions of a stop picture.
+-- While there are any, the untouched original is kept under a separate object.
+ALTER TABLE stop_pics
+    ADD COLUMN redactions jsonb NOT NULL DEFAULT '[]';
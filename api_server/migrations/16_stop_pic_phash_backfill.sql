@@ -0,0 +1,3 @@
+-- Hashes the stop pictures that were uploaded before perceptual hashes
+INSERT INTO jobs (kind, name)
+VALUES (2, 'Stop picture hash backfill');
@@ -0,0 +1,3 @@
+INSERT INTO jobs (kind, name, interval)
+VALUES (3, 'Region and parish reconciliation', 1440),
+       (4, 'OSM stop sync', 1440);
@@ -0,0 +1,29 @@
+-- National holidays are known by the API server. These are the additional
+-- (regional, municipal or one-off) holidays.
+CREATE TABLE holidays
+(
+    id           serial PRIMARY KEY,
+    name         text  NOT NULL,
+    -- commons::models::calendar::HolidayRule
+    rule         jsonb NOT NULL,
+    -- Neither being set means it applies nationwide
+    region       integer REFERENCES regions (id) ON DELETE CASCADE,
+    municipality integer REFERENCES municipalities (id) ON DELETE CASCADE,
+    CHECK (region IS NULL OR municipality IS NULL)
+);
+
+CREATE INDEX holidays_region_idx ON holidays (region);
+CREATE INDEX holidays_municipality_idx ON holidays (municipality);
+
+CREATE TABLE school_periods
+(
+    id         serial PRIMARY KEY,
+    name       text,
+    start_date date NOT NULL,
+    end_date   date NOT NULL,
+    -- Null means it applies nationwide
+    region     integer REFERENCES regions (id) ON DELETE CASCADE,
+    CHECK (start_date <= end_date)
+);
+
+CREATE INDEX school_periods_dates_idx ON school_periods (start_date, end_date);
@@ -0,0 +1,6 @@
+-- Permission assignments can be limited to a set of operators or regions.
+-- Assignments without either apply globally.
+ALTER TABLE user_permissions
+    ADD COLUMN operator_ids integer[],
+    ADD COLUMN region_ids   integer[],
+    ADD CONSTRAINT single_scope CHECK (operator_ids IS NULL OR region_ids IS NULL);
@@ -0,0 +1,30 @@
+-- The original ticket tables were never used and had unusable column types.
+DROP TABLE ticket_comments;
+DROP TABLE tickets;
+
+CREATE TABLE tickets
+(
+    id          serial PRIMARY KEY,
+    title       text                                        NOT NULL,
+    message     text                                        NOT NULL,
+    reason      smallint                                    NOT NULL,
+    creation    timestamp with time zone DEFAULT now()      NOT NULL,
+    operator_id integer REFERENCES operators (id)           NOT NULL,
+    user_id     integer REFERENCES users (id)               NOT NULL,
+    public      boolean                  DEFAULT false      NOT NULL,
+    status      smallint                 DEFAULT 0          NOT NULL
+);
+
+CREATE INDEX tickets_operator_idx ON tickets (operator_id);
+CREATE INDEX tickets_user_idx ON tickets (user_id);
+
+CREATE TABLE ticket_comments
+(
+    id        serial PRIMARY KEY,
+    ticket_id integer REFERENCES tickets (id) ON DELETE CASCADE NOT NULL,
+    message   text                                              NOT NULL,
+    datetime  timestamp with time zone DEFAULT now()            NOT NULL,
+    user_id   integer REFERENCES users (id)                     NOT NULL
+);
+
+CREATE INDEX ticket_comments_ticket_idx ON ticket_comments (ticket_id);
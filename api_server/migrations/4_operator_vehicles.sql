@@ -0,0 +1,18 @@
+-- Vehicles are the models that operators have in their fleets
+ALTER TABLE vehicle
+    ADD COLUMN service_year integer,
+    ALTER COLUMN usb_outlets TYPE boolean USING usb_outlets > 0;
+ALTER TABLE vehicle
+    RENAME COLUMN usb_outlets TO has_usb_outlets;
+
+CREATE INDEX vehicle_operator_idx ON vehicle (operator_id);
+
+-- The vehicle models that usually serve the routes of a given type
+CREATE TABLE route_type_vehicles
+(
+    route_type_id integer NOT NULL REFERENCES route_types (id) ON DELETE CASCADE,
+    vehicle_id    integer NOT NULL REFERENCES vehicle (id) ON DELETE CASCADE,
+    PRIMARY KEY (route_type_id, vehicle_id)
+);
+
+CREATE INDEX route_type_vehicles_vehicle_idx ON route_type_vehicles (vehicle_id);
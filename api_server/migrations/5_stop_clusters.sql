@@ -0,0 +1 @@
+CREATE INDEX stops_cluster_idx ON stops (cluster);
@@ -0,0 +1,42 @@
+-- Products that riders can buy from an operator.
+-- Prices are in cents.
+CREATE TABLE fare_products
+(
+    id             serial PRIMARY KEY,
+    operator_id    integer REFERENCES operators (id) ON DELETE CASCADE NOT NULL,
+    name           text                                                NOT NULL,
+    -- 0: single, 1: pass, 2: zone-based
+    kind           smallint                                            NOT NULL,
+    price          integer                                             NOT NULL,
+    -- Extra cost for every zone that is crossed beyond the first (zone-based only)
+    zone_price     integer,
+    -- Route types that the product is valid in. Empty means every type.
+    route_type_ids integer[] DEFAULT ARRAY []::integer[]               NOT NULL
+);
+
+CREATE INDEX fare_products_operator_idx ON fare_products (operator_id);
+
+CREATE TABLE fare_zones
+(
+    id          serial PRIMARY KEY,
+    operator_id integer REFERENCES operators (id) ON DELETE CASCADE NOT NULL,
+    name        text                                                NOT NULL
+);
+
+CREATE TABLE fare_zone_stops
+(
+    zone_id integer REFERENCES fare_zones (id) ON DELETE CASCADE NOT NULL,
+    stop_id integer REFERENCES stops (id) ON DELETE CASCADE      NOT NULL,
+    PRIMARY KEY (zone_id, stop_id)
+);
+
+-- The cost of the second leg when transferring between two route types
+CREATE TABLE fare_transfer_rules
+(
+    id                 serial PRIMARY KEY,
+    operator_id        integer REFERENCES operators (id) ON DELETE CASCADE   NOT NULL,
+    from_route_type_id integer REFERENCES route_types (id) ON DELETE CASCADE NOT NULL,
+    to_route_type_id   integer REFERENCES route_types (id) ON DELETE CASCADE NOT NULL,
+    cost               integer                                               NOT NULL,
+    UNIQUE (from_route_type_id, to_route_type_id)
+);
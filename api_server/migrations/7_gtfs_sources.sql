@@ -0,0 +1,43 @@
+-- Where the GTFS feed of each operator is fetched from
+CREATE TABLE gtfs_sources
+(
+    id               serial PRIMARY KEY,
+    operator_id      integer REFERENCES operators (id) ON DELETE CASCADE NOT NULL UNIQUE,
+    -- 0: zip download, 1: Transporlis, 2: manual upload
+    kind             smallint                                            NOT NULL,
+    -- The zip location (zip downloads only)
+    url              text,
+    -- The operator identifier within Transporlis (Transporlis only)
+    transporlis_id   integer,
+    -- Extra HTTP headers sent along with the download (eg. API keys)
+    headers          jsonb    DEFAULT '{}'::jsonb                        NOT NULL,
+    -- Minutes between automatic refreshes. Null for on-demand refreshes only
+    refresh_interval integer,
+    -- SHA1 of the last fetched archive
+    checksum         text,
+    last_fetch       timestamp with time zone
+);
+
+-- The sources that used to be hard-coded
+INSERT INTO gtfs_sources (operator_id, kind, url)
+SELECT id, 0, 'https://api.carrismetropolitana.pt/gtfs*'
+FROM operators
+WHERE tag = 'cmet';
+
+INSERT INTO gtfs_sources (operator_id, kind, url)
+SELECT id, 0, 'https://gateway.carris.pt/gateway/gtfs/api/v2.11/GTFS'
+FROM operators
+WHERE tag = 'carris';
+
+INSERT INTO gtfs_sources (operator_id, kind, url)
+SELECT id,
+       0,
+       'https://dadosabertos.cascais.pt/dataset/ddef8977-0ad0-4d23-99d3-ae269a21b589/resource/819dac57-8843-43a3-a630-9cc7987325c0/download/gtfs-mobicascais.zip'
+FROM operators
+WHERE tag = 'mobic';
+
+INSERT INTO gtfs_sources (operator_id, kind, transporlis_id)
+SELECT operators.id, 1, transporlis.id
+FROM operators
+         JOIN (VALUES ('tcb', 41), ('ttsl', 4), ('ml', 2), ('cp', 3), ('fert', 13))
+    AS transporlis (tag, id) ON transporlis.tag = operators.tag;
@@ -0,0 +1,36 @@
+-- Maintenance tasks that the API servers run in the background
+CREATE TABLE jobs
+(
+    id           serial PRIMARY KEY,
+    -- 0: GTFS refresh, 1: database integrity check
+    kind         smallint                 NOT NULL,
+    name         text                     NOT NULL,
+    -- Minutes between runs. Null for jobs that only run on demand
+    interval     integer,
+    enabled      boolean DEFAULT true     NOT NULL,
+    next_run     timestamp with time zone,
+    -- The server instance that is running the job and until when it holds it
+    locked_by    uuid,
+    locked_until timestamp with time zone
+);
+
+CREATE TABLE job_runs
+(
+    id       serial PRIMARY KEY,
+    job_id   integer REFERENCES jobs (id) ON DELETE CASCADE NOT NULL,
+    instance uuid                                           NOT NULL,
+    -- Whether someone requested the run instead of it being scheduled
+    manual   boolean                                        NOT NULL,
+    start    timestamp with time zone DEFAULT now()         NOT NULL,
+    "end"    timestamp with time zone,
+    -- 0: running, 1: succeeded, 2: failed
+    status   smallint                 DEFAULT 0             NOT NULL,
+    -- A summary of what the run did or why it failed
+    output   jsonb
+);
+
+CREATE INDEX job_runs_job_idx ON job_runs (job_id);
+
+INSERT INTO jobs (kind, name, interval)
+VALUES (0, 'GTFS refresh', 60),
+       (1, 'Database integrity check', 1440);
@@ -0,0 +1,4 @@
+-- The reason and expiration of the current suspension (if any)
+ALTER TABLE users
+    ADD COLUMN suspension_reason text,
+    ADD COLUMN suspended_until   timestamp with time zone;
@@ -1,4 +1,4 @@
-use axum::extract::FromRef;
+use axum::extract::{FromRef, RawPathParams};
 use axum::http::header::USER_AGENT;
 use axum::{
     async_trait, extract::FromRequestParts, http::request::Parts,
@@ -158,9 +158,64 @@ where
     ) -> Result<Self, Self::Rejection> {
         let claims = models::Claims::from_request_parts(parts, state).await?;
         if P::is_valid(&claims.permissions) {
-            Ok(Self(claims, std::marker::PhantomData))
-        } else {
-            Err(Error::Forbidden)
+            return Ok(Self(claims, std::marker::PhantomData));
+        }
+
+        if P::SCOPABLE && claims.permissions.has_scoped() {
+            let state = parts
+                .extract_with_state::<AppState, _>(state)
+                .await
+                .map_err(|_| Error::IllegalState)?;
+            let targets = target_scopes(parts, &state).await?;
+
+            // Every targeted entity has to be within the permission scope
+            let in_scope = !targets.is_empty()
+                && targets.iter().all(|target| {
+                    claims
+                        .permissions
+                        .scoped_to(&target.operators, &target.regions)
+                        .any(P::is_valid)
+                });
+            if in_scope {
+                return Ok(Self(claims, std::marker::PhantomData));
+            }
         }
+
+        Err(Error::Forbidden)
     }
 }
+
+/// Scopes of the entities that are referenced in the request path
+async fn target_scopes(
+    parts: &mut Parts,
+    state: &AppState,
+) -> Result<Vec<models::TargetScope>, Error> {
+    let params = parts
+        .extract::<RawPathParams>()
+        .await
+        .map_err(|_| Error::Forbidden)?;
+
+    let mut targets = vec![];
+    for (key, value) in &params {
+        let Ok(id) = value.parse::<i32>() else {
+            continue;
+        };
+
+        let target = match key {
+            "operator_id" => sql::fetch_operator_scope(&state.pool, id).await?,
+            "route_id" => sql::fetch_route_scope(&state.pool, id).await?,
+            "subroute_id" => sql::fetch_subroute_scope(&state.pool, id).await?,
+            "stop_id" => sql::fetch_stop_scope(&state.pool, id).await?,
+            "ticket_id" => sql::fetch_ticket_scope(&state.pool, id).await?,
+            "region_id" => Some(models::TargetScope {
+                operators: vec![],
+                regions: vec![id],
+            }),
+            _ => continue,
+        };
+        // Unknown entities cannot be within any scope
+        targets.push(target.ok_or(Error::Forbidden)?);
+    }
+
+    Ok(targets)
+}
@@ -1,6 +1,6 @@
 INSERT INTO users
-(id, username, password, is_admin, is_trusted, works_for, email, can_edit_departures)
+(id, username, password, is_superuser, works_for, email, verification_level)
 VALUES (1, 'admin', '$pbkdf2-sha256$i=600000,l=32$+vjDQdvAJ9u5mTENXMfUcw$opD9UivlJvbKO0lV8RHrD+LChE9/+6lIuE2P04RlQAM',
-        true, true, NULL, 'admin@users.com', true),
+        true, NULL, 'admin@users.com', 1),
        (2, 'user', '$pbkdf2-sha256$i=600000,l=32$+vjDQdvAJ9u5mTENXMfUcw$opD9UivlJvbKO0lV8RHrD+LChE9/+6lIuE2P04RlQAM',
-        false, false, NULL, 'user1@users.com', false);
\ No newline at end of file
+        false, NULL, 'user1@users.com', 0);
@@ -56,6 +56,7 @@ pub(crate) async fn post_register(
     State(state): State<AppState>,
     args: Query<RegistrationArgs>,
     client_ip: SecureClientIp,
+    UserAgent(user_agent): UserAgent,
     Json(registration): Json<requests::Register>,
 ) -> Result<(), Error> {
     if args.dry {
@@ -71,7 +72,25 @@ pub(crate) async fn post_register(
             ));
         }
 
-        logic::register(&state.pool, registration, client_ip.0).await
+        let username = registration.username.clone();
+        logic::register(&state.pool, registration, client_ip.0).await?;
+
+        let user = sql::fetch_user_by_username(&state.pool, &username)
+            .await?
+            .ok_or(Error::IllegalState)?;
+        // The verification can be requested again, no need to fail here
+        if let Err(err) = logic::request_email_verification(
+            &state.pool,
+            &state.mailer,
+            &user,
+            client_ip.0,
+            &user_agent,
+        )
+        .await
+        {
+            tracing::error!(username, "Unable to send verification: {err}");
+        }
+        Ok(())
     } else {
         // Are we going to ever have a registration without a captcha?
         // Maybe if nobody has registered in the past hour
@@ -119,6 +138,67 @@ pub(crate) async fn post_login(
     ))
 }
 
+pub(crate) async fn post_request_email_verification(
+    State(state): State<AppState>,
+    claims: models::Claims,
+    client_ip: SecureClientIp,
+    UserAgent(user_agent): UserAgent,
+) -> Result<(), Error> {
+    let user = sql::fetch_user_by_id(&state.pool, claims.uid)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    logic::request_email_verification(
+        &state.pool,
+        &state.mailer,
+        &user,
+        client_ip.0,
+        &user_agent,
+    )
+    .await
+}
+
+pub(crate) async fn post_verify_email(
+    State(state): State<AppState>,
+    client_ip: SecureClientIp,
+    Json(request): Json<requests::VerifyEmail>,
+) -> Result<(), Error> {
+    logic::verify_email(&state.pool, request, client_ip.0).await
+}
+
+pub(crate) async fn post_request_password_reset(
+    State(state): State<AppState>,
+    client_ip: SecureClientIp,
+    UserAgent(user_agent): UserAgent,
+    Json(request): Json<requests::RequestPasswordReset>,
+) -> Result<(), Error> {
+    let is_valid = state
+        .captchas
+        .attempt_captcha(request.captcha.uuid, &request.captcha.answer)?;
+    if !is_valid {
+        return Err(Error::ValidationFailure(
+            "Captcha validation failed".to_string(),
+        ));
+    }
+
+    logic::request_password_reset(
+        &state.pool,
+        &state.mailer,
+        request,
+        client_ip.0,
+        &user_agent,
+    )
+    .await
+}
+
+pub(crate) async fn post_reset_password(
+    State(state): State<AppState>,
+    client_ip: SecureClientIp,
+    Json(request): Json<requests::ResetPassword>,
+) -> Result<(), Error> {
+    logic::reset_password(&state.pool, request, client_ip.0).await
+}
+
 pub(crate) async fn post_logout(
     State(state): State<AppState>,
     client_ip: SecureClientIp,
@@ -335,6 +415,30 @@ pub(crate) async fn post_user_change_password(
     logic::change_password(&state.pool, request, &claims, client_ip.0).await
 }
 
+pub(crate) async fn post_suspend_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<i32>,
+    super::ScopedClaim(claims, _): super::ScopedClaim<
+        super::perms::SuspendUsers,
+    >,
+    client_ip: SecureClientIp,
+    Json(request): Json<requests::SuspendUser>,
+) -> Result<(), Error> {
+    logic::suspend_user(&state.pool, request, user_id, &claims, client_ip.0)
+        .await
+}
+
+pub(crate) async fn delete_user_suspension(
+    State(state): State<AppState>,
+    Path(user_id): Path<i32>,
+    super::ScopedClaim(claims, _): super::ScopedClaim<
+        super::perms::SuspendUsers,
+    >,
+    client_ip: SecureClientIp,
+) -> Result<(), Error> {
+    logic::unsuspend_user(&state.pool, user_id, &claims, client_ip.0).await
+}
+
 pub(crate) async fn get_user_audit_log(
     State(state): State<AppState>,
     super::ScopedClaim(_, _): super::ScopedClaim<super::perms::ReadAuditLog>,
@@ -20,11 +20,12 @@ use chrono::Utc;
 use itertools::Itertools;
 use pbkdf2::{
     password_hash::{
-        rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier,
-        SaltString,
+        rand_core::{OsRng, RngCore},
+        PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
     },
     Pbkdf2,
 };
+use sha1::{Digest, Sha1};
 use sqlx::PgPool;
 use std::net::IpAddr;
 use uuid::Uuid;
@@ -34,8 +35,16 @@ use commons::models::auth;
 use super::{jwt, models, models::requests, sql};
 use crate::auth::models::responses;
 use crate::errors::Error;
+use crate::mail::Mailer;
 use crate::settings::SETTINGS;
 
+// Users at this level have confirmed that they own their email address
+pub(crate) const VERIFIED_EMAIL_LEVEL: i32 = 1;
+const EMAIL_VERIFICATION_HOURS: i64 = 48;
+const PASSWORD_RESET_MINUTES: i64 = 60;
+// A new verification mail can't be requested while a pending one is newer
+const VERIFICATION_COOLDOWN_MINUTES: i64 = 5;
+
 pub(crate) async fn login(
     request: requests::Login,
     db_pool: &PgPool,
@@ -59,6 +68,10 @@ pub(crate) async fn login(
         .map_err(|_| Error::Forbidden)?;
 
     let issue_time = Utc::now();
+    if user.is_suspended_at(issue_time) {
+        return Err(Error::Forbidden);
+    }
+
     let expiration_time = issue_time
         + chrono::Duration::try_days(SETTINGS.get().unwrap().jwt.refresh_days)
             .unwrap();
@@ -122,6 +135,10 @@ pub(crate) async fn renew_token(
             );
         })?;
 
+    if user.is_suspended_at(Utc::now()) {
+        return Err(Error::Forbidden);
+    }
+
     let mut transaction = db_pool.begin().await.map_err(|err| {
         tracing::error!("Failed to open transaction: {err}");
         Error::DatabaseExecution
@@ -417,6 +434,7 @@ pub(crate) async fn register(
     sql::insert_user_permission_assignment(
         &mut transaction,
         &permissions,
+        None,
         user_id,
         None,
         0,
@@ -458,18 +476,22 @@ pub(crate) async fn assign_user_permissions(
     let assignment_id = sql::insert_user_permission_assignment(
         &mut transaction,
         &request.permissions,
+        request.scope.as_ref(),
         user_id,
         Some(claims.uid),
         request.priority,
     )
     .await?;
 
+    update_user_cached_permissions(&mut transaction, user_id).await?;
+
     sql::insert_audit_log_entry(
         &mut transaction,
         auth::AuditLogAction::PermissionAssignment {
             user_id,
             assignment_id,
             permissions: Box::new(request.permissions.clone()),
+            scope: request.scope.clone(),
         },
         claims.uid,
         Some(claims.jti),
@@ -488,6 +510,7 @@ pub(crate) async fn assign_user_permissions(
         user_id,
         issuer_id: Some(claims.uid),
         priority: request.priority,
+        scope: request.scope,
     })
 }
 
@@ -518,6 +541,7 @@ pub(crate) async fn revoke_user_permissions(
             user_id: assignment.user_id,
             assignment_id,
             permissions: Box::new(assignment.permissions),
+            scope: assignment.scope,
         },
         claims.uid,
         Some(claims.jti),
@@ -602,6 +626,374 @@ pub(crate) async fn admin_change_password(
     })
 }
 
+pub(crate) async fn suspend_user(
+    db_pool: &PgPool,
+    request: requests::SuspendUser,
+    user_id: i32,
+    claims: &super::Claims,
+    requester_ip: IpAddr,
+) -> Result<(), Error> {
+    if request.reason.trim().is_empty() {
+        return Err(Error::ValidationFailure(
+            "A suspension reason is required".to_string(),
+        ));
+    }
+    if request.until.is_some_and(|until| until <= Utc::now()) {
+        return Err(Error::ValidationFailure(
+            "The suspension must end in the future".to_string(),
+        ));
+    }
+    if user_id == claims.uid {
+        return Err(Error::ValidationFailure(
+            "Users cannot suspend themselves".to_string(),
+        ));
+    }
+
+    let mut transaction = db_pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let user = sql::fetch_user_by_id(&mut *transaction, user_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+    if user.is_superuser {
+        return Err(Error::Forbidden);
+    }
+
+    sql::update_set_user_suspended(
+        &mut transaction,
+        user_id,
+        &request.reason,
+        request.until,
+    )
+    .await?;
+    let revoked_sessions =
+        sql::update_set_user_sessions_revoked(&mut transaction, user_id)
+            .await?;
+
+    sql::insert_audit_log_entry(
+        &mut transaction,
+        auth::AuditLogAction::UserSuspension {
+            user_id,
+            reason: request.reason,
+            until: request.until,
+            revoked_sessions,
+        },
+        claims.uid,
+        Some(claims.jti),
+        &requester_ip.into(),
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn unsuspend_user(
+    db_pool: &PgPool,
+    user_id: i32,
+    claims: &super::Claims,
+    requester_ip: IpAddr,
+) -> Result<(), Error> {
+    let mut transaction = db_pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::update_unset_user_suspended(&mut transaction, user_id).await?;
+    sql::insert_audit_log_entry(
+        &mut transaction,
+        auth::AuditLogAction::UserUnsuspension { user_id },
+        claims.uid,
+        Some(claims.jti),
+        &requester_ip.into(),
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
+
+/// Rejects users that are currently suspended or that have yet to verify
+/// their email. Access tokens outlive suspensions, so actions with lasting
+/// effects (such as contributing) have to check this explicitly.
+pub(crate) async fn ensure_can_contribute(
+    db_pool: &PgPool,
+    user_id: i32,
+) -> Result<(), Error> {
+    let user = sql::fetch_user_by_id(db_pool, user_id)
+        .await?
+        .ok_or(Error::Forbidden)?;
+    if user.is_suspended_at(Utc::now())
+        || user.verification_level < VERIFIED_EMAIL_LEVEL
+    {
+        return Err(Error::Forbidden);
+    }
+    Ok(())
+}
+
+fn gen_verification_secret() -> String {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    base16ct::lower::encode_string(&secret)
+}
+
+fn hash_verification_secret(secret: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(secret.as_bytes());
+    base16ct::lower::encode_string(&hasher.finalize())
+}
+
+/// Issues a verification and returns its secret.
+/// Any previous verification of the same kind stops being valid.
+/// Nothing is issued (`None`) while a recent one is still pending,
+/// so that mail can't be sent out to someone in bulk.
+async fn issue_verification(
+    db_pool: &PgPool,
+    user: &auth::User,
+    kind: models::VerificationKind,
+    validity: chrono::Duration,
+    requester_ip: IpAddr,
+    user_agent: &str,
+) -> Result<Option<String>, Error> {
+    let secret = gen_verification_secret();
+
+    let mut transaction = db_pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let cooldown =
+        chrono::Duration::try_minutes(VERIFICATION_COOLDOWN_MINUTES).unwrap();
+    let is_recent = sql::fetch_pending_user_verification_exists(
+        &mut transaction,
+        user.id,
+        kind,
+        Utc::now() - cooldown,
+    )
+    .await?;
+    if is_recent {
+        return Ok(None);
+    }
+
+    sql::insert_user_verification(
+        &mut transaction,
+        models::NewUserVerification {
+            user_id: user.id,
+            kind,
+            email: &user.email,
+            secret_hash: hash_verification_secret(&secret),
+            expiration: Utc::now() + validity,
+            ip: requester_ip.into(),
+            user_agent,
+        },
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Some(secret))
+}
+
+pub(crate) async fn request_email_verification(
+    db_pool: &PgPool,
+    mailer: &Mailer,
+    user: &auth::User,
+    requester_ip: IpAddr,
+    user_agent: &str,
+) -> Result<(), Error> {
+    if user.verification_level >= VERIFIED_EMAIL_LEVEL {
+        return Err(Error::ValidationFailure(
+            "Email already verified".to_string(),
+        ));
+    }
+
+    let secret = issue_verification(
+        db_pool,
+        user,
+        models::VerificationKind::Email,
+        chrono::Duration::try_hours(EMAIL_VERIFICATION_HOURS).unwrap(),
+        requester_ip,
+        user_agent,
+    )
+    .await?
+    .ok_or_else(|| {
+        Error::ValidationFailure(
+            "A verification was requested recently".to_string(),
+        )
+    })?;
+
+    let site_url = &SETTINGS.get().unwrap().mail.site_url;
+    let body = format!(
+        "Hello {},\n\n\
+        Confirm your email address by following this link:\n\
+        {site_url}/verify?token={secret}\n\n\
+        The link expires in {EMAIL_VERIFICATION_HOURS} hours.",
+        user.username
+    );
+    mailer
+        .send(&user.email, "Confirm your email address", body)
+        .await
+}
+
+pub(crate) async fn verify_email(
+    db_pool: &PgPool,
+    request: requests::VerifyEmail,
+    requester_ip: IpAddr,
+) -> Result<(), Error> {
+    let mut transaction = db_pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let verification = sql::update_take_user_verification(
+        &mut transaction,
+        models::VerificationKind::Email,
+        &hash_verification_secret(&request.token),
+    )
+    .await?
+    .ok_or_else(|| {
+        Error::ValidationFailure("Invalid or expired token".to_string())
+    })?;
+
+    let verified = sql::update_user_verification_level(
+        &mut transaction,
+        verification.user_id,
+        &verification.email,
+        VERIFIED_EMAIL_LEVEL,
+    )
+    .await?;
+    if !verified {
+        return Err(Error::ValidationFailure(
+            "The email address has changed".to_string(),
+        ));
+    }
+
+    sql::insert_audit_log_entry(
+        &mut transaction,
+        auth::AuditLogAction::EmailVerification {
+            email: verification.email,
+        },
+        verification.user_id,
+        None,
+        &requester_ip.into(),
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
+
+/// Mails a password reset link to the owner of an address, if there is one.
+/// Unknown addresses are not reported, so that they can't be probed.
+pub(crate) async fn request_password_reset(
+    db_pool: &PgPool,
+    mailer: &Mailer,
+    request: requests::RequestPasswordReset,
+    requester_ip: IpAddr,
+    user_agent: &str,
+) -> Result<(), Error> {
+    let Some(user) = sql::fetch_user_by_email(db_pool, &request.email).await?
+    else {
+        return Ok(());
+    };
+
+    // Throttled requests are not reported either
+    let Some(secret) = issue_verification(
+        db_pool,
+        &user,
+        models::VerificationKind::PasswordReset,
+        chrono::Duration::try_minutes(PASSWORD_RESET_MINUTES).unwrap(),
+        requester_ip,
+        user_agent,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let site_url = &SETTINGS.get().unwrap().mail.site_url;
+    let body = format!(
+        "Hello {},\n\n\
+        A password reset was requested for your account. \
+        If it was you, follow this link to choose a new password:\n\
+        {site_url}/reset_password?token={secret}\n\n\
+        The link expires in {PASSWORD_RESET_MINUTES} minutes. \
+        Otherwise you can ignore this message.",
+        user.username
+    );
+    // Failing here would tell that the account exists
+    if let Err(err) = mailer.send(&user.email, "Password reset", body).await {
+        tracing::error!(
+            user_id = user.id,
+            "Unable to send password reset: {err}"
+        );
+    }
+    Ok(())
+}
+
+pub(crate) async fn reset_password(
+    db_pool: &PgPool,
+    request: requests::ResetPassword,
+    requester_ip: IpAddr,
+) -> Result<(), Error> {
+    validate_password(&request.new_password)
+        .map_err(Error::ValidationFailure)?;
+    let password_kdf = gen_kdf_password_string(&request.new_password)?;
+
+    let mut transaction = db_pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let verification = sql::update_take_user_verification(
+        &mut transaction,
+        models::VerificationKind::PasswordReset,
+        &hash_verification_secret(&request.token),
+    )
+    .await?
+    .ok_or_else(|| {
+        Error::ValidationFailure("Invalid or expired token".to_string())
+    })?;
+
+    sql::change_user_password(
+        &mut transaction,
+        verification.user_id,
+        &password_kdf,
+    )
+    .await?;
+    // Whoever had access to the account might not be the owner
+    let revoked_sessions = sql::update_set_user_sessions_revoked(
+        &mut transaction,
+        verification.user_id,
+    )
+    .await?;
+
+    sql::insert_audit_log_entry(
+        &mut transaction,
+        auth::AuditLogAction::PasswordReset { revoked_sessions },
+        verification.user_id,
+        None,
+        &requester_ip.into(),
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
+
 async fn update_user_cached_permissions(
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     user_id: i32,
@@ -627,21 +1019,87 @@ fn compile_permission_assignments(
         .into_iter()
         .sorted_by_key(|a| a.priority)
         .rev()
-        .for_each(|assignment| permissions.merge(assignment.permissions));
+        .for_each(|assignment| {
+            if let Some(scope) = &assignment.scope {
+                permissions.merge_scoped(scope, assignment.permissions);
+            } else {
+                permissions.merge(assignment.permissions);
+            }
+        });
 
     permissions
 }
 
+/// Whether `P` is held either globally or within the scope of an operator.
+/// Used where access cannot be decided by the request path alone.
+pub(crate) async fn holds_operator_permission<P: super::ClaimPermission>(
+    pool: &PgPool,
+    permissions: &auth::Permissions,
+    operator_id: i32,
+) -> Result<bool, Error> {
+    if P::is_valid(permissions) {
+        return Ok(true);
+    }
+    if !P::SCOPABLE || !permissions.has_scoped() {
+        return Ok(false);
+    }
+
+    let Some(target) = sql::fetch_operator_scope(pool, operator_id).await?
+    else {
+        return Ok(false);
+    };
+
+    Ok(permissions
+        .scoped_to(&target.operators, &target.regions)
+        .any(P::is_valid))
+}
+
 #[cfg(test)]
 mod tests {
     use sqlx::PgPool;
     use std::net::{IpAddr, Ipv4Addr};
 
-    use commons::models::auth::Permissions;
+    use commons::models::auth::{PermissionScope, Permissions};
 
-    use crate::auth::{jwt, models, models::requests};
+    use crate::auth::{jwt, models, models::requests, models::responses, sql};
     use crate::errors::Error;
 
+    #[test]
+    fn compile_scoped_assignments() {
+        let departures: Permissions = serde_json::from_value(
+            serde_json::json!({"routes": {"modifyDepartures": true}}),
+        )
+        .unwrap();
+        let assignments = vec![
+            responses::UserPermAssignment {
+                id: 1,
+                permissions: Permissions::new_user_default(),
+                user_id: 1,
+                issuer_id: None,
+                priority: 0,
+                scope: None,
+            },
+            responses::UserPermAssignment {
+                id: 2,
+                permissions: departures.clone(),
+                user_id: 1,
+                issuer_id: None,
+                priority: 0,
+                scope: Some(PermissionScope::Operators { ids: vec![1, 2] }),
+            },
+        ];
+
+        let permissions = super::compile_permission_assignments(assignments);
+
+        assert_eq!(permissions.routes, None);
+        assert_eq!(permissions.operator_scoped.get(&1), Some(&departures));
+        assert_eq!(permissions.operator_scoped.get(&2), Some(&departures));
+        assert_eq!(permissions.operator_scoped.get(&3), None);
+        assert!(permissions.region_scoped.is_empty());
+        assert_eq!(permissions.scoped_to(&[3], &[]).count(), 0);
+        assert_eq!(permissions.scoped_to(&[3, 2], &[1]).count(), 1);
+    }
+
     #[sqlx::test]
     async fn ok_register_login(pool: PgPool) {
         // REGISTER
@@ -674,6 +1132,154 @@ mod tests {
         assert!(res.is_ok())
     }
 
+    #[sqlx::test]
+    async fn err_login_suspended(pool: PgPool) {
+        let req_ori = IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4));
+        for username in ["moderator", "username"] {
+            let req = requests::Register {
+                username: username.to_string(),
+                password: "password".to_string(),
+                email: format!("{username}@intermodal.pt"),
+                captcha: None,
+                survey: Default::default(),
+                consent: models::ConsentAnswer {
+                    privacy: true,
+                    terms: true,
+                    copyright: true,
+                    other: Default::default(),
+                },
+            };
+            super::register(&pool, req, req_ori).await.unwrap();
+        }
+
+        let login = |username: &str| requests::Login {
+            username: username.to_string(),
+            password: "password".to_string(),
+        };
+        let (moderator_claims, _) =
+            super::login(login("moderator"), &pool, req_ori, "")
+                .await
+                .unwrap();
+        let moderator_claims = models::Claims {
+            uid: moderator_claims.uid,
+            ..Default::default()
+        };
+        let (refresh_claims, _) =
+            super::login(login("username"), &pool, req_ori, "")
+                .await
+                .unwrap();
+
+        let req = requests::SuspendUser {
+            reason: "Vandalism".to_string(),
+            until: None,
+        };
+        super::suspend_user(
+            &pool,
+            req,
+            refresh_claims.uid,
+            &moderator_claims,
+            req_ori,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            super::login(login("username"), &pool, req_ori, "").await,
+            Err(Error::Forbidden)
+        );
+        assert_eq!(
+            super::renew_token(refresh_claims.clone(), &pool, req_ori, "")
+                .await,
+            Err(Error::Forbidden)
+        );
+
+        super::unsuspend_user(
+            &pool,
+            refresh_claims.uid,
+            &moderator_claims,
+            req_ori,
+        )
+        .await
+        .unwrap();
+        assert!(super::login(login("username"), &pool, req_ori, "")
+            .await
+            .is_ok());
+    }
+
+    #[sqlx::test]
+    async fn ok_verify_email_and_reset_password(pool: PgPool) {
+        let req = requests::Register {
+            username: "username".to_string(),
+            password: "password".to_string(),
+            email: "user@intermodal.pt".to_string(),
+            captcha: None,
+            survey: Default::default(),
+            consent: models::ConsentAnswer {
+                privacy: true,
+                terms: true,
+                copyright: true,
+                other: Default::default(),
+            },
+        };
+        let req_ori = IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4));
+        super::register(&pool, req, req_ori).await.unwrap();
+
+        let user = sql::fetch_user_by_username(&pool, "username")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            super::ensure_can_contribute(&pool, user.id).await,
+            Err(Error::Forbidden)
+        );
+
+        // EMAIL VERIFICATION
+        let secret = super::issue_verification(
+            &pool,
+            &user,
+            models::VerificationKind::Email,
+            chrono::Duration::try_hours(1).unwrap(),
+            req_ori,
+            "",
+        )
+        .await
+        .unwrap();
+        let verify = || requests::VerifyEmail {
+            token: secret.clone(),
+        };
+        super::verify_email(&pool, verify(), req_ori).await.unwrap();
+        assert!(super::verify_email(&pool, verify(), req_ori).await.is_err());
+        assert_eq!(super::ensure_can_contribute(&pool, user.id).await, Ok(()));
+
+        // PASSWORD RESET
+        let secret = super::issue_verification(
+            &pool,
+            &user,
+            models::VerificationKind::PasswordReset,
+            chrono::Duration::try_hours(1).unwrap(),
+            req_ori,
+            "",
+        )
+        .await
+        .unwrap();
+        let reset = || requests::ResetPassword {
+            token: secret.clone(),
+            new_password: "new_password".to_string(),
+        };
+        super::reset_password(&pool, reset(), req_ori)
+            .await
+            .unwrap();
+        assert!(super::reset_password(&pool, reset(), req_ori)
+            .await
+            .is_err());
+
+        let req = requests::Login {
+            username: "username".to_string(),
+            password: "new_password".to_string(),
+        };
+        assert!(super::login(req, &pool, req_ori, "").await.is_ok());
+    }
+
     #[sqlx::test]
     async fn err_register_bad_username_spaces(pool: PgPool) {
         let req = requests::Register {
@@ -937,4 +1543,28 @@ mod tests {
 
         assert!(super::login(req, &pool, req_ori, "").await.is_ok());
     }
+
+    #[sqlx::test(fixtures("users"))]
+    async fn ok_throttle_verifications(pool: PgPool) {
+        let user = sql::fetch_user_by_id(&pool, 2).await.unwrap().unwrap();
+        let req_ori = IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4));
+        let issue = |kind| {
+            super::issue_verification(
+                &pool,
+                &user,
+                kind,
+                chrono::Duration::try_hours(1).unwrap(),
+                req_ori,
+                "",
+            )
+        };
+
+        let reset = models::VerificationKind::PasswordReset;
+        assert!(issue(reset).await.unwrap().is_some());
+        // A pending one was just issued
+        assert!(issue(reset).await.unwrap().is_none());
+        // Other kinds are unaffected
+        let email = models::VerificationKind::Email;
+        assert!(issue(email).await.unwrap().is_some());
+    }
 }
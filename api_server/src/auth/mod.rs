@@ -24,5 +24,7 @@ mod models;
 pub(crate) mod perms;
 mod sql;
 
+pub(crate) use logic::{ensure_can_contribute, holds_operator_permission};
 pub(crate) use models::Claims;
+pub(crate) use sql::insert_audit_log_entry;
 pub(super) use perms::{ClaimPermission, ScopedClaim};
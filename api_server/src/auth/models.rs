@@ -21,7 +21,7 @@ use sqlx::types::ipnetwork::IpNetwork;
 use std::collections::HashMap;
 use uuid::Uuid;
 
-use commons::models::auth::Permissions;
+use commons::models::auth::{PermissionScope, Permissions};
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub(crate) struct JwtAccess(pub(crate) String);
@@ -119,6 +119,37 @@ pub(crate) struct NewUserSessionAccessMeta<'a> {
     pub(crate) expiration: chrono::DateTime<Utc>,
 }
 
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) enum VerificationKind {
+    Email = 0,
+    PasswordReset = 1,
+}
+
+impl From<VerificationKind> for i16 {
+    fn from(kind: VerificationKind) -> Self {
+        i16::from(kind as u8)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct NewUserVerification<'a> {
+    pub(crate) user_id: i32,
+    pub(crate) kind: VerificationKind,
+    pub(crate) email: &'a str,
+    // The secret itself is only known by the recipient
+    pub(crate) secret_hash: String,
+    pub(crate) expiration: chrono::DateTime<Utc>,
+    pub(crate) ip: IpNetwork,
+    pub(crate) user_agent: &'a str,
+}
+
+#[derive(Debug)]
+pub(crate) struct PendingVerification {
+    pub(crate) user_id: i32,
+    pub(crate) email: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConsentAnswer {
     pub copyright: bool,
@@ -135,6 +166,27 @@ pub struct UserPermAssignment {
     pub user_id: i32,
     pub issuer_id: Option<i32>,
     pub priority: i32,
+    pub operator_ids: Option<Vec<i32>>,
+    pub region_ids: Option<Vec<i32>>,
+}
+
+/// The operators and regions that an entity belongs to
+#[derive(Debug, Default)]
+pub(crate) struct TargetScope {
+    pub(crate) operators: Vec<i32>,
+    pub(crate) regions: Vec<i32>,
+}
+
+impl UserPermAssignment {
+    pub fn scope(&self) -> Option<PermissionScope> {
+        if let Some(ids) = &self.operator_ids {
+            Some(PermissionScope::Operators { ids: ids.clone() })
+        } else {
+            self.region_ids
+                .as_ref()
+                .map(|ids| PermissionScope::Regions { ids: ids.clone() })
+        }
+    }
 }
 
 pub(crate) mod requests {
@@ -214,6 +266,35 @@ pub(crate) mod requests {
         }
     }
 
+    #[derive(Deserialize)]
+    pub struct VerifyEmail {
+        pub token: String,
+    }
+
+    impl Debug for VerifyEmail {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("VerifyEmail").finish()
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct RequestPasswordReset {
+        pub email: String,
+        pub captcha: CaptchaAnswer,
+    }
+
+    #[derive(Deserialize)]
+    pub struct ResetPassword {
+        pub token: String,
+        pub new_password: String,
+    }
+
+    impl Debug for ResetPassword {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("ResetPassword").finish()
+        }
+    }
+
     #[derive(Debug, Deserialize)]
     pub struct UsernameAvailability {
         pub username: String,
@@ -228,6 +309,17 @@ pub(crate) mod requests {
     pub struct UserPermAssignments {
         pub permissions: super::Permissions,
         pub priority: i32,
+        // Restricts the assignment to some operators or regions
+        #[serde(default)]
+        pub scope: Option<super::PermissionScope>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct SuspendUser {
+        pub reason: String,
+        // Indefinite if absent
+        #[serde(default)]
+        pub until: Option<chrono::DateTime<chrono::Utc>>,
     }
 
     #[derive(Debug, Deserialize)]
@@ -308,11 +400,14 @@ pub(crate) mod responses {
         pub user_id: i32,
         pub issuer_id: Option<i32>,
         pub priority: i32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub scope: Option<super::PermissionScope>,
     }
 
     impl From<super::UserPermAssignment> for UserPermAssignment {
         fn from(assignment: super::UserPermAssignment) -> Self {
             Self {
+                scope: assignment.scope(),
                 id: assignment.id,
                 permissions: assignment.permissions.0,
                 user_id: assignment.user_id,
@@ -332,6 +427,8 @@ pub(crate) mod responses {
         pub(crate) consent: sqlx::types::JsonValue,
         pub(crate) consent_date: Option<chrono::DateTime<Utc>>,
         pub(crate) survey_version: i32,
+        pub(crate) suspension_reason: Option<String>,
+        pub(crate) suspended_until: Option<chrono::DateTime<Utc>>,
     }
 
     #[allow(clippy::struct_field_names)]
@@ -3,6 +3,11 @@ use commons::models::auth::Permissions;
 use crate::auth::Claims;
 
 pub(crate) trait ClaimPermission {
+    /// Whether this permission can be granted by an operator or region
+    /// scoped assignment, given that the request path targets entities
+    /// within that scope.
+    const SCOPABLE: bool = false;
+
     fn is_valid(permissions: &Permissions) -> bool;
 }
 
@@ -46,6 +51,8 @@ impl ClaimPermission for CreateOperator {
 pub struct ModifyOperatorMeta;
 
 impl ClaimPermission for ModifyOperatorMeta {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions
             .operators
@@ -57,6 +64,8 @@ impl ClaimPermission for ModifyOperatorMeta {
 pub struct ModifyOperatorStops;
 
 impl ClaimPermission for ModifyOperatorStops {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions
             .operators
@@ -68,6 +77,8 @@ impl ClaimPermission for ModifyOperatorStops {
 pub struct ModifyOperatorCalendars;
 
 impl ClaimPermission for ModifyOperatorCalendars {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions
             .operators
@@ -76,6 +87,32 @@ impl ClaimPermission for ModifyOperatorCalendars {
     }
 }
 
+pub struct HandleOperatorTickets;
+
+impl ClaimPermission for HandleOperatorTickets {
+    const SCOPABLE: bool = true;
+
+    fn is_valid(permissions: &Permissions) -> bool {
+        permissions
+            .operators
+            .as_ref()
+            .is_some_and(|p| p.handle_tickets)
+    }
+}
+
+pub struct ModifyOperatorVehicles;
+
+impl ClaimPermission for ModifyOperatorVehicles {
+    const SCOPABLE: bool = true;
+
+    fn is_valid(permissions: &Permissions) -> bool {
+        permissions
+            .operators
+            .as_ref()
+            .is_some_and(|p| p.modify_vehicles)
+    }
+}
+
 pub struct DeleteOperator;
 
 impl ClaimPermission for DeleteOperator {
@@ -94,6 +131,8 @@ impl ClaimPermission for CreateRoute {
 pub struct ModifyRouteBase;
 
 impl ClaimPermission for ModifyRouteBase {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions.routes.as_ref().is_some_and(|p| p.modify_base)
     }
@@ -102,6 +141,8 @@ impl ClaimPermission for ModifyRouteBase {
 pub struct ModifyRouteSubroutes;
 
 impl ClaimPermission for ModifyRouteSubroutes {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions
             .routes
@@ -113,6 +154,8 @@ impl ClaimPermission for ModifyRouteSubroutes {
 pub struct ModifyRouteStops;
 
 impl ClaimPermission for ModifyRouteStops {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions.routes.as_ref().is_some_and(|p| p.modify_stops)
     }
@@ -121,6 +164,8 @@ impl ClaimPermission for ModifyRouteStops {
 pub struct ModifyRouteDepartures;
 
 impl ClaimPermission for ModifyRouteDepartures {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions
             .routes
@@ -132,6 +177,8 @@ impl ClaimPermission for ModifyRouteDepartures {
 pub struct AuthenticateRoute;
 
 impl ClaimPermission for AuthenticateRoute {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions.routes.as_ref().is_some_and(|p| p.authenticate)
     }
@@ -140,6 +187,8 @@ impl ClaimPermission for AuthenticateRoute {
 pub struct DeleteRoute;
 
 impl ClaimPermission for DeleteRoute {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions.routes.as_ref().is_some_and(|p| p.delete)
     }
@@ -156,6 +205,8 @@ impl ClaimPermission for CreateStop {
 pub struct ModifyStopPos;
 
 impl ClaimPermission for ModifyStopPos {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions.stops.as_ref().is_some_and(|p| p.modify_pos)
     }
@@ -164,6 +215,8 @@ impl ClaimPermission for ModifyStopPos {
 pub struct ModifyStopAttrs;
 
 impl ClaimPermission for ModifyStopAttrs {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions.stops.as_ref().is_some_and(|p| p.modify_attrs)
     }
@@ -172,6 +225,8 @@ impl ClaimPermission for ModifyStopAttrs {
 pub struct ModifyStopMapFeatures;
 
 impl ClaimPermission for ModifyStopMapFeatures {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions
             .stops
@@ -183,6 +238,8 @@ impl ClaimPermission for ModifyStopMapFeatures {
 pub struct AuthenticateStop;
 
 impl ClaimPermission for AuthenticateStop {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions.stops.as_ref().is_some_and(|p| p.authenticate)
     }
@@ -401,6 +458,14 @@ impl ClaimPermission for SuspendUsers {
     }
 }
 
+pub struct ManageJobs;
+
+impl ClaimPermission for ManageJobs {
+    fn is_valid(permissions: &Permissions) -> bool {
+        permissions.admin.as_ref().is_some_and(|p| p.manage_jobs)
+    }
+}
+
 pub struct ModifyIssues;
 
 impl ClaimPermission for ModifyIssues {
@@ -431,6 +496,8 @@ impl ClaimPermission for ExpensiveCalls {
 pub struct PatchGtfs;
 
 impl ClaimPermission for PatchGtfs {
+    const SCOPABLE: bool = true;
+
     fn is_valid(permissions: &Permissions) -> bool {
         permissions.misc.as_ref().is_some_and(|p| p.patch_gtfs)
     }
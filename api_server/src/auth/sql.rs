@@ -41,7 +41,8 @@ where
     sqlx::query_as!(
         auth::User,
         r#"
-SELECT id, username, password, email, is_superuser, works_for
+SELECT id, username, password, email, is_superuser, is_suspended,
+    suspended_until, verification_level, works_for
 FROM Users
 WHERE id=$1
     "#,
@@ -62,7 +63,8 @@ pub(crate) async fn fetch_user_by_username(
     sqlx::query_as!(
         auth::User,
         r#"
-SELECT id, username, password, email, is_superuser, works_for
+SELECT id, username, password, email, is_superuser, is_suspended,
+    suspended_until, verification_level, works_for
 FROM Users
 WHERE username=$1
     "#,
@@ -76,6 +78,28 @@ WHERE username=$1
     })
 }
 
+pub(crate) async fn fetch_user_by_email(
+    pool: &PgPool,
+    email: &str,
+) -> Result<Option<auth::User>> {
+    sqlx::query_as!(
+        auth::User,
+        r#"
+SELECT id, username, password, email, is_superuser, is_suspended,
+    suspended_until, verification_level, works_for
+FROM Users
+WHERE email=$1
+    "#,
+        email
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), email);
+        Error::DatabaseExecution
+    })
+}
+
 pub(crate) async fn fetch_user_by_username_or_email(
     pool: &PgPool,
     username: &str,
@@ -84,7 +108,8 @@ pub(crate) async fn fetch_user_by_username_or_email(
     sqlx::query_as!(
         auth::User,
         r#"
-SELECT id, username, password, email, is_superuser, works_for
+SELECT id, username, password, email, is_superuser, is_suspended,
+    suspended_until, verification_level, works_for
 FROM Users
 WHERE username=$1 or email = $2
     "#,
@@ -211,7 +236,7 @@ pub(crate) async fn fetch_permission_assignment(
     sqlx::query_as!(
         models::UserPermAssignment,
         r#"
-SELECT id, user_id, issuer_id, priority,
+SELECT id, user_id, issuer_id, priority, operator_ids, region_ids,
     permissions as "permissions!: sqlx::types::Json<auth::Permissions>"
 FROM user_permissions
 WHERE id=$1"#,
@@ -236,7 +261,7 @@ where
     sqlx::query_as!(
         models::UserPermAssignment,
         r#"
-SELECT id, user_id, issuer_id, priority,
+SELECT id, user_id, issuer_id, priority, operator_ids, region_ids,
     permissions as "permissions!: sqlx::types::Json<auth::Permissions>"
 FROM user_permissions
 WHERE user_id=$1"#,
@@ -254,20 +279,30 @@ WHERE user_id=$1"#,
 pub(crate) async fn insert_user_permission_assignment(
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     permissions: &auth::Permissions,
+    scope: Option<&auth::PermissionScope>,
     user_id: i32,
     issuer_id: Option<i32>,
     priority: i32,
 ) -> Result<i32> {
+    let (operator_ids, region_ids) = match scope {
+        Some(auth::PermissionScope::Operators { ids }) => (Some(ids), None),
+        Some(auth::PermissionScope::Regions { ids }) => (None, Some(ids)),
+        None => (None, None),
+    };
+
     let res = sqlx::query!(
         r#"
-INSERT INTO user_permissions(user_id, issuer_id, priority, permissions)
-VALUES ($1, $2, $3, $4)
+INSERT INTO user_permissions(user_id, issuer_id, priority, permissions,
+    operator_ids, region_ids)
+VALUES ($1, $2, $3, $4, $5, $6)
 RETURNING id
         "#,
         user_id,
         issuer_id,
         priority,
-        json!(permissions)
+        json!(permissions),
+        operator_ids.map(Vec::as_slice),
+        region_ids.map(Vec::as_slice)
     )
     .fetch_one(&mut **transaction)
     .await
@@ -277,7 +312,8 @@ RETURNING id
             user_id,
             issuer_id,
             priority,
-            permissions = ?permissions
+            permissions = ?permissions,
+            scope = ?scope
         );
         Error::DatabaseExecution
     })?;
@@ -528,6 +564,205 @@ pub(crate) async fn change_user_password(
     Ok(())
 }
 
+pub(crate) async fn update_set_user_suspended(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+    reason: &str,
+    until: Option<chrono::DateTime<Utc>>,
+) -> Result<()> {
+    let res = sqlx::query!(
+        r#"
+UPDATE users
+SET is_suspended=true, suspension_reason=$2, suspended_until=$3
+WHERE id=$1
+    "#,
+        user_id,
+        reason,
+        until
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), user_id, reason);
+        Error::DatabaseExecution
+    })?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::NotFoundUpstream);
+    }
+    Ok(())
+}
+
+pub(crate) async fn update_unset_user_suspended(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+) -> Result<()> {
+    let res = sqlx::query!(
+        r#"
+UPDATE users
+SET is_suspended=false, suspension_reason=NULL, suspended_until=NULL
+WHERE id=$1
+    "#,
+        user_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), user_id);
+        Error::DatabaseExecution
+    })?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::NotFoundUpstream);
+    }
+    Ok(())
+}
+
+// Management tokens are sessions as well, so this revokes them too
+pub(crate) async fn update_set_user_sessions_revoked(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+) -> Result<u64> {
+    sqlx::query!(
+        r#"
+UPDATE user_sessions
+SET revoked=true
+WHERE user_id=$1 AND NOT revoked
+    "#,
+        user_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map(|res| res.rows_affected())
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), user_id);
+        Error::DatabaseExecution
+    })
+}
+
+/// Whether the user has a verification of `kind` pending since `since`
+pub(crate) async fn fetch_pending_user_verification_exists(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+    kind: models::VerificationKind,
+    since: DateTime<Utc>,
+) -> Result<bool> {
+    sqlx::query!(
+        r#"
+SELECT EXISTS(
+    SELECT 1
+    FROM user_verifications
+    WHERE user_id=$1 AND kind=$2 AND creation > $3
+        AND NOT completed AND expiration > now()
+) as "exists!"
+    "#,
+        user_id,
+        i16::from(kind),
+        since
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), user_id, kind = ?kind);
+        Error::DatabaseExecution
+    })
+    .map(|row| row.exists)
+}
+
+pub(crate) async fn insert_user_verification(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    verification: models::NewUserVerification<'_>,
+) -> Result<()> {
+    // Only one pending verification of each kind per user
+    sqlx::query!(
+        r#"
+UPDATE user_verifications
+SET expiration=now()
+WHERE user_id=$1 AND kind=$2 AND NOT completed AND expiration > now()
+    "#,
+        verification.user_id,
+        i16::from(verification.kind)
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), verification = ?verification);
+        Error::DatabaseExecution
+    })?;
+
+    sqlx::query!(
+        r#"
+INSERT INTO user_verifications(user_id, email, secret, kind, expiration, ip, user_agent)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+    "#,
+        verification.user_id,
+        verification.email,
+        verification.secret_hash,
+        i16::from(verification.kind),
+        verification.expiration,
+        verification.ip,
+        verification.user_agent
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), verification = ?verification);
+        Error::DatabaseExecution
+    })?;
+    Ok(())
+}
+
+/// Takes a pending verification, preventing it from being used again
+pub(crate) async fn update_take_user_verification(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    kind: models::VerificationKind,
+    secret_hash: &str,
+) -> Result<Option<models::PendingVerification>> {
+    sqlx::query_as!(
+        models::PendingVerification,
+        r#"
+UPDATE user_verifications
+SET completed=true
+WHERE kind=$1 AND secret=$2 AND NOT completed AND expiration > now()
+RETURNING user_id, email
+    "#,
+        i16::from(kind),
+        secret_hash
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), kind = ?kind);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn update_user_verification_level(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+    email: &str,
+    level: i32,
+) -> Result<bool> {
+    // The email might have changed since the verification was issued
+    sqlx::query!(
+        r#"
+UPDATE users
+SET verification_level=GREATEST(verification_level, $3)
+WHERE id=$1 AND email=$2
+    "#,
+        user_id,
+        email,
+        level
+    )
+    .execute(&mut **transaction)
+    .await
+    .map(|res| res.rows_affected() > 0)
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), user_id, email, level);
+        Error::DatabaseExecution
+    })
+}
+
 pub(crate) async fn fetch_audit_log_entries<'c, E>(
     executor: E,
     skip: i64,
@@ -696,7 +931,7 @@ pub(crate) async fn get_user_info(
         responses::UserInfo,
         r#"
 SELECT email, registration_date, is_superuser, is_suspended, verification_level,
-    consent, consent_date, survey_version
+    consent, consent_date, survey_version, suspension_reason, suspended_until
 FROM users
 WHERE users.id = $1"#,
         user_id
@@ -815,3 +1050,138 @@ VALUES ($1, $2, $3, $4, $5, $6)
     })?;
     Ok(())
 }
+
+pub(crate) async fn fetch_operator_scope(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Option<models::TargetScope>> {
+    sqlx::query_as!(
+        models::TargetScope,
+        r#"
+SELECT ARRAY[id] as "operators!",
+    ARRAY(
+        SELECT region_id
+        FROM region_operators
+        WHERE operator_id = $1
+    ) as "regions!"
+FROM operators
+WHERE id = $1
+"#,
+        operator_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_route_scope(
+    pool: &PgPool,
+    route_id: i32,
+) -> Result<Option<models::TargetScope>> {
+    sqlx::query_as!(
+        models::TargetScope,
+        r#"
+SELECT ARRAY[operator] as "operators!",
+    ARRAY(
+        SELECT region_id
+        FROM region_routes
+        WHERE route_id = $1
+    ) as "regions!"
+FROM routes
+WHERE id = $1
+"#,
+        route_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), route_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_subroute_scope(
+    pool: &PgPool,
+    subroute_id: i32,
+) -> Result<Option<models::TargetScope>> {
+    sqlx::query_as!(
+        models::TargetScope,
+        r#"
+SELECT ARRAY[routes.operator] as "operators!",
+    ARRAY(
+        SELECT region_id
+        FROM region_routes
+        WHERE route_id = routes.id
+    ) as "regions!"
+FROM subroutes
+JOIN routes ON routes.id = subroutes.route
+WHERE subroutes.id = $1
+"#,
+        subroute_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), subroute_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_stop_scope(
+    pool: &PgPool,
+    stop_id: i32,
+) -> Result<Option<models::TargetScope>> {
+    sqlx::query_as!(
+        models::TargetScope,
+        r#"
+SELECT ARRAY(
+        SELECT operator_id
+        FROM stop_operators
+        WHERE stop_id = $1
+    ) as "operators!",
+    ARRAY(
+        SELECT region_id
+        FROM region_stops
+        WHERE stop_id = $1
+    ) as "regions!"
+FROM stops
+WHERE id = $1
+"#,
+        stop_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), stop_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_ticket_scope(
+    pool: &PgPool,
+    ticket_id: i32,
+) -> Result<Option<models::TargetScope>> {
+    sqlx::query_as!(
+        models::TargetScope,
+        r#"
+SELECT ARRAY[operator_id] as "operators!",
+    ARRAY(
+        SELECT region_id
+        FROM region_operators
+        WHERE region_operators.operator_id = tickets.operator_id
+    ) as "regions!"
+FROM tickets
+WHERE id = $1
+"#,
+        ticket_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ticket_id);
+        Error::DatabaseExecution
+    })
+}
@@ -22,13 +22,15 @@ use chrono::Local;
 use futures::future;
 use serde::Deserialize;
 
+use commons::models::routes as commons_routes;
 use commons::models::{history, stops};
 
 use super::{logic, requests, responses, sql};
 use crate::errors::Error;
+use crate::pics::models::responses as pic_responses;
 use crate::responses::{IdReturn, Pagination};
 use crate::utils::get_exactly_one_field;
-use crate::{auth, pics, AppState};
+use crate::{auth, auth::ClaimPermission, pics, routes, AppState};
 
 #[derive(Deserialize, Default)]
 pub(crate) struct Page {
@@ -221,12 +223,96 @@ pub(crate) async fn get_changelog(
     }))
 }
 
+pub(crate) async fn get_stop_history(
+    State(state): State<AppState>,
+    Path(stop_id): Path<i32>,
+    Query(params): Query<requests::EntityHistory>,
+) -> Result<Json<responses::EntityHistory<stops::Stop>>, Error> {
+    let changesets =
+        logic::entity_history(&state.pool, logic::HistoryEntity::Stop(stop_id))
+            .await?;
+
+    let Some(at) = params.at else {
+        return Ok(Json(responses::EntityHistory::Changesets(changesets)));
+    };
+
+    let current = crate::stops::sql::fetch_stop(&state.pool, stop_id)
+        .await?
+        .map(Into::into);
+    let stop =
+        logic::rewind_stop(current, logic::changes_since(changesets, at))?
+            .ok_or(Error::NotFoundUpstream)?;
+
+    Ok(Json(responses::EntityHistory::Snapshot(stop)))
+}
+
+pub(crate) async fn get_route_history(
+    State(state): State<AppState>,
+    Path(route_id): Path<i32>,
+    Query(params): Query<requests::EntityHistory>,
+) -> Result<Json<responses::EntityHistory<commons_routes::Route>>, Error> {
+    let changesets = logic::entity_history(
+        &state.pool,
+        logic::HistoryEntity::Route(route_id),
+    )
+    .await?;
+
+    let Some(at) = params.at else {
+        return Ok(Json(responses::EntityHistory::Changesets(changesets)));
+    };
+
+    let current =
+        routes::sql::fetch_commons_route(&state.pool, route_id).await?;
+    let route =
+        logic::rewind_route(current, logic::changes_since(changesets, at))
+            .ok_or(Error::NotFoundUpstream)?;
+
+    Ok(Json(responses::EntityHistory::Snapshot(route)))
+}
+
+pub(crate) async fn get_subroute_history(
+    State(state): State<AppState>,
+    Path(subroute_id): Path<i32>,
+    Query(params): Query<requests::EntityHistory>,
+) -> Result<Json<responses::EntityHistory<commons_routes::Subroute>>, Error> {
+    let changesets = logic::entity_history(
+        &state.pool,
+        logic::HistoryEntity::Subroute(subroute_id),
+    )
+    .await?;
+
+    let Some(at) = params.at else {
+        return Ok(Json(responses::EntityHistory::Changesets(changesets)));
+    };
+
+    let current =
+        routes::sql::fetch_simple_subroute(&state.pool, subroute_id).await?;
+    let subroute =
+        logic::rewind_subroute(current, logic::changes_since(changesets, at))?
+            .ok_or(Error::NotFoundUpstream)?;
+
+    Ok(Json(responses::EntityHistory::Snapshot(subroute)))
+}
+
+pub(crate) async fn post_revert_changeset(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<auth::perms::HandleContrib>,
+    Path(changeset_id): Path<i64>,
+) -> Result<Json<IdReturn<i64>>, Error> {
+    Ok(Json(IdReturn {
+        id: logic::revert_changeset(&state.pool, changeset_id, claims.uid)
+            .await?,
+    }))
+}
+
 pub(crate) async fn post_contrib_stop_data(
     State(state): State<AppState>,
     claims: auth::Claims,
     Path(stop_id): Path<i32>,
     Json(contribution): Json<requests::NewStopMetaContribution>,
 ) -> Result<Json<IdReturn<i64>>, Error> {
+    auth::ensure_can_contribute(&state.pool, claims.uid).await?;
+
     let stop: stops::Stop = crate::stops::sql::fetch_stop(&state.pool, stop_id)
         .await?
         .ok_or(Error::NotFoundUpstream)?
@@ -257,11 +343,230 @@ pub(crate) async fn post_contrib_stop_data(
     }))
 }
 
-pub(crate) async fn post_contrib_stop_picture(
+pub(crate) async fn post_contrib_route_data(
     State(state): State<AppState>,
     claims: auth::Claims,
-    mut multipart: Multipart,
+    Path(route_id): Path<i32>,
+    Json(contribution): Json<requests::NewRouteContribution>,
 ) -> Result<Json<IdReturn<i64>>, Error> {
+    auth::ensure_can_contribute(&state.pool, claims.uid).await?;
+
+    let route = routes::sql::fetch_commons_route(&state.pool, route_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let patch = contribution.contribution.derive_patch(&route);
+
+    if patch.is_empty() {
+        return Ok(Json(IdReturn { id: -1 }));
+    }
+
+    let contribution = history::Contribution {
+        id: 0,
+        author_id: claims.uid,
+        change: history::Change::RouteUpdate {
+            original: route.into(),
+            patch,
+        },
+        accepted: None,
+        evaluator_id: None,
+        evaluation_date: None,
+        submission_date: Local::now(),
+        comment: contribution.comment,
+    };
+
+    Ok(Json(IdReturn {
+        id: sql::insert_new_contribution(&state.pool, contribution).await?,
+    }))
+}
+
+pub(crate) async fn post_contrib_subroute_data(
+    State(state): State<AppState>,
+    claims: auth::Claims,
+    Path(subroute_id): Path<i32>,
+    Json(contribution): Json<requests::NewSubrouteContribution>,
+) -> Result<Json<IdReturn<i64>>, Error> {
+    auth::ensure_can_contribute(&state.pool, claims.uid).await?;
+
+    let subroute = routes::sql::fetch_simple_subroute(&state.pool, subroute_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let patch = contribution.contribution.derive_patch(&subroute);
+
+    if patch.is_empty() {
+        return Ok(Json(IdReturn { id: -1 }));
+    }
+
+    let contribution = history::Contribution {
+        id: 0,
+        author_id: claims.uid,
+        change: history::Change::SubrouteUpdate {
+            original: subroute.into(),
+            patch,
+        },
+        accepted: None,
+        evaluator_id: None,
+        evaluation_date: None,
+        submission_date: Local::now(),
+        comment: contribution.comment,
+    };
+
+    Ok(Json(IdReturn {
+        id: sql::insert_new_contribution(&state.pool, contribution).await?,
+    }))
+}
+
+pub(crate) async fn post_contrib_subroute_stops(
+    State(state): State<AppState>,
+    claims: auth::Claims,
+    Path(subroute_id): Path<i32>,
+    Json(contribution): Json<requests::NewSubrouteStopsContribution>,
+) -> Result<Json<IdReturn<i64>>, Error> {
+    auth::ensure_can_contribute(&state.pool, claims.uid).await?;
+
+    let sr_stops =
+        routes::sql::fetch_subroute_stops(&state.pool, subroute_id).await?;
+
+    if sr_stops != contribution.contribution.from {
+        return Err(Error::ValidationFailure("Check mismatch".to_string()));
+    }
+
+    if sr_stops == contribution.contribution.to {
+        return Ok(Json(IdReturn { id: -1 }));
+    }
+
+    let contribution = history::Contribution {
+        id: 0,
+        author_id: claims.uid,
+        change: history::Change::SubrouteStopsUpdate {
+            subroute_id,
+            original: sr_stops,
+            stops: contribution.contribution.to,
+        },
+        accepted: None,
+        evaluator_id: None,
+        evaluation_date: None,
+        submission_date: Local::now(),
+        comment: contribution.comment,
+    };
+
+    Ok(Json(IdReturn {
+        id: sql::insert_new_contribution(&state.pool, contribution).await?,
+    }))
+}
+
+pub(crate) async fn post_contrib_departure_creation(
+    State(state): State<AppState>,
+    claims: auth::Claims,
+    Path(subroute_id): Path<i32>,
+    Json(contribution): Json<requests::NewDepartureContribution>,
+) -> Result<Json<IdReturn<i64>>, Error> {
+    auth::ensure_can_contribute(&state.pool, claims.uid).await?;
+
+    routes::sql::fetch_simple_subroute(&state.pool, subroute_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let contribution = history::Contribution {
+        id: 0,
+        author_id: claims.uid,
+        change: history::Change::DepartureCreation {
+            data: history::routes::Departure {
+                id: 0,
+                subroute_id,
+                time: contribution.contribution.time,
+                calendar_id: contribution.contribution.calendar_id,
+            },
+        },
+        accepted: None,
+        evaluator_id: None,
+        evaluation_date: None,
+        submission_date: Local::now(),
+        comment: contribution.comment,
+    };
+
+    Ok(Json(IdReturn {
+        id: sql::insert_new_contribution(&state.pool, contribution).await?,
+    }))
+}
+
+pub(crate) async fn post_contrib_departure_data(
+    State(state): State<AppState>,
+    claims: auth::Claims,
+    Path(departure_id): Path<i32>,
+    Json(contribution): Json<requests::NewDepartureContribution>,
+) -> Result<Json<IdReturn<i64>>, Error> {
+    auth::ensure_can_contribute(&state.pool, claims.uid).await?;
+
+    let departure = routes::sql::fetch_departure(&state.pool, departure_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let patch = contribution.contribution.derive_patch(&departure);
+
+    if patch.is_empty() {
+        return Ok(Json(IdReturn { id: -1 }));
+    }
+
+    let contribution = history::Contribution {
+        id: 0,
+        author_id: claims.uid,
+        change: history::Change::DepartureUpdate {
+            original: departure.into(),
+            patch,
+        },
+        accepted: None,
+        evaluator_id: None,
+        evaluation_date: None,
+        submission_date: Local::now(),
+        comment: contribution.comment,
+    };
+
+    Ok(Json(IdReturn {
+        id: sql::insert_new_contribution(&state.pool, contribution).await?,
+    }))
+}
+
+pub(crate) async fn post_contrib_departure_deletion(
+    State(state): State<AppState>,
+    claims: auth::Claims,
+    Path(departure_id): Path<i32>,
+    Json(contribution): Json<requests::NewDeletionContribution>,
+) -> Result<Json<IdReturn<i64>>, Error> {
+    auth::ensure_can_contribute(&state.pool, claims.uid).await?;
+
+    let departure = routes::sql::fetch_departure(&state.pool, departure_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let contribution = history::Contribution {
+        id: 0,
+        author_id: claims.uid,
+        change: history::Change::DepartureDeletion {
+            data: departure.into(),
+        },
+        accepted: None,
+        evaluator_id: None,
+        evaluation_date: None,
+        submission_date: Local::now(),
+        comment: contribution.comment,
+    };
+
+    Ok(Json(IdReturn {
+        id: sql::insert_new_contribution(&state.pool, contribution).await?,
+    }))
+}
+
+pub(crate) async fn post_contrib_stop_picture(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ContribUploadStopPic,
+    >,
+    mut multipart: Multipart,
+) -> Result<Json<pic_responses::StopPicUpload<IdReturn<i64>>>, Error> {
+    auth::ensure_can_contribute(&state.pool, claims.uid).await?;
+
     let field = get_exactly_one_field(&mut multipart).await?;
 
     let filename = field
@@ -276,10 +581,11 @@ pub(crate) async fn post_contrib_stop_picture(
         .map_err(|err| Error::ValidationFailure(err.to_string()))?;
 
     // TODO limit maximum number of unverified pictures per user
-    let pic = pics::logic::upload_stop_picture(
+    let (pic, similar_pics) = pics::logic::upload_stop_picture(
         claims.uid,
+        auth::perms::ViewSensitiveStopPic::is_valid(&claims.permissions),
         filename.clone(),
-        &state.bucket,
+        &state.storage,
         &state.pool,
         &content,
         &[],
@@ -300,17 +606,24 @@ pub(crate) async fn post_contrib_stop_picture(
         comment: None,
     };
 
-    Ok(Json(IdReturn {
-        id: sql::insert_new_contribution(&state.pool, contribution).await?,
+    Ok(Json(pic_responses::StopPicUpload {
+        upload: IdReturn {
+            id: sql::insert_new_contribution(&state.pool, contribution).await?,
+        },
+        similar_pics,
     }))
 }
 
 pub(crate) async fn patch_contrib_stop_picture_meta(
     State(state): State<AppState>,
-    claims: auth::Claims,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ContribModifyStopPic,
+    >,
     Path(contribution_id): Path<i64>,
     Json(contribution_meta): Json<requests::NewPictureContribution>,
 ) -> Result<(), Error> {
+    auth::ensure_can_contribute(&state.pool, claims.uid).await?;
+
     let mut contribution =
         sql::fetch_contribution(&state.pool, contribution_id)
             .await?
@@ -409,7 +722,20 @@ pub(crate) async fn post_decline_contrib_data(
     )
     .await?;
 
-    // TODO, file deletions
+    if let history::Change::StopPicUpload { pic, .. } = &contribution.change {
+        // Pictures that got tagged in the meanwhile are no longer ours to drop
+        let db_pic =
+            pics::sql::fetch_stop_pic(&mut *transaction, pic.id).await?;
+        if db_pic.is_some_and(|db_pic| !db_pic.tagged) {
+            return pics::logic::discard_stop_picture(
+                transaction,
+                pic.id,
+                &pic.sha1,
+                &state.storage,
+            )
+            .await;
+        }
+    }
 
     transaction.commit().await.map_err(|err| {
         tracing::error!("Transaction failed to commit: {err}");
@@ -19,12 +19,16 @@
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
 use sqlx::PgPool;
 
-use commons::models::{history, stops};
+use commons::models::{history, routes, stops};
 
-use super::sql;
+use super::{responses, sql};
 use crate::errors::Error;
+use crate::pics::models::requests::ChangeStopPic;
+use crate::routes::models::requests::ChangeDeparture;
 
 pub(crate) fn summarize_stop_meta_contributions(
     contributions: Vec<history::Contribution>,
@@ -88,20 +92,186 @@ pub(crate) async fn accept_contribution(
                     .into();
 
             *original = stop.clone().into();
+            let original_position = (stop.lon, stop.lat);
             // FIXME This we might want to check if the original has been patched
             // and if that conflicts with the patch
             let stop = accept_stop_contribution(stop, patch, verify, ignored)?;
+            let (stop_id, position) = (stop.id, (stop.lon, stop.lat));
 
             crate::stops::sql::update_stop(
                 &mut transaction,
-                stop.id,
+                stop_id,
                 stop.into(),
                 user_id,
             )
             .await?;
+
+            crate::geo::logic::reassign_moved_stop_divisions(
+                pool,
+                &mut transaction,
+                stop_id,
+                original_position,
+                position,
+            )
+            .await?;
+        }
+        history::Change::RouteUpdate { original, patch } => {
+            let route =
+                crate::routes::sql::fetch_commons_route(pool, original.id)
+                    .await?
+                    .ok_or(Error::ValidationFailure(
+                        "Route no longer exists".to_string(),
+                    ))?;
+
+            *original = route.clone().into();
+            let route = accept_route_contribution(route, patch)?;
+
+            crate::routes::sql::update_route(
+                &mut transaction,
+                route.id,
+                route.into(),
+            )
+            .await?;
+        }
+        history::Change::SubrouteUpdate { original, patch } => {
+            let subroute =
+                crate::routes::sql::fetch_simple_subroute(pool, original.id)
+                    .await?
+                    .ok_or(Error::ValidationFailure(
+                        "Subroute no longer exists".to_string(),
+                    ))?;
+
+            *original = subroute.clone().into();
+            let subroute = accept_subroute_contribution(subroute, patch)?;
+
+            crate::routes::sql::update_subroute(
+                &mut transaction,
+                subroute.id,
+                subroute.into(),
+            )
+            .await?;
         }
-        history::Change::StopPicUpload { .. } => {
-            todo!()
+        history::Change::SubrouteStopsUpdate {
+            subroute_id,
+            original,
+            stops,
+        } => {
+            let current = crate::routes::sql::fetch_subroute_stops(
+                &mut *transaction,
+                *subroute_id,
+            )
+            .await?;
+
+            // Stop sequences are replaced as a whole, so any change made
+            // since the submission would be silently discarded
+            if &current != original {
+                return Err(Error::ValidationFailure(
+                    "Subroute stops changed since the contribution".to_string(),
+                ));
+            }
+            if &current == stops {
+                return Err(Error::ValidationFailure(
+                    "Patch no longer does anything".to_string(),
+                ));
+            }
+
+            crate::routes::sql::update_subroute_stops(
+                &mut transaction,
+                *subroute_id,
+                stops,
+            )
+            .await?;
+        }
+        history::Change::DepartureCreation { data } => {
+            crate::routes::sql::fetch_simple_subroute(pool, data.subroute_id)
+                .await?
+                .ok_or(Error::ValidationFailure(
+                    "Subroute no longer exists".to_string(),
+                ))?;
+
+            let departure = crate::routes::sql::insert_departure(
+                &mut transaction,
+                data.subroute_id,
+                ChangeDeparture {
+                    time: data.time,
+                    calendar_id: data.calendar_id,
+                },
+            )
+            .await?;
+
+            *data = departure.into();
+        }
+        history::Change::DepartureUpdate { original, patch } => {
+            let departure =
+                crate::routes::sql::fetch_departure(pool, original.id)
+                    .await?
+                    .ok_or(Error::ValidationFailure(
+                        "Departure no longer exists".to_string(),
+                    ))?;
+
+            *original = departure.clone().into();
+            let departure = accept_departure_contribution(departure, patch)?;
+
+            crate::routes::sql::update_departure(
+                &mut transaction,
+                original.subroute_id,
+                departure.id,
+                ChangeDeparture {
+                    time: departure.time,
+                    calendar_id: departure.calendar_id,
+                },
+            )
+            .await?;
+        }
+        history::Change::DepartureDeletion { data } => {
+            let departure = crate::routes::sql::fetch_departure(pool, data.id)
+                .await?
+                .ok_or(Error::ValidationFailure(
+                    "Departure no longer exists".to_string(),
+                ))?;
+
+            crate::routes::sql::delete_departure(
+                &mut transaction,
+                departure.subroute_id,
+                departure.id,
+            )
+            .await?;
+
+            *data = departure.into();
+        }
+        history::Change::StopPicUpload { pic, stops } => {
+            let db_pic = crate::pics::sql::fetch_stop_pic(pool, pic.id)
+                .await?
+                .ok_or(Error::ValidationFailure(
+                    "Picture no longer exists".to_string(),
+                ))?;
+
+            if db_pic.tagged {
+                return Err(Error::ValidationFailure(
+                    "Picture was already tagged".to_string(),
+                ));
+            }
+
+            // Accepting the upload is what publishes it
+            pic.dyn_meta.public = true;
+            let meta = pic.dyn_meta.clone();
+            crate::pics::sql::update_stop_pic_meta(
+                &mut transaction,
+                pic.id,
+                ChangeStopPic {
+                    public: meta.public,
+                    sensitive: meta.sensitive,
+                    lon: meta.lon,
+                    lat: meta.lat,
+                    tags: meta.tags,
+                    attrs: meta.attrs,
+                    stops: history::vec_into_vec(stops.clone()),
+                    notes: meta.notes,
+                    quality: meta.quality,
+                },
+                user_id,
+            )
+            .await?;
         }
         _ => {
             unreachable!()
@@ -163,3 +333,680 @@ pub(crate) fn accept_stop_contribution(
 
     Ok(current)
 }
+
+pub(crate) fn accept_route_contribution(
+    mut current: routes::Route,
+    patch: &mut history::routes::RoutePatch,
+) -> Result<routes::Route, Error> {
+    patch.drop_noops(&current);
+
+    if patch.is_empty() {
+        return Err(Error::ValidationFailure(
+            "Patch no longer does anything".to_string(),
+        ));
+    }
+
+    patch.clone().apply(&mut current);
+
+    Ok(current)
+}
+
+pub(crate) fn accept_subroute_contribution(
+    mut current: routes::Subroute,
+    patch: &mut history::routes::SubroutePatch,
+) -> Result<routes::Subroute, Error> {
+    patch.drop_noops(&current);
+
+    if patch.is_empty() {
+        return Err(Error::ValidationFailure(
+            "Patch no longer does anything".to_string(),
+        ));
+    }
+
+    patch.clone().apply(&mut current)?;
+
+    Ok(current)
+}
+
+pub(crate) fn accept_departure_contribution(
+    mut current: routes::Departure,
+    patch: &mut history::routes::DeparturePatch,
+) -> Result<routes::Departure, Error> {
+    patch.drop_noops(&current);
+
+    if patch.is_empty() {
+        return Err(Error::ValidationFailure(
+            "Patch no longer does anything".to_string(),
+        ));
+    }
+    if patch.subroute_id.is_some() {
+        return Err(Error::ValidationFailure(
+            "Departures cannot be moved between subroutes".to_string(),
+        ));
+    }
+
+    patch.clone().apply(&mut current);
+
+    Ok(current)
+}
+
+pub(crate) async fn revert_changeset(
+    pool: &PgPool,
+    changeset_id: i64,
+    user_id: i32,
+) -> Result<i64, Error> {
+    let changeset = sql::fetch_changeset(pool, changeset_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let mut transaction = pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let targets = changeset
+        .changes
+        .iter()
+        .flat_map(history::Change::targets)
+        .collect::<HashSet<_>>();
+
+    let target_ids = targets
+        .iter()
+        .copied()
+        .map(history::ChangeTarget::id)
+        .unique()
+        .collect_vec();
+    let later_changesets = sql::fetch_changesets_after(
+        &mut transaction,
+        changeset_id,
+        &target_ids,
+    )
+    .await?;
+    if let Some(conflict) = later_changesets.iter().find(|later| {
+        later
+            .changes
+            .iter()
+            .flat_map(history::Change::targets)
+            .any(|target| targets.contains(&target))
+    }) {
+        return Err(Error::ValidationFailure(format!(
+            "Changeset {} modified the same data afterwards",
+            conflict.id
+        )));
+    }
+
+    let mut inverse_changes = vec![];
+    // Unwind in the opposite order so that dependent changes go first
+    for change in changeset.changes.into_iter().rev() {
+        inverse_changes.extend(
+            revert_change(pool, &mut transaction, change, user_id).await?,
+        );
+    }
+
+    let revert_id = sql::insert_changeset_log(
+        &mut transaction,
+        user_id,
+        &inverse_changes,
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(revert_id)
+}
+
+#[allow(clippy::too_many_lines)]
+async fn revert_change(
+    pool: &PgPool,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    change: history::Change,
+    user_id: i32,
+) -> Result<Vec<history::Change>, Error> {
+    let lacking_data = || {
+        Error::ValidationFailure(
+            "Changeset lacks the data to be reverted".to_string(),
+        )
+    };
+
+    match change {
+        history::Change::StopUpdate { original, patch } => {
+            let current: stops::Stop =
+                crate::stops::sql::fetch_stop(&mut **transaction, original.id)
+                    .await?
+                    .ok_or(Error::ValidationFailure(
+                        "Stop no longer exists".to_string(),
+                    ))?
+                    .into();
+
+            let inverse = patch.invert(&original);
+            if inverse.is_empty() {
+                return Err(lacking_data());
+            }
+
+            let mut reverted = current.clone();
+            inverse.apply(&mut reverted)?;
+            let position = (reverted.lon, reverted.lat);
+
+            crate::stops::sql::update_stop(
+                transaction,
+                current.id,
+                reverted.into(),
+                user_id,
+            )
+            .await?;
+
+            crate::geo::logic::reassign_moved_stop_divisions(
+                pool,
+                transaction,
+                current.id,
+                (current.lon, current.lat),
+                position,
+            )
+            .await?;
+
+            Ok(vec![history::Change::StopUpdate {
+                original: current.into(),
+                patch: inverse,
+            }])
+        }
+        history::Change::RouteCreation { data } => {
+            let current = crate::routes::sql::fetch_commons_route(
+                &mut **transaction,
+                data.id,
+            )
+            .await?
+            .ok_or(Error::ValidationFailure(
+                "Route no longer exists".to_string(),
+            ))?;
+
+            crate::routes::sql::delete_route(transaction, current.id).await?;
+
+            Ok(vec![history::Change::RouteDeletion {
+                data: current.into(),
+            }])
+        }
+        history::Change::RouteUpdate { original, patch } => {
+            let current = crate::routes::sql::fetch_commons_route(
+                &mut **transaction,
+                original.id,
+            )
+            .await?
+            .ok_or(Error::ValidationFailure(
+                "Route no longer exists".to_string(),
+            ))?;
+
+            let inverse = patch.invert(&original);
+            if inverse.is_empty() {
+                return Err(lacking_data());
+            }
+
+            let mut reverted = current.clone();
+            inverse.clone().apply(&mut reverted);
+
+            crate::routes::sql::update_route(
+                transaction,
+                current.id,
+                reverted.into(),
+            )
+            .await?;
+
+            Ok(vec![history::Change::RouteUpdate {
+                original: current.into(),
+                patch: inverse,
+            }])
+        }
+        history::Change::RouteDeletion { data } => {
+            let route = routes::Route {
+                id: data.id,
+                type_id: data.type_id,
+                operator_id: data.operator_id,
+                code: data.code,
+                name: data.name,
+                circular: data.circular.unwrap_or_default(),
+                active: data.active,
+                badge_text_color: data.badge_text_color,
+                badge_bg_color: data.badge_bg_color,
+                // The subroutes were gone by the time the route got deleted
+                main_subroute: None,
+            };
+
+            crate::routes::sql::restore_route(transaction, &route).await?;
+
+            Ok(vec![history::Change::RouteCreation { data: route.into() }])
+        }
+        history::Change::SubrouteCreation { data } => {
+            let current = crate::routes::sql::fetch_simple_subroute(
+                &mut **transaction,
+                data.id,
+            )
+            .await?
+            .ok_or(Error::ValidationFailure(
+                "Subroute no longer exists".to_string(),
+            ))?;
+
+            let stops = crate::routes::sql::fetch_subroute_stops(
+                &mut **transaction,
+                data.id,
+            )
+            .await?;
+            let departures = crate::routes::sql::fetch_subroute_departures(
+                transaction,
+                data.id,
+            )
+            .await?;
+
+            crate::routes::sql::delete_subroute_stops(transaction, data.id)
+                .await?;
+            crate::routes::sql::delete_subroute_departures(
+                transaction,
+                data.id,
+            )
+            .await?;
+            crate::routes::sql::delete_subroute(transaction, data.id).await?;
+
+            Ok(vec![history::Change::SubrouteDeletion {
+                subroute: current.into(),
+                stops: Some(stops),
+                departures: Some(history::vec_into_vec(departures)),
+            }])
+        }
+        history::Change::SubrouteUpdate { original, patch } => {
+            let current = crate::routes::sql::fetch_simple_subroute(
+                &mut **transaction,
+                original.id,
+            )
+            .await?
+            .ok_or(Error::ValidationFailure(
+                "Subroute no longer exists".to_string(),
+            ))?;
+
+            let inverse = patch.invert(&original);
+            if inverse.is_empty() {
+                return Err(lacking_data());
+            }
+
+            let mut reverted = current.clone();
+            inverse.clone().apply(&mut reverted)?;
+
+            crate::routes::sql::update_subroute(
+                transaction,
+                current.id,
+                reverted.into(),
+            )
+            .await?;
+
+            Ok(vec![history::Change::SubrouteUpdate {
+                original: current.into(),
+                patch: inverse,
+            }])
+        }
+        history::Change::SubrouteDeletion {
+            subroute,
+            stops,
+            departures,
+        } => {
+            let (Some(stops), Some(departures)) = (stops, departures) else {
+                return Err(lacking_data());
+            };
+
+            let subroute = routes::Subroute {
+                id: subroute.id,
+                route_id: subroute.route_id,
+                group: subroute.group.ok_or_else(lacking_data)?,
+                origin: subroute.origin.ok_or_else(lacking_data)?,
+                destination: subroute.destination.ok_or_else(lacking_data)?,
+                headsign: subroute.headsign.ok_or_else(lacking_data)?,
+                via: history::vec_into_vec(
+                    subroute.via.ok_or_else(lacking_data)?,
+                ),
+                circular: subroute.circular,
+                polyline: subroute.polyline,
+                validation: None,
+                flag: subroute.flag.ok_or_else(lacking_data)?,
+            };
+            let departures: Vec<routes::Departure> =
+                history::vec_try_into_vec(departures)?;
+
+            crate::routes::sql::restore_subroute(transaction, &subroute)
+                .await?;
+            crate::routes::sql::update_subroute_stops(
+                transaction,
+                subroute.id,
+                &stops,
+            )
+            .await?;
+            for departure in &departures {
+                crate::routes::sql::restore_departure(transaction, departure)
+                    .await?;
+            }
+
+            let subroute_id = subroute.id;
+            let mut changes = vec![
+                history::Change::SubrouteCreation {
+                    data: subroute.into(),
+                },
+                history::Change::SubrouteStopsUpdate {
+                    subroute_id,
+                    original: vec![],
+                    stops,
+                },
+            ];
+            changes.extend(departures.into_iter().map(|departure| {
+                history::Change::DepartureCreation {
+                    data: departure.into(),
+                }
+            }));
+            Ok(changes)
+        }
+        history::Change::SubrouteStopsUpdate {
+            subroute_id,
+            original,
+            stops,
+        } => {
+            let current = crate::routes::sql::fetch_subroute_stops(
+                &mut **transaction,
+                subroute_id,
+            )
+            .await?;
+
+            // Stop sequence changes were not always logged
+            if current != stops {
+                return Err(Error::ValidationFailure(
+                    "Subroute stops changed since the changeset".to_string(),
+                ));
+            }
+
+            crate::routes::sql::update_subroute_stops(
+                transaction,
+                subroute_id,
+                &original,
+            )
+            .await?;
+
+            Ok(vec![history::Change::SubrouteStopsUpdate {
+                subroute_id,
+                original: stops,
+                stops: original,
+            }])
+        }
+        history::Change::DepartureCreation { data } => {
+            let current = crate::routes::sql::fetch_departure(
+                &mut **transaction,
+                data.id,
+            )
+            .await?
+            .ok_or(Error::ValidationFailure(
+                "Departure no longer exists".to_string(),
+            ))?;
+
+            crate::routes::sql::delete_departure(
+                transaction,
+                current.subroute_id,
+                current.id,
+            )
+            .await?;
+
+            Ok(vec![history::Change::DepartureDeletion {
+                data: current.into(),
+            }])
+        }
+        history::Change::DepartureUpdate { original, patch } => {
+            let current = crate::routes::sql::fetch_departure(
+                &mut **transaction,
+                original.id,
+            )
+            .await?
+            .ok_or(Error::ValidationFailure(
+                "Departure no longer exists".to_string(),
+            ))?;
+
+            let inverse = patch.invert(&original);
+            if inverse.is_empty() {
+                return Err(lacking_data());
+            }
+
+            let mut reverted = current.clone();
+            inverse.clone().apply(&mut reverted);
+
+            crate::routes::sql::update_departure(
+                transaction,
+                current.subroute_id,
+                current.id,
+                ChangeDeparture {
+                    time: reverted.time,
+                    calendar_id: reverted.calendar_id,
+                },
+            )
+            .await?;
+
+            Ok(vec![history::Change::DepartureUpdate {
+                original: current.into(),
+                patch: inverse,
+            }])
+        }
+        history::Change::DepartureDeletion { data } => {
+            let departure: routes::Departure = data.try_into()?;
+
+            crate::routes::sql::restore_departure(transaction, &departure)
+                .await?;
+
+            Ok(vec![history::Change::DepartureCreation {
+                data: departure.into(),
+            }])
+        }
+        history::Change::StopPicMetaUpdate {
+            pic_id: Some(pic_id),
+            original_meta,
+            original_stops,
+            ..
+        } => {
+            let pic =
+                crate::pics::sql::fetch_stop_pic(&mut **transaction, pic_id)
+                    .await?
+                    .ok_or(Error::ValidationFailure(
+                        "Picture no longer exists".to_string(),
+                    ))?;
+            let current_stops =
+                crate::pics::sql::fetch_picture_stops_rel_attrs(
+                    transaction,
+                    pic_id,
+                )
+                .await?;
+
+            let reverted = ChangeStopPic {
+                public: original_meta.public,
+                sensitive: original_meta.sensitive,
+                lon: original_meta.lon,
+                lat: original_meta.lat,
+                tags: original_meta.tags,
+                attrs: original_meta.attrs,
+                stops: history::vec_into_vec(original_stops.clone()),
+                notes: original_meta.notes,
+                quality: original_meta.quality,
+            };
+            let inverse = reverted.derive_patch(&pic);
+
+            crate::pics::sql::update_stop_pic_meta(
+                transaction,
+                pic_id,
+                reverted,
+                user_id,
+            )
+            .await?;
+
+            Ok(vec![history::Change::StopPicMetaUpdate {
+                pic_id: Some(pic_id),
+                original_meta: pic.dyn_meta.into(),
+                original_stops: history::vec_into_vec(current_stops),
+                meta_patch: inverse,
+                stops: original_stops,
+            }])
+        }
+        // Stops are never deleted, picture files do not outlive their
+        // deletion and issues are closed rather than undone
+        _ => Err(Error::ValidationFailure(
+            "This kind of change can't be reverted".to_string(),
+        )),
+    }
+}
+
+/// Entities whose changes can be tracked across the changelog
+#[derive(Clone, Copy)]
+pub(crate) enum HistoryEntity {
+    Stop(i32),
+    Route(i32),
+    Subroute(i32),
+}
+
+impl HistoryEntity {
+    fn id(self) -> i32 {
+        match self {
+            HistoryEntity::Stop(id)
+            | HistoryEntity::Route(id)
+            | HistoryEntity::Subroute(id) => id,
+        }
+    }
+
+    // The `history::Change` variant names that can touch this entity
+    fn change_kinds(self) -> &'static [&'static str] {
+        match self {
+            HistoryEntity::Stop(_) => {
+                &["StopCreation", "StopUpdate", "StopDeletion"]
+            }
+            HistoryEntity::Route(_) => {
+                &["RouteCreation", "RouteUpdate", "RouteDeletion"]
+            }
+            HistoryEntity::Subroute(_) => &[
+                "SubrouteCreation",
+                "SubrouteUpdate",
+                "SubrouteDeletion",
+                "SubrouteStopsUpdate",
+            ],
+        }
+    }
+
+    fn is_touched_by(self, change: &history::Change) -> bool {
+        let targets = change.targets();
+        match self {
+            HistoryEntity::Stop(id) => {
+                targets.contains(&history::ChangeTarget::Stop(id))
+            }
+            HistoryEntity::Route(id) => {
+                targets.contains(&history::ChangeTarget::Route(id))
+            }
+            HistoryEntity::Subroute(id) => {
+                targets.contains(&history::ChangeTarget::Subroute(id))
+                    || targets
+                        .contains(&history::ChangeTarget::SubrouteStops(id))
+            }
+        }
+    }
+}
+
+/// Every changeset touching an entity, oldest first,
+/// stripped of the changes that concern other entities
+pub(crate) async fn entity_history(
+    pool: &PgPool,
+    entity: HistoryEntity,
+) -> Result<Vec<responses::Changeset>, Error> {
+    let mut changesets =
+        sql::fetch_entity_changesets(pool, entity.change_kinds(), entity.id())
+            .await?;
+
+    for changeset in &mut changesets {
+        changeset
+            .changes
+            .retain(|change| entity.is_touched_by(change));
+    }
+    changesets.retain(|changeset| !changeset.changes.is_empty());
+
+    Ok(changesets)
+}
+
+/// The changes made after `at`, latest first
+pub(crate) fn changes_since(
+    changesets: Vec<responses::Changeset>,
+    at: DateTime<Utc>,
+) -> Vec<history::Change> {
+    changesets
+        .into_iter()
+        .rev()
+        .take_while(|changeset| changeset.datetime > at)
+        .flat_map(|changeset| changeset.changes.into_iter().rev())
+        .collect()
+}
+
+// The rewind functions undo `changes` (latest first) over an entity's
+// current state. `None` stands for an entity that does not exist.
+
+pub(crate) fn rewind_stop(
+    stop: Option<stops::Stop>,
+    changes: Vec<history::Change>,
+) -> Result<Option<stops::Stop>, Error> {
+    changes
+        .into_iter()
+        .try_fold(stop, |stop, change| match change {
+            history::Change::StopCreation { .. } => Ok(None),
+            history::Change::StopUpdate { original, patch } => {
+                let inverse = patch.invert(&original);
+                let mut stop = match stop {
+                    Some(stop) => stop,
+                    None => original.try_into()?,
+                };
+                inverse.apply(&mut stop)?;
+                Ok(Some(stop))
+            }
+            history::Change::StopDeletion { data } => {
+                Ok(Some(data.try_into()?))
+            }
+            _ => Ok(stop),
+        })
+}
+
+pub(crate) fn rewind_route(
+    route: Option<routes::Route>,
+    changes: Vec<history::Change>,
+) -> Option<routes::Route> {
+    changes
+        .into_iter()
+        .fold(route, |route, change| match change {
+            history::Change::RouteCreation { .. } => None,
+            history::Change::RouteUpdate { original, patch } => {
+                let inverse = patch.invert(&original);
+                let mut route = route.unwrap_or_else(|| original.into());
+                inverse.apply(&mut route);
+                Some(route)
+            }
+            history::Change::RouteDeletion { data } => Some(data.into()),
+            _ => route,
+        })
+}
+
+pub(crate) fn rewind_subroute(
+    subroute: Option<routes::Subroute>,
+    changes: Vec<history::Change>,
+) -> Result<Option<routes::Subroute>, Error> {
+    changes
+        .into_iter()
+        .try_fold(subroute, |subroute, change| match change {
+            history::Change::SubrouteCreation { .. } => Ok(None),
+            history::Change::SubrouteUpdate { original, patch } => {
+                let inverse = patch.invert(&original);
+                let mut subroute = match subroute {
+                    Some(subroute) => subroute,
+                    None => original.try_into()?,
+                };
+                inverse.apply(&mut subroute)?;
+                Ok(Some(subroute))
+            }
+            history::Change::SubrouteDeletion { subroute, .. } => {
+                Ok(Some(subroute.try_into()?))
+            }
+            // Stop sequences aren't part of the subroute itself
+            _ => Ok(subroute),
+        })
+}
@@ -17,11 +17,13 @@
 */
 
 pub(crate) mod requests {
-    use chrono::NaiveDate;
+    use chrono::{DateTime, NaiveDate, Utc};
     use serde::Deserialize;
 
     use commons::models::{history, pics, stops};
 
+    use crate::routes::models as routes;
+
     #[derive(Deserialize)]
     pub struct NewStopMetaContribution {
         pub contribution: StopMetaContribution,
@@ -209,6 +211,41 @@ pub(crate) mod requests {
         pub stops: Vec<pics::StopAttrs>,
         pub comment: Option<String>,
     }
+
+    #[derive(Deserialize)]
+    pub struct NewRouteContribution {
+        pub contribution: routes::requests::ChangeRoute,
+        pub comment: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct NewSubrouteContribution {
+        pub contribution: routes::requests::ChangeSubroute,
+        pub comment: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct NewSubrouteStopsContribution {
+        pub contribution: routes::requests::ChangeSubrouteStops,
+        pub comment: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct NewDepartureContribution {
+        pub contribution: routes::requests::ChangeDeparture,
+        pub comment: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct NewDeletionContribution {
+        pub comment: Option<String>,
+    }
+
+    #[derive(Deserialize, Default)]
+    pub struct EntityHistory {
+        // Reconstruct the entity as it was at this moment
+        pub at: Option<DateTime<Utc>>,
+    }
 }
 
 pub(crate) mod responses {
@@ -236,6 +273,15 @@ pub(crate) mod responses {
         pub contribution_id: Option<i64>,
     }
 
+    #[derive(Serialize)]
+    #[serde(tag = "type", content = "data")]
+    pub enum EntityHistory<T> {
+        // Every changeset that touched the entity
+        Changesets(Vec<Changeset>),
+        // The entity as it was at the requested moment
+        Snapshot(T),
+    }
+
     #[derive(Debug, Serialize)]
     pub struct Contributor {
         pub id: i32,
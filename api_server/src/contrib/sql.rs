@@ -503,6 +503,140 @@ LIMIT $1 OFFSET $2
     .collect()
 }
 
+// Changesets with at least one change of one of `kinds` over `entity_id`,
+// oldest first. The changesets are returned whole.
+pub(crate) async fn fetch_entity_changesets(
+    pool: &PgPool,
+    kinds: &[&str],
+    entity_id: i32,
+) -> Result<Vec<responses::Changeset>> {
+    sqlx::query!(
+        r#"
+SELECT Changelog.id, Changelog.author_id, Changelog.changes, Changelog.datetime,
+    Changelog.contribution_id, Users.username as author_username
+FROM Changelog
+INNER JOIN Users ON author_id = Users.id
+WHERE EXISTS (
+    SELECT 1
+    FROM jsonb_array_elements(Changelog.changes) AS change,
+        jsonb_each(change) AS variant(kind, body)
+    WHERE variant.kind = ANY($1)
+        AND jsonb_path_exists(
+            variant.body,
+            '$ ? (@.*.id == $id || @.subroute_id == $id)',
+            jsonb_build_object('id', $2::int))
+)
+ORDER BY datetime ASC, Changelog.id ASC
+    "#,
+        kinds,
+        entity_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ?kinds, entity_id);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|r| {
+        Ok(responses::Changeset {
+            id: r.id,
+            author_id: r.author_id,
+            author_username: r.author_username,
+            changes: serde_json::from_value(r.changes).map_err(|e| {
+                tracing::error!("Error deserializing {e}");
+                Error::DatabaseDeserialization
+            })?,
+            datetime: r.datetime.with_timezone(&Local),
+            contribution_id: r.contribution_id,
+        })
+    })
+    .collect()
+}
+
+pub(crate) async fn fetch_changeset(
+    pool: &PgPool,
+    changeset_id: i64,
+) -> Result<Option<history::Changeset>> {
+    let res = sqlx::query!(
+        r#"
+SELECT id, author_id, changes, datetime, contribution_id
+FROM Changelog
+WHERE id=$1
+    "#,
+        changeset_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), changeset_id);
+        Error::DatabaseExecution
+    })?;
+
+    if let Some(r) = res {
+        Ok(Some(history::Changeset {
+            id: r.id,
+            author_id: r.author_id,
+            changes: serde_json::from_value(r.changes).map_err(|e| {
+                tracing::error!("Error deserializing {e}");
+                Error::DatabaseDeserialization
+            })?,
+            datetime: r.datetime.with_timezone(&Local),
+            contribution_id: r.contribution_id,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+// Changesets after `changeset_id` that mention any of `entity_ids` in
+// one of the fields that `history::Change::targets` reads its IDs from.
+// This is a superset of the conflicting changesets; filter them exactly
+// with `targets`.
+pub(crate) async fn fetch_changesets_after(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    changeset_id: i64,
+    entity_ids: &[i32],
+) -> Result<Vec<history::Changeset>> {
+    sqlx::query!(
+        r#"
+SELECT id, author_id, changes, datetime, contribution_id
+FROM Changelog
+WHERE id > $1
+    AND jsonb_path_exists(
+        changes,
+        '$.** ? (@.id == $ids[*] || @.subroute_id == $ids[*]
+            || @.pic_id == $ids[*] || @.cluster_id == $ids[*]
+            || @.type_id == $ids[*] || @.zone_id == $ids[*]
+            || @.operator_id == $ids[*])',
+        jsonb_build_object('ids', $2::int[]))
+ORDER BY id ASC
+    "#,
+        changeset_id,
+        entity_ids
+    )
+    .fetch_all(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), changeset_id, ?entity_ids);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|r| {
+        Ok(history::Changeset {
+            id: r.id,
+            author_id: r.author_id,
+            changes: serde_json::from_value(r.changes).map_err(|e| {
+                tracing::error!("Error deserializing {e}");
+                Error::DatabaseDeserialization
+            })?,
+            datetime: r.datetime.with_timezone(&Local),
+            contribution_id: r.contribution_id,
+        })
+    })
+    .collect()
+}
+
 pub(crate) async fn count_changeset_logs(pool: &PgPool) -> Result<i64> {
     Ok(sqlx::query!(
         r#"
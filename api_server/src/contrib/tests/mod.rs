@@ -2,4 +2,5 @@ mod deverify;
 mod drop_fields;
 mod drop_noops;
 mod patch_application;
+mod route_patches;
 mod stops;
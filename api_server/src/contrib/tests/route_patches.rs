@@ -0,0 +1,295 @@
+use once_cell::sync::Lazy;
+
+use commons::models::history::routes::{
+    DeparturePatch, RoutePatch, SubroutePatch,
+};
+use commons::models::{history, routes};
+
+use crate::contrib::logic;
+use crate::errors::Error;
+
+static ROUTE1: Lazy<routes::Route> = Lazy::new(|| routes::Route {
+    id: 1,
+    type_id: 1,
+    operator_id: 1,
+    code: Some("1".to_string()),
+    name: "name".to_string(),
+    circular: false,
+    active: true,
+    badge_text_color: Some("#ffffff".to_string()),
+    badge_bg_color: Some("#000000".to_string()),
+    main_subroute: None,
+});
+
+static SUBROUTE1: Lazy<routes::Subroute> = Lazy::new(|| routes::Subroute {
+    id: 1,
+    route_id: 1,
+    group: 0,
+    origin: "origin".to_string(),
+    destination: "destination".to_string(),
+    headsign: "headsign".to_string(),
+    via: vec![routes::SubrouteVia {
+        name: "via".to_string(),
+        stops: None,
+    }],
+    circular: false,
+    polyline: None,
+    validation: None,
+    flag: "flag".to_string(),
+});
+
+static DEPARTURE1: Lazy<routes::Departure> = Lazy::new(|| routes::Departure {
+    id: 1,
+    subroute_id: 1,
+    time: 480,
+    calendar_id: 1,
+});
+
+#[test]
+fn route_drops_noops() {
+    let mut patch = RoutePatch {
+        code: Some(Some("1".to_string())),
+        name: Some("name".to_string()),
+        active: Some(true),
+        ..RoutePatch::default()
+    };
+
+    patch.drop_noops(&ROUTE1);
+    assert!(patch.is_empty());
+}
+
+#[test]
+fn route_keeps_cleared_code() {
+    let mut patch = RoutePatch {
+        code: Some(None),
+        ..RoutePatch::default()
+    };
+
+    patch.drop_noops(&ROUTE1);
+    assert!(!patch.is_empty());
+}
+
+#[test]
+fn ok_route_patch_application() {
+    let mut patch = RoutePatch {
+        name: Some("changed".to_string()),
+        active: Some(true),
+        ..RoutePatch::default()
+    };
+
+    let route =
+        logic::accept_route_contribution(ROUTE1.clone(), &mut patch).unwrap();
+
+    assert_eq!(route.name, "changed".to_string());
+    assert!(patch.active.is_none());
+    assert_eq!(route.badge_bg_color, Some("#000000".to_string()));
+}
+
+#[test]
+fn err_repeated_route_patch_application() {
+    let mut patch = RoutePatch {
+        name: Some("changed".to_string()),
+        ..RoutePatch::default()
+    };
+
+    let route =
+        logic::accept_route_contribution(ROUTE1.clone(), &mut patch).unwrap();
+    let error =
+        logic::accept_route_contribution(route, &mut patch).unwrap_err();
+
+    assert_eq!(
+        error,
+        Error::ValidationFailure("Patch no longer does anything".to_string())
+    );
+}
+
+#[test]
+fn subroute_drops_noops() {
+    let mut patch = SubroutePatch {
+        headsign: Some("headsign".to_string()),
+        via: Some(vec![commons::models::history::routes::SubrouteVia {
+            name: "via".to_string(),
+            stops: None,
+        }]),
+        ..SubroutePatch::default()
+    };
+
+    patch.drop_noops(&SUBROUTE1);
+    assert!(patch.is_empty());
+}
+
+#[test]
+fn ok_subroute_patch_application() {
+    let mut patch = SubroutePatch {
+        headsign: Some("changed".to_string()),
+        origin: Some("origin".to_string()),
+        ..SubroutePatch::default()
+    };
+
+    let subroute =
+        logic::accept_subroute_contribution(SUBROUTE1.clone(), &mut patch)
+            .unwrap();
+
+    assert_eq!(subroute.headsign, "changed".to_string());
+    assert!(patch.origin.is_none());
+}
+
+#[test]
+fn err_noop_departure_patch() {
+    let mut patch = DeparturePatch {
+        time: Some(480),
+        calendar_id: Some(1),
+        ..DeparturePatch::default()
+    };
+
+    let error =
+        logic::accept_departure_contribution(DEPARTURE1.clone(), &mut patch)
+            .unwrap_err();
+
+    assert_eq!(
+        error,
+        Error::ValidationFailure("Patch no longer does anything".to_string())
+    );
+}
+
+#[test]
+fn err_departure_subroute_patch() {
+    let mut patch = DeparturePatch {
+        time: Some(500),
+        subroute_id: Some(2),
+        ..DeparturePatch::default()
+    };
+
+    let error =
+        logic::accept_departure_contribution(DEPARTURE1.clone(), &mut patch)
+            .unwrap_err();
+
+    assert_eq!(
+        error,
+        Error::ValidationFailure(
+            "Departures cannot be moved between subroutes".to_string()
+        )
+    );
+}
+
+#[test]
+fn ok_departure_patch_application() {
+    let mut patch = DeparturePatch {
+        time: Some(500),
+        calendar_id: Some(1),
+        ..DeparturePatch::default()
+    };
+
+    let departure =
+        logic::accept_departure_contribution(DEPARTURE1.clone(), &mut patch)
+            .unwrap();
+
+    assert_eq!(departure.time, 500);
+    assert!(patch.calendar_id.is_none());
+}
+
+#[test]
+fn route_patch_inversion() {
+    let patch = RoutePatch {
+        name: Some("changed".to_string()),
+        code: Some(None),
+        ..RoutePatch::default()
+    };
+
+    let mut route = ROUTE1.clone();
+    patch.clone().apply(&mut route);
+    assert_eq!(route.code, None);
+
+    let inverse = patch.invert(&ROUTE1.clone().into());
+    assert!(inverse.active.is_none());
+    inverse.apply(&mut route);
+
+    assert_eq!(route.name, ROUTE1.name);
+    assert_eq!(route.code, ROUTE1.code);
+}
+
+#[test]
+fn departure_patch_inversion() {
+    let patch = DeparturePatch {
+        time: Some(500),
+        ..DeparturePatch::default()
+    };
+
+    let mut departure = DEPARTURE1.clone();
+    patch.clone().apply(&mut departure);
+
+    let inverse = patch.invert(&DEPARTURE1.clone().into());
+    assert!(inverse.calendar_id.is_none());
+    inverse.apply(&mut departure);
+
+    assert_eq!(departure.time, DEPARTURE1.time);
+}
+
+#[test]
+fn route_rewind() {
+    let rename = || {
+        let mut original = ROUTE1.clone();
+        original.name = "old name".to_string();
+        history::Change::RouteUpdate {
+            original: original.into(),
+            patch: RoutePatch {
+                name: Some(ROUTE1.name.clone()),
+                ..RoutePatch::default()
+            },
+        }
+    };
+    let creation = history::Change::RouteCreation {
+        data: ROUTE1.clone().into(),
+    };
+
+    let route =
+        logic::rewind_route(Some(ROUTE1.clone()), vec![rename()]).unwrap();
+    assert_eq!(route.name, "old name");
+    assert_eq!(route.badge_bg_color, ROUTE1.badge_bg_color);
+
+    let route =
+        logic::rewind_route(Some(ROUTE1.clone()), vec![rename(), creation]);
+    assert!(route.is_none());
+}
+
+#[test]
+fn subroute_rewind() {
+    let rename = || {
+        let mut original = SUBROUTE1.clone();
+        original.headsign = "old headsign".to_string();
+        history::Change::SubrouteUpdate {
+            original: original.into(),
+            patch: SubroutePatch {
+                headsign: Some(SUBROUTE1.headsign.clone()),
+                ..SubroutePatch::default()
+            },
+        }
+    };
+    let deletion = history::Change::SubrouteDeletion {
+        subroute: SUBROUTE1.clone().into(),
+        stops: Some(vec![]),
+        departures: Some(vec![]),
+    };
+
+    let subroute =
+        logic::rewind_subroute(Some(SUBROUTE1.clone()), vec![rename()])
+            .unwrap()
+            .unwrap();
+    assert_eq!(subroute.headsign, "old headsign");
+    assert_eq!(subroute.origin, SUBROUTE1.origin);
+
+    // Deleted subroutes come back when rewinding past their deletion
+    let subroute = logic::rewind_subroute(None, vec![deletion, rename()])
+        .unwrap()
+        .unwrap();
+    assert_eq!(subroute.id, SUBROUTE1.id);
+    assert_eq!(subroute.headsign, "old headsign");
+
+    let creation = history::Change::SubrouteCreation {
+        data: SUBROUTE1.clone().into(),
+    };
+    let subroute =
+        logic::rewind_subroute(Some(SUBROUTE1.clone()), vec![creation])
+            .unwrap();
+    assert!(subroute.is_none());
+}
@@ -1,6 +1,8 @@
 use chrono::NaiveDate;
 use once_cell::sync::Lazy;
+use sqlx::PgPool;
 
+use commons::models::history;
 use commons::models::history::opt_vec_into_opt_vec;
 use commons::models::history::stops::StopPatch;
 use commons::models::stops::{
@@ -9,6 +11,8 @@ use commons::models::stops::{
     ParkingVisualLimitation, Schedule, ScheduleType, Stop,
 };
 
+use crate::contrib::{logic, sql};
+use crate::errors::Error;
 use crate::stops::models::requests::ChangeStop;
 
 static STOP1: Lazy<Stop> = Lazy::new(|| Stop {
@@ -1615,3 +1619,105 @@ fn ok_apply_tmp_issues_patch() {
     stop.a11y.tmp_issues = STOP1.a11y.tmp_issues.clone();
     assert_eq!(stop, *STOP1);
 }
+
+#[test]
+fn ok_invert_patch() {
+    let mut stop: Stop = STOP1.clone();
+
+    let patch = StopPatch {
+        name: Some("New name".to_string()),
+        short_name: Some(None),
+        has_bench: Some(Some(false)),
+        tags: Some(vec![]),
+        ..StopPatch::default()
+    };
+
+    assert!(patch.clone().apply(&mut stop).is_ok());
+    assert_ne!(stop, *STOP1);
+
+    let inverse = patch.invert(&STOP1.clone().into());
+    // Only the fields that the patch touched are restored
+    assert!(inverse.street.is_none());
+    assert!(inverse.has_cover.is_none());
+
+    assert!(inverse.apply(&mut stop).is_ok());
+    assert_eq!(stop, *STOP1);
+}
+
+#[test]
+fn ok_stop_rewind() {
+    let rename = || {
+        let mut original = STOP1.clone();
+        original.name = "Older".to_string();
+        history::Change::StopUpdate {
+            original: original.into(),
+            patch: StopPatch {
+                name: Some(STOP1.name.clone()),
+                ..StopPatch::default()
+            },
+        }
+    };
+
+    let stop = logic::rewind_stop(Some(STOP1.clone()), vec![rename()])
+        .unwrap()
+        .unwrap();
+    assert_eq!(stop.name, "Older");
+    assert_eq!(stop.street, STOP1.street);
+
+    // Deleted stops come back when rewinding past their deletion
+    let deletion = history::Change::StopDeletion {
+        data: STOP1.clone().into(),
+    };
+    let stop = logic::rewind_stop(None, vec![deletion, rename()])
+        .unwrap()
+        .unwrap();
+    assert_eq!(stop.id, STOP1.id);
+    assert_eq!(stop.name, "Older");
+
+    let creation = history::Change::StopCreation {
+        data: STOP1.clone().into(),
+    };
+    let stop = logic::rewind_stop(Some(STOP1.clone()), vec![creation]);
+    assert_eq!(stop, Ok(None));
+}
+
+#[sqlx::test(fixtures(path = "../../auth/fixtures", scripts("users")))]
+async fn err_revert_overwritten_changeset(pool: PgPool) {
+    let change = |name: &str| history::Change::StopUpdate {
+        original: STOP1.clone().into(),
+        patch: StopPatch {
+            name: Some(name.to_string()),
+            ..StopPatch::default()
+        },
+    };
+
+    let mut transaction = pool.begin().await.unwrap();
+    let changeset_id = sql::insert_changeset_log(
+        &mut transaction,
+        1,
+        &[change("First name")],
+        None,
+    )
+    .await
+    .unwrap();
+    let later_changeset_id = sql::insert_changeset_log(
+        &mut transaction,
+        2,
+        &[change("Second name")],
+        None,
+    )
+    .await
+    .unwrap();
+    transaction.commit().await.unwrap();
+
+    let error = logic::revert_changeset(&pool, changeset_id, 1)
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        error,
+        Error::ValidationFailure(format!(
+            "Changeset {later_changeset_id} modified the same data afterwards"
+        ))
+    );
+}
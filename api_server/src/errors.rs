@@ -55,6 +55,8 @@ pub enum Error {
     DatabaseExecution,
     #[error("Unable to download an external resource")]
     UpstreamResourceDownload,
+    #[error("Unable to deliver an email")]
+    MailDelivery,
     #[error("Attempted to duplicate resource`")]
     DuplicatedResource(Box<Resource>),
     #[error("The API server state is compromised")]
@@ -75,17 +77,17 @@ impl IntoResponse for Error {
             Error::Forbidden => {
                 JsonErrorResponse::new_response(StatusCode::FORBIDDEN, message)
             }
-            Error::Unauthorized => {
-                JsonErrorResponse::new_response(StatusCode::UNAUTHORIZED, message)
-            }
+            Error::Unauthorized => JsonErrorResponse::new_response(
+                StatusCode::UNAUTHORIZED,
+                message,
+            ),
             Error::DependenciesNotMet => JsonErrorResponse::new_response(
                 StatusCode::FAILED_DEPENDENCY,
                 message,
             ),
-            Error::ValidationFailure(msg) => JsonErrorResponse::new_response(
-                StatusCode::BAD_REQUEST,
-                msg,
-            ),
+            Error::ValidationFailure(msg) => {
+                JsonErrorResponse::new_response(StatusCode::BAD_REQUEST, msg)
+            }
             Error::MalformedRequest(msg) => JsonErrorResponse::new_response(
                 StatusCode::BAD_REQUEST,
                 msg.to_string(),
@@ -108,21 +110,22 @@ impl IntoResponse for Error {
                     )
                 }
             },
-            Error::Processing | Error::UpstreamResourceDownload |
-            Error::Serialization | Error::Filesystem |
-            Error::ModelCompatibility  | Error::IllegalState => {
-                JsonErrorResponse::new_response(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "The server had an internal error".to_string(),
-                )
-            }
+            Error::Processing
+            | Error::UpstreamResourceDownload
+            | Error::Serialization
+            | Error::Filesystem
+            | Error::ModelCompatibility
+            | Error::IllegalState => JsonErrorResponse::new_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "The server had an internal error".to_string(),
+            ),
             Error::ObjectStorageFailure
-            | Error::DatabaseExecution => {
-                JsonErrorResponse::new_response(
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    "The server is unable to complete the request at the moment".to_string(),
-                )
-            }
+            | Error::DatabaseExecution
+            | Error::MailDelivery => JsonErrorResponse::new_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The server is unable to complete the request at the moment"
+                    .to_string(),
+            ),
         }
     }
 }
@@ -0,0 +1,548 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use axum::extract::{Path, State};
+use axum::Json;
+use commons::models::history;
+use futures::future;
+use itertools::Itertools;
+
+use super::models::{requests, responses};
+use super::{logic, sql};
+use crate::responses::IdReturn;
+use crate::{auth, contrib, operators, stops, AppState, Error};
+
+async fn validate_route_types(
+    state: &AppState,
+    operator_id: i32,
+    route_type_ids: &[i32],
+) -> Result<(), Error> {
+    let route_types =
+        operators::sql::fetch_operator_route_types(&state.pool, operator_id)
+            .await?;
+    let is_valid = route_type_ids.iter().all(|type_id| {
+        route_types
+            .iter()
+            .any(|route_type| route_type.id == *type_id)
+    });
+    if !is_valid {
+        return Err(Error::ValidationFailure(
+            "Route type does not belong to the operator".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) async fn get_operator_fare_products(
+    State(state): State<AppState>,
+    Path(operator_id): Path<i32>,
+) -> Result<Json<Vec<responses::FareProduct>>, Error> {
+    Ok(Json(
+        sql::fetch_fare_products(&state.pool, &[operator_id]).await?,
+    ))
+}
+
+pub(crate) async fn post_operator_fare_product(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorMeta,
+    >,
+    Path(operator_id): Path<i32>,
+    Json(product): Json<requests::ChangeFareProduct>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    product.validate()?;
+    validate_route_types(&state, operator_id, &product.route_type_ids).await?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id = sql::insert_fare_product(&mut transaction, operator_id, &product)
+        .await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::FareProductCreation {
+            data: product.to_history(id, operator_id),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn patch_operator_fare_product(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorMeta,
+    >,
+    Path((operator_id, product_id)): Path<(i32, i32)>,
+    Json(product): Json<requests::ChangeFareProduct>,
+) -> Result<(), Error> {
+    product.validate()?;
+    validate_route_types(&state, operator_id, &product.route_type_ids).await?;
+
+    let original = sql::fetch_fare_products(&state.pool, &[operator_id])
+        .await?
+        .into_iter()
+        .find(|product| product.id == product_id)
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::update_fare_product(
+        &mut transaction,
+        operator_id,
+        product_id,
+        &product,
+    )
+    .await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::FareProductUpdate {
+            original: original.into(),
+            product: product.to_history(product_id, operator_id),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn delete_operator_fare_product(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorMeta,
+    >,
+    Path((operator_id, product_id)): Path<(i32, i32)>,
+) -> Result<(), Error> {
+    let product = sql::fetch_fare_products(&state.pool, &[operator_id])
+        .await?
+        .into_iter()
+        .find(|product| product.id == product_id)
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::delete_fare_product(&mut transaction, operator_id, product_id).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::FareProductDeletion {
+            data: product.into(),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_operator_fare_zones(
+    State(state): State<AppState>,
+    Path(operator_id): Path<i32>,
+) -> Result<Json<Vec<responses::FareZone>>, Error> {
+    Ok(Json(sql::fetch_fare_zones(&state.pool, operator_id).await?))
+}
+
+pub(crate) async fn post_operator_fare_zone(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorMeta,
+    >,
+    Path(operator_id): Path<i32>,
+    Json(zone): Json<requests::ChangeFareZone>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    if zone.name.trim().is_empty() {
+        return Err(Error::ValidationFailure("Empty name".to_string()));
+    }
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id =
+        sql::insert_fare_zone(&mut transaction, operator_id, &zone).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::FareZoneCreation {
+            data: zone.to_history(id, operator_id),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn patch_operator_fare_zone(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorMeta,
+    >,
+    Path((operator_id, zone_id)): Path<(i32, i32)>,
+    Json(zone): Json<requests::ChangeFareZone>,
+) -> Result<(), Error> {
+    if zone.name.trim().is_empty() {
+        return Err(Error::ValidationFailure("Empty name".to_string()));
+    }
+
+    let original = sql::fetch_fare_zones(&state.pool, operator_id)
+        .await?
+        .into_iter()
+        .find(|zone| zone.id == zone_id)
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::update_fare_zone(&mut transaction, operator_id, zone_id, &zone)
+        .await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::FareZoneUpdate {
+            original: original.into(),
+            zone: zone.to_history(zone_id, operator_id),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn delete_operator_fare_zone(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorMeta,
+    >,
+    Path((operator_id, zone_id)): Path<(i32, i32)>,
+) -> Result<(), Error> {
+    let mut zone = sql::fetch_fare_zones(&state.pool, operator_id)
+        .await?
+        .into_iter()
+        .find(|zone| zone.id == zone_id)
+        .ok_or(Error::NotFoundUpstream)?;
+    let stops = std::mem::take(&mut zone.stop_ids);
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::delete_fare_zone(&mut transaction, operator_id, zone_id).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::FareZoneDeletion {
+            data: zone.into(),
+            stops,
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn put_operator_fare_zone_stops(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorMeta,
+    >,
+    Path((operator_id, zone_id)): Path<(i32, i32)>,
+    Json(stop_ids): Json<Vec<i32>>,
+) -> Result<(), Error> {
+    let (zones, stops) = future::join(
+        sql::fetch_fare_zones(&state.pool, operator_id),
+        stops::sql::fetch_stop_list(&state.pool, &stop_ids),
+    )
+    .await;
+
+    let original = zones?
+        .into_iter()
+        .find(|zone| zone.id == zone_id)
+        .ok_or(Error::NotFoundUpstream)?
+        .stop_ids;
+    let stops = stops?;
+    if stop_ids
+        .iter()
+        .any(|id| !stops.iter().any(|stop| stop.id == *id))
+    {
+        return Err(Error::ValidationFailure("Unknown stop".to_string()));
+    }
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::update_fare_zone_stops(&mut transaction, zone_id, &stop_ids).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::FareZoneStopsUpdate {
+            zone_id,
+            original,
+            stops: stop_ids,
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_operator_transfer_rules(
+    State(state): State<AppState>,
+    Path(operator_id): Path<i32>,
+) -> Result<Json<Vec<responses::FareTransferRule>>, Error> {
+    Ok(Json(
+        sql::fetch_transfer_rules(&state.pool, &[operator_id]).await?,
+    ))
+}
+
+pub(crate) async fn post_operator_transfer_rule(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorMeta,
+    >,
+    Path(operator_id): Path<i32>,
+    Json(rule): Json<requests::NewFareTransferRule>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    if rule.cost < 0 {
+        return Err(Error::ValidationFailure("Negative cost".to_string()));
+    }
+    validate_route_types(
+        &state,
+        operator_id,
+        &[rule.from_route_type_id, rule.to_route_type_id],
+    )
+    .await?;
+
+    // Rules are unique per route type pair, so this might replace one
+    let original = sql::fetch_transfer_rules(&state.pool, &[operator_id])
+        .await?
+        .into_iter()
+        .find(|existing| {
+            existing.from_route_type_id == rule.from_route_type_id
+                && existing.to_route_type_id == rule.to_route_type_id
+        });
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id =
+        sql::insert_transfer_rule(&mut transaction, operator_id, &rule).await?;
+
+    let change = match original {
+        Some(original) => history::Change::FareTransferRuleUpdate {
+            original: original.into(),
+            rule: rule.to_history(id, operator_id),
+        },
+        None => history::Change::FareTransferRuleCreation {
+            data: rule.to_history(id, operator_id),
+        },
+    };
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[change],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn delete_operator_transfer_rule(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorMeta,
+    >,
+    Path((operator_id, rule_id)): Path<(i32, i32)>,
+) -> Result<(), Error> {
+    let rule = sql::fetch_transfer_rules(&state.pool, &[operator_id])
+        .await?
+        .into_iter()
+        .find(|rule| rule.id == rule_id)
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::delete_transfer_rule(&mut transaction, operator_id, rule_id).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::FareTransferRuleDeletion { data: rule.into() }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn post_fare_quote(
+    State(state): State<AppState>,
+    Json(request): Json<requests::Quote>,
+) -> Result<Json<responses::FareQuote>, Error> {
+    if request.legs.is_empty() {
+        return Err(Error::ValidationFailure("No legs to quote".to_string()));
+    }
+
+    let route_ids = request
+        .legs
+        .iter()
+        .map(|leg| leg.route_id)
+        .unique()
+        .collect_vec();
+    let stop_ids = request
+        .legs
+        .iter()
+        .flat_map(|leg| [leg.from_stop_id, leg.to_stop_id])
+        .unique()
+        .collect_vec();
+
+    let (fares, route_stops, zones) = future::join3(
+        sql::fetch_route_fares(&state.pool, &route_ids),
+        sql::fetch_routes_subroute_stops(&state.pool, &route_ids),
+        sql::fetch_stop_zones(&state.pool, &stop_ids),
+    )
+    .await;
+    let (fares, route_stops) = (fares?, route_stops?);
+
+    let legs = request
+        .legs
+        .iter()
+        .map(|leg| {
+            let fare = fares
+                .iter()
+                .find(|fare| fare.route_id == leg.route_id)
+                .ok_or_else(|| {
+                    Error::ValidationFailure(format!(
+                        "Unknown route {}",
+                        leg.route_id
+                    ))
+                })?;
+            let subroutes = route_stops
+                .get(&leg.route_id)
+                .map_or(&[][..], Vec::as_slice);
+            if !logic::rides_between(
+                subroutes,
+                leg.from_stop_id,
+                leg.to_stop_id,
+            ) {
+                return Err(Error::ValidationFailure(format!(
+                    "Route {} does not go from stop {} to stop {}",
+                    leg.route_id, leg.from_stop_id, leg.to_stop_id
+                )));
+            }
+            Ok(logic::Leg {
+                fare: fare.clone(),
+                from_stop_id: leg.from_stop_id,
+                to_stop_id: leg.to_stop_id,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let operator_ids = fares
+        .iter()
+        .map(|fare| fare.operator_id)
+        .unique()
+        .collect_vec();
+    let (products, transfers) = future::join(
+        sql::fetch_fare_products(&state.pool, &operator_ids),
+        sql::fetch_transfer_rules(&state.pool, &operator_ids),
+    )
+    .await;
+
+    Ok(Json(logic::quote(&legs, &products?, &transfers?, &zones?)))
+}
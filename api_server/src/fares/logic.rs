@@ -0,0 +1,288 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use super::models::{responses, FareProductKind, RouteFare};
+use crate::routes::models::responses::SubrouteStops;
+
+/// A zone that a stop belongs to
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_field_names)]
+pub(crate) struct StopZone {
+    pub(crate) stop_id: i32,
+    pub(crate) zone_id: i32,
+    pub(crate) operator_id: i32,
+}
+
+/// A leg of the journey that is being quoted
+#[derive(Debug, Clone)]
+pub(crate) struct Leg {
+    pub(crate) fare: RouteFare,
+    pub(crate) from_stop_id: i32,
+    pub(crate) to_stop_id: i32,
+}
+
+/// Whether any of the subroutes goes through both stops, in that order
+pub(crate) fn rides_between(
+    subroutes: &[SubrouteStops],
+    from_stop_id: i32,
+    to_stop_id: i32,
+) -> bool {
+    subroutes.iter().any(|subroute| {
+        let boarding =
+            subroute.stops.iter().position(|stop| *stop == from_stop_id);
+        let alighting =
+            subroute.stops.iter().rposition(|stop| *stop == to_stop_id);
+        matches!((boarding, alighting), (Some(from), Some(to)) if from < to)
+    })
+}
+
+/// Number of zones that a ride between two stops goes through.
+/// Zones have no known order, so rides either stay within a zone
+/// or go through two of them.
+fn zones_crossed(leg: &Leg, zones: &[StopZone]) -> Option<i32> {
+    let stop_zones = |stop_id: i32| {
+        zones
+            .iter()
+            .filter(|zone| {
+                zone.stop_id == stop_id
+                    && zone.operator_id == leg.fare.operator_id
+            })
+            .map(|zone| zone.zone_id)
+            .collect::<Vec<_>>()
+    };
+    let boarding = stop_zones(leg.from_stop_id);
+    let alighting = stop_zones(leg.to_stop_id);
+
+    if boarding.is_empty() || alighting.is_empty() {
+        None
+    } else if boarding.iter().any(|zone| alighting.contains(zone)) {
+        Some(1)
+    } else {
+        Some(2)
+    }
+}
+
+/// Cheapest way to pay for a leg on its own, along with the product used
+fn leg_price(
+    leg: &Leg,
+    products: &[responses::FareProduct],
+    zones: &[StopZone],
+) -> (Option<i32>, i32) {
+    let fare = &leg.fare;
+    let covering = products
+        .iter()
+        .filter(|product| product.covers(fare.operator_id, fare.route_type_id));
+
+    let product_prices = covering.filter_map(|product| match product.kind {
+        FareProductKind::Single => Some((Some(product.id), product.price)),
+        FareProductKind::Zone => {
+            let zones = zones_crossed(leg, zones)?;
+            let zone_price = product.zone_price.unwrap_or(0);
+            Some((Some(product.id), product.price + zone_price * (zones - 1)))
+        }
+        FareProductKind::Pass => None,
+    });
+
+    std::iter::once((None, fare.zapping_cost))
+        .chain(product_prices)
+        .min_by_key(|(_, price)| *price)
+        .unwrap_or((None, fare.zapping_cost))
+}
+
+pub(crate) fn quote(
+    legs: &[Leg],
+    products: &[responses::FareProduct],
+    transfers: &[responses::FareTransferRule],
+    zones: &[StopZone],
+) -> responses::FareQuote {
+    let mut quoted = Vec::with_capacity(legs.len());
+    let mut previous: Option<&RouteFare> = None;
+
+    for leg in legs {
+        let fare = &leg.fare;
+        let (mut product_id, mut price) = leg_price(leg, products, zones);
+        let mut transfer = false;
+
+        let transfer_cost = previous
+            .filter(|prev| prev.operator_id == fare.operator_id)
+            .and_then(|prev| {
+                if fare.multi_trip && prev.route_type_id == fare.route_type_id {
+                    return Some(0);
+                }
+                transfers
+                    .iter()
+                    .find(|rule| {
+                        rule.from_route_type_id == prev.route_type_id
+                            && rule.to_route_type_id == fare.route_type_id
+                    })
+                    .map(|rule| rule.cost)
+            });
+        if let Some(cost) = transfer_cost {
+            if cost < price {
+                price = cost;
+                product_id = None;
+                transfer = true;
+            }
+        }
+
+        quoted.push(responses::LegQuote {
+            route_id: fare.route_id,
+            operator_id: fare.operator_id,
+            route_type_id: fare.route_type_id,
+            board_cost: fare.board_cost,
+            product_id,
+            price,
+            transfer,
+        });
+        previous = Some(fare);
+    }
+
+    let passes = products
+        .iter()
+        .filter(|product| {
+            product.kind == FareProductKind::Pass
+                && legs.iter().all(|leg| {
+                    product.covers(leg.fare.operator_id, leg.fare.route_type_id)
+                })
+        })
+        .cloned()
+        .collect();
+
+    responses::FareQuote {
+        total: quoted.iter().map(|leg| leg.price).sum(),
+        legs: quoted,
+        passes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leg(route_id: i32, route_type_id: i32, multi_trip: bool) -> Leg {
+        Leg {
+            fare: RouteFare {
+                route_id,
+                operator_id: 1,
+                route_type_id,
+                board_cost: 200,
+                zapping_cost: 150,
+                multi_trip,
+            },
+            from_stop_id: 1,
+            to_stop_id: 2,
+        }
+    }
+
+    fn product(
+        id: i32,
+        kind: FareProductKind,
+        price: i32,
+        zone_price: Option<i32>,
+    ) -> responses::FareProduct {
+        responses::FareProduct {
+            id,
+            operator_id: 1,
+            name: String::new(),
+            kind,
+            price,
+            zone_price,
+            route_type_ids: vec![],
+        }
+    }
+
+    #[test]
+    fn route_type_fare_by_default() {
+        let quote = quote(&[leg(1, 1, false)], &[], &[], &[]);
+        assert_eq!(quote.total, 150);
+        assert_eq!(quote.legs[0].board_cost, 200);
+        assert!(quote.legs[0].product_id.is_none());
+    }
+
+    #[test]
+    fn cheapest_product_wins() {
+        let products = [
+            product(1, FareProductKind::Single, 120, None),
+            product(2, FareProductKind::Zone, 100, Some(50)),
+        ];
+        let zones = [
+            StopZone {
+                stop_id: 1,
+                zone_id: 1,
+                operator_id: 1,
+            },
+            StopZone {
+                stop_id: 2,
+                zone_id: 2,
+                operator_id: 1,
+            },
+        ];
+
+        let quote = quote(&[leg(1, 1, false)], &products, &[], &zones);
+        assert_eq!(quote.legs[0].product_id, Some(1));
+        assert_eq!(quote.total, 120);
+    }
+
+    #[test]
+    fn transfers_apply_between_legs() {
+        let transfers = [responses::FareTransferRule {
+            id: 1,
+            operator_id: 1,
+            from_route_type_id: 1,
+            to_route_type_id: 2,
+            cost: 30,
+        }];
+        let legs = [leg(1, 1, false), leg(2, 2, false), leg(3, 2, true)];
+
+        let quote = quote(&legs, &[], &transfers, &[]);
+        let prices = quote.legs.iter().map(|l| l.price).collect::<Vec<_>>();
+        assert_eq!(prices, vec![150, 30, 0]);
+        assert_eq!(quote.total, 180);
+    }
+
+    #[test]
+    fn passes_must_cover_every_leg() {
+        let mut partial = product(2, FareProductKind::Pass, 4000, None);
+        partial.route_type_ids = vec![1];
+        let products = [product(1, FareProductKind::Pass, 3000, None), partial];
+
+        let quote =
+            quote(&[leg(1, 1, false), leg(2, 2, false)], &products, &[], &[]);
+        assert_eq!(quote.passes.len(), 1);
+        assert_eq!(quote.passes[0].id, 1);
+    }
+
+    #[test]
+    fn rides_follow_the_subroute_order() {
+        let subroutes = [
+            SubrouteStops {
+                subroute: 1,
+                stops: vec![1, 2, 3],
+            },
+            SubrouteStops {
+                subroute: 2,
+                stops: vec![4, 5],
+            },
+        ];
+        assert!(rides_between(&subroutes, 1, 3));
+        assert!(!rides_between(&subroutes, 3, 1));
+        assert!(!rides_between(&subroutes, 1, 5));
+        assert!(!rides_between(&subroutes, 2, 2));
+        assert!(!rides_between(&subroutes, 6, 1));
+    }
+}
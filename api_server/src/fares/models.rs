@@ -0,0 +1,292 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use commons::models::history;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::Error;
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize_repr, Deserialize_repr)]
+pub enum FareProductKind {
+    // Valid for a single ride
+    Single = 0,
+    // Valid for an unlimited number of rides over a period
+    Pass = 1,
+    // Priced by the number of zones that a ride goes through
+    Zone = 2,
+}
+
+impl TryFrom<i16> for FareProductKind {
+    type Error = Error;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Single),
+            1 => Ok(Self::Pass),
+            2 => Ok(Self::Zone),
+            _ => Err(Error::DatabaseDeserialization),
+        }
+    }
+}
+
+impl From<FareProductKind> for i16 {
+    fn from(kind: FareProductKind) -> Self {
+        i16::from(kind as u8)
+    }
+}
+
+impl From<FareProductKind> for history::fares::FareProductKind {
+    fn from(kind: FareProductKind) -> Self {
+        match kind {
+            FareProductKind::Single => Self::Single,
+            FareProductKind::Pass => Self::Pass,
+            FareProductKind::Zone => Self::Zone,
+        }
+    }
+}
+
+/// The costs that an operator defines for one of its route types
+#[derive(Debug, Clone)]
+pub(crate) struct RouteFare {
+    pub(crate) route_id: i32,
+    pub(crate) operator_id: i32,
+    pub(crate) route_type_id: i32,
+    pub(crate) board_cost: i32,
+    pub(crate) zapping_cost: i32,
+    pub(crate) multi_trip: bool,
+}
+
+pub(crate) mod requests {
+    use commons::models::history;
+    use serde::Deserialize;
+
+    use super::FareProductKind;
+    use crate::Error;
+
+    #[derive(Debug, Deserialize)]
+    pub struct ChangeFareProduct {
+        pub name: String,
+        pub kind: FareProductKind,
+        pub price: i32,
+        pub zone_price: Option<i32>,
+        #[serde(default)]
+        pub route_type_ids: Vec<i32>,
+    }
+
+    impl ChangeFareProduct {
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            if self.name.trim().is_empty() {
+                return Err(Error::ValidationFailure("Empty name".to_string()));
+            }
+            if self.price < 0 || self.zone_price.is_some_and(|p| p < 0) {
+                return Err(Error::ValidationFailure(
+                    "Negative price".to_string(),
+                ));
+            }
+            if self.kind == FareProductKind::Zone && self.zone_price.is_none() {
+                return Err(Error::ValidationFailure(
+                    "Zone products need a zone price".to_string(),
+                ));
+            }
+            if self.kind != FareProductKind::Zone && self.zone_price.is_some() {
+                return Err(Error::ValidationFailure(
+                    "Only zone products have a zone price".to_string(),
+                ));
+            }
+            Ok(())
+        }
+
+        pub(crate) fn to_history(
+            &self,
+            id: i32,
+            operator_id: i32,
+        ) -> history::fares::FareProduct {
+            history::fares::FareProduct {
+                id,
+                operator_id,
+                name: self.name.clone(),
+                kind: self.kind.into(),
+                price: self.price,
+                zone_price: self.zone_price,
+                route_type_ids: self.route_type_ids.clone(),
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ChangeFareZone {
+        pub name: String,
+    }
+
+    impl ChangeFareZone {
+        pub(crate) fn to_history(
+            &self,
+            id: i32,
+            operator_id: i32,
+        ) -> history::fares::FareZone {
+            history::fares::FareZone {
+                id,
+                operator_id,
+                name: self.name.clone(),
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct NewFareTransferRule {
+        pub from_route_type_id: i32,
+        pub to_route_type_id: i32,
+        pub cost: i32,
+    }
+
+    impl NewFareTransferRule {
+        pub(crate) fn to_history(
+            &self,
+            id: i32,
+            operator_id: i32,
+        ) -> history::fares::FareTransferRule {
+            history::fares::FareTransferRule {
+                id,
+                operator_id,
+                from_route_type_id: self.from_route_type_id,
+                to_route_type_id: self.to_route_type_id,
+                cost: self.cost,
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(clippy::struct_field_names)]
+    pub struct QuoteLeg {
+        pub route_id: i32,
+        pub from_stop_id: i32,
+        pub to_stop_id: i32,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Quote {
+        pub legs: Vec<QuoteLeg>,
+    }
+}
+
+pub(crate) mod responses {
+    use commons::models::history;
+    use serde::Serialize;
+
+    use super::FareProductKind;
+
+    #[derive(Debug, Clone, Serialize)]
+    pub struct FareProduct {
+        pub id: i32,
+        pub operator_id: i32,
+        pub name: String,
+        pub kind: FareProductKind,
+        pub price: i32,
+        pub zone_price: Option<i32>,
+        pub route_type_ids: Vec<i32>,
+    }
+
+    impl FareProduct {
+        pub(crate) fn covers(
+            &self,
+            operator_id: i32,
+            route_type_id: i32,
+        ) -> bool {
+            self.operator_id == operator_id
+                && (self.route_type_ids.is_empty()
+                    || self.route_type_ids.contains(&route_type_id))
+        }
+    }
+
+    impl From<FareProduct> for history::fares::FareProduct {
+        fn from(product: FareProduct) -> Self {
+            history::fares::FareProduct {
+                id: product.id,
+                operator_id: product.operator_id,
+                name: product.name,
+                kind: product.kind.into(),
+                price: product.price,
+                zone_price: product.zone_price,
+                route_type_ids: product.route_type_ids,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct FareZone {
+        pub id: i32,
+        pub operator_id: i32,
+        pub name: String,
+        pub stop_ids: Vec<i32>,
+    }
+
+    impl From<FareZone> for history::fares::FareZone {
+        fn from(zone: FareZone) -> Self {
+            history::fares::FareZone {
+                id: zone.id,
+                operator_id: zone.operator_id,
+                name: zone.name,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub struct FareTransferRule {
+        pub id: i32,
+        pub operator_id: i32,
+        pub from_route_type_id: i32,
+        pub to_route_type_id: i32,
+        pub cost: i32,
+    }
+
+    impl From<FareTransferRule> for history::fares::FareTransferRule {
+        fn from(rule: FareTransferRule) -> Self {
+            history::fares::FareTransferRule {
+                id: rule.id,
+                operator_id: rule.operator_id,
+                from_route_type_id: rule.from_route_type_id,
+                to_route_type_id: rule.to_route_type_id,
+                cost: rule.cost,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct LegQuote {
+        pub route_id: i32,
+        pub operator_id: i32,
+        pub route_type_id: i32,
+        // Cost when buying the ticket on board
+        pub board_cost: i32,
+        // Cheapest cost with the products that the operator sells.
+        // `None` when the route type fare was the cheapest option
+        pub product_id: Option<i32>,
+        pub price: i32,
+        // Whether the price comes from a transfer with the previous leg
+        pub transfer: bool,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct FareQuote {
+        pub legs: Vec<LegQuote>,
+        pub total: i32,
+        // Passes that are valid for every leg
+        pub passes: Vec<FareProduct>,
+    }
+}
@@ -0,0 +1,453 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use sqlx::PgPool;
+
+use super::logic::StopZone;
+use super::models::{requests, responses, FareProductKind, RouteFare};
+use crate::routes::models::responses::SubrouteStops;
+use crate::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+pub(crate) async fn fetch_route_fares(
+    pool: &PgPool,
+    route_ids: &[i32],
+) -> Result<Vec<RouteFare>> {
+    sqlx::query_as!(
+        RouteFare,
+        r#"
+SELECT routes.id as route_id, routes.operator as operator_id,
+    route_types.id as route_type_id, route_types.board_cost,
+    route_types.zapping_cost, route_types.multi_trip
+FROM routes
+JOIN route_types ON routes.type = route_types.id
+WHERE routes.id = ANY($1)
+"#,
+        route_ids
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ?route_ids);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_routes_subroute_stops(
+    pool: &PgPool,
+    route_ids: &[i32],
+) -> Result<HashMap<i32, Vec<SubrouteStops>>> {
+    let res = sqlx::query!(
+        r#"
+SELECT subroutes.route, subroutes.id as subroute, subroute_stops.stop
+FROM subroutes
+JOIN subroute_stops ON subroute_stops.subroute = subroutes.id
+WHERE subroutes.route = ANY($1)
+ORDER BY subroutes.id ASC, subroute_stops.idx ASC
+"#,
+        route_ids
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ?route_ids);
+        Error::DatabaseExecution
+    })?;
+
+    let mut route_stops: HashMap<i32, Vec<SubrouteStops>> = HashMap::new();
+    for ((route, subroute), group) in
+        &res.into_iter().chunk_by(|row| (row.route, row.subroute))
+    {
+        route_stops.entry(route).or_default().push(SubrouteStops {
+            subroute,
+            stops: group.map(|row| row.stop).collect(),
+        });
+    }
+    Ok(route_stops)
+}
+
+pub(crate) async fn fetch_stop_zones(
+    pool: &PgPool,
+    stop_ids: &[i32],
+) -> Result<Vec<StopZone>> {
+    sqlx::query_as!(
+        StopZone,
+        r#"
+SELECT fare_zone_stops.stop_id, fare_zone_stops.zone_id, fare_zones.operator_id
+FROM fare_zone_stops
+JOIN fare_zones ON fare_zones.id = fare_zone_stops.zone_id
+WHERE fare_zone_stops.stop_id = ANY($1)
+"#,
+        stop_ids
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ?stop_ids);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_fare_products(
+    pool: &PgPool,
+    operator_ids: &[i32],
+) -> Result<Vec<responses::FareProduct>> {
+    sqlx::query!(
+        r#"
+SELECT id, operator_id, name, kind, price, zone_price, route_type_ids
+FROM fare_products
+WHERE operator_id = ANY($1)
+ORDER BY id
+"#,
+        operator_ids
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ?operator_ids);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| {
+        Ok(responses::FareProduct {
+            id: row.id,
+            operator_id: row.operator_id,
+            name: row.name,
+            kind: FareProductKind::try_from(row.kind)?,
+            price: row.price,
+            zone_price: row.zone_price,
+            route_type_ids: row.route_type_ids,
+        })
+    })
+    .collect()
+}
+
+pub(crate) async fn insert_fare_product(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+    product: &requests::ChangeFareProduct,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO fare_products(operator_id, name, kind, price, zone_price,
+    route_type_ids)
+VALUES ($1, $2, $3, $4, $5, $6)
+RETURNING id
+"#,
+        operator_id,
+        product.name,
+        i16::from(product.kind),
+        product.price,
+        product.zone_price,
+        &product.route_type_ids
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, ?product);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn update_fare_product(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+    product_id: i32,
+    product: &requests::ChangeFareProduct,
+) -> Result<()> {
+    let res = sqlx::query!(
+        r#"
+UPDATE fare_products
+SET name = $3, kind = $4, price = $5, zone_price = $6, route_type_ids = $7
+WHERE operator_id = $1 AND id = $2
+"#,
+        operator_id,
+        product_id,
+        product.name,
+        i16::from(product.kind),
+        product.price,
+        product.zone_price,
+        &product.route_type_ids
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            error = err.to_string(),
+            operator_id,
+            product_id,
+            ?product
+        );
+        Error::DatabaseExecution
+    })?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::NotFoundUpstream);
+    }
+    Ok(())
+}
+
+pub(crate) async fn delete_fare_product(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+    product_id: i32,
+) -> Result<()> {
+    let res = sqlx::query!(
+        r#"
+DELETE FROM fare_products
+WHERE operator_id = $1 AND id = $2
+"#,
+        operator_id,
+        product_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, product_id);
+        Error::DatabaseExecution
+    })?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::NotFoundUpstream);
+    }
+    Ok(())
+}
+
+pub(crate) async fn fetch_fare_zones(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Vec<responses::FareZone>> {
+    sqlx::query_as!(
+        responses::FareZone,
+        r#"
+SELECT fare_zones.id, fare_zones.operator_id, fare_zones.name,
+    array_remove(array_agg(fare_zone_stops.stop_id), NULL) as "stop_ids!: Vec<i32>"
+FROM fare_zones
+LEFT JOIN fare_zone_stops ON fare_zone_stops.zone_id = fare_zones.id
+WHERE fare_zones.operator_id = $1
+GROUP BY fare_zones.id
+ORDER BY fare_zones.id
+"#,
+        operator_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn insert_fare_zone(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+    zone: &requests::ChangeFareZone,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO fare_zones(operator_id, name)
+VALUES ($1, $2)
+RETURNING id
+"#,
+        operator_id,
+        zone.name
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, ?zone);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn update_fare_zone(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+    zone_id: i32,
+    zone: &requests::ChangeFareZone,
+) -> Result<()> {
+    let res = sqlx::query!(
+        r#"
+UPDATE fare_zones
+SET name = $3
+WHERE operator_id = $1 AND id = $2
+"#,
+        operator_id,
+        zone_id,
+        zone.name
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, zone_id, ?zone);
+        Error::DatabaseExecution
+    })?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::NotFoundUpstream);
+    }
+    Ok(())
+}
+
+pub(crate) async fn delete_fare_zone(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+    zone_id: i32,
+) -> Result<()> {
+    let res = sqlx::query!(
+        r#"
+DELETE FROM fare_zones
+WHERE operator_id = $1 AND id = $2
+"#,
+        operator_id,
+        zone_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, zone_id);
+        Error::DatabaseExecution
+    })?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::NotFoundUpstream);
+    }
+    Ok(())
+}
+
+pub(crate) async fn update_fare_zone_stops(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    zone_id: i32,
+    stop_ids: &[i32],
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+DELETE FROM fare_zone_stops
+WHERE zone_id = $1
+"#,
+        zone_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), zone_id);
+        Error::DatabaseExecution
+    })?;
+
+    sqlx::query!(
+        r#"
+INSERT INTO fare_zone_stops(zone_id, stop_id)
+SELECT $1, unnest($2::int[])
+"#,
+        zone_id,
+        stop_ids
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), zone_id, ?stop_ids);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn fetch_transfer_rules(
+    pool: &PgPool,
+    operator_ids: &[i32],
+) -> Result<Vec<responses::FareTransferRule>> {
+    sqlx::query_as!(
+        responses::FareTransferRule,
+        r#"
+SELECT id, operator_id, from_route_type_id, to_route_type_id, cost
+FROM fare_transfer_rules
+WHERE operator_id = ANY($1)
+ORDER BY id
+"#,
+        operator_ids
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ?operator_ids);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn insert_transfer_rule(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+    rule: &requests::NewFareTransferRule,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO fare_transfer_rules(operator_id, from_route_type_id,
+    to_route_type_id, cost)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (from_route_type_id, to_route_type_id)
+DO UPDATE SET cost = EXCLUDED.cost
+RETURNING id
+"#,
+        operator_id,
+        rule.from_route_type_id,
+        rule.to_route_type_id,
+        rule.cost
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, ?rule);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn delete_transfer_rule(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+    rule_id: i32,
+) -> Result<()> {
+    let res = sqlx::query!(
+        r#"
+DELETE FROM fare_transfer_rules
+WHERE operator_id = $1 AND id = $2
+"#,
+        operator_id,
+        rule_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, rule_id);
+        Error::DatabaseExecution
+    })?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::NotFoundUpstream);
+    }
+    Ok(())
+}
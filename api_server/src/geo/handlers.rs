@@ -16,17 +16,33 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::Json;
+use axum_client_ip::SecureClientIp;
 use futures::future;
+use serde::Deserialize;
 
+use commons::models::auth::AuditLogAction;
 use commons::models::geo;
 
-use super::{models::responses, sql};
+use super::models::{requests, responses};
+use super::{logic, sql};
 use crate::auth;
 use crate::operators::sql as operators_sql;
+use crate::responses::IdReturn;
 use crate::{AppState, Error};
 
+#[derive(Deserialize, Default)]
+pub(crate) struct HolidayFilter {
+    region: Option<i32>,
+    municipality: Option<i32>,
+}
+
+#[derive(Deserialize, Default)]
+pub(crate) struct SchoolPeriodFilter {
+    region: Option<i32>,
+}
+
 pub(crate) async fn get_regions(
     State(state): State<AppState>,
 ) -> Result<Json<Vec<geo::Region>>, Error> {
@@ -64,6 +80,86 @@ pub(crate) async fn get_region(
     Ok(Json(region_with_operators))
 }
 
+pub(crate) async fn post_region(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::CreateRegion>,
+    Json(mut region): Json<requests::ChangeRegion>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    region.validate()?;
+    let view = logic::map_view(&region.geometry);
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id = sql::insert_region(&mut transaction, &region, &view).await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn patch_region(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ModifyRegion>,
+    Path(region_id): Path<i32>,
+    Json(mut region): Json<requests::ChangeRegion>,
+) -> Result<(), Error> {
+    region.validate()?;
+    let view = logic::map_view(&region.geometry);
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let updated =
+        sql::update_region(&mut transaction, region_id, &region, &view).await?;
+    if !updated {
+        return Err(Error::NotFoundUpstream);
+    }
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn delete_region(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<auth::perms::DeleteRegion>,
+    client_ip: SecureClientIp,
+    Path(region_id): Path<i32>,
+) -> Result<(), Error> {
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    // The deletion cascades into everything tied to the region,
+    // so it is kept on record
+    let name = sql::delete_region(&mut transaction, region_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+    auth::insert_audit_log_entry(
+        &mut transaction,
+        AuditLogAction::RegionDeletion { region_id, name },
+        claims.uid,
+        Some(claims.jti),
+        &client_ip.0.into(),
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
+
 pub(crate) async fn get_operator_regions(
     State(state): State<AppState>,
     Path(operator_id): Path<i32>,
@@ -208,6 +304,71 @@ pub(crate) async fn get_parishes(
     Ok(Json(sql::fetch_parishes(&state.pool, region_id).await?))
 }
 
+pub(crate) async fn post_parish(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::CreateRegion>,
+    Json(mut parish): Json<requests::ChangeParish>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    parish.validate()?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id = sql::insert_parish(&mut transaction, &parish).await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn patch_parish(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ModifyRegion>,
+    Path(parish_id): Path<i32>,
+    Json(mut parish): Json<requests::ChangeParish>,
+) -> Result<(), Error> {
+    parish.validate()?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    if !sql::update_parish(&mut transaction, parish_id, &parish).await? {
+        return Err(Error::NotFoundUpstream);
+    }
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn delete_parish(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::DeleteRegion>,
+    Path(parish_id): Path<i32>,
+) -> Result<(), Error> {
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    if !sql::delete_parish(&mut transaction, parish_id).await? {
+        return Err(Error::NotFoundUpstream);
+    }
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
+
 pub(crate) async fn put_stop_parish(
     State(state): State<AppState>,
     Path((stop_id, parish_id)): Path<(i32, i32)>,
@@ -216,3 +377,122 @@ pub(crate) async fn put_stop_parish(
     sql::update_stop_parish(&state.pool, stop_id, parish_id).await?;
     Ok(())
 }
+
+pub(crate) async fn post_reconcile_divisions(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ManageJobs>,
+) -> Result<Json<responses::DivisionReconciliation>, Error> {
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let summary =
+        logic::reconcile_divisions(&state.pool, &mut transaction).await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(summary))
+}
+
+pub(crate) async fn get_holidays(
+    State(state): State<AppState>,
+    filter: Query<HolidayFilter>,
+) -> Result<Json<Vec<responses::Holiday>>, Error> {
+    Ok(Json(
+        sql::fetch_holidays(&state.pool, filter.region, filter.municipality)
+            .await?,
+    ))
+}
+
+pub(crate) async fn post_holiday(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ModifyRegion>,
+    Json(holiday): Json<requests::NewHoliday>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    holiday.validate()?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id = sql::insert_holiday(&mut transaction, &holiday).await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn delete_holiday(
+    State(state): State<AppState>,
+    Path(holiday_id): Path<i32>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ModifyRegion>,
+) -> Result<(), Error> {
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::delete_holiday(&mut transaction, holiday_id).await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn get_school_periods(
+    State(state): State<AppState>,
+    filter: Query<SchoolPeriodFilter>,
+) -> Result<Json<Vec<responses::SchoolPeriod>>, Error> {
+    Ok(Json(
+        sql::fetch_school_periods(&state.pool, filter.region).await?,
+    ))
+}
+
+pub(crate) async fn post_school_period(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ModifyRegion>,
+    Json(period): Json<requests::NewSchoolPeriod>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    period.validate()?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id = sql::insert_school_period(&mut transaction, &period).await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn delete_school_period(
+    State(state): State<AppState>,
+    Path(period_id): Path<i32>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ModifyRegion>,
+) -> Result<(), Error> {
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::delete_school_period(&mut transaction, period_id).await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
@@ -0,0 +1,611 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+use sqlx::PgPool;
+
+use commons::models::geo::GeojsonGeometry;
+
+use super::models::responses;
+use super::sql;
+use crate::Error;
+
+/// Outline of a region or of a parish, as a set of polygons
+pub(crate) struct Division {
+    pub(crate) id: i32,
+    polygons: Vec<Polygon>,
+}
+
+struct Polygon {
+    // The outer ring followed by its holes, as (lon, lat) pairs
+    rings: Vec<Vec<(f64, f64)>>,
+    // min lon, min lat, max lon, max lat
+    bbox: [f64; 4],
+}
+
+impl Division {
+    /// Parses either a bare geometry or a feature wrapping one
+    pub(crate) fn from_geojson(id: i32, geojson: Value) -> Option<Self> {
+        let geometry = match geojson {
+            Value::Object(mut feature) if feature.contains_key("geometry") => {
+                feature.remove("geometry")?
+            }
+            geometry => geometry,
+        };
+        let polygons = match serde_json::from_value(geometry).ok()? {
+            GeojsonGeometry::Polygon { coordinates } => vec![coordinates],
+            GeojsonGeometry::MultiPolygon { coordinates } => coordinates,
+        }
+        .into_iter()
+        .filter_map(Polygon::from_coords)
+        .collect::<Vec<_>>();
+
+        if polygons.is_empty() {
+            None
+        } else {
+            Some(Self { id, polygons })
+        }
+    }
+
+    pub(crate) fn contains(&self, lon: f64, lat: f64) -> bool {
+        self.polygons
+            .iter()
+            .any(|polygon| polygon.contains(lon, lat))
+    }
+}
+
+impl Polygon {
+    fn from_coords(coordinates: Vec<Vec<Vec<f64>>>) -> Option<Self> {
+        let rings = coordinates
+            .into_iter()
+            .map(|ring| {
+                ring.into_iter()
+                    .map(|point| match point[..] {
+                        [lon, lat, ..] => Some((lon, lat)),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let outer = rings.first().filter(|ring| ring.len() >= 3)?;
+        let bbox = outer.iter().fold(
+            [f64::MAX, f64::MAX, f64::MIN, f64::MIN],
+            |[min_lon, min_lat, max_lon, max_lat], &(lon, lat)| {
+                [
+                    min_lon.min(lon),
+                    min_lat.min(lat),
+                    max_lon.max(lon),
+                    max_lat.max(lat),
+                ]
+            },
+        );
+
+        Some(Self { rings, bbox })
+    }
+
+    fn contains(&self, lon: f64, lat: f64) -> bool {
+        let [min_lon, min_lat, max_lon, max_lat] = self.bbox;
+        if lon < min_lon || lon > max_lon || lat < min_lat || lat > max_lat {
+            return false;
+        }
+
+        // Even-odd rule; a point within a hole is within two rings
+        self.rings
+            .iter()
+            .filter(|ring| ring_contains(ring, lon, lat))
+            .count()
+            % 2
+            == 1
+    }
+}
+
+// Ray casting towards increasing longitudes
+pub(crate) fn ring_contains(ring: &[(f64, f64)], lon: f64, lat: f64) -> bool {
+    let Some(mut prev) = ring.last().copied() else {
+        return false;
+    };
+
+    let mut inside = false;
+    for &curr in ring {
+        let ((x0, y0), (x1, y1)) = (prev, curr);
+        if (y1 > lat) != (y0 > lat)
+            && lon < (x0 - x1) * (lat - y1) / (y0 - y1) + x1
+        {
+            inside = !inside;
+        }
+        prev = curr;
+    }
+    inside
+}
+
+// The map view is sized to frame a geometry within this many pixels,
+// over 256px web mercator tiles
+const MAP_VIEWPORT_SIZE: f64 = 512.0;
+const MAP_TILE_SIZE: f64 = 256.0;
+const MAP_MAX_ZOOM: f64 = 18.0;
+
+/// How a map is to be framed to display a geometry
+pub(crate) struct MapView {
+    pub(crate) center_lat: f64,
+    pub(crate) center_lon: f64,
+    pub(crate) zoom: f64,
+    // min lon, min lat, max lon, max lat
+    pub(crate) bbox: [f64; 4],
+}
+
+fn geometry_polygons(geometry: &GeojsonGeometry) -> &[Vec<Vec<Vec<f64>>>] {
+    match geometry {
+        GeojsonGeometry::Polygon { coordinates } => {
+            std::slice::from_ref(coordinates)
+        }
+        GeojsonGeometry::MultiPolygon { coordinates } => coordinates,
+    }
+}
+
+fn geometry_polygons_mut(
+    geometry: &mut GeojsonGeometry,
+) -> &mut [Vec<Vec<Vec<f64>>>] {
+    match geometry {
+        GeojsonGeometry::Polygon { coordinates } => {
+            std::slice::from_mut(coordinates)
+        }
+        GeojsonGeometry::MultiPolygon { coordinates } => coordinates,
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::ValidationFailure(format!("Invalid geometry: {reason}"))
+}
+
+fn ring_points(ring: &[Vec<f64>]) -> Result<Vec<(f64, f64)>, Error> {
+    let points = ring
+        .iter()
+        .map(|position| match position[..] {
+            [lon, lat, ..]
+                if (-180.0..=180.0).contains(&lon)
+                    && (-90.0..=90.0).contains(&lat) =>
+            {
+                Ok((lon, lat))
+            }
+            _ => Err(invalid("positions must be valid [lon, lat] pairs")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if points.len() < 4 {
+        return Err(invalid("rings need at least four positions"));
+    }
+    if points.first() != points.last() {
+        return Err(invalid("rings must be closed"));
+    }
+    Ok(points)
+}
+
+// Twice the signed area of a closed ring; positive when counterclockwise
+fn ring_winding(ring: &[(f64, f64)]) -> f64 {
+    ring.windows(2)
+        .map(|pair| pair[0].0 * pair[1].1 - pair[1].0 * pair[0].1)
+        .sum()
+}
+
+fn orientation(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+// Whether `c`, known to be collinear with `a`-`b`, lies within that segment
+fn within_segment(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> bool {
+    c.0 >= a.0.min(b.0)
+        && c.0 <= a.0.max(b.0)
+        && c.1 >= a.1.min(b.1)
+        && c.1 <= a.1.max(b.1)
+}
+
+fn segments_touch(
+    (p0, p1): ((f64, f64), (f64, f64)),
+    (q0, q1): ((f64, f64), (f64, f64)),
+) -> bool {
+    let d0 = orientation(q0, q1, p0);
+    let d1 = orientation(q0, q1, p1);
+    let d2 = orientation(p0, p1, q0);
+    let d3 = orientation(p0, p1, q1);
+
+    if d0 * d1 < 0.0 && d2 * d3 < 0.0 {
+        return true;
+    }
+
+    (d0 == 0.0 && within_segment(q0, q1, p0))
+        || (d1 == 0.0 && within_segment(q0, q1, p1))
+        || (d2 == 0.0 && within_segment(p0, p1, q0))
+        || (d3 == 0.0 && within_segment(p0, p1, q1))
+}
+
+// Whether any two non-consecutive edges of a polygon's rings touch.
+// Edges are swept by their western end to skip the pairs that can't meet.
+fn rings_intersect(rings: &[Vec<(f64, f64)>]) -> bool {
+    // (ring index, edge index, edge count, edge)
+    let mut edges = rings
+        .iter()
+        .enumerate()
+        .flat_map(|(ring_idx, ring)| {
+            let edge_count = ring.len() - 1;
+            ring.windows(2).enumerate().map(move |(edge_idx, pair)| {
+                (ring_idx, edge_idx, edge_count, (pair[0], pair[1]))
+            })
+        })
+        .collect::<Vec<_>>();
+    edges.sort_by(|(_, _, _, (a0, a1)), (_, _, _, (b0, b1))| {
+        a0.0.min(a1.0).total_cmp(&b0.0.min(b1.0))
+    });
+
+    for (i, &(ring_a, idx_a, count, edge_a)) in edges.iter().enumerate() {
+        let max_lon = edge_a.0 .0.max(edge_a.1 .0);
+        for &(ring_b, idx_b, _, edge_b) in &edges[i + 1..] {
+            if edge_b.0 .0.min(edge_b.1 .0) > max_lon {
+                break;
+            }
+            let consecutive = ring_a == ring_b
+                && (idx_a.abs_diff(idx_b) == 1
+                    || idx_a.abs_diff(idx_b) == count - 1);
+            if !consecutive && segments_touch(edge_a, edge_b) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Ensures that a geometry is made of well-formed RFC 7946 polygons:
+/// closed rings, holes within their exterior and no self-intersections.
+/// Rings that break the right-hand rule are tolerated (as RFC 7946 asks of
+/// parsers) and rewound into counterclockwise exteriors and clockwise holes.
+pub(crate) fn normalize_geometry(
+    geometry: &mut GeojsonGeometry,
+) -> Result<(), Error> {
+    let polygons = geometry_polygons_mut(geometry);
+    if polygons.is_empty() {
+        return Err(invalid("no polygons"));
+    }
+
+    for polygon in polygons {
+        if polygon.is_empty() {
+            return Err(invalid("polygons need an exterior ring"));
+        }
+        let rings = polygon
+            .iter()
+            .map(|ring| ring_points(ring))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (idx, ring) in rings.iter().enumerate() {
+            let winding = ring_winding(ring);
+            if winding == 0.0 {
+                return Err(invalid("rings must enclose an area"));
+            }
+            let is_exterior = idx == 0;
+            if is_exterior == (winding < 0.0) {
+                polygon[idx].reverse();
+            }
+        }
+
+        let exterior = &rings[0];
+        if rings[1..]
+            .iter()
+            .any(|hole| !ring_contains(exterior, hole[0].0, hole[0].1))
+        {
+            return Err(invalid("holes must be within their exterior ring"));
+        }
+
+        if rings_intersect(&rings) {
+            return Err(invalid("rings cannot intersect"));
+        }
+    }
+
+    Ok(())
+}
+
+/// The map framing for a geometry, centered on its bounding box
+pub(crate) fn map_view(geometry: &GeojsonGeometry) -> MapView {
+    let bbox = geometry_polygons(geometry)
+        .iter()
+        .filter_map(|polygon| polygon.first())
+        .flatten()
+        .fold(
+            [f64::MAX, f64::MAX, f64::MIN, f64::MIN],
+            |[min_lon, min_lat, max_lon, max_lat], position| {
+                [
+                    min_lon.min(position[0]),
+                    min_lat.min(position[1]),
+                    max_lon.max(position[0]),
+                    max_lat.max(position[1]),
+                ]
+            },
+        );
+    let [min_lon, min_lat, max_lon, max_lat] = bbox;
+
+    let mercator_y = |lat: f64| lat.to_radians().tan().asinh();
+    let world_size = MAP_VIEWPORT_SIZE / MAP_TILE_SIZE;
+    let lon_zoom = (world_size * 360.0 / (max_lon - min_lon)).log2();
+    let lat_zoom = (world_size * 2.0 * std::f64::consts::PI
+        / (mercator_y(max_lat) - mercator_y(min_lat)))
+    .log2();
+
+    MapView {
+        center_lat: f64::midpoint(min_lat, max_lat),
+        center_lon: f64::midpoint(min_lon, max_lon),
+        zoom: lon_zoom.min(lat_zoom).clamp(0.0, MAP_MAX_ZOOM).floor(),
+        bbox,
+    }
+}
+
+fn parse_divisions(geometries: Vec<(i32, Value)>) -> Vec<Division> {
+    geometries
+        .into_iter()
+        .filter_map(|(id, geometry)| {
+            let division = Division::from_geojson(id, geometry);
+            if division.is_none() {
+                tracing::warn!(division_id = id, "Unusable division geometry");
+            }
+            division
+        })
+        .collect()
+}
+
+/// Places a stop in the regions that contain it and in the parish that
+/// contains it, among the parishes of those regions.
+/// Regions without a geometry are left untouched. Stops that fall outside
+/// every known parish lose theirs, unless it lacks a usable geometry.
+pub(crate) async fn assign_stop_divisions(
+    pool: &PgPool,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    stop_id: i32,
+    lon: f64,
+    lat: f64,
+) -> Result<(), Error> {
+    let regions = parse_divisions(sql::fetch_region_geometries(pool).await?);
+    let managed_regions = regions.iter().map(|r| r.id).collect::<Vec<_>>();
+    let region_ids = regions
+        .iter()
+        .filter(|region| region.contains(lon, lat))
+        .map(|region| region.id)
+        .collect::<Vec<_>>();
+
+    let parishes = parse_divisions(
+        sql::fetch_regions_parish_geometries(pool, &region_ids).await?,
+    );
+
+    sql::replace_region_stops(
+        transaction,
+        &region_ids,
+        &vec![stop_id; region_ids.len()],
+        &managed_regions,
+        Some(stop_id),
+    )
+    .await?;
+
+    if let Some(parish) = parishes.iter().find(|p| p.contains(lon, lat)) {
+        sql::update_stops_parishes(transaction, &[stop_id], &[parish.id])
+            .await?;
+    } else if let Some((parish_id, geometry)) =
+        sql::fetch_stop_parish_geometry(transaction, stop_id).await?
+    {
+        let left_parish = Division::from_geojson(parish_id, geometry)
+            .is_some_and(|parish| !parish.contains(lon, lat));
+        if left_parish {
+            sql::update_unset_stops_parishes(
+                transaction,
+                &[stop_id],
+                &[parish_id],
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reassigns the divisions of a stop, if an update moved it
+pub(crate) async fn reassign_moved_stop_divisions(
+    pool: &PgPool,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    stop_id: i32,
+    (from_lon, from_lat): (f64, f64),
+    (lon, lat): (f64, f64),
+) -> Result<(), Error> {
+    // Any difference whatsoever means that it was moved
+    if from_lon.to_bits() == lon.to_bits()
+        && from_lat.to_bits() == lat.to_bits()
+    {
+        return Ok(());
+    }
+    assign_stop_divisions(pool, transaction, stop_id, lon, lat).await
+}
+
+/// Recomputes the region and parish of every stop
+pub(crate) async fn reconcile_divisions(
+    pool: &PgPool,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<responses::DivisionReconciliation, Error> {
+    let regions = parse_divisions(sql::fetch_region_geometries(pool).await?);
+    let managed_regions = regions.iter().map(|r| r.id).collect::<Vec<_>>();
+    let parishes = parse_divisions(
+        sql::fetch_regions_parish_geometries(pool, &managed_regions).await?,
+    );
+    let known_parishes =
+        parse_divisions(sql::fetch_parish_geometries(pool).await?)
+            .into_iter()
+            .map(|parish| (parish.id, parish))
+            .collect::<HashMap<_, _>>();
+    let stops = sql::fetch_stop_positions(pool).await?;
+
+    let mut region_ids = vec![];
+    let mut region_stop_ids = vec![];
+    let mut parish_ids = vec![];
+    let mut parish_stop_ids = vec![];
+    let mut stale_parish_ids = vec![];
+    let mut stale_parish_stop_ids = vec![];
+
+    for (stop_id, lon, lat, current_parish) in stops {
+        for region in regions.iter().filter(|r| r.contains(lon, lat)) {
+            region_ids.push(region.id);
+            region_stop_ids.push(stop_id);
+        }
+        if let Some(parish) = parishes.iter().find(|p| p.contains(lon, lat)) {
+            parish_ids.push(parish.id);
+            parish_stop_ids.push(stop_id);
+        } else if let Some(parish) =
+            current_parish.and_then(|id| known_parishes.get(&id))
+        {
+            if !parish.contains(lon, lat) {
+                stale_parish_ids.push(parish.id);
+                stale_parish_stop_ids.push(stop_id);
+            }
+        }
+    }
+
+    let (added_region_stops, removed_region_stops) = sql::replace_region_stops(
+        transaction,
+        &region_ids,
+        &region_stop_ids,
+        &managed_regions,
+        None,
+    )
+    .await?;
+    let updated_parishes =
+        sql::update_stops_parishes(transaction, &parish_stop_ids, &parish_ids)
+            .await?;
+    let cleared_parishes = sql::update_unset_stops_parishes(
+        transaction,
+        &stale_parish_stop_ids,
+        &stale_parish_ids,
+    )
+    .await?;
+
+    Ok(responses::DivisionReconciliation {
+        added_region_stops,
+        removed_region_stops,
+        updated_parishes,
+        cleared_parishes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn division_containment() {
+        let square = json!({
+            "type": "Polygon",
+            "coordinates": [
+                [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0], [0.0, 0.0]],
+                [[1.0, 1.0], [2.0, 1.0], [2.0, 2.0], [1.0, 2.0], [1.0, 1.0]]
+            ]
+        });
+        let division = Division::from_geojson(1, square).unwrap();
+        assert!(division.contains(3.0, 3.0));
+        assert!(!division.contains(1.5, 1.5));
+        assert!(!division.contains(5.0, 3.0));
+
+        let feature = json!({
+            "type": "Feature",
+            "id": "123456",
+            "geometry": {
+                "type": "MultiPolygon",
+                "coordinates": [
+                    [[[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [0.0, 0.0]]],
+                    [[[5.0, 5.0], [6.0, 5.0], [5.0, 6.0], [5.0, 5.0]]]
+                ]
+            }
+        });
+        let division = Division::from_geojson(2, feature).unwrap();
+        assert!(division.contains(0.2, 0.2));
+        assert!(division.contains(5.2, 5.2));
+        assert!(!division.contains(0.8, 0.8));
+
+        assert!(Division::from_geojson(3, json!({"type": "Point"})).is_none());
+    }
+
+    fn polygon(rings: &serde_json::Value) -> GeojsonGeometry {
+        serde_json::from_value(json!({"type": "Polygon", "coordinates": rings}))
+            .unwrap()
+    }
+
+    #[test]
+    fn geometry_validation() {
+        let mut valid = polygon(&json!([
+            [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0], [0.0, 0.0]],
+            [[1.0, 1.0], [1.0, 2.0], [2.0, 2.0], [2.0, 1.0], [1.0, 1.0]]
+        ]));
+        assert!(normalize_geometry(&mut valid).is_ok());
+
+        let mut unclosed =
+            polygon(&json!([[[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]]]));
+        assert!(normalize_geometry(&mut unclosed).is_err());
+
+        let mut crossed = polygon(&json!([[
+            [0.0, 0.0],
+            [4.0, 0.0],
+            [4.0, 4.0],
+            [2.0, -1.0],
+            [0.0, 4.0],
+            [0.0, 0.0]
+        ]]));
+        assert!(normalize_geometry(&mut crossed).is_err());
+
+        let mut stray_hole = polygon(&json!([
+            [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0], [0.0, 0.0]],
+            [[5.0, 5.0], [5.0, 6.0], [6.0, 6.0], [6.0, 5.0], [5.0, 5.0]]
+        ]));
+        assert!(normalize_geometry(&mut stray_hole).is_err());
+    }
+
+    #[test]
+    fn geometry_rewinding() {
+        let mut clockwise = polygon(&json!([
+            [[0.0, 0.0], [0.0, 4.0], [4.0, 4.0], [4.0, 0.0], [0.0, 0.0]],
+            [[1.0, 1.0], [2.0, 1.0], [2.0, 2.0], [1.0, 2.0], [1.0, 1.0]]
+        ]));
+        assert!(normalize_geometry(&mut clockwise).is_ok());
+
+        let rings = &geometry_polygons(&clockwise)[0];
+        let exterior = ring_points(&rings[0]).unwrap();
+        let hole = ring_points(&rings[1]).unwrap();
+        assert!(ring_winding(&exterior) > 0.0);
+        assert!(ring_winding(&hole) < 0.0);
+    }
+
+    #[test]
+    fn geometry_map_view() {
+        let view = map_view(&polygon(&json!([[
+            [-9.5, 38.5],
+            [-8.5, 38.5],
+            [-8.5, 39.0],
+            [-9.5, 38.5]
+        ]])));
+        assert!((view.center_lon + 9.0).abs() < 1e-9);
+        assert!((view.center_lat - 38.75).abs() < 1e-9);
+        assert!(view.zoom > 0.0 && view.zoom < MAP_MAX_ZOOM);
+        for (value, expected) in view.bbox.iter().zip([-9.5, 38.5, -8.5, 39.0])
+        {
+            assert!((value - expected).abs() < 1e-9);
+        }
+    }
+}
@@ -16,10 +16,122 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+pub(crate) mod requests {
+    use chrono::NaiveDate;
+    use serde::Deserialize;
+
+    use commons::models::calendar::HolidayRule;
+    use commons::models::geo::GeojsonGeometry;
+
+    use crate::geo::logic;
+    use crate::Error;
+
+    #[derive(Deserialize, Debug)]
+    pub struct NewHoliday {
+        pub name: String,
+        pub rule: HolidayRule,
+        pub region: Option<i32>,
+        pub municipality: Option<i32>,
+    }
+
+    impl NewHoliday {
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            if self.name.trim().is_empty() {
+                return Err(Error::ValidationFailure("Empty name".to_string()));
+            }
+
+            if self.region.is_some() && self.municipality.is_some() {
+                return Err(Error::ValidationFailure(
+                    "Holiday cannot be both regional and municipal".to_string(),
+                ));
+            }
+
+            // Leap year, so that February 29th is accepted
+            if self.rule.date_in(2024).is_none() {
+                return Err(Error::ValidationFailure(
+                    "Invalid holiday date".to_string(),
+                ));
+            }
+
+            Ok(())
+        }
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct ChangeRegion {
+        pub name: String,
+        pub geometry: GeojsonGeometry,
+        pub level: i32,
+        pub active: bool,
+    }
+
+    impl ChangeRegion {
+        pub(crate) fn validate(&mut self) -> Result<(), Error> {
+            if self.name.trim().is_empty() {
+                return Err(Error::ValidationFailure("Empty name".to_string()));
+            }
+
+            logic::normalize_geometry(&mut self.geometry)
+        }
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct ChangeParish {
+        pub name: String,
+        pub short_name: String,
+        pub municipality: i32,
+        // The national parish code
+        pub dicofre: String,
+        pub geometry: GeojsonGeometry,
+    }
+
+    impl ChangeParish {
+        pub(crate) fn validate(&mut self) -> Result<(), Error> {
+            if self.name.trim().is_empty() || self.short_name.trim().is_empty()
+            {
+                return Err(Error::ValidationFailure("Empty name".to_string()));
+            }
+
+            if self.dicofre.len() != 6
+                || !self.dicofre.chars().all(|c| c.is_ascii_digit())
+            {
+                return Err(Error::ValidationFailure(
+                    "The parish code must have six digits".to_string(),
+                ));
+            }
+
+            logic::normalize_geometry(&mut self.geometry)
+        }
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct NewSchoolPeriod {
+        pub name: Option<String>,
+        pub start: NaiveDate,
+        pub end: NaiveDate,
+        pub region: Option<i32>,
+    }
+
+    impl NewSchoolPeriod {
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            if self.start > self.end {
+                return Err(Error::ValidationFailure(
+                    "School period ends before it starts".to_string(),
+                ));
+            }
+
+            Ok(())
+        }
+    }
+}
+
 pub(crate) mod responses {
-    use commons::models::operators;
+    use chrono::NaiveDate;
     use serde::Serialize;
 
+    use commons::models::calendar::HolidayRule;
+    use commons::models::operators;
+
     #[derive(Serialize, Debug, Clone)]
     pub struct SimpleRegion {
         pub id: i32,
@@ -36,4 +148,30 @@ pub(crate) mod responses {
         pub zoom: Option<f64>,
         pub operators: Vec<operators::Operator>,
     }
+
+    #[derive(Serialize, Debug)]
+    pub struct DivisionReconciliation {
+        pub added_region_stops: u64,
+        pub removed_region_stops: u64,
+        pub updated_parishes: u64,
+        pub cleared_parishes: u64,
+    }
+
+    #[derive(Serialize, Debug)]
+    pub struct Holiday {
+        pub id: i32,
+        pub name: String,
+        pub rule: HolidayRule,
+        pub region: Option<i32>,
+        pub municipality: Option<i32>,
+    }
+
+    #[derive(Serialize, Debug)]
+    pub struct SchoolPeriod {
+        pub id: i32,
+        pub name: Option<String>,
+        pub start: NaiveDate,
+        pub end: NaiveDate,
+        pub region: Option<i32>,
+    }
 }
@@ -16,11 +16,14 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use sqlx::types::Json;
 use sqlx::PgPool;
 
+use commons::models::calendar::{Almanac, HolidayRule, SchoolPeriod};
 use commons::models::geo;
 
-use super::models::responses;
+use super::logic;
+use super::models::{requests, responses};
 use crate::Error;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -74,6 +77,87 @@ WHERE id = $1
     })
 }
 
+pub(crate) async fn insert_region(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    region: &requests::ChangeRegion,
+    view: &logic::MapView,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO regions(name, geometry, active, level, center_lat, center_lon,
+    zoom, bbox)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+RETURNING id
+"#,
+        region.name,
+        Json(&region.geometry) as _,
+        region.active,
+        region.level,
+        view.center_lat,
+        view.center_lon,
+        view.zoom,
+        &view.bbox[..]
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), name = region.name);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn update_region(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    region_id: i32,
+    region: &requests::ChangeRegion,
+    view: &logic::MapView,
+) -> Result<bool> {
+    let res = sqlx::query!(
+        r#"
+UPDATE regions
+SET name=$2, geometry=$3, active=$4, level=$5, center_lat=$6, center_lon=$7,
+    zoom=$8, bbox=$9
+WHERE id=$1
+"#,
+        region_id,
+        region.name,
+        Json(&region.geometry) as _,
+        region.active,
+        region.level,
+        view.center_lat,
+        view.center_lon,
+        view.zoom,
+        &view.bbox[..]
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), region_id);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.rows_affected() != 0)
+}
+
+// Returns the name of the deleted region, if it existed
+pub(crate) async fn delete_region(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    region_id: i32,
+) -> Result<Option<String>> {
+    sqlx::query_scalar!(
+        "DELETE FROM regions WHERE id=$1 RETURNING name",
+        region_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), region_id);
+        Error::DatabaseExecution
+    })
+}
+
 pub(crate) async fn fetch_operator_regions(
     pool: &PgPool,
     operator_id: i32,
@@ -237,6 +321,28 @@ WHERE stop_id = $1
     .collect())
 }
 
+pub(crate) async fn fetch_stop_municipality(
+    pool: &PgPool,
+    stop_id: i32,
+) -> Result<Option<i32>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT parishes.municipality
+FROM stops
+JOIN parishes ON parishes.id = stops.parish
+WHERE stops.id = $1
+"#,
+        stop_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), stop_id);
+        Error::DatabaseExecution
+    })?
+    .map(|row| row.municipality))
+}
+
 pub(crate) async fn upsert_stop_into_region(
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     region_id: i32,
@@ -311,6 +417,75 @@ WHERE parishes.id IN (
     })
 }
 
+pub(crate) async fn insert_parish(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    parish: &requests::ChangeParish,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO parishes(name, short_name, municipality, dicofre, geometry)
+VALUES ($1, $2, $3, $4, $5)
+RETURNING id
+"#,
+        parish.name,
+        parish.short_name,
+        parish.municipality,
+        parish.dicofre,
+        Json(&parish.geometry) as _
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), dicofre = parish.dicofre);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn update_parish(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    parish_id: i32,
+    parish: &requests::ChangeParish,
+) -> Result<bool> {
+    let res = sqlx::query!(
+        r#"
+UPDATE parishes
+SET name=$2, short_name=$3, municipality=$4, dicofre=$5, geometry=$6
+WHERE id=$1
+"#,
+        parish_id,
+        parish.name,
+        parish.short_name,
+        parish.municipality,
+        parish.dicofre,
+        Json(&parish.geometry) as _
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), parish_id);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.rows_affected() != 0)
+}
+
+pub(crate) async fn delete_parish(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    parish_id: i32,
+) -> Result<bool> {
+    let res = sqlx::query!("DELETE FROM parishes WHERE id=$1", parish_id)
+        .execute(&mut **transaction)
+        .await
+        .map_err(|err| {
+            tracing::error!(error = err.to_string(), parish_id);
+            Error::DatabaseExecution
+        })?;
+
+    Ok(res.rows_affected() != 0)
+}
+
 pub(crate) async fn update_stop_parish(
     pool: &PgPool,
     stop_id: i32,
@@ -331,6 +506,234 @@ pub(crate) async fn update_stop_parish(
     Ok(())
 }
 
+pub(crate) async fn fetch_region_geometries(
+    pool: &PgPool,
+) -> Result<Vec<(i32, serde_json::Value)>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, geometry as "geometry!"
+FROM regions
+WHERE geometry IS NOT NULL
+    "#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| (row.id, row.geometry))
+    .collect())
+}
+
+pub(crate) async fn fetch_regions_parish_geometries(
+    pool: &PgPool,
+    region_ids: &[i32],
+) -> Result<Vec<(i32, serde_json::Value)>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, geometry
+FROM parishes
+WHERE id IN (
+    SELECT parish_id
+    FROM region_parishes
+    WHERE region_id = ANY($1)
+)
+    "#,
+        region_ids
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ?region_ids);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| (row.id, row.geometry))
+    .collect())
+}
+
+pub(crate) async fn fetch_parish_geometries(
+    pool: &PgPool,
+) -> Result<Vec<(i32, serde_json::Value)>> {
+    Ok(sqlx::query!("SELECT id, geometry FROM parishes")
+        .fetch_all(pool)
+        .await
+        .map_err(|err| {
+            tracing::error!(error = err.to_string());
+            Error::DatabaseExecution
+        })?
+        .into_iter()
+        .map(|row| (row.id, row.geometry))
+        .collect())
+}
+
+// The parish that a stop is currently in, along with its geometry
+pub(crate) async fn fetch_stop_parish_geometry(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    stop_id: i32,
+) -> Result<Option<(i32, serde_json::Value)>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT parishes.id, parishes.geometry
+FROM stops
+JOIN parishes ON parishes.id = stops.parish
+WHERE stops.id = $1
+    "#,
+        stop_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), stop_id);
+        Error::DatabaseExecution
+    })?
+    .map(|row| (row.id, row.geometry)))
+}
+
+// The position of every stop, along with its current parish
+pub(crate) async fn fetch_stop_positions(
+    pool: &PgPool,
+) -> Result<Vec<(i32, f64, f64, Option<i32>)>> {
+    Ok(sqlx::query!("SELECT id, lon, lat, parish FROM stops")
+        .fetch_all(pool)
+        .await
+        .map_err(|err| {
+            tracing::error!(error = err.to_string());
+            Error::DatabaseExecution
+        })?
+        .into_iter()
+        .map(|row| (row.id, row.lon, row.lat, row.parish))
+        .collect())
+}
+
+// Replaces the stop memberships of the regions in `managed_regions` with
+// `region_ids[i]`-`stop_ids[i]`, optionally limited to a single stop.
+// Stops served by a route of a region are kept in (or added to) that region.
+// Returns how many memberships were added and how many were removed.
+pub(crate) async fn replace_region_stops(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    region_ids: &[i32],
+    stop_ids: &[i32],
+    managed_regions: &[i32],
+    stop_id: Option<i32>,
+) -> Result<(u64, u64)> {
+    let removed = sqlx::query!(
+        r#"
+DELETE FROM region_stops
+WHERE region_id = ANY($3)
+    AND ($4::int IS NULL OR stop_id = $4)
+    AND NOT EXISTS (
+        SELECT 1
+        FROM unnest($1::int[], $2::int[]) AS memberships(region_id, stop_id)
+        WHERE memberships.region_id = region_stops.region_id
+            AND memberships.stop_id = region_stops.stop_id
+    )
+    AND NOT EXISTS (
+        SELECT 1
+        FROM region_routes
+        JOIN subroutes ON subroutes.route = region_routes.route_id
+        JOIN subroute_stops ON subroute_stops.subroute = subroutes.id
+        WHERE region_routes.region_id = region_stops.region_id
+            AND subroute_stops.stop = region_stops.stop_id
+    )
+    "#,
+        region_ids,
+        stop_ids,
+        managed_regions,
+        stop_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), stop_id);
+        Error::DatabaseExecution
+    })?
+    .rows_affected();
+
+    let added = sqlx::query!(
+        r#"
+INSERT INTO region_stops (region_id, stop_id)
+SELECT region_id, stop_id
+FROM unnest($1::int[], $2::int[]) AS memberships(region_id, stop_id)
+UNION
+SELECT region_routes.region_id, subroute_stops.stop
+FROM region_routes
+JOIN subroutes ON subroutes.route = region_routes.route_id
+JOIN subroute_stops ON subroute_stops.subroute = subroutes.id
+WHERE $3::int IS NULL OR subroute_stops.stop = $3
+ON CONFLICT DO NOTHING
+    "#,
+        region_ids,
+        stop_ids,
+        stop_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), stop_id);
+        Error::DatabaseExecution
+    })?
+    .rows_affected();
+
+    Ok((added, removed))
+}
+
+pub(crate) async fn update_stops_parishes(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    stop_ids: &[i32],
+    parish_ids: &[i32],
+) -> Result<u64> {
+    let res = sqlx::query!(
+        r#"
+UPDATE stops
+SET parish = assignments.parish_id
+FROM unnest($1::int[], $2::int[]) AS assignments(stop_id, parish_id)
+WHERE stops.id = assignments.stop_id
+    AND stops.parish IS DISTINCT FROM assignments.parish_id
+    "#,
+        stop_ids,
+        parish_ids
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.rows_affected())
+}
+
+// Unsets the parish of the stops `stop_ids[i]`
+// as long as it still is `parish_ids[i]`
+pub(crate) async fn update_unset_stops_parishes(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    stop_ids: &[i32],
+    parish_ids: &[i32],
+) -> Result<u64> {
+    let res = sqlx::query!(
+        r#"
+UPDATE stops
+SET parish = NULL
+FROM unnest($1::int[], $2::int[]) AS stale(stop_id, parish_id)
+WHERE stops.id = stale.stop_id
+    AND stops.parish = stale.parish_id
+    "#,
+        stop_ids,
+        parish_ids
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.rows_affected())
+}
+
 pub(crate) async fn fetch_issue_regions(
     pool: &PgPool,
     issue_id: i32,
@@ -426,3 +829,203 @@ WHERE abnormality_regions.abnormality_id IN (
         Error::DatabaseExecution
     })
 }
+
+/// Builds the almanac of a place that is within a set of regions
+/// and (optionally) a municipality.
+pub(crate) async fn fetch_almanac(
+    pool: &PgPool,
+    regions: &[i32],
+    municipality: Option<i32>,
+) -> Result<Almanac> {
+    let mut almanac = Almanac::national();
+
+    let holidays = sqlx::query!(
+        r#"
+SELECT rule as "rule!: Json<HolidayRule>"
+FROM holidays
+WHERE (region IS NULL AND municipality IS NULL)
+    OR region = ANY($1)
+    OR municipality = $2
+"#,
+        regions,
+        municipality
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            error = err.to_string(),
+            regions = ?regions,
+            municipality
+        );
+        Error::DatabaseExecution
+    })?;
+    almanac
+        .holidays
+        .extend(holidays.into_iter().map(|row| row.rule.0));
+
+    almanac.school_periods = sqlx::query_as!(
+        SchoolPeriod,
+        r#"
+SELECT start_date as start, end_date as end
+FROM school_periods
+WHERE region IS NULL OR region = ANY($1)
+"#,
+        regions
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), regions = ?regions);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(almanac)
+}
+
+pub(crate) async fn fetch_holidays(
+    pool: &PgPool,
+    region: Option<i32>,
+    municipality: Option<i32>,
+) -> Result<Vec<responses::Holiday>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, name, rule as "rule!: Json<HolidayRule>", region, municipality
+FROM holidays
+WHERE ($1::int IS NULL OR region = $1)
+    AND ($2::int IS NULL OR municipality = $2)
+ORDER BY id ASC
+"#,
+        region,
+        municipality
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), region, municipality);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| responses::Holiday {
+        id: row.id,
+        name: row.name,
+        rule: row.rule.0,
+        region: row.region,
+        municipality: row.municipality,
+    })
+    .collect())
+}
+
+pub(crate) async fn insert_holiday(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    holiday: &requests::NewHoliday,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO holidays(name, rule, region, municipality)
+VALUES ($1, $2, $3, $4)
+RETURNING id
+"#,
+        holiday.name,
+        Json(&holiday.rule) as _,
+        holiday.region,
+        holiday.municipality
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), holiday = ?holiday);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn delete_holiday(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    holiday_id: i32,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+DELETE FROM holidays
+WHERE id = $1
+"#,
+        holiday_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), holiday_id);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn fetch_school_periods(
+    pool: &PgPool,
+    region: Option<i32>,
+) -> Result<Vec<responses::SchoolPeriod>> {
+    sqlx::query_as!(
+        responses::SchoolPeriod,
+        r#"
+SELECT id, name, start_date as start, end_date as end, region
+FROM school_periods
+WHERE $1::int IS NULL OR region = $1
+ORDER BY start_date ASC
+"#,
+        region
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), region);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn insert_school_period(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    period: &requests::NewSchoolPeriod,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO school_periods(name, start_date, end_date, region)
+VALUES ($1, $2, $3, $4)
+RETURNING id
+"#,
+        period.name,
+        period.start,
+        period.end,
+        period.region
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), period = ?period);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn delete_school_period(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    period_id: i32,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+DELETE FROM school_periods
+WHERE id = $1
+"#,
+        period_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), period_id);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,477 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use itertools::Itertools;
+
+use commons::models::gtfs::{self, File};
+use commons::utils::geo::distance;
+use commons::utils::gtfs::calculate_gtfs_stop_sequence;
+
+use super::loaders;
+use super::models::changes;
+use crate::Error;
+
+const CHANGES_FILENAME: &str = "changes.json";
+// Stops that moved less than this (in meters) are considered to be unchanged
+const MOVED_STOP_MIN_DISTANCE: f64 = 10.0;
+
+struct Feed {
+    stops: HashMap<gtfs::StopId, gtfs::Stop>,
+    route_clusters: HashMap<gtfs::RouteId, Vec<gtfs::PatternCluster>>,
+    service_dates: HashMap<gtfs::ServiceId, BTreeSet<NaiveDate>>,
+}
+
+fn load_feed(root: &Path) -> Result<Feed, Error> {
+    let stops: Vec<gtfs::Stop> = loaders::gtfs_file(root, &File::Stops)?;
+    let routes: Vec<gtfs::Route> = loaders::gtfs_file(root, &File::Routes)?;
+    let trips: Vec<gtfs::Trip> = loaders::gtfs_file(root, &File::Trips)?;
+    let stop_times: Vec<gtfs::StopTime> =
+        loaders::gtfs_file(root, &File::StopTimes)?;
+    let calendars: Vec<gtfs::Calendar> =
+        loaders::optional_gtfs_file(root, &File::Calendar)?;
+    let calendar_dates: Vec<gtfs::CalendarDate> =
+        loaders::optional_gtfs_file(root, &File::CalendarDates)?;
+
+    let trip_stops = calculate_gtfs_stop_sequence(&stop_times);
+    let mut route_clusters = routes
+        .into_iter()
+        .map(|route| (route.route_id, vec![]))
+        .collect::<HashMap<_, _>>();
+    for (route_id, route_trips) in trips
+        .into_iter()
+        .into_group_map_by(|trip| trip.route_id.clone())
+    {
+        route_clusters
+            .insert(route_id, pattern_clusters(route_trips, &trip_stops));
+    }
+
+    Ok(Feed {
+        stops: stops
+            .into_iter()
+            .map(|stop| (stop.stop_id.clone(), stop))
+            .collect(),
+        route_clusters,
+        service_dates: service_dates(&calendars, &calendar_dates),
+    })
+}
+
+// Groups the trips of a route by the stops they go through
+fn pattern_clusters(
+    trips: Vec<gtfs::Trip>,
+    trip_stops: &HashMap<gtfs::TripId, Vec<gtfs::StopId>>,
+) -> Vec<gtfs::PatternCluster> {
+    let mut clusters: HashMap<Vec<gtfs::StopId>, gtfs::PatternCluster> =
+        HashMap::new();
+    for trip in trips {
+        let Some(stops) = trip_stops.get(&trip.trip_id) else {
+            continue;
+        };
+        let cluster = clusters.entry(stops.clone()).or_insert_with(|| {
+            gtfs::PatternCluster {
+                stops: stops.clone(),
+                headsigns: HashSet::new(),
+                patterns: HashSet::new(),
+                trips: HashSet::new(),
+            }
+        });
+        if let Some(pattern_id) = trip.pattern_id {
+            cluster.patterns.insert(pattern_id);
+        }
+        if let Some(headsign) = trip.trip_headsign {
+            cluster.headsigns.insert(headsign.to_lowercase());
+        }
+        cluster.trips.insert(trip.trip_id);
+    }
+    clusters.into_values().collect()
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y%m%d").ok()
+}
+
+fn runs_on(calendar: &gtfs::Calendar, weekday: Weekday) -> bool {
+    let flag = match weekday {
+        Weekday::Mon => calendar.monday,
+        Weekday::Tue => calendar.tuesday,
+        Weekday::Wed => calendar.wednesday,
+        Weekday::Thu => calendar.thursday,
+        Weekday::Fri => calendar.friday,
+        Weekday::Sat => calendar.saturday,
+        Weekday::Sun => calendar.sunday,
+    };
+    flag == 1
+}
+
+// Every date in which each service runs
+fn service_dates(
+    calendars: &[gtfs::Calendar],
+    calendar_dates: &[gtfs::CalendarDate],
+) -> HashMap<gtfs::ServiceId, BTreeSet<NaiveDate>> {
+    let mut services: HashMap<gtfs::ServiceId, BTreeSet<NaiveDate>> =
+        HashMap::new();
+
+    for calendar in calendars {
+        let dates = services.entry(calendar.service_id.clone()).or_default();
+        let (Some(start), Some(end)) = (
+            parse_date(&calendar.start_date),
+            parse_date(&calendar.end_date),
+        ) else {
+            continue;
+        };
+        dates.extend(
+            start
+                .iter_days()
+                .take_while(|date| *date <= end)
+                .filter(|date| runs_on(calendar, date.weekday())),
+        );
+    }
+
+    for calendar_date in calendar_dates {
+        let dates = services
+            .entry(calendar_date.service_id.clone())
+            .or_default();
+        let Some(date) = parse_date(&calendar_date.date) else {
+            continue;
+        };
+        match calendar_date.exception_type {
+            1 => {
+                dates.insert(date);
+            }
+            2 => {
+                dates.remove(&date);
+            }
+            _ => {}
+        }
+    }
+    services
+}
+
+fn compare_feeds(
+    old: &Feed,
+    new: &Feed,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> changes::FeedChanges {
+    let added_routes = new
+        .route_clusters
+        .keys()
+        .filter(|route_id| !old.route_clusters.contains_key(*route_id))
+        .cloned()
+        .sorted()
+        .collect();
+    let removed_routes = old
+        .route_clusters
+        .keys()
+        .filter(|route_id| !new.route_clusters.contains_key(*route_id))
+        .cloned()
+        .sorted()
+        .collect();
+
+    let pattern_changes = old
+        .route_clusters
+        .keys()
+        .chain(new.route_clusters.keys())
+        .unique()
+        .sorted()
+        .filter_map(|route_id| {
+            let old_clusters = old.route_clusters.get(route_id);
+            let new_clusters = new.route_clusters.get(route_id);
+            let added = missing_clusters(new_clusters, old_clusters);
+            let removed = missing_clusters(old_clusters, new_clusters);
+
+            (!added.is_empty() || !removed.is_empty()).then(|| {
+                changes::PatternChange {
+                    route_id: route_id.clone(),
+                    added,
+                    removed,
+                }
+            })
+        })
+        .collect();
+
+    let moved_stops = old
+        .stops
+        .values()
+        .filter_map(|old_stop| {
+            let new_stop = new.stops.get(&old_stop.stop_id)?;
+            let distance = distance(
+                old_stop.stop_lat,
+                old_stop.stop_lon,
+                new_stop.stop_lat,
+                new_stop.stop_lon,
+            );
+            (distance >= MOVED_STOP_MIN_DISTANCE).then(|| changes::MovedStop {
+                stop_id: old_stop.stop_id.clone(),
+                previous_lat: old_stop.stop_lat,
+                previous_lon: old_stop.stop_lon,
+                lat: new_stop.stop_lat,
+                lon: new_stop.stop_lon,
+                distance,
+            })
+        })
+        .sorted_by(|a, b| a.stop_id.cmp(&b.stop_id))
+        .collect();
+
+    let no_dates = BTreeSet::new();
+    let service_changes = old
+        .service_dates
+        .keys()
+        .chain(new.service_dates.keys())
+        .unique()
+        .sorted()
+        .filter_map(|service_id| {
+            let old_dates =
+                old.service_dates.get(service_id).unwrap_or(&no_dates);
+            let new_dates =
+                new.service_dates.get(service_id).unwrap_or(&no_dates);
+            let added_dates =
+                new_dates.difference(old_dates).copied().collect::<Vec<_>>();
+            let removed_dates =
+                old_dates.difference(new_dates).copied().collect::<Vec<_>>();
+
+            (!added_dates.is_empty() || !removed_dates.is_empty()).then(|| {
+                changes::ServiceChange {
+                    service_id: service_id.clone(),
+                    added_dates,
+                    removed_dates,
+                }
+            })
+        })
+        .collect();
+
+    changes::FeedChanges {
+        from,
+        to,
+        added_routes,
+        removed_routes,
+        pattern_changes,
+        moved_stops,
+        service_changes,
+    }
+}
+
+// Clusters in `clusters` whose stops are not those of any cluster in `others`
+fn missing_clusters(
+    clusters: Option<&Vec<gtfs::PatternCluster>>,
+    others: Option<&Vec<gtfs::PatternCluster>>,
+) -> Vec<gtfs::PatternCluster> {
+    let other_stops = others
+        .into_iter()
+        .flatten()
+        .map(|cluster| &cluster.stops)
+        .collect::<HashSet<_>>();
+    clusters
+        .into_iter()
+        .flatten()
+        .filter(|cluster| !other_stops.contains(&cluster.stops))
+        .cloned()
+        .collect()
+}
+
+pub(crate) fn diff_feeds(
+    old_root: &Path,
+    new_root: &Path,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<changes::FeedChanges, Error> {
+    let old = load_feed(old_root)?;
+    let new = load_feed(new_root)?;
+    Ok(compare_feeds(&old, &new, from, to))
+}
+
+/// IML subroutes that were matched against GTFS data that has since changed
+pub(crate) fn affected_subroutes(
+    feed_changes: &changes::FeedChanges,
+    subroute_clusters: &[(i32, gtfs::PatternCluster)],
+) -> Vec<i32> {
+    let removed_clusters = feed_changes
+        .pattern_changes
+        .iter()
+        .flat_map(|change| &change.removed)
+        .collect::<Vec<_>>();
+    let moved_stops = feed_changes
+        .moved_stops
+        .iter()
+        .map(|stop| &stop.stop_id)
+        .collect::<HashSet<_>>();
+
+    subroute_clusters
+        .iter()
+        .filter(|(_, cluster)| {
+            removed_clusters.iter().any(|removed| {
+                removed.stops == cluster.stops
+                    || !removed.patterns.is_disjoint(&cluster.patterns)
+                    || !removed.trips.is_disjoint(&cluster.trips)
+            }) || cluster.stops.iter().any(|stop| moved_stops.contains(stop))
+        })
+        .map(|(subroute_id, _)| *subroute_id)
+        .collect()
+}
+
+pub(crate) fn has_feed_changes(version_root: &Path) -> bool {
+    version_root.join(CHANGES_FILENAME).exists()
+}
+
+pub(crate) fn store_feed_changes(
+    version_root: &Path,
+    feed_changes: &changes::FeedChanges,
+) -> Result<(), Error> {
+    let path = version_root.join(CHANGES_FILENAME);
+    let file = fs::File::create(&path).map_err(|err| {
+        tracing::error!(msg = "Unable to create file", err=?err, path=?path);
+        Error::Filesystem
+    })?;
+    serde_json::to_writer(file, feed_changes).map_err(|err| {
+        tracing::error!(msg = "Unable to serialize changes", err=?err);
+        Error::Serialization
+    })
+}
+
+pub(crate) fn load_feed_changes(
+    version_root: &Path,
+) -> Result<changes::FeedChanges, Error> {
+    let path = version_root.join(CHANGES_FILENAME);
+    let file = fs::File::open(&path).map_err(|err| {
+        tracing::error!(msg = "Filesystem error", err=?err, path=?path);
+        Error::Filesystem
+    })?;
+    serde_json::from_reader(file).map_err(|err| {
+        tracing::error!(msg = "Unable to deserialize changes", err=?err);
+        Error::Serialization
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(id: &str, lat: f64, lon: f64) -> (gtfs::StopId, gtfs::Stop) {
+        (
+            id.to_string(),
+            gtfs::Stop {
+                stop_id: id.to_string(),
+                stop_name: id.to_string(),
+                stop_lat: lat,
+                stop_lon: lon,
+            },
+        )
+    }
+
+    fn cluster(stops: &[&str], trip_id: &str) -> gtfs::PatternCluster {
+        gtfs::PatternCluster {
+            stops: stops.iter().map(ToString::to_string).collect(),
+            headsigns: HashSet::new(),
+            patterns: HashSet::new(),
+            trips: HashSet::from([trip_id.to_string()]),
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    #[test]
+    fn service_dates_apply_exceptions() {
+        let calendar = gtfs::Calendar {
+            service_id: "weekdays".to_string(),
+            monday: 1,
+            tuesday: 1,
+            wednesday: 1,
+            thursday: 1,
+            friday: 1,
+            saturday: 0,
+            sunday: 0,
+            start_date: "20240301".to_string(),
+            end_date: "20240310".to_string(),
+        };
+        let exceptions = [
+            gtfs::CalendarDate {
+                service_id: "weekdays".to_string(),
+                date: "20240305".to_string(),
+                exception_type: 2,
+            },
+            gtfs::CalendarDate {
+                service_id: "weekdays".to_string(),
+                date: "20240309".to_string(),
+                exception_type: 1,
+            },
+        ];
+
+        let dates = service_dates(&[calendar], &exceptions);
+        assert_eq!(
+            dates["weekdays"].iter().copied().collect::<Vec<_>>(),
+            vec![date(1), date(4), date(6), date(7), date(8), date(9)]
+        );
+    }
+
+    #[test]
+    fn feed_changes() {
+        let old = Feed {
+            stops: HashMap::from([
+                stop("a", 38.70, -9.14),
+                stop("b", 38.71, -9.14),
+            ]),
+            route_clusters: HashMap::from([
+                ("1".to_string(), vec![cluster(&["a", "b"], "t1")]),
+                ("2".to_string(), vec![cluster(&["b", "a"], "t2")]),
+            ]),
+            service_dates: HashMap::from([(
+                "s".to_string(),
+                BTreeSet::from([date(1), date(2)]),
+            )]),
+        };
+        let new = Feed {
+            stops: HashMap::from([
+                stop("a", 38.70, -9.14),
+                // ~111m north
+                stop("b", 38.711, -9.14),
+            ]),
+            route_clusters: HashMap::from([
+                ("1".to_string(), vec![cluster(&["a"], "t1")]),
+                ("3".to_string(), vec![]),
+            ]),
+            service_dates: HashMap::from([(
+                "s".to_string(),
+                BTreeSet::from([date(2), date(3)]),
+            )]),
+        };
+
+        let changes = compare_feeds(&old, &new, Utc::now(), Utc::now());
+        assert_eq!(changes.added_routes, vec!["3".to_string()]);
+        assert_eq!(changes.removed_routes, vec!["2".to_string()]);
+        assert_eq!(changes.pattern_changes.len(), 2);
+        assert_eq!(changes.pattern_changes[0].added.len(), 1);
+        assert_eq!(changes.pattern_changes[0].removed.len(), 1);
+        assert_eq!(changes.moved_stops.len(), 1);
+        assert_eq!(changes.moved_stops[0].stop_id, "b");
+        assert_eq!(changes.service_changes[0].added_dates, vec![date(3)]);
+        assert_eq!(changes.service_changes[0].removed_dates, vec![date(1)]);
+
+        let subroutes = [
+            (10, cluster(&["a", "b"], "t1")),
+            (11, cluster(&["c"], "t3")),
+            (12, cluster(&["b", "c"], "t4")),
+        ];
+        assert_eq!(affected_subroutes(&changes, &subroutes), vec![10, 12]);
+    }
+}
@@ -0,0 +1,473 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use commons::models::calendar::Almanac;
+
+use super::models::export;
+use super::sql;
+use crate::geo::sql as geo_sql;
+use crate::operators::models::responses::OperatorCalendar;
+use crate::operators::sql as operators_sql;
+use crate::Error;
+
+const TIMEZONE: &str = "Europe/Lisbon";
+const LANG: &str = "pt";
+// Every IML route is currently a bus route
+const ROUTE_TYPE_BUS: u8 = 3;
+// Precision used in the stored subroute polylines
+const POLYLINE_PRECISION: u32 = 6;
+
+#[derive(Serialize)]
+struct AgencyRecord<'a> {
+    agency_id: i32,
+    agency_name: &'a str,
+    agency_url: &'a str,
+    agency_timezone: &'static str,
+    agency_lang: &'static str,
+}
+
+#[derive(Serialize)]
+struct StopRecord<'a> {
+    stop_id: i32,
+    stop_name: &'a str,
+    stop_lat: f64,
+    stop_lon: f64,
+}
+
+#[derive(Serialize)]
+struct RouteRecord<'a> {
+    route_id: i32,
+    agency_id: i32,
+    route_short_name: &'a str,
+    route_long_name: &'a str,
+    route_type: u8,
+    route_color: &'a str,
+    route_text_color: &'a str,
+}
+
+#[derive(Serialize)]
+struct TripRecord<'a> {
+    route_id: i32,
+    service_id: i32,
+    trip_id: i32,
+    trip_headsign: &'a str,
+    direction_id: Option<u8>,
+    shape_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct StopTimeRecord {
+    trip_id: i32,
+    arrival_time: String,
+    departure_time: String,
+    stop_id: i32,
+    stop_sequence: usize,
+}
+
+#[derive(Serialize)]
+struct CalendarDateRecord {
+    service_id: i32,
+    date: String,
+    exception_type: u8,
+}
+
+#[derive(Serialize)]
+struct ShapeRecord {
+    shape_id: i32,
+    shape_pt_lat: f64,
+    shape_pt_lon: f64,
+    shape_pt_sequence: usize,
+}
+
+#[derive(Serialize)]
+struct FeedInfoRecord<'a> {
+    feed_publisher_name: &'a str,
+    feed_publisher_url: &'a str,
+    feed_lang: &'static str,
+    feed_start_date: String,
+    feed_end_date: String,
+}
+
+/// Builds a GTFS feed (as zip file contents) for an operator's active network.
+/// The operator calendars are expanded into `calendar_dates.txt`
+/// entries for every day in `[from, from + days[`.
+pub(crate) async fn build_operator_feed(
+    pool: &PgPool,
+    operator_id: i32,
+    from: NaiveDate,
+    days: u16,
+) -> Result<Vec<u8>, Error> {
+    let agency = sql::fetch_export_agency(pool, operator_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+    let stops = sql::fetch_export_stops(pool, operator_id).await?;
+    let routes = sql::fetch_export_routes(pool, operator_id).await?;
+    let subroutes = sql::fetch_export_subroutes(pool, operator_id).await?;
+    let subroute_stops =
+        sql::fetch_export_subroute_stops(pool, operator_id).await?;
+    let departures = sql::fetch_export_departures(pool, operator_id).await?;
+    let calendars =
+        operators_sql::fetch_operator_calendars(pool, operator_id).await?;
+    let regions = geo_sql::fetch_operator_regions(pool, operator_id).await?;
+    // Municipal holidays only hold within their municipality,
+    // so they cannot apply to a network as a whole
+    let almanac = geo_sql::fetch_almanac(pool, &regions, None).await?;
+
+    let feed = Feed {
+        agency,
+        stops,
+        routes,
+        subroutes,
+        subroute_stops,
+        departures,
+        calendars,
+        almanac,
+        from,
+        days,
+    };
+
+    let mut buffer = io::Cursor::new(vec![]);
+    feed.write(&mut buffer)?;
+    Ok(buffer.into_inner())
+}
+
+struct Feed {
+    agency: export::Agency,
+    stops: Vec<export::Stop>,
+    routes: Vec<export::Route>,
+    subroutes: Vec<export::Subroute>,
+    subroute_stops: Vec<export::SubrouteStop>,
+    departures: Vec<export::Departure>,
+    calendars: Vec<OperatorCalendar>,
+    almanac: Almanac,
+    from: NaiveDate,
+    days: u16,
+}
+
+impl Feed {
+    fn write<W: Write + io::Seek>(&self, writer: W) -> Result<(), Error> {
+        let mut zip = zip::ZipWriter::new(writer);
+
+        // Services without any date in the window would be dangling
+        let calendar_dates = self.calendar_dates();
+        let active_services = calendar_dates
+            .iter()
+            .map(|record| record.service_id)
+            .collect::<HashSet<i32>>();
+        // Trips without times at every stop would be invalid
+        let timed_subroutes = timed_subroutes(&self.subroute_stops);
+        let departures = self
+            .departures
+            .iter()
+            .filter(|dep| {
+                active_services.contains(&dep.calendar_id)
+                    && timed_subroutes.contains(&dep.subroute_id)
+            })
+            .collect::<Vec<_>>();
+
+        write_file(&mut zip, "agency.txt", &[self.agency_record()])?;
+        write_file(&mut zip, "stops.txt", &self.stop_records())?;
+        write_file(&mut zip, "routes.txt", &self.route_records())?;
+        write_file(&mut zip, "trips.txt", &self.trip_records(&departures))?;
+        write_file(
+            &mut zip,
+            "stop_times.txt",
+            &self.stop_time_records(&departures),
+        )?;
+        write_file(&mut zip, "calendar_dates.txt", &calendar_dates)?;
+        write_file(&mut zip, "shapes.txt", &self.shape_records())?;
+        write_file(&mut zip, "feed_info.txt", &[self.feed_info_record()])?;
+
+        zip.finish().map_err(|err| {
+            tracing::error!("Failed to finish the GTFS archive: {err}");
+            Error::Serialization
+        })?;
+        Ok(())
+    }
+
+    fn agency_record(&self) -> AgencyRecord<'_> {
+        AgencyRecord {
+            agency_id: self.agency.id,
+            agency_name: &self.agency.name,
+            agency_url: self.agency_url(),
+            agency_timezone: TIMEZONE,
+            agency_lang: LANG,
+        }
+    }
+
+    fn agency_url(&self) -> &str {
+        self.agency
+            .website_url
+            .as_deref()
+            .unwrap_or("https://intermodal.pt")
+    }
+
+    fn stop_records(&self) -> Vec<StopRecord<'_>> {
+        self.stops
+            .iter()
+            .map(|stop| StopRecord {
+                stop_id: stop.id,
+                stop_name: &stop.name,
+                stop_lat: stop.lat,
+                stop_lon: stop.lon,
+            })
+            .collect()
+    }
+
+    fn route_records(&self) -> Vec<RouteRecord<'_>> {
+        self.routes
+            .iter()
+            .map(|route| RouteRecord {
+                route_id: route.id,
+                agency_id: self.agency.id,
+                route_short_name: route.code.as_deref().unwrap_or_default(),
+                route_long_name: &route.name,
+                route_type: ROUTE_TYPE_BUS,
+                route_color: route.badge_bg.trim_start_matches('#'),
+                route_text_color: route.badge_text.trim_start_matches('#'),
+            })
+            .collect()
+    }
+
+    fn trip_records(
+        &self,
+        departures: &[&export::Departure],
+    ) -> Vec<TripRecord<'_>> {
+        let subroutes = self
+            .subroutes
+            .iter()
+            .map(|subroute| (subroute.id, subroute))
+            .collect::<HashMap<i32, &export::Subroute>>();
+
+        departures
+            .iter()
+            .filter_map(|departure| {
+                let subroute = subroutes.get(&departure.subroute_id)?;
+                Some(TripRecord {
+                    route_id: subroute.route_id,
+                    service_id: departure.calendar_id,
+                    trip_id: departure.id,
+                    trip_headsign: &subroute.headsign,
+                    direction_id: None,
+                    shape_id: subroute.polyline.as_ref().map(|_| subroute.id),
+                })
+            })
+            .collect()
+    }
+
+    fn stop_time_records(
+        &self,
+        departures: &[&export::Departure],
+    ) -> Vec<StopTimeRecord> {
+        let mut subroute_stops: HashMap<i32, Vec<&export::SubrouteStop>> =
+            HashMap::new();
+        for subroute_stop in &self.subroute_stops {
+            subroute_stops
+                .entry(subroute_stop.subroute_id)
+                .or_default()
+                .push(subroute_stop);
+        }
+
+        let mut records = vec![];
+        for departure in departures {
+            let Some(stops) = subroute_stops.get(&departure.subroute_id) else {
+                continue;
+            };
+
+            let mut offset = i32::from(departure.time) * 60;
+            for (sequence, stop) in stops.iter().enumerate() {
+                let time = format_gtfs_time(offset);
+                records.push(StopTimeRecord {
+                    trip_id: departure.id,
+                    arrival_time: time.clone(),
+                    departure_time: time,
+                    stop_id: stop.stop_id,
+                    stop_sequence: sequence,
+                });
+                offset += stop.time_to_next.unwrap_or_default();
+            }
+        }
+        records
+    }
+
+    fn calendar_dates(&self) -> Vec<CalendarDateRecord> {
+        let mut records = vec![];
+        for date in self.from.iter_days().take(usize::from(self.days)) {
+            for calendar in &self.calendars {
+                if calendar.calendar.includes(date, &self.almanac) {
+                    records.push(CalendarDateRecord {
+                        service_id: calendar.id,
+                        date: format_gtfs_date(date),
+                        exception_type: 1,
+                    });
+                }
+            }
+        }
+        records
+    }
+
+    fn shape_records(&self) -> Vec<ShapeRecord> {
+        let mut records = vec![];
+        for subroute in &self.subroutes {
+            let Some(polyline) = &subroute.polyline else {
+                continue;
+            };
+            let line =
+                match polyline::decode_polyline(polyline, POLYLINE_PRECISION) {
+                    Ok(line) => line,
+                    Err(err) => {
+                        tracing::warn!(
+                            "Subroute {} has an invalid polyline: {err}",
+                            subroute.id
+                        );
+                        continue;
+                    }
+                };
+            records.extend(line.coords().enumerate().map(
+                |(sequence, coord)| ShapeRecord {
+                    shape_id: subroute.id,
+                    shape_pt_lat: coord.y,
+                    shape_pt_lon: coord.x,
+                    shape_pt_sequence: sequence,
+                },
+            ));
+        }
+        records
+    }
+
+    fn feed_info_record(&self) -> FeedInfoRecord<'_> {
+        let end =
+            self.from + chrono::Days::new(u64::from(self.days.max(1)) - 1);
+        FeedInfoRecord {
+            feed_publisher_name: "Intermodal",
+            feed_publisher_url: "https://intermodal.pt",
+            feed_lang: LANG,
+            feed_start_date: format_gtfs_date(self.from),
+            feed_end_date: format_gtfs_date(end),
+        }
+    }
+}
+
+fn write_file<W, R>(
+    zip: &mut zip::ZipWriter<W>,
+    name: &str,
+    records: &[R],
+) -> Result<(), Error>
+where
+    W: Write + io::Seek,
+    R: Serialize,
+{
+    zip.start_file(name, zip::write::SimpleFileOptions::default())
+        .map_err(|err| {
+            tracing::error!("Failed to start {name} in the archive: {err}");
+            Error::Serialization
+        })?;
+
+    let mut wtr = csv::Writer::from_writer(zip);
+    for record in records {
+        wtr.serialize(record).map_err(|err| {
+            tracing::error!("Failed to serialize a {name} record: {err}");
+            Error::Serialization
+        })?;
+    }
+    wtr.flush().map_err(|err| {
+        tracing::error!("Failed to write {name}: {err}");
+        Error::Serialization
+    })
+}
+
+/// Subroutes with a known travel time between every pair of consecutive stops
+fn timed_subroutes(subroute_stops: &[export::SubrouteStop]) -> HashSet<i32> {
+    let mut subroutes: HashMap<i32, Vec<&export::SubrouteStop>> =
+        HashMap::new();
+    for subroute_stop in subroute_stops {
+        subroutes
+            .entry(subroute_stop.subroute_id)
+            .or_default()
+            .push(subroute_stop);
+    }
+
+    subroutes
+        .into_iter()
+        .filter(|(_, stops)| {
+            stops.split_last().is_some_and(|(_, preceding)| {
+                preceding.iter().all(|stop| stop.time_to_next.is_some())
+            })
+        })
+        .map(|(subroute_id, _)| subroute_id)
+        .collect()
+}
+
+/// Formats a number of seconds since midnight as `HH:MM:SS`.
+/// Hours are allowed to go past 24 as per the GTFS specification
+fn format_gtfs_time(seconds: i32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
+
+fn format_gtfs_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gtfs_time_formatting() {
+        assert_eq!(format_gtfs_time(0), "00:00:00");
+        assert_eq!(format_gtfs_time(8 * 3600 + 5 * 60 + 7), "08:05:07");
+        assert_eq!(format_gtfs_time(25 * 3600 + 30 * 60), "25:30:00");
+    }
+
+    #[test]
+    fn untimed_subroutes_are_left_out() {
+        let stop = |subroute_id, stop_id, time_to_next| export::SubrouteStop {
+            subroute_id,
+            stop_id,
+            time_to_next,
+        };
+        let subroute_stops = [
+            stop(1, 10, Some(60)),
+            stop(1, 11, Some(90)),
+            stop(1, 12, None),
+            stop(2, 10, Some(60)),
+            stop(2, 11, None),
+            stop(2, 12, None),
+        ];
+
+        assert_eq!(timed_subroutes(&subroute_stops), HashSet::from([1]));
+    }
+
+    #[test]
+    fn gtfs_date_formatting() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 9).unwrap();
+        assert_eq!(format_gtfs_date(date), "20240309");
+    }
+}
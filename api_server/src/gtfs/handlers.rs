@@ -19,32 +19,225 @@
 use std::sync::Arc;
 use std::{fs, io};
 
-use axum::extract::{Path, State};
+use axum::extract::{Multipart, Path, Query, State};
+use axum::http::header;
+use axum::response::IntoResponse;
 use axum::Json;
+use chrono::NaiveDate;
+use serde::Deserialize;
 use tracing::log;
 
 use commons::models::gtfs::{self, File};
+use commons::models::history;
 use commons::utils::gtfs::{
     calculate_gtfs_stop_sequence, calculate_stop_sliding_windows,
 };
 
 use super::models::{requests, responses};
 use super::sql;
-use super::{loaders, models};
-use crate::operators::import::{update_operator_gtfs, OperatorData};
+use super::{diff, export, loaders, logic, models};
+use crate::operators::import::{get_operator_feed_changes, OperatorData};
 use crate::operators::sql as operators_sql;
-use crate::{auth, AppState, Error};
+use crate::responses::IdReturn;
+use crate::utils::get_exactly_one_field;
+use crate::{auth, contrib, AppState, Error};
+
+// Default amount of days covered by an exported feed
+const EXPORT_DEFAULT_DAYS: u16 = 60;
+const EXPORT_MAX_DAYS: u16 = 366;
+
+#[derive(Deserialize, Default)]
+pub(crate) struct ExportParams {
+    from: Option<NaiveDate>,
+    days: Option<u16>,
+}
 
 pub(crate) async fn post_update_operator_gtfs(
     State(state): State<AppState>,
     Path(operator_id): Path<i32>,
     auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::PatchGtfs>,
 ) -> Result<(), Error> {
+    let source = sql::fetch_operator_gtfs_source(&state.pool, operator_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    logic::refresh_gtfs_source(&state.pool, &source).await?;
+    Ok(())
+}
+
+pub(crate) async fn post_upload_operator_gtfs(
+    State(state): State<AppState>,
+    Path(operator_id): Path<i32>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::PatchGtfs>,
+    mut multipart: Multipart,
+) -> Result<(), Error> {
+    let source = sql::fetch_operator_gtfs_source(&state.pool, operator_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let field = get_exactly_one_field(&mut multipart).await?;
+    let content = field
+        .bytes()
+        .await
+        .map_err(|err| Error::ValidationFailure(err.to_string()))?;
+
+    logic::import_gtfs_archive(&state.pool, &source, &content).await?;
+    Ok(())
+}
+
+pub(crate) async fn get_gtfs_sources(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::PatchGtfs>,
+) -> Result<Json<Vec<responses::GtfsSource>>, Error> {
+    Ok(Json(
+        sql::fetch_gtfs_sources(&state.pool)
+            .await?
+            .into_iter()
+            .map(responses::GtfsSource::from)
+            .collect(),
+    ))
+}
+
+pub(crate) async fn get_operator_gtfs_source(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::PatchGtfs>,
+    Path(operator_id): Path<i32>,
+) -> Result<Json<responses::GtfsSource>, Error> {
+    sql::fetch_operator_gtfs_source(&state.pool, operator_id)
+        .await?
+        .map(|source| Json(source.into()))
+        .ok_or(Error::NotFoundUpstream)
+}
+
+pub(crate) async fn put_operator_gtfs_source(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<auth::perms::PatchGtfs>,
+    Path(operator_id): Path<i32>,
+    Json(source): Json<requests::ChangeGtfsSource>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    source.validate()?;
+
+    let original =
+        sql::fetch_operator_gtfs_source(&state.pool, operator_id).await?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id = sql::upsert_operator_gtfs_source(
+        &mut transaction,
+        operator_id,
+        &source,
+    )
+    .await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::GtfsSourceUpdate {
+            original: original.map(Into::into),
+            source: source.to_history(id, operator_id),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn delete_operator_gtfs_source(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<auth::perms::PatchGtfs>,
+    Path(operator_id): Path<i32>,
+) -> Result<(), Error> {
+    let source = sql::fetch_operator_gtfs_source(&state.pool, operator_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::delete_operator_gtfs_source(&mut transaction, operator_id).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::GtfsSourceDeletion {
+            data: source.into(),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn get_operator_gtfs_export(
+    State(state): State<AppState>,
+    Path(operator_id): Path<i32>,
+    params: Query<ExportParams>,
+) -> Result<impl IntoResponse, Error> {
+    let from = params
+        .from
+        .unwrap_or_else(|| chrono::Local::now().date_naive());
+    let days = params.days.unwrap_or(EXPORT_DEFAULT_DAYS);
+    if days == 0 || days > EXPORT_MAX_DAYS {
+        return Err(Error::MalformedRequest("Invalid amount of days"));
+    }
+
+    let feed =
+        export::build_operator_feed(&state.pool, operator_id, from, days)
+            .await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"iml_{operator_id}_gtfs.zip\""),
+            ),
+        ],
+        feed,
+    ))
+}
+
+/// Lists the changes between every pair of consecutive feed versions,
+/// newest first
+pub(crate) async fn get_operator_gtfs_changes(
+    State(state): State<AppState>,
+    Path(operator_id): Path<i32>,
+) -> Result<Json<Vec<responses::FeedChanges>>, Error> {
     let operator = operators_sql::fetch_operator(&state.pool, operator_id)
         .await?
         .ok_or(Error::NotFoundUpstream)?;
 
-    update_operator_gtfs(operator.id, &operator.tag).await
+    let feed_changes = get_operator_feed_changes(operator.id)?;
+    let subroute_clusters =
+        sql::fetch_operator_subroute_clusters(&state.pool, operator.id).await?;
+
+    Ok(Json(
+        feed_changes
+            .into_iter()
+            .map(|changes| responses::FeedChanges {
+                affected_subroutes: diff::affected_subroutes(
+                    &changes,
+                    &subroute_clusters,
+                ),
+                changes,
+            })
+            .collect(),
+    ))
 }
 
 pub(crate) async fn get_gtfs_stops(
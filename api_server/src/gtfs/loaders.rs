@@ -17,11 +17,13 @@
 */
 
 use std::collections::HashMap;
+use std::path::Path;
 use std::{fs, io};
 
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::de::DeserializeOwned;
 use tracing::log;
 
 use commons::models::gtfs::{self, File};
@@ -127,6 +129,46 @@ pub(crate) fn gtfs_stop_times(
         .collect::<Vec<gtfs::StopTime>>())
 }
 
+// Read every entity of a file in the GTFS at `root`
+pub(crate) fn gtfs_file<E: DeserializeOwned>(
+    root: &Path,
+    file: &File,
+) -> Result<Vec<E>, Error> {
+    let path = file.prepend_root(root);
+
+    if !path.exists() {
+        return Err(Error::NotFoundUpstream);
+    }
+
+    let f = fs::File::open(&path).map_err(|err| {
+        tracing::error!(msg = "Filesystem error", err=?err, path=?path);
+        Error::Filesystem
+    })?;
+    let reader = io::BufReader::new(f);
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    rdr.deserialize()
+        .collect::<Result<Vec<E>, _>>()
+        .map_err(|err| {
+            tracing::error!(msg = "Invalid GTFS file", err=?err, path=?path);
+            Error::Processing
+        })
+}
+
+// Same as `gtfs_file`, for files that feeds can omit
+pub(crate) fn optional_gtfs_file<E: DeserializeOwned>(
+    root: &Path,
+    file: &File,
+) -> Result<Vec<E>, Error> {
+    match gtfs_file(root, file) {
+        Err(Error::NotFoundUpstream) => Ok(vec![]),
+        res => res,
+    }
+}
+
 // Have regex as a static
 static SUBROUTE_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^[\w\d]*_(?P<subroute>\d{4}_\d_\d)_").unwrap());
@@ -0,0 +1,50 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use sqlx::PgPool;
+
+use super::models;
+use super::sql;
+use crate::operators::import;
+use crate::Error;
+
+/// Fetches the feed of a source. Returns whether it changed since the last
+/// time that it was fetched.
+pub(crate) async fn refresh_gtfs_source(
+    pool: &PgPool,
+    source: &models::GtfsSource,
+) -> Result<bool, Error> {
+    let content = import::download_gtfs(source).await?;
+    import_gtfs_archive(pool, source, &content).await
+}
+
+/// Stores an archive as the feed of a source, unless it is the same one
+/// that was last fetched. Returns whether the feed changed.
+pub(crate) async fn import_gtfs_archive(
+    pool: &PgPool,
+    source: &models::GtfsSource,
+    content: &[u8],
+) -> Result<bool, Error> {
+    let checksum = import::gtfs_checksum(content);
+    let changed = source.checksum.as_deref() != Some(checksum.as_str());
+    if changed {
+        import::store_gtfs(source.operator_id, content)?;
+    }
+    sql::update_gtfs_source_fetch(pool, source.id, &checksum).await?;
+    Ok(changed)
+}
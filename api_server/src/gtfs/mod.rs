@@ -16,7 +16,10 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+pub(crate) mod diff;
+mod export;
 pub(crate) mod handlers;
 mod loaders;
+pub(crate) mod logic;
 pub(crate) mod models;
-mod sql;
+pub(crate) mod sql;
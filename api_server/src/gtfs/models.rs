@@ -16,12 +16,91 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
 use sqlx::types::Json;
 
-use commons::models::gtfs;
+use commons::models::{gtfs, history};
+
+use crate::Error;
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize_repr, Deserialize_repr)]
+pub enum GtfsSourceKind {
+    // A zip archive at a known URL
+    Zip = 0,
+    // A feed published by Transporlis
+    Transporlis = 1,
+    // Archives manually uploaded by editors
+    Upload = 2,
+}
+
+impl TryFrom<i16> for GtfsSourceKind {
+    type Error = Error;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Zip),
+            1 => Ok(Self::Transporlis),
+            2 => Ok(Self::Upload),
+            _ => Err(Error::DatabaseDeserialization),
+        }
+    }
+}
+
+impl From<GtfsSourceKind> for i16 {
+    fn from(kind: GtfsSourceKind) -> Self {
+        i16::from(kind as u8)
+    }
+}
+
+impl From<GtfsSourceKind> for history::gtfs::GtfsSourceKind {
+    fn from(kind: GtfsSourceKind) -> Self {
+        match kind {
+            GtfsSourceKind::Zip => Self::Zip,
+            GtfsSourceKind::Transporlis => Self::Transporlis,
+            GtfsSourceKind::Upload => Self::Upload,
+        }
+    }
+}
+
+/// Where the feed of an operator comes from
+#[derive(Debug)]
+pub(crate) struct GtfsSource {
+    pub(crate) id: i32,
+    pub(crate) operator_id: i32,
+    pub(crate) kind: GtfsSourceKind,
+    pub(crate) url: Option<String>,
+    pub(crate) transporlis_id: Option<i32>,
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) refresh_interval: Option<i32>,
+    pub(crate) checksum: Option<String>,
+    pub(crate) last_fetch: Option<DateTime<Utc>>,
+}
+
+impl From<GtfsSource> for history::gtfs::GtfsSource {
+    fn from(source: GtfsSource) -> Self {
+        Self {
+            id: source.id,
+            operator_id: source.operator_id,
+            kind: source.kind.into(),
+            url: source.url,
+            transporlis_id: source.transporlis_id,
+            header_names: header_names(&source.headers),
+            refresh_interval: source.refresh_interval,
+        }
+    }
+}
+
+fn header_names(headers: &HashMap<String, String>) -> Vec<String> {
+    let mut names = headers.keys().cloned().collect::<Vec<_>>();
+    names.sort_unstable();
+    names
+}
 
 #[derive(Debug, Eq, Clone, Serialize, Deserialize)]
 pub struct TMLTrip {
@@ -70,12 +149,106 @@ pub(crate) struct SubrouteValidationData {
     // pub(crate) gtfs: Json<gtfs::PatternCluster>,
 }
 
+/// Data needed to assemble a GTFS export of an operator's network
+pub(crate) mod export {
+    pub(crate) struct Agency {
+        pub(crate) id: i32,
+        pub(crate) name: String,
+        pub(crate) website_url: Option<String>,
+    }
+
+    pub(crate) struct Stop {
+        pub(crate) id: i32,
+        pub(crate) name: String,
+        pub(crate) lat: f64,
+        pub(crate) lon: f64,
+    }
+
+    pub(crate) struct Route {
+        pub(crate) id: i32,
+        pub(crate) code: Option<String>,
+        pub(crate) name: String,
+        pub(crate) badge_text: String,
+        pub(crate) badge_bg: String,
+    }
+
+    pub(crate) struct Subroute {
+        pub(crate) id: i32,
+        pub(crate) route_id: i32,
+        pub(crate) headsign: String,
+        pub(crate) polyline: Option<String>,
+    }
+
+    pub(crate) struct SubrouteStop {
+        pub(crate) subroute_id: i32,
+        pub(crate) stop_id: i32,
+        // Travel time (in seconds) to the next stop in the subroute
+        pub(crate) time_to_next: Option<i32>,
+    }
+
+    pub(crate) struct Departure {
+        pub(crate) id: i32,
+        pub(crate) subroute_id: i32,
+        pub(crate) calendar_id: i32,
+        // Departure time in minutes starting at midnight
+        pub(crate) time: i16,
+    }
+}
+
+/// Differences between two consecutive versions of an operator's feed
+pub(crate) mod changes {
+    use chrono::{DateTime, NaiveDate, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use commons::models::gtfs;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub(crate) struct FeedChanges {
+        // Versions being compared
+        pub(crate) from: DateTime<Utc>,
+        pub(crate) to: DateTime<Utc>,
+        pub(crate) added_routes: Vec<gtfs::RouteId>,
+        pub(crate) removed_routes: Vec<gtfs::RouteId>,
+        pub(crate) pattern_changes: Vec<PatternChange>,
+        pub(crate) moved_stops: Vec<MovedStop>,
+        pub(crate) service_changes: Vec<ServiceChange>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub(crate) struct PatternChange {
+        pub(crate) route_id: gtfs::RouteId,
+        pub(crate) added: Vec<gtfs::PatternCluster>,
+        pub(crate) removed: Vec<gtfs::PatternCluster>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub(crate) struct MovedStop {
+        pub(crate) stop_id: gtfs::StopId,
+        pub(crate) previous_lat: f64,
+        pub(crate) previous_lon: f64,
+        pub(crate) lat: f64,
+        pub(crate) lon: f64,
+        // Meters between both positions
+        pub(crate) distance: f64,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub(crate) struct ServiceChange {
+        pub(crate) service_id: gtfs::ServiceId,
+        pub(crate) added_dates: Vec<NaiveDate>,
+        pub(crate) removed_dates: Vec<NaiveDate>,
+    }
+}
+
 pub(crate) mod requests {
     use serde::Deserialize;
     use std::collections::HashMap;
 
-    use commons::models::gtfs;
     use commons::models::gtfs::PatternId;
+    use commons::models::{gtfs, history};
+
+    use super::GtfsSourceKind;
+    use crate::Error;
 
     #[derive(Debug, Deserialize)]
     pub(crate) struct RouteSubroutesValidation {
@@ -97,18 +270,124 @@ pub(crate) mod requests {
         pub(crate) from_stop_ids: Vec<i32>,
         pub(crate) to_stop_ids: Vec<i32>,
     }
+
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct ChangeGtfsSource {
+        pub(crate) kind: GtfsSourceKind,
+        pub(crate) url: Option<String>,
+        pub(crate) transporlis_id: Option<i32>,
+        #[serde(default)]
+        pub(crate) headers: HashMap<String, String>,
+        pub(crate) refresh_interval: Option<i32>,
+    }
+
+    impl ChangeGtfsSource {
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            match self.kind {
+                GtfsSourceKind::Zip => {
+                    if !self.url.as_ref().is_some_and(|url| {
+                        url.starts_with("https://")
+                            || url.starts_with("http://")
+                    }) {
+                        return Err(Error::ValidationFailure(
+                            "Zip sources need an HTTP(S) URL".to_string(),
+                        ));
+                    }
+                }
+                GtfsSourceKind::Transporlis => {
+                    if self.transporlis_id.is_none() {
+                        return Err(Error::ValidationFailure(
+                            "Transporlis sources need an ID".to_string(),
+                        ));
+                    }
+                }
+                GtfsSourceKind::Upload => {
+                    if self.refresh_interval.is_some() {
+                        return Err(Error::ValidationFailure(
+                            "Uploaded sources cannot be refreshed".to_string(),
+                        ));
+                    }
+                }
+            }
+            if self.refresh_interval.is_some_and(|interval| interval <= 0) {
+                return Err(Error::ValidationFailure(
+                    "Invalid refresh interval".to_string(),
+                ));
+            }
+            Ok(())
+        }
+
+        /// The source as it gets recorded in the changelog
+        pub(crate) fn to_history(
+            &self,
+            id: i32,
+            operator_id: i32,
+        ) -> history::gtfs::GtfsSource {
+            history::gtfs::GtfsSource {
+                id,
+                operator_id,
+                kind: self.kind.into(),
+                url: self.url.clone(),
+                transporlis_id: self.transporlis_id,
+                header_names: super::header_names(&self.headers),
+                refresh_interval: self.refresh_interval,
+            }
+        }
+    }
 }
 
 pub(crate) mod responses {
+    use chrono::{DateTime, Utc};
     use serde::Serialize;
     use std::collections::HashMap;
 
     use commons::models::gtfs;
+
+    use super::{changes, GtfsSourceKind};
+
     #[derive(Debug, Serialize)]
     pub(crate) struct RouteValidation {
         pub(crate) validation: Option<sqlx::types::Json<gtfs::RouteValidation>>,
         pub(crate) subroutes: HashMap<i32, gtfs::SubrouteValidation>,
     }
+
+    #[derive(Debug, Serialize)]
+    pub(crate) struct FeedChanges {
+        #[serde(flatten)]
+        pub(crate) changes: changes::FeedChanges,
+        // IML subroutes whose GTFS counterparts got changed
+        pub(crate) affected_subroutes: Vec<i32>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub(crate) struct GtfsSource {
+        pub(crate) id: i32,
+        pub(crate) operator_id: i32,
+        pub(crate) kind: GtfsSourceKind,
+        pub(crate) url: Option<String>,
+        pub(crate) transporlis_id: Option<i32>,
+        // The values are left out, as they tend to be credentials
+        pub(crate) header_names: Vec<String>,
+        pub(crate) refresh_interval: Option<i32>,
+        pub(crate) checksum: Option<String>,
+        pub(crate) last_fetch: Option<DateTime<Utc>>,
+    }
+
+    impl From<super::GtfsSource> for GtfsSource {
+        fn from(source: super::GtfsSource) -> Self {
+            GtfsSource {
+                header_names: super::header_names(&source.headers),
+                id: source.id,
+                operator_id: source.operator_id,
+                kind: source.kind,
+                url: source.url,
+                transporlis_id: source.transporlis_id,
+                refresh_interval: source.refresh_interval,
+                checksum: source.checksum,
+                last_fetch: source.last_fetch,
+            }
+        }
+    }
 }
 
 #[cfg(test)]
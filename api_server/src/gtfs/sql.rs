@@ -16,13 +16,16 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
 use serde_json::json;
 use sqlx::types::Json;
 use sqlx::PgPool;
 
 use commons::models::gtfs;
 
-use super::models::{self, responses};
+use super::models::{self, export, requests, responses, GtfsSourceKind};
 use crate::Error;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -268,3 +271,339 @@ WHERE id=$2
 
     Ok(())
 }
+
+pub(crate) async fn fetch_export_agency(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Option<export::Agency>> {
+    sqlx::query_as!(
+        export::Agency,
+        r#"
+SELECT id, name, website_url
+FROM operators
+WHERE id = $1
+"#,
+        operator_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })
+}
+
+/// Fetches the stops that are served by the operator's active routes
+pub(crate) async fn fetch_export_stops(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Vec<export::Stop>> {
+    sqlx::query_as!(
+        export::Stop,
+        r#"
+SELECT DISTINCT stops.id, stops.name, stops.lat, stops.lon
+FROM stops
+JOIN subroute_stops ON subroute_stops.stop = stops.id
+JOIN subroutes ON subroutes.id = subroute_stops.subroute
+JOIN routes ON routes.id = subroutes.route
+WHERE routes.operator = $1 AND routes.active
+ORDER BY stops.id ASC
+"#,
+        operator_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_export_routes(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Vec<export::Route>> {
+    sqlx::query_as!(
+        export::Route,
+        r#"
+SELECT routes.id, routes.code, routes.name,
+    COALESCE(routes.badge_text_color, route_types.badge_text_color) as "badge_text!: String",
+    COALESCE(routes.badge_bg_color, route_types.badge_bg_color) as "badge_bg!: String"
+FROM routes
+JOIN route_types ON routes.type = route_types.id
+WHERE routes.operator = $1 AND routes.active
+ORDER BY routes.id ASC
+"#,
+        operator_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_export_subroutes(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Vec<export::Subroute>> {
+    sqlx::query_as!(
+        export::Subroute,
+        r#"
+SELECT subroutes.id, subroutes.route as route_id, subroutes.headsign,
+    subroutes.polyline
+FROM subroutes
+JOIN routes ON routes.id = subroutes.route
+WHERE routes.operator = $1 AND routes.active
+ORDER BY subroutes.id ASC
+"#,
+        operator_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })
+}
+
+/// Fetches every subroute stop of the operator's active routes,
+/// ordered by subroute and then by the stop order within it
+pub(crate) async fn fetch_export_subroute_stops(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Vec<export::SubrouteStop>> {
+    sqlx::query_as!(
+        export::SubrouteStop,
+        r#"
+SELECT subroute_stops.subroute as subroute_id,
+    subroute_stops.stop as stop_id,
+    subroute_stops.time_to_next
+FROM subroute_stops
+JOIN subroutes ON subroutes.id = subroute_stops.subroute
+JOIN routes ON routes.id = subroutes.route
+WHERE routes.operator = $1 AND routes.active
+ORDER BY subroute_stops.subroute ASC, subroute_stops.idx ASC
+"#,
+        operator_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_export_departures(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Vec<export::Departure>> {
+    sqlx::query_as!(
+        export::Departure,
+        r#"
+SELECT departures.id, departures.subroute as subroute_id,
+    departures.calendar_id, departures.time
+FROM departures
+JOIN subroutes ON subroutes.id = departures.subroute
+JOIN routes ON routes.id = subroutes.route
+WHERE routes.operator = $1 AND routes.active
+ORDER BY departures.subroute ASC, departures.time ASC
+"#,
+        operator_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_operator_subroute_clusters(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Vec<(i32, gtfs::PatternCluster)>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT subroutes.id,
+    subroutes.validation_gtfs as "validation_gtfs!: Json<gtfs::PatternCluster>"
+FROM subroutes
+JOIN routes ON routes.id = subroutes.route
+WHERE routes.operator = $1
+    AND subroutes.validation_gtfs IS NOT NULL
+    AND subroutes.validation_gtfs != '{}'::jsonb
+"#,
+        operator_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| (row.id, row.validation_gtfs.0))
+    .collect())
+}
+
+struct GtfsSourceRow {
+    id: i32,
+    operator_id: i32,
+    kind: i16,
+    url: Option<String>,
+    transporlis_id: Option<i32>,
+    headers: Json<HashMap<String, String>>,
+    refresh_interval: Option<i32>,
+    checksum: Option<String>,
+    last_fetch: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<GtfsSourceRow> for models::GtfsSource {
+    type Error = Error;
+
+    fn try_from(row: GtfsSourceRow) -> Result<Self> {
+        Ok(models::GtfsSource {
+            id: row.id,
+            operator_id: row.operator_id,
+            kind: GtfsSourceKind::try_from(row.kind)?,
+            url: row.url,
+            transporlis_id: row.transporlis_id,
+            headers: row.headers.0,
+            refresh_interval: row.refresh_interval,
+            checksum: row.checksum,
+            last_fetch: row.last_fetch,
+        })
+    }
+}
+
+pub(crate) async fn fetch_gtfs_sources(
+    pool: &PgPool,
+) -> Result<Vec<models::GtfsSource>> {
+    sqlx::query_as!(
+        GtfsSourceRow,
+        r#"
+SELECT id, operator_id, kind, url, transporlis_id,
+    headers as "headers!: Json<HashMap<String, String>>",
+    refresh_interval, checksum, last_fetch
+FROM gtfs_sources
+ORDER BY operator_id
+"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(models::GtfsSource::try_from)
+    .collect()
+}
+
+pub(crate) async fn fetch_operator_gtfs_source(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Option<models::GtfsSource>> {
+    sqlx::query_as!(
+        GtfsSourceRow,
+        r#"
+SELECT id, operator_id, kind, url, transporlis_id,
+    headers as "headers!: Json<HashMap<String, String>>",
+    refresh_interval, checksum, last_fetch
+FROM gtfs_sources
+WHERE operator_id = $1
+"#,
+        operator_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })?
+    .map(models::GtfsSource::try_from)
+    .transpose()
+}
+
+pub(crate) async fn upsert_operator_gtfs_source(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+    source: &requests::ChangeGtfsSource,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO gtfs_sources
+    (operator_id, kind, url, transporlis_id, headers, refresh_interval)
+VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (operator_id) DO UPDATE
+SET kind = EXCLUDED.kind, url = EXCLUDED.url,
+    transporlis_id = EXCLUDED.transporlis_id, headers = EXCLUDED.headers,
+    refresh_interval = EXCLUDED.refresh_interval
+RETURNING id
+"#,
+        operator_id,
+        i16::from(source.kind),
+        source.url,
+        source.transporlis_id,
+        Json(&source.headers) as _,
+        source.refresh_interval
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, ?source);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn delete_operator_gtfs_source(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+) -> Result<()> {
+    let res = sqlx::query!(
+        r#"
+DELETE FROM gtfs_sources
+WHERE operator_id = $1
+"#,
+        operator_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::NotFoundUpstream);
+    }
+    Ok(())
+}
+
+pub(crate) async fn update_gtfs_source_fetch(
+    pool: &PgPool,
+    source_id: i32,
+    checksum: &str,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE gtfs_sources
+SET checksum = $2, last_fetch = now()
+WHERE id = $1
+"#,
+        source_id,
+        checksum
+    )
+    .execute(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), source_id);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
@@ -30,7 +30,8 @@ use tower_http::trace::{self, TraceLayer};
 
 use crate::state::AppState;
 use crate::{
-    auth, contrib, geo, gtfs, info, operators, osm, pics, routes, stops,
+    auth, contrib, fares, geo, gtfs, info, jobs, operators, osm, pics, routes,
+    search, stops,
 };
 
 #[allow(clippy::too_many_lines)]
@@ -49,9 +50,40 @@ pub fn build_paths(state: AppState) -> Router {
         .allow_credentials(true);
 
     Router::new()
-        .route("/v1/regions", get(geo::handlers::get_regions))
+        .route(
+            "/v1/regions",
+            get(geo::handlers::get_regions).post(geo::handlers::post_region),
+        )
         .route("/v1/regions/simple", get(geo::handlers::get_simple_regions))
-        .route("/v1/regions/:region_id", get(geo::handlers::get_region))
+        .route(
+            "/v1/regions/:region_id",
+            get(geo::handlers::get_region)
+                .patch(geo::handlers::patch_region)
+                .delete(geo::handlers::delete_region),
+        )
+        .route("/v1/parishes", post(geo::handlers::post_parish))
+        .route(
+            "/v1/parishes/:parish_id",
+            patch(geo::handlers::patch_parish)
+                .delete(geo::handlers::delete_parish),
+        )
+        .route(
+            "/v1/holidays",
+            get(geo::handlers::get_holidays).post(geo::handlers::post_holiday),
+        )
+        .route(
+            "/v1/holidays/:holiday_id",
+            delete(geo::handlers::delete_holiday),
+        )
+        .route(
+            "/v1/school_periods",
+            get(geo::handlers::get_school_periods)
+                .post(geo::handlers::post_school_period),
+        )
+        .route(
+            "/v1/school_periods/:period_id",
+            delete(geo::handlers::delete_school_period),
+        )
         .route(
             "/v1/regions/:region_id/parishes",
             get(geo::handlers::get_parishes),
@@ -124,11 +156,39 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/stops/map_features",
             get(osm::handlers::get_stops_map_features),
         )
+        .route(
+            "/v1/stops/:stop_id/cluster_suggestions",
+            get(stops::handlers::get_stop_cluster_suggestions),
+        )
+        .route(
+            "/v1/stop_clusters",
+            get(stops::handlers::get_stop_clusters)
+                .post(stops::handlers::post_stop_cluster),
+        )
+        .route(
+            "/v1/stop_clusters/:cluster_id",
+            get(stops::handlers::get_stop_cluster)
+                .patch(stops::handlers::patch_stop_cluster)
+                .delete(stops::handlers::delete_stop_cluster),
+        )
+        .route(
+            "/v1/stop_clusters/:cluster_id/stops",
+            put(stops::handlers::put_stop_cluster_stops),
+        )
+        .route(
+            "/v1/stop_clusters/:cluster_id/spider",
+            get(stops::handlers::get_stop_cluster_spider),
+        )
         .route(
             "/v1/stops/:stop_id",
             get(stops::handlers::get_stop).patch(stops::handlers::patch_stop),
         )
+        .route(
+            "/v1/stops/:stop_id/history",
+            get(contrib::handlers::get_stop_history),
+        )
         .route("/v1/stops/list/:stops", get(stops::handlers::get_stop_list))
+        .route("/v1/stops/near", get(stops::handlers::get_near_stops))
         .route(
             "/v1/stops/within_boundary/:x0/:y0/:x1/:y1",
             get(stops::handlers::get_bounded_stops),
@@ -161,6 +221,10 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/stops/:stop_id/routes",
             get(stops::handlers::get_stop_routes),
         )
+        .route(
+            "/v1/stops/:stop_id/departures",
+            get(stops::handlers::get_stop_departures),
+        )
         .route(
             "/v1/stops/:stop_id/regions",
             get(geo::handlers::get_stop_regions),
@@ -169,6 +233,10 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/stops/:stop_id/parish/:parish_id",
             put(geo::handlers::put_stop_parish),
         )
+        .route(
+            "/v1/admin/divisions/reconcile",
+            post(geo::handlers::post_reconcile_divisions),
+        )
         .route(
             "/v1/stops/:stop_id/map_features",
             put(osm::handlers::put_stop_map_features),
@@ -197,6 +265,10 @@ pub fn build_paths(state: AppState) -> Router {
                 .patch(routes::handlers::patch_route)
                 .delete(routes::handlers::delete_route),
         )
+        .route(
+            "/v1/routes/:route_id/history",
+            get(contrib::handlers::get_route_history),
+        )
         .route(
             "/v1/routes/:route_id/full",
             get(routes::handlers::get_route_full),
@@ -209,6 +281,10 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/routes/:route_id/schedule",
             get(routes::handlers::get_schedule),
         )
+        .route(
+            "/v1/routes/:route_id/vehicles",
+            get(operators::handlers::get_route_vehicles),
+        )
         .route(
             "/v1/routes/:route_id/regions",
             get(geo::handlers::get_route_regions),
@@ -235,6 +311,10 @@ pub fn build_paths(state: AppState) -> Router {
             patch(routes::handlers::patch_subroute)
                 .delete(routes::handlers::delete_subroute),
         )
+        .route(
+            "/v1/subroutes/:subroute_id/history",
+            get(contrib::handlers::get_subroute_history),
+        )
         .route(
             "/v1/subroutes/:subroute_id/stops",
             patch(routes::handlers::patch_subroute_stops),
@@ -269,6 +349,10 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/stop_pics/unpositioned",
             get(pics::handlers::get_unpositioned_stop_pictures),
         )
+        .route(
+            "/v1/stop_pics/duplicates",
+            get(pics::handlers::get_duplicate_stop_pictures),
+        )
         .route(
             "/v1/stop_pics/linked/:stop_id",
             post(pics::handlers::upload_stop_picture),
@@ -288,6 +372,16 @@ pub fn build_paths(state: AppState) -> Router {
                 .patch(pics::handlers::patch_stop_picture_meta)
                 .delete(pics::handlers::delete_picture),
         )
+        .route(
+            "/v1/stop_pics/:picture_id/redactions",
+            get(pics::handlers::get_stop_picture_redactions)
+                .put(pics::handlers::put_stop_picture_redactions),
+        )
+        .route(
+            "/v1/stop_pics/:picture_id/unredacted",
+            get(pics::handlers::get_unredacted_stop_picture),
+        )
+        .route("/v1/objects/*path", get(pics::handlers::get_local_object))
         .route(
             "/v1/stop_pics/by_stop",
             get(pics::handlers::get_picture_count_by_stop),
@@ -298,6 +392,16 @@ pub fn build_paths(state: AppState) -> Router {
             get(operators::handlers::get_issue)
                 .patch(operators::handlers::patch_issue),
         )
+        .route("/v1/tickets/own", get(operators::handlers::get_own_tickets))
+        .route(
+            "/v1/tickets/:ticket_id",
+            get(operators::handlers::get_ticket)
+                .patch(operators::handlers::patch_ticket),
+        )
+        .route(
+            "/v1/tickets/:ticket_id/comments",
+            post(operators::handlers::post_ticket_comment),
+        )
         .route("/v1/abnormalities", post(operators::handlers::post_abnormality))
         .route(
             "/v1/abnormalities/:abnormality_id",
@@ -344,6 +448,10 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/contrib/changelog",
             get(contrib::handlers::get_changelog),
         )
+        .route(
+            "/v1/contrib/changelog/:changeset_id/revert",
+            post(contrib::handlers::post_revert_changeset),
+        )
         .route(
             "/v1/contrib/pics",
             post(contrib::handlers::post_contrib_stop_picture),
@@ -356,6 +464,30 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/contrib/stops/update/:stop_id",
             post(contrib::handlers::post_contrib_stop_data),
         )
+        .route(
+            "/v1/contrib/routes/update/:route_id",
+            post(contrib::handlers::post_contrib_route_data),
+        )
+        .route(
+            "/v1/contrib/subroutes/update/:subroute_id",
+            post(contrib::handlers::post_contrib_subroute_data),
+        )
+        .route(
+            "/v1/contrib/subroutes/stops/:subroute_id",
+            post(contrib::handlers::post_contrib_subroute_stops),
+        )
+        .route(
+            "/v1/contrib/subroutes/departures/:subroute_id",
+            post(contrib::handlers::post_contrib_departure_creation),
+        )
+        .route(
+            "/v1/contrib/departures/update/:departure_id",
+            post(contrib::handlers::post_contrib_departure_data),
+        )
+        .route(
+            "/v1/contrib/departures/delete/:departure_id",
+            post(contrib::handlers::post_contrib_departure_deletion),
+        )
         .route(
             "/v1/contrib/:contribution_id/accept",
             post(contrib::handlers::post_accept_contrib_data),
@@ -427,6 +559,55 @@ pub fn build_paths(state: AppState) -> Router {
             patch(operators::handlers::patch_operator_route_type)
                 .delete(operators::handlers::delete_operator_route_type),
         )
+        .route(
+            "/v1/operators/:operator_id/routes/types/:type_id/vehicles",
+            get(operators::handlers::get_operator_route_type_vehicles)
+                .put(operators::handlers::put_operator_route_type_vehicles),
+        )
+        .route(
+            "/v1/operators/:operator_id/fares/products",
+            get(fares::handlers::get_operator_fare_products)
+                .post(fares::handlers::post_operator_fare_product),
+        )
+        .route(
+            "/v1/operators/:operator_id/fares/products/:product_id",
+            patch(fares::handlers::patch_operator_fare_product)
+                .delete(fares::handlers::delete_operator_fare_product),
+        )
+        .route(
+            "/v1/operators/:operator_id/fares/zones",
+            get(fares::handlers::get_operator_fare_zones)
+                .post(fares::handlers::post_operator_fare_zone),
+        )
+        .route(
+            "/v1/operators/:operator_id/fares/zones/:zone_id",
+            patch(fares::handlers::patch_operator_fare_zone)
+                .delete(fares::handlers::delete_operator_fare_zone),
+        )
+        .route(
+            "/v1/operators/:operator_id/fares/zones/:zone_id/stops",
+            put(fares::handlers::put_operator_fare_zone_stops),
+        )
+        .route(
+            "/v1/operators/:operator_id/fares/transfers",
+            get(fares::handlers::get_operator_transfer_rules)
+                .post(fares::handlers::post_operator_transfer_rule),
+        )
+        .route(
+            "/v1/operators/:operator_id/fares/transfers/:rule_id",
+            delete(fares::handlers::delete_operator_transfer_rule),
+        )
+        .route("/v1/fares/quote", post(fares::handlers::post_fare_quote))
+        .route(
+            "/v1/operators/:operator_id/vehicles",
+            get(operators::handlers::get_operator_vehicles)
+                .post(operators::handlers::post_operator_vehicle),
+        )
+        .route(
+            "/v1/operators/:operator_id/vehicles/:vehicle_id",
+            patch(operators::handlers::patch_operator_vehicle)
+                .delete(operators::handlers::delete_operator_vehicle),
+        )
         .route(
             "/v1/operators/:operator_id/issues",
             get(operators::handlers::get_operator_issues),
@@ -435,6 +616,15 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/operators/:operator_id/abnormalities",
             get(operators::handlers::get_operator_abnormalities),
         )
+        .route(
+            "/v1/operators/:operator_id/tickets",
+            get(operators::handlers::get_operator_tickets)
+                .post(operators::handlers::post_operator_ticket),
+        )
+        .route(
+            "/v1/operators/:operator_id/tickets/all",
+            get(operators::handlers::get_operator_moderation_tickets),
+        )
         .route(
             "/v1/operators/:operator_id/gtfs/stops",
             get(gtfs::handlers::get_gtfs_stops),
@@ -447,10 +637,29 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/operators/:operator_id/gtfs/routes",
             get(gtfs::handlers::get_gtfs_route_trips),
         )
+        .route(
+            "/v1/operators/:operator_id/gtfs/export",
+            get(gtfs::handlers::get_operator_gtfs_export),
+        )
+        .route(
+            "/v1/operators/:operator_id/gtfs/changes",
+            get(gtfs::handlers::get_operator_gtfs_changes),
+        )
         .route(
             "/v1/operators/:operator_id/gtfs/update",
             post(gtfs::handlers::post_update_operator_gtfs),
         )
+        .route(
+            "/v1/operators/:operator_id/gtfs/upload",
+            post(gtfs::handlers::post_upload_operator_gtfs),
+        )
+        .route(
+            "/v1/operators/:operator_id/gtfs/source",
+            get(gtfs::handlers::get_operator_gtfs_source)
+                .put(gtfs::handlers::put_operator_gtfs_source)
+                .delete(gtfs::handlers::delete_operator_gtfs_source),
+        )
+        .route("/v1/gtfs/sources", get(gtfs::handlers::get_gtfs_sources))
         .route(
             "/v1/operators/:operator_id/regions",
             get(geo::handlers::get_operator_regions),
@@ -572,6 +781,22 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/auth/mtokens/:token_id",
             delete(auth::handlers::delete_revoke_management_token),
         )
+        .route(
+            "/v1/auth/verification",
+            post(auth::handlers::post_request_email_verification),
+        )
+        .route(
+            "/v1/auth/verification/confirm",
+            post(auth::handlers::post_verify_email),
+        )
+        .route(
+            "/v1/auth/password_reset",
+            post(auth::handlers::post_request_password_reset),
+        )
+        .route(
+            "/v1/auth/password_reset/confirm",
+            post(auth::handlers::post_reset_password),
+        )
         .route("/v1/auth/get_captcha", get(auth::handlers::get_captcha))
         .route(
             "/v1/auth/register/username_check",
@@ -595,6 +820,11 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/admin/change_password",
             post(auth::handlers::post_admin_change_password),
         )
+        .route(
+            "/v1/admin/users/:user_id/suspension",
+            post(auth::handlers::post_suspend_user)
+                .delete(auth::handlers::delete_user_suspension),
+        )
         .route("/v1/admin/audit/log", post(auth::handlers::get_audit_log))
         .route(
             "/v1/admin/audit/log/user/:user_id",
@@ -608,6 +838,18 @@ pub fn build_paths(state: AppState) -> Router {
             "/v1/admin/audit/sessions/:session_id/accesses",
             post(auth::handlers::get_session_accesses),
         )
+        .route("/v1/admin/jobs", get(jobs::handlers::get_jobs))
+        .route("/v1/admin/jobs/:job_id", patch(jobs::handlers::patch_job))
+        .route(
+            "/v1/admin/jobs/:job_id/runs",
+            get(jobs::handlers::get_job_runs)
+                .post(jobs::handlers::post_job_run),
+        )
+        .route(
+            "/v1/admin/jobs/runs/:run_id",
+            get(jobs::handlers::get_job_run),
+        )
+        .route("/v1/search", get(search::handlers::get_search))
         .route("/v1/user/info", get(auth::handlers::get_user_info))
         .route("/v1/user/stats", get(auth::handlers::get_user_stats))
         .route(
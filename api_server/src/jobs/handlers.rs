@@ -0,0 +1,106 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use serde::Deserialize;
+
+use super::models::{requests, responses};
+use super::{logic, sql};
+use crate::responses::IdReturn;
+use crate::{auth, AppState, Error};
+
+#[derive(Deserialize, Default)]
+pub(crate) struct Page {
+    #[serde(default)]
+    p: u32,
+}
+
+const PAGE_SIZE: u32 = 20;
+
+pub(crate) async fn get_jobs(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ManageJobs>,
+) -> Result<Json<Vec<responses::Job>>, Error> {
+    Ok(Json(sql::fetch_jobs(&state.pool).await?))
+}
+
+pub(crate) async fn patch_job(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ManageJobs>,
+    Path(job_id): Path<i32>,
+    Json(change): Json<requests::ChangeJob>,
+) -> Result<(), Error> {
+    change.validate()?;
+    sql::update_job(&state.pool, job_id, &change).await
+}
+
+pub(crate) async fn post_job_run(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ManageJobs>,
+    Path(job_id): Path<i32>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    let job = sql::claim_job(
+        &state.pool,
+        job_id,
+        state.instance,
+        logic::JOB_LOCK_MINUTES,
+    )
+    .await?;
+
+    let Some(job) = job else {
+        // Either it does not exist or is already running
+        return if sql::fetch_jobs(&state.pool)
+            .await?
+            .iter()
+            .any(|job| job.id == job_id)
+        {
+            Err(Error::DependenciesNotMet)
+        } else {
+            Err(Error::NotFoundUpstream)
+        };
+    };
+
+    let id = logic::start_job(&state, job, true).await?;
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn get_job_runs(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ManageJobs>,
+    Path(job_id): Path<i32>,
+    paginator: Query<Page>,
+) -> Result<Json<Vec<responses::JobRun>>, Error> {
+    let offset = i64::from(paginator.p * PAGE_SIZE);
+    let take = i64::from(PAGE_SIZE);
+
+    Ok(Json(
+        sql::fetch_job_runs(&state.pool, job_id, offset, take).await?,
+    ))
+}
+
+pub(crate) async fn get_job_run(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ManageJobs>,
+    Path(run_id): Path<i32>,
+) -> Result<Json<responses::JobRun>, Error> {
+    sql::fetch_job_run(&state.pool, run_id)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFoundUpstream)
+}
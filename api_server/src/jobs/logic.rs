@@ -0,0 +1,322 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::json;
+
+use commons::models::{history, osm};
+
+use super::models::{
+    responses, GtfsRefreshSummary, HashBackfillSummary, IntegrityReport,
+    JobKind, JobRunStatus,
+};
+use super::sql;
+use crate::state::AppState;
+use crate::Error;
+use crate::{geo, gtfs, osm, pics};
+
+// How often the scheduler looks for due jobs
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+// How long a job stays locked to an instance before others can take it over
+pub(crate) const JOB_LOCK_MINUTES: i32 = 60;
+// How often a running job renews its lock. Well under the lock duration.
+const LOCK_RENEWAL_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Periodically runs the jobs that are due.
+/// Jobs are locked in the database, so several instances can run this.
+pub(crate) async fn run_scheduler(state: AppState) {
+    let mut interval = tokio::time::interval(SCHEDULER_TICK);
+    loop {
+        interval.tick().await;
+
+        let jobs = match sql::claim_due_jobs(
+            &state.pool,
+            state.instance,
+            JOB_LOCK_MINUTES,
+        )
+        .await
+        {
+            Ok(jobs) => jobs,
+            Err(err) => {
+                tracing::error!("Unable to claim due jobs: {err}");
+                continue;
+            }
+        };
+
+        for job in jobs {
+            if let Err(err) = start_job(&state, job, false).await {
+                tracing::error!("Unable to start a job: {err}");
+            }
+        }
+    }
+}
+
+/// Starts a job that was claimed by this instance.
+/// Returns the ID of the run, which goes on in the background.
+pub(crate) async fn start_job(
+    state: &AppState,
+    job: responses::Job,
+    manual: bool,
+) -> Result<i32, Error> {
+    let run_id =
+        match sql::insert_job_run(&state.pool, job.id, state.instance, manual)
+            .await
+        {
+            Ok(run_id) => run_id,
+            Err(err) => {
+                sql::release_job(&state.pool, job.id, state.instance).await?;
+                return Err(err);
+            }
+        };
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        tracing::info!("Running job {} ({})", job.id, job.name);
+        let heartbeat = tokio::spawn(renew_lock(state.clone(), job.id));
+        let result = perform(&state, job.kind).await;
+        heartbeat.abort();
+
+        let (status, output) = match result {
+            Ok(output) => (JobRunStatus::Succeeded, output),
+            Err(err) => {
+                tracing::error!("Job {} failed: {err}", job.id);
+                (JobRunStatus::Failed, json!({"error": err.to_string()}))
+            }
+        };
+
+        if let Err(err) =
+            sql::finish_job_run(&state.pool, run_id, status, &output).await
+        {
+            tracing::error!("Unable to record the run {run_id}: {err}");
+        }
+        if let Err(err) =
+            sql::release_job(&state.pool, job.id, state.instance).await
+        {
+            tracing::error!("Unable to release the job {}: {err}", job.id);
+        }
+    });
+
+    Ok(run_id)
+}
+
+/// Keeps the lock of a running job from expiring, so that no other
+/// instance takes it over halfway through
+async fn renew_lock(state: AppState, job_id: i32) {
+    let mut interval = tokio::time::interval(LOCK_RENEWAL_INTERVAL);
+    // The first tick completes immediately, right after the job was claimed
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+
+        match sql::extend_job_lock(
+            &state.pool,
+            job_id,
+            state.instance,
+            JOB_LOCK_MINUTES,
+        )
+        .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::warn!("Lost the lock of job {job_id}");
+                return;
+            }
+            Err(err) => {
+                tracing::error!(
+                    "Unable to renew the lock of job {job_id}: {err}"
+                );
+            }
+        }
+    }
+}
+
+async fn perform(
+    state: &AppState,
+    kind: JobKind,
+) -> Result<serde_json::Value, Error> {
+    match kind {
+        JobKind::GtfsRefresh => Ok(json!(refresh_gtfs_sources(state).await?)),
+        JobKind::IntegrityCheck => Ok(json!(check_integrity(state).await?)),
+        JobKind::StopPicHashBackfill => {
+            Ok(json!(backfill_stop_pic_hashes(state).await?))
+        }
+        JobKind::DivisionReconciliation => {
+            Ok(json!(reconcile_divisions(state).await?))
+        }
+        JobKind::OsmStopSync => {
+            Ok(json!(osm::logic::sync_osm_stops(&state.pool).await?))
+        }
+    }
+}
+
+async fn reconcile_divisions(
+    state: &AppState,
+) -> Result<geo::models::responses::DivisionReconciliation, Error> {
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let summary =
+        geo::logic::reconcile_divisions(&state.pool, &mut transaction).await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(summary)
+}
+
+async fn refresh_gtfs_sources(
+    state: &AppState,
+) -> Result<GtfsRefreshSummary, Error> {
+    let now = Utc::now();
+    let sources = gtfs::sql::fetch_gtfs_sources(&state.pool).await?;
+
+    let mut summary = GtfsRefreshSummary::default();
+    for source in sources.iter().filter(|source| is_due(source, now)) {
+        match gtfs::logic::refresh_gtfs_source(&state.pool, source).await {
+            Ok(true) => summary.changed.push(source.operator_id),
+            Ok(false) => summary.unchanged.push(source.operator_id),
+            Err(err) => {
+                tracing::error!(
+                    "Unable to refresh the GTFS of operator {}: {err}",
+                    source.operator_id
+                );
+                summary.failed.push(source.operator_id);
+            }
+        }
+    }
+    Ok(summary)
+}
+
+fn is_due(
+    source: &gtfs::models::GtfsSource,
+    now: chrono::DateTime<Utc>,
+) -> bool {
+    let Some(interval) = source.refresh_interval else {
+        return false;
+    };
+    match source.last_fetch {
+        Some(last_fetch) => {
+            last_fetch + chrono::Duration::minutes(i64::from(interval)) <= now
+        }
+        None => true,
+    }
+}
+
+async fn check_integrity(state: &AppState) -> Result<IntegrityReport, Error> {
+    let mut report = IntegrityReport::default();
+
+    let changesets = sql::fetch_changelog_changes(&state.pool).await?;
+    report.checked_changesets = changesets.len();
+    for (id, changes) in changesets {
+        if let Err(err) =
+            serde_json::from_value::<Vec<history::Change>>(changes)
+        {
+            tracing::warn!("Changeset {id} is malformed: {err}");
+            report.faulty_changesets.push(id);
+        }
+    }
+
+    let histories = sql::fetch_osm_stop_histories(&state.pool).await?;
+    report.checked_osm_stops = histories.len();
+    for (id, history) in histories {
+        if let Err(err) = serde_json::from_value::<osm::NodeHistory>(history) {
+            tracing::warn!("OSM stop {id} has a malformed history: {err}");
+            report.faulty_osm_stops.push(id);
+        }
+    }
+
+    Ok(report)
+}
+
+async fn backfill_stop_pic_hashes(
+    state: &AppState,
+) -> Result<HashBackfillSummary, Error> {
+    let mut summary = HashBackfillSummary::default();
+
+    for (pic_id, sha1, filename) in
+        pics::sql::fetch_unhashed_stop_pics(&state.pool).await?
+    {
+        let hashed = pics::logic::hash_stored_stop_picture(
+            &state.storage,
+            &sha1,
+            &filename,
+        )
+        .await;
+        match hashed {
+            Ok(phash) => {
+                pics::sql::update_stop_pic_phash(&state.pool, pic_id, phash)
+                    .await?;
+                summary.hashed.push(pic_id);
+            }
+            Err(err) => {
+                tracing::warn!("Unable to hash stop picture {pic_id}: {err}");
+                summary.failed.push(pic_id);
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::{Duration, Utc};
+
+    use super::is_due;
+    use crate::gtfs::models::{GtfsSource, GtfsSourceKind};
+
+    fn source(
+        refresh_interval: Option<i32>,
+        last_fetch: Option<chrono::DateTime<Utc>>,
+    ) -> GtfsSource {
+        GtfsSource {
+            id: 1,
+            operator_id: 1,
+            kind: GtfsSourceKind::Zip,
+            url: Some("https://example.com/gtfs.zip".to_string()),
+            transporlis_id: None,
+            headers: HashMap::new(),
+            refresh_interval,
+            checksum: None,
+            last_fetch,
+        }
+    }
+
+    #[test]
+    fn source_due_for_refresh() {
+        let now = Utc::now();
+        assert!(!is_due(&source(None, None), now));
+        assert!(is_due(&source(Some(60), None), now));
+        assert!(is_due(
+            &source(Some(60), Some(now - Duration::minutes(61))),
+            now
+        ));
+        assert!(!is_due(
+            &source(Some(60), Some(now - Duration::minutes(30))),
+            now
+        ));
+    }
+}
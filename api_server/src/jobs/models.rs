@@ -0,0 +1,156 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use serde::Serialize;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::Error;
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize_repr, Deserialize_repr)]
+pub enum JobKind {
+    // Fetches the GTFS sources that are due for a refresh
+    GtfsRefresh = 0,
+    // Looks for stored data that no longer deserializes
+    IntegrityCheck = 1,
+    // Hashes the stop pictures that lack a perceptual hash
+    StopPicHashBackfill = 2,
+    // Recomputes the region and parish of every stop
+    DivisionReconciliation = 3,
+    // Pulls the newer OSM stop versions from Overpass and the OSM API
+    OsmStopSync = 4,
+}
+
+impl TryFrom<i16> for JobKind {
+    type Error = Error;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::GtfsRefresh),
+            1 => Ok(Self::IntegrityCheck),
+            2 => Ok(Self::StopPicHashBackfill),
+            3 => Ok(Self::DivisionReconciliation),
+            4 => Ok(Self::OsmStopSync),
+            _ => Err(Error::DatabaseDeserialization),
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize_repr, Deserialize_repr)]
+pub enum JobRunStatus {
+    Running = 0,
+    Succeeded = 1,
+    Failed = 2,
+}
+
+impl TryFrom<i16> for JobRunStatus {
+    type Error = Error;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Running),
+            1 => Ok(Self::Succeeded),
+            2 => Ok(Self::Failed),
+            _ => Err(Error::DatabaseDeserialization),
+        }
+    }
+}
+
+impl From<JobRunStatus> for i16 {
+    fn from(status: JobRunStatus) -> Self {
+        i16::from(status as u8)
+    }
+}
+
+/// The outcome of a GTFS refresh run, as operator IDs
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct GtfsRefreshSummary {
+    pub(crate) unchanged: Vec<i32>,
+    pub(crate) changed: Vec<i32>,
+    pub(crate) failed: Vec<i32>,
+}
+
+/// The outcome of an integrity check run
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct IntegrityReport {
+    pub(crate) checked_changesets: usize,
+    pub(crate) faulty_changesets: Vec<i64>,
+    pub(crate) checked_osm_stops: usize,
+    pub(crate) faulty_osm_stops: Vec<i64>,
+}
+
+/// The outcome of a stop picture hash backfill run, as picture IDs
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct HashBackfillSummary {
+    pub(crate) hashed: Vec<i32>,
+    pub(crate) failed: Vec<i32>,
+}
+
+pub(crate) mod requests {
+    use serde::Deserialize;
+
+    use crate::Error;
+
+    #[derive(Debug, Deserialize)]
+    pub struct ChangeJob {
+        pub interval: Option<i32>,
+        pub enabled: bool,
+    }
+
+    impl ChangeJob {
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            if self.interval.is_some_and(|interval| interval <= 0) {
+                return Err(Error::ValidationFailure(
+                    "Invalid job interval".to_string(),
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+pub(crate) mod responses {
+    use chrono::{DateTime, Utc};
+    use serde::Serialize;
+
+    use super::{JobKind, JobRunStatus};
+
+    #[derive(Debug, Clone, Serialize)]
+    pub struct Job {
+        pub id: i32,
+        pub kind: JobKind,
+        pub name: String,
+        pub interval: Option<i32>,
+        pub enabled: bool,
+        pub next_run: Option<DateTime<Utc>>,
+        // Set while the job is running
+        pub locked_until: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct JobRun {
+        pub id: i32,
+        pub job_id: i32,
+        pub manual: bool,
+        pub start: DateTime<Utc>,
+        pub end: Option<DateTime<Utc>>,
+        pub status: JobRunStatus,
+        pub output: Option<serde_json::Value>,
+    }
+}
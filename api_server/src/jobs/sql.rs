@@ -0,0 +1,406 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::models::{requests, responses, JobKind, JobRunStatus};
+use crate::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+struct JobRow {
+    id: i32,
+    kind: i16,
+    name: String,
+    interval: Option<i32>,
+    enabled: bool,
+    next_run: Option<DateTime<Utc>>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<JobRow> for responses::Job {
+    type Error = Error;
+
+    fn try_from(row: JobRow) -> Result<Self> {
+        Ok(responses::Job {
+            id: row.id,
+            kind: JobKind::try_from(row.kind)?,
+            name: row.name,
+            interval: row.interval,
+            enabled: row.enabled,
+            next_run: row.next_run,
+            locked_until: row.locked_until,
+        })
+    }
+}
+
+pub(crate) async fn fetch_jobs(pool: &PgPool) -> Result<Vec<responses::Job>> {
+    sqlx::query_as!(
+        JobRow,
+        r#"
+SELECT id, kind, name, interval, enabled, next_run, locked_until
+FROM jobs
+ORDER BY id
+"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(responses::Job::try_from)
+    .collect()
+}
+
+pub(crate) async fn update_job(
+    pool: &PgPool,
+    job_id: i32,
+    change: &requests::ChangeJob,
+) -> Result<()> {
+    let res = sqlx::query!(
+        r#"
+UPDATE jobs
+SET interval = $2, enabled = $3
+WHERE id = $1
+"#,
+        job_id,
+        change.interval,
+        change.enabled
+    )
+    .execute(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), job_id, ?change);
+        Error::DatabaseExecution
+    })?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::NotFoundUpstream);
+    }
+    Ok(())
+}
+
+/// Locks every enabled job that is due to run on behalf of `instance`
+pub(crate) async fn claim_due_jobs(
+    pool: &PgPool,
+    instance: Uuid,
+    lock_minutes: i32,
+) -> Result<Vec<responses::Job>> {
+    sqlx::query_as!(
+        JobRow,
+        r#"
+UPDATE jobs
+SET locked_by = $1, locked_until = now() + make_interval(mins => $2)
+WHERE enabled AND interval IS NOT NULL
+    AND (next_run IS NULL OR next_run <= now())
+    AND (locked_until IS NULL OR locked_until < now())
+RETURNING id, kind, name, interval, enabled, next_run, locked_until
+"#,
+        instance,
+        lock_minutes
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            error = err.to_string(),
+            instance = instance.to_string()
+        );
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(responses::Job::try_from)
+    .collect()
+}
+
+/// Locks a job on behalf of `instance` regardless of its schedule.
+/// Returns `None` if the job is being run elsewhere.
+pub(crate) async fn claim_job(
+    pool: &PgPool,
+    job_id: i32,
+    instance: Uuid,
+    lock_minutes: i32,
+) -> Result<Option<responses::Job>> {
+    sqlx::query_as!(
+        JobRow,
+        r#"
+UPDATE jobs
+SET locked_by = $2, locked_until = now() + make_interval(mins => $3)
+WHERE id = $1 AND (locked_until IS NULL OR locked_until < now())
+RETURNING id, kind, name, interval, enabled, next_run, locked_until
+"#,
+        job_id,
+        instance,
+        lock_minutes
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            error = err.to_string(),
+            job_id,
+            instance = instance.to_string()
+        );
+        Error::DatabaseExecution
+    })?
+    .map(responses::Job::try_from)
+    .transpose()
+}
+
+/// Pushes back the lock expiry of a job still held by `instance`.
+/// Returns false if the lock was lost in the meanwhile.
+pub(crate) async fn extend_job_lock(
+    pool: &PgPool,
+    job_id: i32,
+    instance: Uuid,
+    lock_minutes: i32,
+) -> Result<bool> {
+    let res = sqlx::query!(
+        r#"
+UPDATE jobs
+SET locked_until = now() + make_interval(mins => $3)
+WHERE id = $1 AND locked_by = $2
+"#,
+        job_id,
+        instance,
+        lock_minutes
+    )
+    .execute(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            error = err.to_string(),
+            job_id,
+            instance = instance.to_string()
+        );
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Unlocks a job held by `instance` and schedules its next run
+pub(crate) async fn release_job(
+    pool: &PgPool,
+    job_id: i32,
+    instance: Uuid,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE jobs
+SET locked_by = NULL, locked_until = NULL,
+    next_run = now() + make_interval(mins => interval)
+WHERE id = $1 AND locked_by = $2
+"#,
+        job_id,
+        instance
+    )
+    .execute(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            error = err.to_string(),
+            job_id,
+            instance = instance.to_string()
+        );
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn insert_job_run(
+    pool: &PgPool,
+    job_id: i32,
+    instance: Uuid,
+    manual: bool,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO job_runs (job_id, instance, manual)
+VALUES ($1, $2, $3)
+RETURNING id
+"#,
+        job_id,
+        instance,
+        manual
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            error = err.to_string(),
+            job_id,
+            instance = instance.to_string()
+        );
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn finish_job_run(
+    pool: &PgPool,
+    run_id: i32,
+    status: JobRunStatus,
+    output: &serde_json::Value,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE job_runs
+SET "end" = now(), status = $2, output = $3
+WHERE id = $1
+"#,
+        run_id,
+        i16::from(status),
+        output
+    )
+    .execute(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), run_id, ?status);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+struct JobRunRow {
+    id: i32,
+    job_id: i32,
+    manual: bool,
+    start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+    status: i16,
+    output: Option<serde_json::Value>,
+}
+
+impl TryFrom<JobRunRow> for responses::JobRun {
+    type Error = Error;
+
+    fn try_from(row: JobRunRow) -> Result<Self> {
+        Ok(responses::JobRun {
+            id: row.id,
+            job_id: row.job_id,
+            manual: row.manual,
+            start: row.start,
+            end: row.end,
+            status: JobRunStatus::try_from(row.status)?,
+            output: row.output,
+        })
+    }
+}
+
+pub(crate) async fn fetch_job_runs(
+    pool: &PgPool,
+    job_id: i32,
+    offset: i64,
+    take: i64,
+) -> Result<Vec<responses::JobRun>> {
+    sqlx::query_as!(
+        JobRunRow,
+        r#"
+SELECT id, job_id, manual, start, "end", status, output
+FROM job_runs
+WHERE job_id = $1
+ORDER BY id DESC
+LIMIT $2 OFFSET $3
+"#,
+        job_id,
+        take,
+        offset
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), job_id);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(responses::JobRun::try_from)
+    .collect()
+}
+
+pub(crate) async fn fetch_job_run(
+    pool: &PgPool,
+    run_id: i32,
+) -> Result<Option<responses::JobRun>> {
+    sqlx::query_as!(
+        JobRunRow,
+        r#"
+SELECT id, job_id, manual, start, "end", status, output
+FROM job_runs
+WHERE id = $1
+"#,
+        run_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), run_id);
+        Error::DatabaseExecution
+    })?
+    .map(responses::JobRun::try_from)
+    .transpose()
+}
+
+pub(crate) async fn fetch_changelog_changes(
+    pool: &PgPool,
+) -> Result<Vec<(i64, serde_json::Value)>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, changes
+FROM changelog
+ORDER BY id
+"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| (row.id, row.changes))
+    .collect())
+}
+
+pub(crate) async fn fetch_osm_stop_histories(
+    pool: &PgPool,
+) -> Result<Vec<(i64, serde_json::Value)>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, history
+FROM osm_stops
+ORDER BY id
+"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| (row.id, row.history))
+    .collect())
+}
@@ -20,18 +20,23 @@
 pub mod auth;
 pub mod contrib;
 pub mod errors;
+pub mod fares;
 pub mod geo;
 pub mod gtfs;
 pub mod http;
 pub mod info;
+pub mod jobs;
+pub mod mail;
 pub mod operators;
 pub mod osm;
 pub mod pics;
 mod responses;
 pub mod routes;
+pub mod search;
 pub mod settings;
 pub mod state;
 pub mod stops;
+pub mod storage;
 pub mod utils;
 
 pub use errors::Error;
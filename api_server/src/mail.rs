@@ -0,0 +1,170 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::path::PathBuf;
+
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::errors::Error;
+use crate::settings::{self, MailTransport};
+
+/// Delivers the messages that are sent to users
+pub enum Mailer {
+    Smtp {
+        sender: Mailbox,
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+    },
+    File {
+        sender: Mailbox,
+        dir: PathBuf,
+    },
+    Log,
+}
+
+impl Mailer {
+    pub(crate) fn from_settings(
+        settings: &settings::Mail,
+    ) -> Result<Self, Error> {
+        let sender = || {
+            settings.sender.parse::<Mailbox>().map_err(|err| {
+                tracing::error!("Invalid mail sender: {err}");
+                Error::IllegalState
+            })
+        };
+
+        Ok(match &settings.transport {
+            MailTransport::Smtp {
+                host,
+                port,
+                username,
+                password,
+                starttls,
+            } => {
+                let builder = if *starttls {
+                    AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                } else {
+                    AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+                }
+                .map_err(|err| {
+                    tracing::error!("Invalid SMTP relay: {err}");
+                    Error::IllegalState
+                })?
+                .credentials(Credentials::new(
+                    username.clone(),
+                    password.clone(),
+                ));
+                let builder = match port {
+                    Some(port) => builder.port(*port),
+                    None => builder,
+                };
+
+                Mailer::Smtp {
+                    sender: sender()?,
+                    transport: builder.build(),
+                }
+            }
+            MailTransport::File { dir } => Mailer::File {
+                sender: sender()?,
+                dir: PathBuf::from(dir),
+            },
+            MailTransport::Log => Mailer::Log,
+        })
+    }
+
+    pub(crate) async fn send(
+        &self,
+        recipient: &str,
+        subject: &str,
+        body: String,
+    ) -> Result<(), Error> {
+        let build = |sender: &Mailbox| {
+            let recipient = recipient.parse::<Mailbox>().map_err(|err| {
+                tracing::warn!(recipient, "Invalid mail recipient: {err}");
+                Error::ValidationFailure("Invalid email address".to_string())
+            })?;
+            Message::builder()
+                .from(sender.clone())
+                .to(recipient)
+                .subject(subject)
+                .header(ContentType::TEXT_PLAIN)
+                .body(body.clone())
+                .map_err(|err| {
+                    tracing::error!("Unable to build message: {err}");
+                    Error::Processing
+                })
+        };
+
+        match self {
+            Mailer::Smtp { sender, transport } => {
+                let message = build(sender)?;
+                transport.send(message).await.map_err(|err| {
+                    tracing::error!(recipient, "Unable to send mail: {err}");
+                    Error::MailDelivery
+                })?;
+            }
+            Mailer::File { sender, dir } => {
+                let message = build(sender)?;
+                let path = dir.join(format!("{}.eml", uuid::Uuid::new_v4()));
+                tokio::fs::write(&path, message.formatted()).await.map_err(
+                    |err| {
+                        tracing::error!(?path, "Unable to write mail: {err}");
+                        Error::Filesystem
+                    },
+                )?;
+            }
+            Mailer::Log => {
+                // The body can carry secrets, such as verification tokens
+                tracing::info!(recipient, subject, "Mail not sent");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mailer;
+    use crate::settings::{Mail, MailTransport};
+
+    #[tokio::test]
+    async fn file_transport() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mailer = Mailer::from_settings(&Mail {
+            sender: "Intermodal <noreply@intermodal.pt>".to_string(),
+            site_url: "https://intermodal.pt".to_string(),
+            transport: MailTransport::File {
+                dir: dir.path().to_string_lossy().to_string(),
+            },
+        })
+        .unwrap();
+        mailer
+            .send("user@intermodal.pt", "Subject", "Body".to_string())
+            .await
+            .unwrap();
+
+        let files = std::fs::read_dir(dir.path()).unwrap().collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        let content =
+            std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        assert!(content.contains("To: user@intermodal.pt"));
+        assert!(content.contains("Subject: Subject"));
+    }
+}
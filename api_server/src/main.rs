@@ -28,22 +28,26 @@
 mod auth;
 mod contrib;
 mod errors;
+mod fares;
 mod geo;
 pub(crate) mod gtfs;
 mod http;
 pub mod info;
+mod jobs;
+mod mail;
 mod operators;
 mod osm;
 mod pics;
 mod responses;
 mod routes;
+mod search;
 pub(crate) mod settings;
 pub(crate) mod state;
 mod stops;
+mod storage;
 mod utils;
 
 use std::net::SocketAddr;
-use std::process::exit;
 use std::sync::Arc;
 
 use sqlx::postgres::PgPool;
@@ -63,36 +67,8 @@ async fn main() {
     settings::load();
 
     let settings = SETTINGS.get().unwrap();
-    let credentials = s3::creds::Credentials::new(
-        Some(settings.s3.access_key.as_str()),
-        Some(settings.s3.secret_key.as_str()),
-        None,
-        None,
-        None,
-    )
-    .unwrap();
-
-    let region = if let Some(endpoint) = &settings.s3.endpoint {
-        s3::Region::Custom {
-            region: "minio".to_owned(),
-            endpoint: endpoint.clone(),
-        }
-    } else if let Some(account_id) = &settings.s3.account_id {
-        s3::Region::R2 {
-            account_id: account_id.clone(),
-        }
-    } else {
-        eprint!("No S3 endpoint or account ID provided");
-        exit(-1);
-    };
-    let bucket_name = settings.s3.bucket_name.as_str();
-    let bucket = s3::Bucket::new(bucket_name, region, credentials)
-        .unwrap()
-        .with_path_style();
-    tracing::info!("Configured to use the {bucket_name} bucket");
-    if !(bucket_name.ends_with("dev") || bucket_name.ends_with("test")) {
-        tracing::warn!("Using a production bucket");
-    }
+    let storage = storage::from_settings(settings)
+        .expect("Invalid object storage settings");
 
     let db_url = settings.db.url.as_str();
     let (_, db_selection) =
@@ -105,7 +81,11 @@ async fn main() {
         tracing::warn!("Using the production database");
     }
 
-    let state = Arc::new(State::new(bucket, pool));
+    let mailer = mail::Mailer::from_settings(&settings.mail)
+        .expect("Invalid mail settings");
+
+    let state = Arc::new(State::new(storage, pool, mailer));
+    tokio::spawn(jobs::run_scheduler(AppState(state.clone())));
 
     let addr = SocketAddr::from(([0, 0, 0, 0], settings.http.port));
 
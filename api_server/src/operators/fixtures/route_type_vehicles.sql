@@ -0,0 +1,14 @@
+INSERT INTO operators (id, name, tag)
+VALUES (1, 'Operator', 'operator'),
+       (2, 'Other operator', 'other');
+
+INSERT INTO route_types (id, operator, name, zapping_cost, board_cost)
+VALUES (1, 1, 'Urban', 0, 100),
+       (2, 2, 'Urban', 0, 100);
+
+INSERT INTO vehicle
+(id, operator_id, name, quantity, bench_seats, foot_seats, has_ac,
+ has_wheelchair_ramp, has_bicycle_rack, has_wifi, has_usb_outlets)
+VALUES (1, 1, 'Standard', 10, 30, 40, true, true, false, false, false),
+       (2, 1, 'Articulated', 2, 45, 70, true, true, true, false, true),
+       (3, 2, 'Minibus', 4, 15, 10, false, false, false, false, false);
@@ -19,6 +19,7 @@ use axum::extract::{Path, State};
 use axum::Json;
 use chrono::NaiveDate;
 use futures::future;
+use itertools::Itertools;
 use std::collections::HashMap;
 
 use commons::models::{history, operators};
@@ -833,7 +834,556 @@ pub(crate) async fn get_operator_calendars_for_date(
     let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
         .map_err(|err| Error::ValidationFailure(err.to_string()))?;
 
+    let regions =
+        geo::sql::fetch_operator_regions(&state.pool, operator_id).await?;
+    // Municipal holidays only hold within their municipality,
+    // so they cannot apply to a network as a whole
+    let almanac = geo::sql::fetch_almanac(&state.pool, &regions, None).await?;
+
+    Ok(Json(
+        sql::fetch_calendars_for_date(&state.pool, operator_id, date, &almanac)
+            .await?,
+    ))
+}
+
+pub(crate) async fn get_operator_tickets(
+    State(state): State<AppState>,
+    Path(operator_id): Path<i32>,
+) -> Result<Json<Vec<operators::Ticket>>, Error> {
+    Ok(Json(
+        sql::fetch_operator_tickets(&state.pool, operator_id, false).await?,
+    ))
+}
+
+pub(crate) async fn get_operator_moderation_tickets(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<
+        auth::perms::HandleOperatorTickets,
+    >,
+    Path(operator_id): Path<i32>,
+) -> Result<Json<Vec<operators::Ticket>>, Error> {
+    Ok(Json(
+        sql::fetch_operator_tickets(&state.pool, operator_id, true).await?,
+    ))
+}
+
+pub(crate) async fn post_operator_ticket(
+    State(state): State<AppState>,
+    claims: auth::Claims,
+    Path(operator_id): Path<i32>,
+    Json(ticket): Json<requests::NewTicket>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    ticket.validate()?;
+
+    if sql::fetch_operator(&state.pool, operator_id)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFoundUpstream);
+    }
+
+    let id = sql::insert_ticket(&state.pool, operator_id, claims.uid, &ticket)
+        .await?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn get_own_tickets(
+    State(state): State<AppState>,
+    claims: auth::Claims,
+) -> Result<Json<Vec<operators::Ticket>>, Error> {
+    Ok(Json(
+        sql::fetch_user_tickets(&state.pool, claims.uid).await?,
+    ))
+}
+
+pub(crate) async fn get_ticket(
+    State(state): State<AppState>,
+    claims: Option<auth::Claims>,
+    Path(ticket_id): Path<i32>,
+) -> Result<Json<responses::FullTicket>, Error> {
+    let ticket = sql::fetch_ticket(&state.pool, ticket_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    if !ticket.public {
+        let Some(claims) = claims else {
+            return Err(Error::Forbidden);
+        };
+        let is_staff = auth::holds_operator_permission::<
+            auth::perms::HandleOperatorTickets,
+        >(
+            &state.pool, &claims.permissions, ticket.operator_id
+        )
+        .await?;
+        if claims.uid != ticket.user_id && !is_staff {
+            return Err(Error::Forbidden);
+        }
+    }
+
+    let comments = sql::fetch_ticket_comments(&state.pool, ticket_id).await?;
+
+    Ok(Json(responses::FullTicket { ticket, comments }))
+}
+
+pub(crate) async fn post_ticket_comment(
+    State(state): State<AppState>,
+    claims: auth::Claims,
+    Path(ticket_id): Path<i32>,
+    Json(comment): Json<requests::NewTicketComment>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    comment.validate()?;
+
+    let ticket = sql::fetch_ticket(&state.pool, ticket_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let is_staff = auth::holds_operator_permission::<
+        auth::perms::HandleOperatorTickets,
+    >(&state.pool, &claims.permissions, ticket.operator_id)
+    .await?;
+    if claims.uid != ticket.user_id && !is_staff {
+        return Err(Error::Forbidden);
+    }
+
+    // Staff replies answer the ticket; replies from the author reopen it
+    let status = if is_staff {
+        operators::TicketStatus::Answered
+    } else if ticket.status == operators::TicketStatus::Answered {
+        operators::TicketStatus::Unanswered
+    } else {
+        ticket.status
+    };
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id = sql::insert_ticket_comment(
+        &mut transaction,
+        ticket_id,
+        claims.uid,
+        &comment.message,
+    )
+    .await?;
+
+    if status != ticket.status {
+        sql::update_ticket(&mut transaction, ticket_id, status, ticket.public)
+            .await?;
+    }
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn patch_ticket(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<
+        auth::perms::HandleOperatorTickets,
+    >,
+    Path(ticket_id): Path<i32>,
+    Json(change): Json<requests::ChangeTicket>,
+) -> Result<(), Error> {
+    let ticket = sql::fetch_ticket(&state.pool, ticket_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let status = match change.status {
+        Some(status) if status != ticket.status => {
+            if !ticket.status.can_transition_to(status) {
+                return Err(Error::ValidationFailure(format!(
+                    "Ticket cannot go from {:?} to {status:?}",
+                    ticket.status
+                )));
+            }
+            status
+        }
+        _ => ticket.status,
+    };
+    let public = change.public.unwrap_or(ticket.public);
+
+    if status == ticket.status && public == ticket.public {
+        return Ok(());
+    }
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::update_ticket(&mut transaction, ticket_id, status, public).await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_operator_vehicles(
+    State(state): State<AppState>,
+    Path(operator_id): Path<i32>,
+) -> Result<Json<Vec<operators::OperatorVehicle>>, Error> {
     Ok(Json(
-        sql::fetch_calendars_for_date(&state.pool, operator_id, date).await?,
+        sql::fetch_operator_vehicles(&state.pool, operator_id).await?,
     ))
 }
+
+pub(crate) async fn post_operator_vehicle(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorVehicles,
+    >,
+    Path(operator_id): Path<i32>,
+    Json(vehicle): Json<requests::ChangeVehicle>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    vehicle.validate()?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id =
+        sql::insert_operator_vehicle(&mut transaction, operator_id, &vehicle)
+            .await?;
+
+    let vehicle = operators::OperatorVehicle {
+        id,
+        operator_id,
+        name: vehicle.name,
+        service_year: vehicle.service_year,
+        quantity: vehicle.quantity,
+        bench_seats: vehicle.bench_seats,
+        foot_seats: vehicle.foot_seats,
+        has_ac: vehicle.has_ac,
+        has_usb_outlets: vehicle.has_usb_outlets,
+        has_wifi: vehicle.has_wifi,
+        has_bicycle_rack: vehicle.has_bicycle_rack,
+        has_wheelchair_ramp: vehicle.has_wheelchair_ramp,
+    };
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::VehicleCreation {
+            data: vehicle.into(),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn patch_operator_vehicle(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorVehicles,
+    >,
+    Path((operator_id, vehicle_id)): Path<(i32, i32)>,
+    Json(change): Json<requests::ChangeVehicle>,
+) -> Result<(), Error> {
+    change.validate()?;
+
+    let vehicle =
+        sql::fetch_operator_vehicle(&state.pool, operator_id, vehicle_id)
+            .await?
+            .ok_or(Error::NotFoundUpstream)?;
+
+    let patch = change.derive_patch(&vehicle);
+    if patch.is_empty() {
+        return Ok(());
+    }
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::update_operator_vehicle(&mut transaction, vehicle_id, &change).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::VehicleUpdate {
+            original: vehicle.into(),
+            patch,
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn delete_operator_vehicle(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorVehicles,
+    >,
+    Path((operator_id, vehicle_id)): Path<(i32, i32)>,
+) -> Result<(), Error> {
+    let vehicle =
+        sql::fetch_operator_vehicle(&state.pool, operator_id, vehicle_id)
+            .await?
+            .ok_or(Error::NotFoundUpstream)?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::delete_operator_vehicle(&mut transaction, vehicle_id).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::VehicleDeletion {
+            data: vehicle.into(),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_operator_route_type_vehicles(
+    State(state): State<AppState>,
+    Path((operator_id, type_id)): Path<(i32, i32)>,
+) -> Result<Json<Vec<operators::OperatorVehicle>>, Error> {
+    if !sql::fetch_operator_route_types(&state.pool, operator_id)
+        .await?
+        .iter()
+        .any(|route_type| route_type.id == type_id)
+    {
+        return Err(Error::NotFoundUpstream);
+    }
+
+    Ok(Json(
+        sql::fetch_route_type_vehicles(&state.pool, type_id).await?,
+    ))
+}
+
+pub(crate) async fn put_operator_route_type_vehicles(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOperatorVehicles,
+    >,
+    Path((operator_id, type_id)): Path<(i32, i32)>,
+    Json(vehicle_ids): Json<Vec<i32>>,
+) -> Result<(), Error> {
+    if !vehicle_ids.iter().all_unique() {
+        return Err(Error::ValidationFailure("Duplicated vehicle".to_string()));
+    }
+
+    let (route_types, vehicles) = future::join(
+        sql::fetch_operator_route_types(&state.pool, operator_id),
+        sql::fetch_operator_vehicles(&state.pool, operator_id),
+    )
+    .await;
+
+    if !route_types?
+        .iter()
+        .any(|route_type| route_type.id == type_id)
+    {
+        return Err(Error::NotFoundUpstream);
+    }
+    let vehicles = vehicles?;
+    if !vehicle_ids
+        .iter()
+        .all(|id| vehicles.iter().any(|vehicle| vehicle.id == *id))
+    {
+        return Err(Error::ValidationFailure(
+            "Vehicle does not belong to the operator".to_string(),
+        ));
+    }
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let original = sql::fetch_route_type_vehicles(&state.pool, type_id)
+        .await?
+        .into_iter()
+        .map(|vehicle| vehicle.id)
+        .collect();
+
+    sql::set_route_type_vehicles(&mut transaction, type_id, &vehicle_ids)
+        .await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::RouteTypeVehiclesUpdate {
+            type_id,
+            original,
+            vehicles: vehicle_ids,
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_route_vehicles(
+    State(state): State<AppState>,
+    Path(route_id): Path<i32>,
+) -> Result<Json<Vec<operators::OperatorVehicle>>, Error> {
+    Ok(Json(
+        sql::fetch_route_vehicles(&state.pool, route_id).await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::{Path, State};
+    use axum::Json;
+    use sqlx::PgPool;
+    use std::marker::PhantomData;
+    use std::sync::Arc;
+
+    use commons::models::history;
+
+    use super::{
+        get_operator_route_type_vehicles, put_operator_route_type_vehicles,
+    };
+    use crate::{auth, contrib, AppState, Error};
+
+    fn app_state(pool: PgPool, dir: &tempfile::TempDir) -> AppState {
+        AppState(Arc::new(crate::State::test_state(pool, dir.path())))
+    }
+
+    fn claim<P: auth::ClaimPermission>() -> auth::ScopedClaim<P> {
+        auth::ScopedClaim(
+            auth::Claims {
+                uid: 1,
+                ..auth::Claims::default()
+            },
+            PhantomData,
+        )
+    }
+
+    #[sqlx::test(fixtures(
+        "../auth/fixtures/users.sql",
+        "fixtures/route_type_vehicles.sql"
+    ))]
+    async fn ok_set_route_type_vehicles(pool: PgPool) {
+        let dir = tempfile::tempdir().unwrap();
+        let state = app_state(pool.clone(), &dir);
+
+        put_operator_route_type_vehicles(
+            State(state.clone()),
+            claim(),
+            Path((1, 1)),
+            Json(vec![2, 1]),
+        )
+        .await
+        .unwrap();
+
+        let Json(vehicles) =
+            get_operator_route_type_vehicles(State(state), Path((1, 1)))
+                .await
+                .unwrap();
+        assert_eq!(
+            vehicles
+                .iter()
+                .map(|vehicle| vehicle.id)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let mut transaction = pool.begin().await.unwrap();
+        let changesets =
+            contrib::sql::fetch_changesets_after(&mut transaction, 0)
+                .await
+                .unwrap();
+        assert_eq!(changesets.len(), 1);
+        assert!(matches!(
+            changesets[0].changes.as_slice(),
+            [history::Change::RouteTypeVehiclesUpdate {
+                type_id: 1,
+                original,
+                vehicles,
+            }] if original.is_empty() && *vehicles == [2, 1]
+        ));
+    }
+
+    #[sqlx::test(fixtures(
+        "../auth/fixtures/users.sql",
+        "fixtures/route_type_vehicles.sql"
+    ))]
+    async fn err_set_duplicated_route_type_vehicles(pool: PgPool) {
+        let dir = tempfile::tempdir().unwrap();
+
+        let res = put_operator_route_type_vehicles(
+            State(app_state(pool, &dir)),
+            claim(),
+            Path((1, 1)),
+            Json(vec![1, 1]),
+        )
+        .await;
+        assert_eq!(
+            res,
+            Err(Error::ValidationFailure("Duplicated vehicle".to_string()))
+        );
+    }
+
+    #[sqlx::test(fixtures(
+        "../auth/fixtures/users.sql",
+        "fixtures/route_type_vehicles.sql"
+    ))]
+    async fn err_set_foreign_route_type_vehicles(pool: PgPool) {
+        let dir = tempfile::tempdir().unwrap();
+        let state = app_state(pool, &dir);
+
+        // The vehicle belongs to another operator
+        let res = put_operator_route_type_vehicles(
+            State(state.clone()),
+            claim(),
+            Path((1, 1)),
+            Json(vec![3]),
+        )
+        .await;
+        assert_eq!(
+            res,
+            Err(Error::ValidationFailure(
+                "Vehicle does not belong to the operator".to_string()
+            ))
+        );
+
+        // The route type belongs to another operator
+        let res =
+            get_operator_route_type_vehicles(State(state), Path((1, 2))).await;
+        assert!(matches!(res, Err(Error::NotFoundUpstream)));
+    }
+}
@@ -21,12 +21,15 @@ use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
 
 use commons::models::operators;
 use commons::utils::{gtfs as gtfs_utils, http};
 
 use super::models;
 use crate::errors::Error;
+use crate::gtfs::diff;
+use crate::gtfs::models::{changes, GtfsSource, GtfsSourceKind};
 use crate::settings::SETTINGS;
 
 fn get_data_root() -> PathBuf {
@@ -46,6 +49,21 @@ fn get_operator_data_root(operator_id: i32) -> PathBuf {
     path
 }
 
+fn get_operator_gtfs_versions_root(operator_id: i32) -> PathBuf {
+    let mut path = get_operator_data_root(operator_id);
+    path.push("gtfs_versions");
+    path
+}
+
+fn get_operator_gtfs_version_root(
+    operator_id: i32,
+    version: &DateTime<Utc>,
+) -> PathBuf {
+    let mut path = get_operator_gtfs_versions_root(operator_id);
+    path.push(version.format("%Y%m%d%H%M%S").to_string());
+    path
+}
+
 fn get_operator_storage_meta_path(operator_id: i32) -> PathBuf {
     let mut meta_path = get_operator_data_root(operator_id);
     meta_path.push("meta.json");
@@ -82,6 +100,9 @@ impl OperatorData for operators::Operator {
 pub struct OperatorStorageMeta {
     pub(crate) last_update: Option<DateTime<Utc>>,
     pub(crate) last_gtfs: Option<DateTime<Utc>>,
+    // Every distinct feed that got downloaded, oldest first
+    #[serde(default)]
+    pub(crate) gtfs_versions: Vec<DateTime<Utc>>,
 }
 
 pub(crate) fn get_operator_storage_meta(
@@ -163,142 +184,130 @@ pub(crate) fn set_operator_storage_meta(
     Ok(())
 }
 
-pub(crate) async fn update_operator_gtfs(
-    operator_id: i32,
-    operator_tag: &str,
-) -> Result<(), Error> {
-    let mut meta = get_operator_storage_meta(operator_id)?;
-    match operator_tag {
-        "cmet" => {
-            let mut zip_path = get_operator_data_root(operator_id);
-            zip_path.push("gtfs.zip");
-            let mut gtfs_path = get_operator_data_root(operator_id);
-            gtfs_path.push("gtfs");
-
-            let url = "https://api.carrismetropolitana.pt/gtfs*";
-
-            http::download_file(url, &zip_path, None)
-                .await
-                .inspect_err(|err| {
-                    tracing::error!(
-                        msg="Failed to download file",
-                        err=?err,
-                        url,
-                        zip_path = ?zip_path
-                    );
-                })?;
-
-            let newest_file = gtfs_utils::extract(&zip_path, &gtfs_path)?;
-            meta.last_gtfs = Some(newest_file);
-        }
-        "carris" => {
-            let mut zip_path = get_operator_data_root(operator_id);
-            zip_path.push("gtfs.zip");
-            let mut gtfs_path = get_operator_data_root(operator_id);
-            gtfs_path.push("gtfs");
-            let url = "https://gateway.carris.pt/gateway/gtfs/api/v2.11/GTFS";
-
-            http::download_file(url, &zip_path, None)
-                .await
-                .inspect_err(|err| {
-                    tracing::error!(
-                        msg="Failed to download file",
-                        err=?err,
-                        url,
-                        zip_path=?zip_path
-                    );
-                })?;
-
-            let newest_file = gtfs_utils::extract(&zip_path, &gtfs_path)?;
-            meta.last_gtfs = Some(newest_file);
-        }
-        "tcb" => {
-            fetch_transporlis_feed(&mut meta, operator_id, 41).await?;
-        }
-        "ttsl" => {
-            fetch_transporlis_feed(&mut meta, operator_id, 4).await?;
-        }
-        "ml" => {
-            fetch_transporlis_feed(&mut meta, operator_id, 2).await?;
-        }
-        "cp" => {
-            fetch_transporlis_feed(&mut meta, operator_id, 3).await?;
-        }
-        "fert" => {
-            fetch_transporlis_feed(&mut meta, operator_id, 13).await?;
+/// Downloads the current archive of a GTFS source
+pub(crate) async fn download_gtfs(
+    source: &GtfsSource,
+) -> Result<Vec<u8>, Error> {
+    let url = match source.kind {
+        GtfsSourceKind::Zip => source.url.clone().ok_or_else(|| {
+            Error::ValidationFailure("Source without an URL".to_string())
+        })?,
+        GtfsSourceKind::Transporlis => {
+            let transporlis_id = source.transporlis_id.ok_or_else(|| {
+                Error::ValidationFailure(
+                    "Source without a Transporlis ID".to_string(),
+                )
+            })?;
+            format!(
+                "https://www.transporlis.pt/desktopmodules/\
+                trp_opendata/ajax/downloadFile.ashx?op={transporlis_id}&u=web"
+            )
         }
-        "mobic" => {
-            let mut zip_path = get_operator_data_root(operator_id);
-            zip_path.push("gtfs.zip");
-            let mut gtfs_path = get_operator_data_root(operator_id);
-            gtfs_path.push("gtfs");
-            let url = "https://dadosabertos.cascais.pt/\
-                dataset/ddef8977-0ad0-4d23-99d3-ae269a21b589/\
-                resource/819dac57-8843-43a3-a630-9cc7987325c0/\
-                download/gtfs-mobicascais.zip";
-
-            http::download_file(url, &zip_path, None)
-                .await
-                .inspect_err(|err| {
-                    tracing::error!(
-                        msg="Failed to download file",
-                        err=?err,
-                        url,
-                        zip_path=?zip_path
-                    );
-                })?;
+        GtfsSourceKind::Upload => return Err(Error::DependenciesNotMet),
+    };
 
-            let newest_file = gtfs_utils::extract(&zip_path, &gtfs_path)
-                .inspect_err(|err| {
-                    tracing::error!(
-                        msg="Failure extracting GTFS",
-                        operator_id,
-                        err=?err
-                    );
-                })?;
-            meta.last_gtfs = Some(newest_file);
-        }
-        _ => {
-            tracing::warn!("Unknown operator tag: '{operator_tag}'");
-        }
-    }
+    Ok(http::fetch_bytes(&url, &source.headers)
+        .await
+        .inspect_err(|err| {
+            tracing::error!(
+                msg="Failed to download file",
+                err=?err,
+                url,
+                operator_id = source.operator_id
+            );
+        })?)
+}
 
-    meta.last_update = Some(Utc::now());
-    set_operator_storage_meta(operator_id, &meta)
+pub(crate) fn gtfs_checksum(content: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(content);
+    base16ct::lower::encode_string(&hasher.finalize())
 }
 
-async fn fetch_transporlis_feed(
-    meta: &mut OperatorStorageMeta,
+/// Replaces the current feed of an operator with the given archive
+pub(crate) fn store_gtfs(
     operator_id: i32,
-    transporlis_id: i32,
+    content: &[u8],
 ) -> Result<(), Error> {
+    let mut meta = get_operator_storage_meta(operator_id)?;
+
     let mut zip_path = get_operator_data_root(operator_id);
     zip_path.push("gtfs.zip");
     let mut gtfs_path = get_operator_data_root(operator_id);
     gtfs_path.push("gtfs");
-    let url = format!(
-        "https://www.transporlis.pt/desktopmodules/\
-            trp_opendata/ajax/downloadFile.ashx?op={transporlis_id}&u=web"
-    );
 
-    http::download_file(&url, &zip_path, None)
-        .await
-        .inspect_err(|err| {
+    fs::write(&zip_path, content).map_err(|err| {
+        tracing::error!(
+            msg="Unable to write file",
+            err=?err,
+            zip_path=?zip_path
+        );
+        Error::Filesystem
+    })?;
+
+    let newest_file =
+        gtfs_utils::extract(&zip_path, &gtfs_path).inspect_err(|err| {
             tracing::error!(
-                msg="Failed to download file",
-                err=?err,
-                url,
-                zip_path=?zip_path
+                msg="Failure extracting GTFS",
+                operator_id,
+                err=?err
             );
         })?;
+    record_gtfs_version(&mut meta, operator_id, &zip_path, newest_file)?;
 
-    let newest_file = gtfs_utils::extract(
-        &zip_path,
-        &gtfs_path,
-    )
-    .inspect_err(|err| {
-        tracing::error!(msg="Failure extracting GTFS", operator_id, err=?err);
-    })?;
+    meta.last_update = Some(Utc::now());
+    set_operator_storage_meta(operator_id, &meta)
+}
+
+/// Keeps a copy of a downloaded feed if it differs from the last one,
+/// along with the changes between both of them
+fn record_gtfs_version(
+    meta: &mut OperatorStorageMeta,
+    operator_id: i32,
+    zip_path: &PathBuf,
+    newest_file: DateTime<Utc>,
+) -> Result<(), Error> {
     meta.last_gtfs = Some(newest_file);
+    let previous_version = meta.gtfs_versions.last().copied();
+    if previous_version.is_some_and(|version| version >= newest_file) {
+        return Ok(());
+    }
+
+    let version_path =
+        get_operator_gtfs_version_root(operator_id, &newest_file);
+    gtfs_utils::extract(zip_path, &version_path).inspect_err(|err| {
+        tracing::error!(
+            msg="Failure storing GTFS version",
+            operator_id,
+            err=?err
+        );
+    })?;
+
+    if let Some(previous_version) = previous_version {
+        let previous_path =
+            get_operator_gtfs_version_root(operator_id, &previous_version);
+        let feed_changes = diff::diff_feeds(
+            &previous_path,
+            &version_path,
+            previous_version,
+            newest_file,
+        )?;
+        diff::store_feed_changes(&version_path, &feed_changes)?;
+    }
+
+    meta.gtfs_versions.push(newest_file);
     Ok(())
 }
+
+pub(crate) fn get_operator_feed_changes(
+    operator_id: i32,
+) -> Result<Vec<changes::FeedChanges>, Error> {
+    let meta = get_operator_storage_meta(operator_id)?;
+    meta.gtfs_versions
+        .iter()
+        .rev()
+        .map(|version| get_operator_gtfs_version_root(operator_id, version))
+        .filter(|version_path| diff::has_feed_changes(version_path))
+        .map(|version_path| diff::load_feed_changes(&version_path))
+        .collect()
+}
@@ -179,6 +179,13 @@ pub(crate) mod responses {
         pub stops: Vec<SimpleStop>,
         pub regions: Vec<SimpleRegion>,
     }
+
+    #[derive(Debug, Serialize)]
+    pub struct FullTicket {
+        #[serde(flatten)]
+        pub ticket: operators::Ticket,
+        pub comments: Vec<operators::TicketComment>,
+    }
 }
 
 pub(crate) mod requests {
@@ -523,4 +530,121 @@ pub(crate) mod requests {
             patch
         }
     }
+
+    #[derive(Debug, Deserialize)]
+    pub struct NewTicket {
+        pub title: String,
+        pub message: String,
+        pub reason: operators::TicketReason,
+        #[serde(default)]
+        pub public: bool,
+    }
+
+    impl NewTicket {
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            if self.title.trim().is_empty() {
+                return Err(Error::ValidationFailure(
+                    "Empty title".to_string(),
+                ));
+            }
+            if self.message.trim().is_empty() {
+                return Err(Error::ValidationFailure(
+                    "Empty message".to_string(),
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct NewTicketComment {
+        pub message: String,
+    }
+
+    impl NewTicketComment {
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            if self.message.trim().is_empty() {
+                return Err(Error::ValidationFailure(
+                    "Empty message".to_string(),
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ChangeTicket {
+        pub status: Option<operators::TicketStatus>,
+        pub public: Option<bool>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(clippy::struct_excessive_bools)]
+    pub struct ChangeVehicle {
+        pub name: String,
+        pub service_year: Option<i32>,
+        pub quantity: i32,
+        pub bench_seats: i32,
+        pub foot_seats: i32,
+        pub has_ac: bool,
+        pub has_usb_outlets: bool,
+        pub has_wifi: bool,
+        pub has_bicycle_rack: bool,
+        pub has_wheelchair_ramp: bool,
+    }
+
+    impl ChangeVehicle {
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            if self.name.trim().is_empty() {
+                return Err(Error::ValidationFailure("Empty name".to_string()));
+            }
+            if self.quantity < 0 || self.bench_seats < 0 || self.foot_seats < 0
+            {
+                return Err(Error::ValidationFailure(
+                    "Negative quantity or seat count".to_string(),
+                ));
+            }
+            Ok(())
+        }
+
+        pub fn derive_patch(
+            &self,
+            vehicle: &operators::OperatorVehicle,
+        ) -> history::operators::OperatorVehiclePatch {
+            let mut patch = history::operators::OperatorVehiclePatch::default();
+
+            if self.name != vehicle.name {
+                patch.name = Some(self.name.clone());
+            }
+            if self.service_year != vehicle.service_year {
+                patch.service_year = Some(self.service_year);
+            }
+            if self.quantity != vehicle.quantity {
+                patch.quantity = Some(self.quantity);
+            }
+            if self.bench_seats != vehicle.bench_seats {
+                patch.bench_seats = Some(self.bench_seats);
+            }
+            if self.foot_seats != vehicle.foot_seats {
+                patch.foot_seats = Some(self.foot_seats);
+            }
+            if self.has_ac != vehicle.has_ac {
+                patch.has_ac = Some(self.has_ac);
+            }
+            if self.has_usb_outlets != vehicle.has_usb_outlets {
+                patch.has_usb_outlets = Some(self.has_usb_outlets);
+            }
+            if self.has_wifi != vehicle.has_wifi {
+                patch.has_wifi = Some(self.has_wifi);
+            }
+            if self.has_bicycle_rack != vehicle.has_bicycle_rack {
+                patch.has_bicycle_rack = Some(self.has_bicycle_rack);
+            }
+            if self.has_wheelchair_ramp != vehicle.has_wheelchair_ramp {
+                patch.has_wheelchair_ramp = Some(self.has_wheelchair_ramp);
+            }
+
+            patch
+        }
+    }
 }
@@ -16,11 +16,11 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use chrono::{Local, NaiveDate};
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use sqlx::types::Json;
 use sqlx::PgPool;
 
-use commons::models::calendar::Calendar;
+use commons::models::calendar::{Almanac, Calendar};
 use commons::models::content::RichContent;
 use commons::models::operators;
 
@@ -1613,6 +1613,7 @@ pub(crate) async fn fetch_calendars_for_date(
     pool: &PgPool,
     operator_id: i32,
     date: NaiveDate,
+    almanac: &Almanac,
 ) -> Result<Vec<responses::OperatorCalendar>> {
     sqlx::query!(
         r#"
@@ -1632,7 +1633,7 @@ WHERE operator_id=$1
     .filter_map(
         |row| match serde_json::from_value::<Calendar>(row.calendar) {
             Ok(calendar) => {
-                if calendar.includes(date) {
+                if calendar.includes(date, almanac) {
                     Some(Ok(responses::OperatorCalendar {
                         id: row.id,
                         name: row.name,
@@ -1648,3 +1649,454 @@ WHERE operator_id=$1
     )
     .collect()
 }
+
+struct TicketRow {
+    id: i32,
+    title: String,
+    message: String,
+    reason: i16,
+    creation: DateTime<Utc>,
+    operator_id: i32,
+    user_id: i32,
+    public: bool,
+    status: i16,
+}
+
+impl TryFrom<TicketRow> for operators::Ticket {
+    type Error = Error;
+
+    fn try_from(row: TicketRow) -> Result<Self> {
+        Ok(operators::Ticket {
+            id: row.id,
+            title: row.title,
+            message: row.message,
+            reason: operators::TicketReason::try_from(row.reason)?,
+            creation: row.creation.into(),
+            operator_id: row.operator_id,
+            user_id: row.user_id,
+            public: row.public,
+            status: operators::TicketStatus::try_from(row.status)?,
+        })
+    }
+}
+
+pub(crate) async fn fetch_ticket(
+    pool: &PgPool,
+    ticket_id: i32,
+) -> Result<Option<operators::Ticket>> {
+    sqlx::query_as!(
+        TicketRow,
+        r#"
+SELECT id, title, message, reason, creation, operator_id, user_id, public,
+    status
+FROM tickets
+WHERE id = $1
+"#,
+        ticket_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ticket_id);
+        Error::DatabaseExecution
+    })?
+    .map(operators::Ticket::try_from)
+    .transpose()
+}
+
+pub(crate) async fn fetch_operator_tickets(
+    pool: &PgPool,
+    operator_id: i32,
+    include_private: bool,
+) -> Result<Vec<operators::Ticket>> {
+    sqlx::query_as!(
+        TicketRow,
+        r#"
+SELECT id, title, message, reason, creation, operator_id, user_id, public,
+    status
+FROM tickets
+WHERE operator_id = $1 AND (public OR $2)
+ORDER BY creation DESC
+"#,
+        operator_id,
+        include_private
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(operators::Ticket::try_from)
+    .collect()
+}
+
+pub(crate) async fn fetch_user_tickets(
+    pool: &PgPool,
+    user_id: i32,
+) -> Result<Vec<operators::Ticket>> {
+    sqlx::query_as!(
+        TicketRow,
+        r#"
+SELECT id, title, message, reason, creation, operator_id, user_id, public,
+    status
+FROM tickets
+WHERE user_id = $1
+ORDER BY creation DESC
+"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), user_id);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(operators::Ticket::try_from)
+    .collect()
+}
+
+pub(crate) async fn insert_ticket(
+    pool: &PgPool,
+    operator_id: i32,
+    user_id: i32,
+    ticket: &requests::NewTicket,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO tickets(title, message, reason, operator_id, user_id, public,
+    status)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING id
+"#,
+        ticket.title,
+        ticket.message,
+        i16::from(ticket.reason),
+        operator_id,
+        user_id,
+        ticket.public,
+        i16::from(operators::TicketStatus::New),
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, user_id);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn update_ticket(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ticket_id: i32,
+    status: operators::TicketStatus,
+    public: bool,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE tickets
+SET status = $2, public = $3
+WHERE id = $1
+"#,
+        ticket_id,
+        i16::from(status),
+        public
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ticket_id);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn fetch_ticket_comments(
+    pool: &PgPool,
+    ticket_id: i32,
+) -> Result<Vec<operators::TicketComment>> {
+    sqlx::query!(
+        r#"
+SELECT id, ticket_id, message, datetime, user_id
+FROM ticket_comments
+WHERE ticket_id = $1
+ORDER BY datetime ASC
+"#,
+        ticket_id
+    )
+    .fetch_all(pool)
+    .await
+    .map(|rows| {
+        rows.into_iter()
+            .map(|row| operators::TicketComment {
+                id: row.id,
+                ticket_id: row.ticket_id,
+                message: row.message,
+                datetime: row.datetime.into(),
+                user_id: row.user_id,
+            })
+            .collect()
+    })
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ticket_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn insert_ticket_comment(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ticket_id: i32,
+    user_id: i32,
+    message: &str,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO ticket_comments(ticket_id, message, user_id)
+VALUES ($1, $2, $3)
+RETURNING id
+"#,
+        ticket_id,
+        message,
+        user_id
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ticket_id, user_id);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn fetch_operator_vehicles(
+    pool: &PgPool,
+    operator_id: i32,
+) -> Result<Vec<operators::OperatorVehicle>> {
+    sqlx::query_as!(
+        operators::OperatorVehicle,
+        r#"
+SELECT id, operator_id, name, service_year, quantity, bench_seats, foot_seats,
+    has_ac, has_usb_outlets, has_wifi, has_bicycle_rack, has_wheelchair_ramp
+FROM vehicle
+WHERE operator_id = $1
+ORDER BY id
+"#,
+        operator_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_operator_vehicle(
+    pool: &PgPool,
+    operator_id: i32,
+    vehicle_id: i32,
+) -> Result<Option<operators::OperatorVehicle>> {
+    sqlx::query_as!(
+        operators::OperatorVehicle,
+        r#"
+SELECT id, operator_id, name, service_year, quantity, bench_seats, foot_seats,
+    has_ac, has_usb_outlets, has_wifi, has_bicycle_rack, has_wheelchair_ramp
+FROM vehicle
+WHERE operator_id = $1 AND id = $2
+"#,
+        operator_id,
+        vehicle_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, vehicle_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn insert_operator_vehicle(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    operator_id: i32,
+    vehicle: &requests::ChangeVehicle,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO vehicle(operator_id, name, service_year, quantity, bench_seats,
+    foot_seats, has_ac, has_usb_outlets, has_wifi, has_bicycle_rack,
+    has_wheelchair_ramp)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+RETURNING id
+"#,
+        operator_id,
+        vehicle.name,
+        vehicle.service_year,
+        vehicle.quantity,
+        vehicle.bench_seats,
+        vehicle.foot_seats,
+        vehicle.has_ac,
+        vehicle.has_usb_outlets,
+        vehicle.has_wifi,
+        vehicle.has_bicycle_rack,
+        vehicle.has_wheelchair_ramp
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), operator_id, ?vehicle);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn update_operator_vehicle(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    vehicle_id: i32,
+    vehicle: &requests::ChangeVehicle,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE vehicle
+SET name = $2, service_year = $3, quantity = $4, bench_seats = $5,
+    foot_seats = $6, has_ac = $7, has_usb_outlets = $8, has_wifi = $9,
+    has_bicycle_rack = $10, has_wheelchair_ramp = $11
+WHERE id = $1
+"#,
+        vehicle_id,
+        vehicle.name,
+        vehicle.service_year,
+        vehicle.quantity,
+        vehicle.bench_seats,
+        vehicle.foot_seats,
+        vehicle.has_ac,
+        vehicle.has_usb_outlets,
+        vehicle.has_wifi,
+        vehicle.has_bicycle_rack,
+        vehicle.has_wheelchair_ramp
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), vehicle_id, ?vehicle);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn delete_operator_vehicle(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    vehicle_id: i32,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+DELETE FROM vehicle
+WHERE id = $1
+"#,
+        vehicle_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), vehicle_id);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn fetch_route_type_vehicles(
+    pool: &PgPool,
+    type_id: i32,
+) -> Result<Vec<operators::OperatorVehicle>> {
+    sqlx::query_as!(
+        operators::OperatorVehicle,
+        r#"
+SELECT vehicle.id, vehicle.operator_id, vehicle.name, vehicle.service_year,
+    vehicle.quantity, vehicle.bench_seats, vehicle.foot_seats, vehicle.has_ac,
+    vehicle.has_usb_outlets, vehicle.has_wifi, vehicle.has_bicycle_rack,
+    vehicle.has_wheelchair_ramp
+FROM vehicle
+JOIN route_type_vehicles ON route_type_vehicles.vehicle_id = vehicle.id
+WHERE route_type_vehicles.route_type_id = $1
+ORDER BY vehicle.id
+"#,
+        type_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), type_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_route_vehicles(
+    pool: &PgPool,
+    route_id: i32,
+) -> Result<Vec<operators::OperatorVehicle>> {
+    sqlx::query_as!(
+        operators::OperatorVehicle,
+        r#"
+SELECT vehicle.id, vehicle.operator_id, vehicle.name, vehicle.service_year,
+    vehicle.quantity, vehicle.bench_seats, vehicle.foot_seats, vehicle.has_ac,
+    vehicle.has_usb_outlets, vehicle.has_wifi, vehicle.has_bicycle_rack,
+    vehicle.has_wheelchair_ramp
+FROM vehicle
+JOIN route_type_vehicles ON route_type_vehicles.vehicle_id = vehicle.id
+JOIN routes ON routes.type = route_type_vehicles.route_type_id
+WHERE routes.id = $1
+ORDER BY vehicle.id
+"#,
+        route_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), route_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn set_route_type_vehicles(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    type_id: i32,
+    vehicle_ids: &[i32],
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+DELETE FROM route_type_vehicles
+WHERE route_type_id = $1
+"#,
+        type_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), type_id);
+        Error::DatabaseExecution
+    })?;
+
+    sqlx::query!(
+        r#"
+INSERT INTO route_type_vehicles(route_type_id, vehicle_id)
+SELECT $1, unnest($2::int[])
+"#,
+        type_id,
+        vehicle_ids
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), type_id, ?vehicle_ids);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
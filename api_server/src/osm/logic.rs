@@ -0,0 +1,235 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use commons::models::osm;
+use commons::utils::http;
+
+use super::models::{requests, responses};
+use super::sql;
+use crate::Error;
+
+const OVERPASS_URL: &str = "https://overpass-api.de/api/interpreter";
+const OSM_API_URL: &str = "https://www.openstreetmap.org/api/0.6";
+// Compliance with the OSM API policy
+const USER_AGENT: &str = "Intermodal (https://intermodal.pt)";
+const OVERPASS_STOPS_QUERY: &str = r#"[out:json];
+area[name="Portugal"][admin_level=2]->.searchArea;
+(
+  node["station"="light_rail"](area.searchArea);
+  node["highway"="bus_stop"](area.searchArea);
+  node["disused:highway"="bus_stop"](area.searchArea);
+);
+out meta;"#;
+// History queries per sync, to be respectful of the OSM API
+const MAX_OSM_CALLS: usize = 30;
+const OSM_CALL_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+struct JsonElements {
+    elements: Vec<JsonNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonNode {
+    id: i64,
+    version: i32,
+    // Absent in deleted versions
+    lat: Option<f64>,
+    lon: Option<f64>,
+    #[serde(default)]
+    visible: Option<bool>,
+    user: String,
+    uid: i32,
+    timestamp: DateTime<Utc>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+}
+
+impl From<JsonNode> for osm::NodeVersion {
+    fn from(node: JsonNode) -> Self {
+        osm::NodeVersion {
+            version: node.version,
+            author: node.uid,
+            author_uname: node.user,
+            lat: node.lat.unwrap_or_default(),
+            lon: node.lon.unwrap_or_default(),
+            attributes: node.tags.into_iter().collect(),
+            timestamp: node.timestamp,
+            deleted: !node.visible.unwrap_or(true),
+        }
+    }
+}
+
+async fn fetch_json_nodes(url: &str) -> Result<Vec<JsonNode>, Error> {
+    let headers =
+        HashMap::from([("User-Agent".to_string(), USER_AGENT.to_string())]);
+    let content =
+        http::fetch_bytes(url, &headers).await.inspect_err(|err| {
+            tracing::error!(msg = "Failed to query OSM", err = ?err, url);
+        })?;
+    let elements: JsonElements =
+        serde_json::from_slice(&content).map_err(|err| {
+            tracing::error!(msg = "Unexpected OSM response", err = ?err, url);
+            Error::Serialization
+        })?;
+    Ok(elements.elements)
+}
+
+async fn fetch_overpass_stops() -> Result<Vec<JsonNode>, Error> {
+    let url = format!(
+        "{OVERPASS_URL}?data={}",
+        urlencoding::encode(OVERPASS_STOPS_QUERY)
+    );
+    fetch_json_nodes(&url).await
+}
+
+async fn fetch_node_history(id: i64) -> Result<osm::NodeHistory, Error> {
+    let url = format!("{OSM_API_URL}/node/{id}/history.json");
+    let mut history = fetch_json_nodes(&url)
+        .await?
+        .into_iter()
+        .map(osm::NodeVersion::from)
+        .collect::<osm::NodeHistory>();
+    history.sort();
+    fill_deleted_positions(&mut history);
+    Ok(history)
+}
+
+/// Deleted versions come without coordinates. They keep the previous ones.
+fn fill_deleted_positions(history: &mut osm::NodeHistory) {
+    let mut last_pos = (0.0, 0.0);
+    history.iter_mut().for_each(|version| {
+        if version.deleted {
+            (version.lon, version.lat) = last_pos;
+        } else {
+            last_pos = (version.lon, version.lat);
+        }
+    });
+}
+
+/// Brings the stored OSM stops up to date with OSM.
+/// Stops that moved a single version ahead are patched from Overpass.
+/// The others (including those gone from Overpass) need their history
+/// queried, of which only a few are done per sync.
+pub(crate) async fn sync_osm_stops(
+    pool: &PgPool,
+) -> Result<responses::OsmStopSync, Error> {
+    let mut histories = sql::fetch_osm_stop_histories(pool).await?;
+    let overpass_stops = fetch_overpass_stops().await?;
+
+    let mut summary = responses::OsmStopSync::default();
+    let mut unreturned_ids = histories
+        .iter()
+        .filter(|(_, history)| history.last().is_some_and(|v| !v.deleted))
+        .map(|(id, _)| *id)
+        .collect::<HashSet<i64>>();
+    let mut pending_ids = vec![];
+    let mut patch = vec![];
+
+    for overpass_stop in overpass_stops {
+        unreturned_ids.remove(&overpass_stop.id);
+
+        let cached_version = histories
+            .get(&overpass_stop.id)
+            .and_then(|history| history.last())
+            .map_or(0, |version| version.version);
+
+        if overpass_stop.version == cached_version + 1 {
+            let id = overpass_stop.id;
+            let mut history = histories.remove(&id).unwrap_or_default();
+            history.push(osm::NodeVersion::from(overpass_stop));
+            patch.push(requests::OsmStop { id, history });
+            summary.appended.push(id);
+        } else if overpass_stop.version > cached_version {
+            pending_ids.push(overpass_stop.id);
+        }
+    }
+    pending_ids.extend(unreturned_ids);
+
+    for (i, id) in pending_ids.into_iter().enumerate() {
+        if i >= MAX_OSM_CALLS {
+            summary.postponed.push(id);
+            continue;
+        }
+        if i > 0 {
+            tokio::time::sleep(OSM_CALL_DELAY).await;
+        }
+
+        match fetch_node_history(id).await {
+            Ok(history) if !history.is_empty() => {
+                patch.push(requests::OsmStop { id, history });
+                summary.refetched.push(id);
+            }
+            Ok(_) | Err(_) => summary.failed.push(id),
+        }
+    }
+
+    if !patch.is_empty() {
+        sql::upsert_osm_stops(pool, &patch).await?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_history_deserialization() {
+        let data = r#"{
+            "version": "0.6",
+            "elements": [
+                {"type": "node", "id": 1111, "visible": true, "version": 1,
+                 "timestamp": "2011-02-16T14:22:25Z", "user": "Foo1",
+                 "uid": 123, "lat": 38.0, "lon": -8.0,
+                 "tags": {"highway": "bus_stop", "bus": "yes"}},
+                {"type": "node", "id": 1111, "visible": false, "version": 2,
+                 "timestamp": "2022-08-12T05:47:09Z", "user": "Foo2",
+                 "uid": 456}
+            ]
+        }"#;
+        let elements: JsonElements = serde_json::from_str(data).unwrap();
+        let mut history = elements
+            .elements
+            .into_iter()
+            .map(osm::NodeVersion::from)
+            .collect::<osm::NodeHistory>();
+        fill_deleted_positions(&mut history);
+
+        assert_eq!(history.len(), 2);
+        assert!(!history[0].deleted);
+        assert_eq!(
+            history[0].attributes,
+            vec![
+                ("bus".to_string(), "yes".to_string()),
+                ("highway".to_string(), "bus_stop".to_string())
+            ]
+        );
+        assert!(history[1].deleted);
+        assert_eq!(history[1].author_uname, "Foo2");
+        assert!((history[1].lon + 8.0).abs() < f64::EPSILON);
+        assert!((history[1].lat - 38.0).abs() < f64::EPSILON);
+    }
+}
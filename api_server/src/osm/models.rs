@@ -84,4 +84,16 @@ pub(crate) mod responses {
         pub env_authors: Vec<String>,
         pub env_update: Option<NaiveDateTime>,
     }
+
+    /// The outcome of an OSM stop sync, as OSM node IDs
+    #[derive(Debug, Default, Serialize)]
+    pub struct OsmStopSync {
+        // Stops that got their newest version appended
+        pub appended: Vec<i64>,
+        // Stops whose whole history had to be fetched
+        pub refetched: Vec<i64>,
+        // Stops left for a later sync, to spare the OSM API
+        pub postponed: Vec<i64>,
+        pub failed: Vec<i64>,
+    }
 }
@@ -19,6 +19,8 @@
 use std::collections::HashMap;
 
 use axum::extract::{Multipart, Path, Query, State};
+use axum::http::header;
+use axum::response::IntoResponse;
 use axum::Json;
 use commons::models::{history, pics};
 use serde::Deserialize;
@@ -26,7 +28,9 @@ use uuid::Uuid;
 
 use super::{logic, models::requests, models::responses, sql};
 use crate::pics::logic::import_external_news_img;
+use crate::pics::models::Redaction;
 use crate::responses::Pagination;
+use crate::settings::SETTINGS;
 use crate::utils::get_exactly_one_field;
 use crate::Error;
 use crate::{auth, auth::ClaimPermission, contrib, AppState};
@@ -199,11 +203,40 @@ pub(crate) async fn get_unpositioned_stop_pictures(
     }))
 }
 
+pub(crate) async fn get_duplicate_stop_pictures(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::DeleteStopPic>,
+) -> Result<Json<Vec<Vec<responses::MinimalStopPic>>>, Error> {
+    let (pics, hashes): (Vec<_>, Vec<_>) =
+        sql::fetch_hashed_stop_pics(&state.pool)
+            .await?
+            .into_iter()
+            .unzip();
+    let mut pics = pics.into_iter().map(Some).collect::<Vec<_>>();
+
+    let clusters = logic::duplicate_clusters(
+        &hashes,
+        SETTINGS.get().unwrap().images.duplicate_distance,
+    );
+
+    Ok(Json(
+        clusters
+            .into_iter()
+            .map(|cluster| {
+                cluster
+                    .into_iter()
+                    .filter_map(|idx| pics[idx].take())
+                    .collect()
+            })
+            .collect(),
+    ))
+}
+
 pub(crate) async fn upload_dangling_stop_picture(
     State(state): State<AppState>,
     claims: auth::Claims,
     mut multipart: Multipart,
-) -> Result<Json<pics::StopPic>, Error> {
+) -> Result<Json<responses::StopPicUpload<pics::StopPic>>, Error> {
     // TODO have some sort of rate limiting for untrusted users
 
     let field = get_exactly_one_field(&mut multipart).await?;
@@ -219,17 +252,42 @@ pub(crate) async fn upload_dangling_stop_picture(
         .await
         .map_err(|err| Error::ValidationFailure(err.to_string()))?;
 
-    let pic = logic::upload_stop_picture(
+    let (pic, similar_pics) = logic::upload_stop_picture(
         claims.uid,
+        auth::perms::ViewSensitiveStopPic::is_valid(&claims.permissions),
         filename.clone(),
-        &state.bucket,
+        &state.storage,
         &state.pool,
         &content,
         &[],
     )
     .await?;
 
-    Ok(Json(pic))
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::StopPicUpload {
+            pic: pic.clone().into(),
+            stops: vec![],
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(responses::StopPicUpload {
+        upload: pic,
+        similar_pics,
+    }))
 }
 
 pub(crate) async fn upload_stop_picture(
@@ -237,7 +295,7 @@ pub(crate) async fn upload_stop_picture(
     claims: auth::Claims,
     Path(stop_id): Path<i32>,
     mut multipart: Multipart,
-) -> Result<Json<responses::PicWithStops>, Error> {
+) -> Result<Json<responses::StopPicUpload<responses::PicWithStops>>, Error> {
     // TODO replace this with some rate limit for untrusted users
     // if !(claims.permissions.is_admin) {
     //     return Err(Error::Forbidden);
@@ -261,10 +319,11 @@ pub(crate) async fn upload_stop_picture(
         Error::DatabaseExecution
     })?;
 
-    let pic = logic::upload_stop_picture(
+    let (pic, similar_pics) = logic::upload_stop_picture(
         claims.uid,
+        auth::perms::ViewSensitiveStopPic::is_valid(&claims.permissions),
         filename.clone(),
-        &state.bucket,
+        &state.storage,
         &state.pool,
         &content,
         &[stop_id],
@@ -299,7 +358,10 @@ pub(crate) async fn upload_stop_picture(
         }],
     ));
 
-    Ok(Json(pic))
+    Ok(Json(responses::StopPicUpload {
+        upload: pic,
+        similar_pics,
+    }))
 }
 
 pub(crate) async fn get_stop_picture_meta(
@@ -425,7 +487,139 @@ pub(crate) async fn delete_picture(
         return Err(Error::Forbidden);
     }
 
-    logic::delete_picture(pic, claims.uid, &state.bucket, &state.pool).await
+    logic::delete_picture(pic, claims.uid, &state.storage, &state.pool).await
+}
+
+pub(crate) async fn get_stop_picture_redactions(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ModifyOwnStopPic>,
+    Path(picture_id): Path<i32>,
+) -> Result<Json<Vec<Redaction>>, Error> {
+    Ok(Json(
+        sql::fetch_stop_pic_redactions(&state.pool, picture_id)
+            .await?
+            .ok_or(Error::NotFoundUpstream)?,
+    ))
+}
+
+pub(crate) async fn put_stop_picture_redactions(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyOwnStopPic,
+    >,
+    Path(picture_id): Path<i32>,
+    Json(change): Json<requests::ChangeStopPicRedactions>,
+) -> Result<(), Error> {
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    // Concurrent redactions would race over the stored objects
+    let original_redactions =
+        sql::fetch_stop_pic_redactions_for_update(&mut transaction, picture_id)
+            .await?
+            .ok_or(Error::NotFoundUpstream)?;
+
+    let pic = sql::fetch_stop_pic(&mut *transaction, picture_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    if !(auth::perms::ModifyOthersStopPic::is_valid(&claims.permissions)
+        || pic.uploader == claims.uid)
+    {
+        return Err(Error::Forbidden);
+    }
+
+    change.validate(&pic)?;
+
+    if original_redactions == change.redactions {
+        return Ok(());
+    }
+
+    sql::update_stop_pic_redactions(
+        &mut transaction,
+        picture_id,
+        &change.redactions,
+    )
+    .await?;
+
+    logic::redact_stop_picture(&state.storage, &pic, &change.redactions)
+        .await?;
+
+    // Still under the lock, as a concurrent redaction would otherwise find
+    // the set-aside copy and take it for the original that it is about to blur
+    if change.redactions.is_empty() {
+        logic::discard_unredacted_stop_picture(&state.storage, &pic.sha1)
+            .await?;
+    }
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    // The published versions were replaced under the same keys
+    logic::purge_cached_stop_picture(&pic).await
+}
+
+pub(crate) async fn get_unredacted_stop_picture(
+    State(state): State<AppState>,
+    auth::ScopedClaim(_, _): auth::ScopedClaim<
+        auth::perms::ViewSensitiveStopPic,
+    >,
+    Path(picture_id): Path<i32>,
+) -> Result<impl IntoResponse, Error> {
+    let pic = sql::fetch_stop_pic(&state.pool, picture_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let content =
+        logic::fetch_unredacted_stop_picture(&state.storage, &pic.sha1).await?;
+    let mime =
+        mime_guess::from_path(&pic.original_filename).first_or_octet_stream();
+
+    Ok(([(header::CONTENT_TYPE, mime.to_string())], content))
+}
+
+// Object prefixes that a public bucket would expose
+const PUBLIC_OBJECT_PREFIXES: [&str; 7] = [
+    "ori",
+    "medium",
+    "thumb",
+    "content",
+    "enews",
+    "enews_ss",
+    "operators",
+];
+
+// Serves objects out of a local storage, at the URLs that the images root
+// setting leads to. The last path segment only names the file.
+pub(crate) async fn get_local_object(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    if !state.storage.is_local() {
+        return Err(Error::NotFoundUpstream);
+    }
+
+    let key = path.rsplit_once('/').map_or(path.as_str(), |(key, _)| key);
+    let key = key.trim_start_matches('/');
+    let is_public = key
+        .split_once('/')
+        .is_some_and(|(prefix, _)| PUBLIC_OBJECT_PREFIXES.contains(&prefix));
+    // Content types are kept next to the objects, in .type files
+    if !is_public || key.ends_with(".type") {
+        return Err(Error::NotFoundUpstream);
+    }
+
+    let content = state.storage.get(key).await?;
+    let content_type =
+        state.storage.content_type(key).await?.unwrap_or_else(|| {
+            mime_guess::mime::APPLICATION_OCTET_STREAM.to_string()
+        });
+
+    Ok(([(header::CONTENT_TYPE, content_type)], content))
 }
 
 pub(crate) async fn get_picture_count_by_stop(
@@ -464,7 +658,7 @@ pub(crate) async fn upload_pano_picture(
     let pic = logic::upload_pano_picture(
         claims.uid,
         filename,
-        &state.bucket,
+        &state.storage,
         &state.pool,
         &content,
     )
@@ -520,7 +714,7 @@ pub(crate) async fn post_upload_operator_logo(
 
     logic::upload_operator_logo(
         operator_id,
-        &state.bucket,
+        &state.storage,
         &state.pool,
         &filename,
         &content,
@@ -555,7 +749,7 @@ pub(crate) async fn post_rich_image(
         .map_err(|err| Error::ValidationFailure(err.to_string()))?;
 
     let img =
-        logic::upload_rich_img(&state.bucket, &state.pool, filename, &content)
+        logic::upload_rich_img(&state.storage, &state.pool, filename, &content)
             .await?;
 
     Ok(Json(img.into()))
@@ -608,9 +802,12 @@ pub(crate) async fn post_import_external_news_image(
         return Err(Error::Forbidden);
     }
 
-    let img =
-        import_external_news_img(&state.bucket, &state.pool, external_image_id)
-            .await?;
+    let img = import_external_news_img(
+        &state.storage,
+        &state.pool,
+        external_image_id,
+    )
+    .await?;
 
     Ok(Json(img.into()))
 }
@@ -636,7 +833,7 @@ pub(crate) async fn post_external_news_image(
 
     let img = logic::upload_external_news_item_img(
         item_id,
-        &state.bucket,
+        &state.storage,
         &state.pool,
         &filename,
         &content,
@@ -667,7 +864,7 @@ pub(crate) async fn put_external_news_screenshot(
 
     logic::upload_external_news_item_screenshot(
         item_id,
-        &state.bucket,
+        &state.storage,
         &state.pool,
         &filename,
         &content,
@@ -16,6 +16,7 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsStr;
 use std::io::{BufReader, Cursor};
 use std::str::FromStr;
@@ -30,8 +31,11 @@ use uuid::Uuid;
 use commons::models::{history, pics};
 use commons::utils::exif::{Exif, Orientation};
 
+use super::models::Redaction;
 use super::sql;
 use crate::contrib;
+use crate::settings::SETTINGS;
+use crate::storage::ObjectStorage;
 use crate::Error;
 
 const THUMBNAIL_MAX_WIDTH: u32 = 300;
@@ -42,15 +46,19 @@ const MEDIUM_IMG_MAX_WIDTH: u32 = 1200;
 const MEDIUM_IMG_MAX_HEIGHT: u32 = 800;
 const MEDIUM_IMG_MAX_QUALITY: f32 = 90.0;
 
+// Redacted areas are shrunk by this factor before being scaled back
+const REDACTION_BLUR_FACTOR: u32 = 16;
+
 #[allow(clippy::cast_possible_wrap)]
 pub(crate) async fn upload_stop_picture(
     user_id: i32,
+    view_sensitive: bool,
     filename: String,
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     db_pool: &PgPool,
     content: &Bytes,
     stops: &[i32],
-) -> Result<pics::StopPic, Error> {
+) -> Result<(pics::StopPic, Vec<i32>), Error> {
     let mut hasher = Sha1::new();
     hasher.update(content);
     let hash = hasher.finalize();
@@ -68,6 +76,24 @@ pub(crate) async fn upload_stop_picture(
     let (original_img, original_img_mime, exif) =
         validate_image(content, &filename)?;
 
+    let phash = perceptual_hash(&original_img);
+    let max_distance = SETTINGS.get().unwrap().images.duplicate_distance;
+    // Only those the uploader can see, lest this reveal hidden pictures
+    let similar_pics = sql::fetch_similar_stop_pics(
+        db_pool,
+        phash,
+        max_distance,
+        view_sensitive,
+        user_id,
+    )
+    .await?;
+    if !similar_pics.is_empty() {
+        tracing::warn!(
+            ?similar_pics,
+            "Stop pic resembling existing ones uploaded"
+        );
+    }
+
     let mut stop_pic_entry = pics::StopPic {
         id: 0,
         original_filename: filename,
@@ -101,7 +127,7 @@ pub(crate) async fn upload_stop_picture(
     }
 
     upload_stop_pic_to_storage(
-        bucket,
+        storage,
         content,
         &original_img,
         original_img_mime,
@@ -116,40 +142,95 @@ pub(crate) async fn upload_stop_picture(
 
     // TODO Delete if insertion fails
     let pic =
-        sql::insert_stop_pic(&mut transaction, stop_pic_entry, stops).await?;
-
-    contrib::sql::insert_changeset_log(
-        &mut transaction,
-        user_id,
-        &[history::Change::StopPicUpload {
-            pic: pic.clone().into(),
-            stops: stops
-                .iter()
-                .map(|stop_id| {
-                    pics::StopAttrs {
-                        id: *stop_id,
-                        attrs: vec![],
-                    }
-                    .into()
-                })
-                .collect::<Vec<history::pics::StopAttrs>>(),
-        }],
-        None,
-    )
-    .await?;
+        sql::insert_stop_pic(&mut transaction, stop_pic_entry, phash, stops)
+            .await?;
 
     transaction.commit().await.map_err(|err| {
         tracing::error!("Transaction failed to commit: {err}");
         Error::DatabaseExecution
     })?;
 
-    Ok(pic)
+    Ok((pic, similar_pics))
+}
+
+/// Difference hash (dHash) of an image.
+/// Each bit tells whether a pixel of a 9x8 grayscale miniature is brighter
+/// than its right neighbour, which survives re-encodes, resizes and
+/// light crops.
+#[allow(clippy::cast_possible_wrap)]
+pub(crate) fn perceptual_hash(img: &image::DynamicImage) -> i64 {
+    let miniature = img
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .into_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if miniature.get_pixel(x, y)[0] > miniature.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash as i64
+}
+
+/// Groups near-duplicate hashes, by index, with `max_distance` being
+/// the largest Hamming distance between two duplicates.
+/// Hashes are split into `max_distance + 1` chunks, so any two duplicates
+/// have at least one identical chunk and only those pairs get compared.
+pub(crate) fn duplicate_clusters(
+    hashes: &[i64],
+    max_distance: u32,
+) -> Vec<Vec<usize>> {
+    fn find(parents: &mut [usize], mut idx: usize) -> usize {
+        while parents[idx] != idx {
+            parents[idx] = parents[parents[idx]];
+            idx = parents[idx];
+        }
+        idx
+    }
+
+    let chunk_count = (max_distance + 1).min(64);
+    let mut buckets: HashMap<(u32, u64), Vec<usize>> = HashMap::new();
+    for (idx, hash) in hashes.iter().enumerate() {
+        let hash = u64::from_ne_bytes(hash.to_ne_bytes());
+        for chunk in 0..chunk_count {
+            let start = chunk * 64 / chunk_count;
+            let width = (chunk + 1) * 64 / chunk_count - start;
+            let bits = (hash >> start) & (u64::MAX >> (64 - width));
+            buckets.entry((chunk, bits)).or_default().push(idx);
+        }
+    }
+
+    let mut parents = (0..hashes.len()).collect::<Vec<_>>();
+    for bucket in buckets.values() {
+        for (i, &a) in bucket.iter().enumerate() {
+            for &b in &bucket[i + 1..] {
+                if (hashes[a] ^ hashes[b]).count_ones() <= max_distance {
+                    let (root_a, root_b) =
+                        (find(&mut parents, a), find(&mut parents, b));
+                    parents[root_a.max(root_b)] = root_a.min(root_b);
+                }
+            }
+        }
+    }
+
+    let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for idx in 0..hashes.len() {
+        let root = find(&mut parents, idx);
+        clusters.entry(root).or_default().push(idx);
+    }
+    clusters
+        .into_values()
+        .filter(|cluster| cluster.len() > 1)
+        .collect()
 }
 
 pub(crate) async fn delete_picture(
     pic: pics::StopPic,
     author_id: i32,
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     db_pool: &PgPool,
 ) -> Result<(), Error> {
     let mut transaction = db_pool.begin().await.map_err(|err| {
@@ -164,7 +245,7 @@ pub(crate) async fn delete_picture(
 
     if let Some(stop_pic) = stop_pic {
         let hex_hash = stop_pic.sha1;
-        delete_picture_from_storage(&hex_hash, bucket).await?;
+        delete_picture_from_storage(&hex_hash, storage).await?;
     } else {
         return Err(Error::NotFoundUpstream);
     }
@@ -188,8 +269,212 @@ pub(crate) async fn delete_picture(
     })
 }
 
+/// Drops a picture that never got published, such as a declined contribution.
+/// The objects are only deleted once the transaction has been committed.
+pub(crate) async fn discard_stop_picture(
+    mut transaction: sqlx::Transaction<'_, sqlx::Postgres>,
+    pic_id: i32,
+    hex_hash: &str,
+    storage: &dyn ObjectStorage,
+) -> Result<(), Error> {
+    sql::delete_stop_pic(&mut transaction, pic_id).await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    delete_picture_from_storage(hex_hash, storage).await
+}
+
+/// The perceptual hash of a stored picture, as it was uploaded
+pub(crate) async fn hash_stored_stop_picture(
+    storage: &dyn ObjectStorage,
+    hex_hash: &str,
+    filename: &str,
+) -> Result<i64, Error> {
+    let content = fetch_unredacted_stop_picture(storage, hex_hash).await?;
+    let (img, _, _) = validate_image(&content, filename)?;
+    Ok(perceptual_hash(&img))
+}
+
+/// Blurs areas out of the published versions of a stop picture.
+/// The untouched original is set aside while there are redactions,
+/// and put back in place once there are none.
+pub(crate) async fn redact_stop_picture(
+    storage: &dyn ObjectStorage,
+    pic: &pics::StopPic,
+    redactions: &[Redaction],
+) -> Result<(), Error> {
+    let hex_hash = &pic.sha1;
+    let (content, set_aside) =
+        fetch_original_stop_picture(storage, hex_hash).await?;
+    let (original_img, original_img_mime, _) =
+        validate_image(&content, &pic.original_filename)?;
+
+    if redactions.is_empty() {
+        if set_aside {
+            upload_stop_pic_to_storage(
+                storage,
+                &content,
+                &original_img,
+                original_img_mime,
+                hex_hash,
+            )
+            .await?;
+        }
+        return Ok(());
+    }
+
+    // Once set aside, the original is never overwritten,
+    // as the published one might have been redacted already
+    if !set_aside {
+        storage
+            .put(&unredacted_key(hex_hash), content.as_ref(), None)
+            .await?;
+    }
+
+    let format = original_img_mime
+        .as_ref()
+        .and_then(image::ImageFormat::from_mime_type)
+        .ok_or_else(|| {
+            tracing::error!(?original_img_mime, hex_hash, "Unknown format");
+            Error::Processing
+        })?;
+    let redacted_img = redact_image(&original_img, redactions);
+    let mut redacted_content = vec![];
+    redacted_img
+        .write_to(&mut Cursor::new(&mut redacted_content), format)
+        .map_err(|err| {
+            tracing::error!(error = err.to_string(), hex_hash);
+            Error::Processing
+        })?;
+
+    upload_stop_pic_to_storage(
+        storage,
+        &Bytes::from(redacted_content),
+        &redacted_img,
+        original_img_mime,
+        hex_hash,
+    )
+    .await
+}
+
+/// The picture as uploaded, regardless of it having been redacted
+pub(crate) async fn fetch_unredacted_stop_picture(
+    storage: &dyn ObjectStorage,
+    hex_hash: &str,
+) -> Result<Bytes, Error> {
+    Ok(fetch_original_stop_picture(storage, hex_hash).await?.0)
+}
+
+/// Drops the original of a picture that is no longer redacted
+pub(crate) async fn discard_unredacted_stop_picture(
+    storage: &dyn ObjectStorage,
+    hex_hash: &str,
+) -> Result<(), Error> {
+    storage.delete(&unredacted_key(hex_hash)).await
+}
+
+/// Evicts the published versions of a stop picture from the CDN,
+/// which would otherwise keep serving them until they expire
+pub(crate) async fn purge_cached_stop_picture(
+    pic: &pics::StopPic,
+) -> Result<(), Error> {
+    let Some(cdn) = &SETTINGS.get().unwrap().images.cdn_purge else {
+        tracing::warn!(pic.id, "No CDN to purge, cached copies might linger");
+        return Ok(());
+    };
+
+    let urls = [
+        super::get_stop_pic_ori_path(&pic.sha1),
+        super::get_stop_pic_ori_named_path(&pic.sha1, &pic.original_filename),
+        super::get_stop_pic_medium_path(&pic.sha1),
+        super::get_stop_pic_thumb_path(&pic.sha1),
+    ];
+
+    reqwest::Client::new()
+        .post(format!(
+            "https://api.cloudflare.com/client/v4/zones/{}/purge_cache",
+            cdn.zone_id
+        ))
+        .bearer_auth(&cdn.api_token)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::json!({ "files": urls }).to_string())
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|err| {
+            tracing::error!(pic.id, "CDN purge failed: {err}");
+            Error::ObjectStorageFailure
+        })?;
+
+    Ok(())
+}
+
+// Unredacted originals live under a prefix that is never made public
+fn unredacted_key(hex_hash: &str) -> String {
+    format!("/private/unredacted/{hex_hash}")
+}
+
+// The original along with whether it was set aside by a redaction
+async fn fetch_original_stop_picture(
+    storage: &dyn ObjectStorage,
+    hex_hash: &str,
+) -> Result<(Bytes, bool), Error> {
+    match storage.get(&unredacted_key(hex_hash)).await {
+        Ok(content) => Ok((content, true)),
+        Err(Error::NotFoundUpstream) => {
+            Ok((storage.get(&format!("/ori/{hex_hash}")).await?, false))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Blurs the redacted areas of a picture beyond recognition
+/// by shrinking them down and scaling them back up
+fn redact_image(
+    img: &image::DynamicImage,
+    redactions: &[Redaction],
+) -> image::DynamicImage {
+    let mut buffer = img.to_rgb8();
+
+    for redaction in redactions {
+        let (x, y, width, height) =
+            redaction.bounds(buffer.width(), buffer.height());
+        if width == 0 || height == 0 {
+            continue;
+        }
+
+        let area =
+            image::imageops::crop_imm(&buffer, x, y, width, height).to_image();
+        let shrunk = image::imageops::resize(
+            &area,
+            (width / REDACTION_BLUR_FACTOR).max(1),
+            (height / REDACTION_BLUR_FACTOR).max(1),
+            image::imageops::FilterType::Triangle,
+        );
+        let blurred = image::imageops::resize(
+            &shrunk,
+            width,
+            height,
+            image::imageops::FilterType::Triangle,
+        );
+
+        for (dx, dy, pixel) in blurred.enumerate_pixels() {
+            let (px, py) = (x + dx, y + dy);
+            // Sampled at the pixel centre
+            if redaction.contains(f64::from(px) + 0.5, f64::from(py) + 0.5) {
+                buffer.put_pixel(px, py, *pixel);
+            }
+        }
+    }
+
+    image::DynamicImage::ImageRgb8(buffer)
+}
+
 async fn upload_stop_pic_to_storage(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     content: &Bytes,
     original_img: &image::DynamicImage,
     original_img_mime: Option<mime::Mime>,
@@ -197,81 +482,41 @@ async fn upload_stop_pic_to_storage(
 ) -> Result<(), Error> {
     let (medium_webp, thumb_webp) = derive_scaled_webps(original_img)?;
 
-    // TODO handle status codes
-    let _status_code = bucket
-        .put_object_with_content_type(
-            format!("/medium/{hex_hash}"),
+    storage
+        .put(
+            &format!("/medium/{hex_hash}"),
             &medium_webp,
-            "image/webp",
+            Some("image/webp"),
         )
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+        .await?;
 
-    let _status_code = bucket
-        .put_object_with_content_type(
-            format!("/thumb/{hex_hash}"),
+    storage
+        .put(
+            &format!("/thumb/{hex_hash}"),
             &thumb_webp,
-            "image/webp",
+            Some("image/webp"),
         )
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+        .await?;
 
-    let _status_code = if let Some(mime) = original_img_mime {
-        bucket
-            .put_object_with_content_type(
-                format!("/ori/{hex_hash}"),
-                content.as_ref(),
-                mime.as_ref(),
-            )
-            .await
-            .map_err(|err| {
-                tracing::error!("Object storage failure: {err}");
-                Error::ObjectStorageFailure
-            })?
-    } else {
-        bucket
-            .put_object(format!("/ori/{hex_hash}"), content.as_ref())
-            .await
-            .map_err(|err| {
-                tracing::error!("Object storage failure: {err}");
-                Error::ObjectStorageFailure
-            })?
-    };
+    storage
+        .put(
+            &format!("/ori/{hex_hash}"),
+            content.as_ref(),
+            original_img_mime.as_ref().map(AsRef::as_ref),
+        )
+        .await?;
 
     Ok(())
 }
 
 async fn delete_picture_from_storage(
     hex_hash: &str,
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
 ) -> Result<(), Error> {
-    bucket
-        .delete_object(format!("/thumb/{hex_hash}"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
-    bucket
-        .delete_object(format!("/medium/{hex_hash}"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
-    bucket
-        .delete_object(format!("/ori/{hex_hash}"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+    storage.delete(&format!("/thumb/{hex_hash}")).await?;
+    storage.delete(&format!("/medium/{hex_hash}")).await?;
+    storage.delete(&format!("/ori/{hex_hash}")).await?;
+    storage.delete(&unredacted_key(hex_hash)).await?;
 
     Ok(())
 }
@@ -279,7 +524,7 @@ async fn delete_picture_from_storage(
 pub(crate) async fn upload_pano_picture(
     user_id: i32,
     name: String,
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     db_pool: &PgPool,
     content: &Bytes,
 ) -> Result<pics::PanoPic, Error> {
@@ -331,7 +576,7 @@ pub(crate) async fn upload_pano_picture(
         sensitive: true,
     };
 
-    upload_pano_to_storage(bucket, content, &hex_hash).await?;
+    upload_pano_to_storage(storage, content, &hex_hash).await?;
 
     let mut transaction = db_pool.begin().await.map_err(|err| {
         tracing::error!("Failed to open transaction: {err}");
@@ -363,28 +608,24 @@ pub(crate) async fn upload_pano_picture(
 }
 
 async fn upload_pano_to_storage(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     content: &Bytes,
     hex_hash: &str,
 ) -> Result<(), Error> {
-    bucket
-        .put_object_with_content_type(
-            format!("/pano/{hex_hash}"),
+    storage
+        .put(
+            &format!("/pano/{hex_hash}"),
             content.as_ref(),
-            mime::IMAGE_JPEG.as_ref(),
+            Some(mime::IMAGE_JPEG.as_ref()),
         )
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+        .await?;
 
     Ok(())
 }
 
 pub(crate) async fn upload_operator_logo(
     operator_id: i32,
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     db_pool: &PgPool,
     filename: &str,
     content: &Bytes,
@@ -434,7 +675,7 @@ pub(crate) async fn upload_operator_logo(
     })?;
 
     upload_operator_pic_to_storage(
-        bucket,
+        storage,
         content,
         &hex_hash,
         operator_id,
@@ -442,7 +683,7 @@ pub(crate) async fn upload_operator_logo(
     )
     .await?;
     if let Some(existing_hash) = curr_hash {
-        delete_operator_pic_from_storage(bucket, &existing_hash, operator_id)
+        delete_operator_pic_from_storage(storage, &existing_hash, operator_id)
             .await?;
     }
 
@@ -455,7 +696,7 @@ pub(crate) async fn upload_operator_logo(
 
     if let Err(db_err) = db_res {
         let storage_res =
-            delete_operator_pic_from_storage(bucket, &hex_hash, operator_id)
+            delete_operator_pic_from_storage(storage, &hex_hash, operator_id)
                 .await;
         if let Err(storage_err) = storage_res {
             tracing::error!(
@@ -476,45 +717,37 @@ pub(crate) async fn upload_operator_logo(
 }
 
 async fn upload_operator_pic_to_storage(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     content: &Bytes,
     hex_hash: &str,
     operator_id: i32,
     mime: &str,
 ) -> Result<(), Error> {
-    bucket
-        .put_object_with_content_type(
-            format!("/operators/{operator_id}/{hex_hash}"),
+    storage
+        .put(
+            &format!("/operators/{operator_id}/{hex_hash}"),
             content.as_ref(),
-            mime,
+            Some(mime),
         )
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+        .await?;
 
     Ok(())
 }
 
 async fn delete_operator_pic_from_storage(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     hex_hash: &str,
     operator_id: i32,
 ) -> Result<(), Error> {
-    bucket
-        .delete_object(format!("/operators/{operator_id}/{hex_hash}"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+    storage
+        .delete(&format!("/operators/{operator_id}/{hex_hash}"))
+        .await?;
 
     Ok(())
 }
 
 pub(crate) async fn upload_rich_img(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     db_pool: &PgPool,
     filename: String,
     content: &Bytes,
@@ -543,7 +776,7 @@ pub(crate) async fn upload_rich_img(
 
     let id = Uuid::new_v4();
     upload_rich_img_to_storage(
-        bucket,
+        storage,
         content,
         &original_img,
         original_img_mime,
@@ -564,7 +797,7 @@ pub(crate) async fn upload_rich_img(
     .await;
 
     if let Err(db_err) = db_res {
-        let storage_res = delete_rich_img_from_storage(bucket, id).await;
+        let storage_res = delete_rich_img_from_storage(storage, id).await;
         if let Err(storage_err) = storage_res {
             tracing::error!(
                 "Reversion failure.\
@@ -595,7 +828,7 @@ pub(crate) async fn upload_rich_img(
 }
 
 async fn upload_rich_img_to_storage(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     content: &Bytes,
     original_img: &image::DynamicImage,
     original_img_mime: Option<mime::Mime>,
@@ -603,87 +836,46 @@ async fn upload_rich_img_to_storage(
 ) -> Result<(), Error> {
     let (medium_webp, thumb_webp) = derive_scaled_webps(original_img)?;
 
-    // TODO handle status codes
-    let _status_code = bucket
-        .put_object_with_content_type(
-            format!("/content/{img_id}/medium"),
+    storage
+        .put(
+            &format!("/content/{img_id}/medium"),
             &medium_webp,
-            "image/webp",
+            Some("image/webp"),
         )
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+        .await?;
 
-    let _status_code = bucket
-        .put_object_with_content_type(
-            format!("/content/{img_id}/thumb"),
+    storage
+        .put(
+            &format!("/content/{img_id}/thumb"),
             &thumb_webp,
-            "image/webp",
+            Some("image/webp"),
         )
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+        .await?;
 
-    let _status_code = if let Some(mime) = original_img_mime {
-        bucket
-            .put_object_with_content_type(
-                format!("/content/{img_id}/ori"),
-                content.as_ref(),
-                mime.as_ref(),
-            )
-            .await
-            .map_err(|err| {
-                tracing::error!("Object storage failure: {err}");
-                Error::ObjectStorageFailure
-            })?
-    } else {
-        bucket
-            .put_object(format!("/content/{img_id}/ori"), content.as_ref())
-            .await
-            .map_err(|err| {
-                tracing::error!("Object storage failure: {err}");
-                Error::ObjectStorageFailure
-            })?
-    };
+    storage
+        .put(
+            &format!("/content/{img_id}/ori"),
+            content.as_ref(),
+            original_img_mime.as_ref().map(AsRef::as_ref),
+        )
+        .await?;
 
     Ok(())
 }
 
 async fn delete_rich_img_from_storage(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     img_id: Uuid,
 ) -> Result<(), Error> {
-    bucket
-        .delete_object(format!("/content/{img_id}/ori"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
-    bucket
-        .delete_object(format!("/content/{img_id}/medium"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
-    bucket
-        .delete_object(format!("/content/{img_id}/thumb"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+    storage.delete(&format!("/content/{img_id}/ori")).await?;
+    storage.delete(&format!("/content/{img_id}/medium")).await?;
+    storage.delete(&format!("/content/{img_id}/thumb")).await?;
 
     Ok(())
 }
 
 pub(crate) async fn import_external_news_img(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     db_pool: &PgPool,
     external_img_id: i32,
 ) -> Result<pics::RichImg, Error> {
@@ -704,24 +896,11 @@ pub(crate) async fn import_external_news_img(
         return Ok(img);
     }
 
-    let img_obj = bucket
-        .get_object(format!("/enews/{hex_hash}"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+    let key = format!("/enews/{hex_hash}");
+    let img_obj = storage.get(&key).await?;
+    let content_type = storage.content_type(&key).await?;
 
-    let (head_resp, _) = bucket
-        .head_object(format!("/enews/{hex_hash}"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
-
-    let mime = head_resp
-        .content_type
+    let mime = content_type
         .map(|ct| mime::Mime::from_str(&ct))
         .transpose()
         .map_err(|err| {
@@ -729,14 +908,13 @@ pub(crate) async fn import_external_news_img(
             Error::ObjectStorageFailure
         })?;
 
-    let img = image::load_from_memory(img_obj.as_slice()).map_err(|err| {
+    let img = image::load_from_memory(&img_obj).map_err(|err| {
         tracing::error!(error = err.to_string(), external_img_id);
         Error::ValidationFailure("Unsupported image".to_string())
     })?;
 
     let img_id = Uuid::new_v4();
-    upload_rich_img_to_storage(bucket, img_obj.bytes(), &img, mime, img_id)
-        .await?;
+    upload_rich_img_to_storage(storage, &img_obj, &img, mime, img_id).await?;
 
     let db_res = sql::insert_rich_img(
         &mut transaction,
@@ -749,7 +927,7 @@ pub(crate) async fn import_external_news_img(
     .await;
 
     if let Err(db_err) = db_res {
-        let storage_res = delete_rich_img_from_storage(bucket, img_id).await;
+        let storage_res = delete_rich_img_from_storage(storage, img_id).await;
         if let Err(storage_err) = storage_res {
             tracing::error!(
                 "Reversion failure. {img_id} was stored into news_imgs.\
@@ -780,7 +958,7 @@ pub(crate) async fn import_external_news_img(
 
 pub(crate) async fn upload_external_news_item_img(
     item_id: i32,
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     db_pool: &PgPool,
     filename: &str,
     content: &Bytes,
@@ -821,7 +999,7 @@ pub(crate) async fn upload_external_news_item_img(
     })?;
 
     upload_external_news_img_to_storage(
-        bucket,
+        storage,
         content,
         &hex_hash,
         mime.as_ref(),
@@ -838,7 +1016,7 @@ pub(crate) async fn upload_external_news_item_img(
 
     if let Err(db_err) = db_res {
         let storage_res =
-            delete_external_news_img_from_storage(bucket, &hex_hash).await;
+            delete_external_news_img_from_storage(storage, &hex_hash).await;
         if let Err(storage_err) = storage_res {
             tracing::error!(
                 "Reversion failure.\
@@ -863,44 +1041,30 @@ pub(crate) async fn upload_external_news_item_img(
 }
 
 async fn upload_external_news_img_to_storage(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     content: &Bytes,
     hex_hash: &str,
     mime: &str,
 ) -> Result<(), Error> {
-    bucket
-        .put_object_with_content_type(
-            format!("/enews/{hex_hash}"),
-            content.as_ref(),
-            mime,
-        )
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+    storage
+        .put(&format!("/enews/{hex_hash}"), content.as_ref(), Some(mime))
+        .await?;
 
     Ok(())
 }
 
 async fn delete_external_news_img_from_storage(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     hex_hash: &str,
 ) -> Result<(), Error> {
-    bucket
-        .delete_object(format!("/enews/{hex_hash}"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+    storage.delete(&format!("/enews/{hex_hash}")).await?;
 
     Ok(())
 }
 
 pub(crate) async fn upload_external_news_item_screenshot(
     item_id: i32,
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     db_pool: &PgPool,
     filename: &str,
     content: &Bytes,
@@ -939,7 +1103,7 @@ pub(crate) async fn upload_external_news_item_screenshot(
     })?;
 
     upload_external_news_item_screenshot_to_storage(
-        bucket,
+        storage,
         content,
         &hex_hash,
         mime.as_ref(),
@@ -955,7 +1119,7 @@ pub(crate) async fn upload_external_news_item_screenshot(
 
     if let Err(db_err) = db_res {
         let storage_res = delete_external_news_item_screenshot_from_storage(
-            bucket, &hex_hash,
+            storage, &hex_hash,
         )
         .await;
         if let Err(storage_err) = storage_res {
@@ -975,7 +1139,7 @@ pub(crate) async fn upload_external_news_item_screenshot(
 
     // Delete the old one
     if let Some(old_sha1) = current_hash {
-        delete_external_news_item_screenshot_from_storage(bucket, &old_sha1)
+        delete_external_news_item_screenshot_from_storage(storage, &old_sha1)
             .await?;
     }
 
@@ -983,37 +1147,27 @@ pub(crate) async fn upload_external_news_item_screenshot(
 }
 
 async fn upload_external_news_item_screenshot_to_storage(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     content: &Bytes,
     hex_hash: &str,
     mime: &str,
 ) -> Result<(), Error> {
-    bucket
-        .put_object_with_content_type(
-            format!("/enews_ss/{hex_hash}"),
+    storage
+        .put(
+            &format!("/enews_ss/{hex_hash}"),
             content.as_ref(),
-            mime,
+            Some(mime),
         )
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+        .await?;
 
     Ok(())
 }
 
 async fn delete_external_news_item_screenshot_from_storage(
-    bucket: &s3::Bucket,
+    storage: &dyn ObjectStorage,
     hex_hash: &str,
 ) -> Result<(), Error> {
-    bucket
-        .delete_object(format!("/enews_ss/{hex_hash}"))
-        .await
-        .map_err(|err| {
-            tracing::error!("Object storage failure: {err}");
-            Error::ObjectStorageFailure
-        })?;
+    storage.delete(&format!("/enews_ss/{hex_hash}")).await?;
 
     Ok(())
 }
@@ -1133,3 +1287,179 @@ fn derive_scaled_webps(
 
     Ok((medium_img_webp.to_vec(), thumbnail_img_webp.to_vec()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStorage;
+
+    // A bright left side and a dark right side
+    fn split_picture(width: u32, height: u32) -> image::DynamicImage {
+        image::DynamicImage::ImageLuma8(image::GrayImage::from_fn(
+            width,
+            height,
+            |x, _| image::Luma([if x < width * 2 / 5 { 200 } else { 50 }]),
+        ))
+    }
+
+    #[test]
+    fn resized_pictures_hash_alike() {
+        let original = split_picture(640, 480);
+        let resized =
+            original.resize(320, 240, image::imageops::FilterType::Lanczos3);
+        let different = original.fliph();
+
+        let hash = perceptual_hash(&original);
+        assert!((hash ^ perceptual_hash(&resized)).count_ones() <= 6);
+        assert!((hash ^ perceptual_hash(&different)).count_ones() > 6);
+    }
+
+    #[test]
+    fn redactions_only_blur_their_area() {
+        let original = split_picture(640, 480);
+        let redacted = redact_image(
+            &original,
+            &[
+                Redaction::Rect {
+                    x: 224,
+                    y: 0,
+                    width: 64,
+                    height: 64,
+                },
+                Redaction::Polygon {
+                    points: vec![(0.0, 200.0), (400.0, 200.0), (0.0, 400.0)],
+                },
+            ],
+        )
+        .into_rgb8();
+
+        // Across the bright and dark edge
+        assert!(redacted.get_pixel(250, 10)[0] < 200);
+        assert!(redacted.get_pixel(255, 210)[0] < 200);
+        // Within the bounds of the polygon, but not the polygon itself
+        assert_eq!(redacted.get_pixel(390, 390)[0], 50);
+        assert_eq!(redacted.get_pixel(250, 100)[0], 200);
+    }
+
+    #[test]
+    fn clusters_of_duplicates() {
+        let hashes = [0b0000, 0b1111 << 40, 0b0001, 0b0011, 0b1110 << 40, -1];
+        assert_eq!(
+            duplicate_clusters(&hashes, 1),
+            vec![vec![0, 2, 3], vec![1, 4]]
+        );
+        assert_eq!(duplicate_clusters(&hashes, 0), Vec::<Vec<usize>>::new());
+        assert_eq!(duplicate_clusters(&[7, 7], 0), vec![vec![0, 1]]);
+    }
+
+    #[sqlx::test(fixtures(path = "../auth/fixtures", scripts("users")))]
+    async fn upload_to_local_storage(pool: PgPool) {
+        crate::settings::load();
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(dir.path());
+
+        let mut content = vec![];
+        split_picture(64, 48)
+            .write_to(&mut Cursor::new(&mut content), image::ImageFormat::Png)
+            .unwrap();
+        let content = Bytes::from(content);
+
+        let (pic, similar_pics) = upload_stop_picture(
+            2,
+            false,
+            "stop.png".to_string(),
+            &storage,
+            &pool,
+            &content,
+            &[],
+        )
+        .await
+        .unwrap();
+        assert!(similar_pics.is_empty());
+        assert_eq!((pic.width, pic.height), (64, 48));
+        assert!(!pic.dyn_meta.public);
+
+        let ori_key = format!("/ori/{}", pic.sha1);
+        assert_eq!(storage.get(&ori_key).await.unwrap(), content);
+        assert_eq!(
+            storage.content_type(&ori_key).await.unwrap(),
+            Some("image/png".to_string())
+        );
+        for key in [
+            format!("/medium/{}", pic.sha1),
+            format!("/thumb/{}", pic.sha1),
+        ] {
+            assert_eq!(
+                storage.content_type(&key).await.unwrap(),
+                Some("image/webp".to_string())
+            );
+        }
+
+        let duplicate = upload_stop_picture(
+            2,
+            false,
+            "again.png".to_string(),
+            &storage,
+            &pool,
+            &content,
+            &[],
+        )
+        .await;
+        assert!(matches!(duplicate, Err(Error::DuplicatedResource(_))));
+    }
+
+    // Uploads a near-identical picture, told apart by a darkened corner
+    async fn upload_variant(
+        storage: &dyn ObjectStorage,
+        pool: &PgPool,
+        user_id: i32,
+        view_sensitive: bool,
+        shade: u8,
+    ) -> (pics::StopPic, Vec<i32>) {
+        let mut img = split_picture(64, 48).into_luma8();
+        img.put_pixel(0, 0, image::Luma([shade]));
+        let mut content = vec![];
+        image::DynamicImage::ImageLuma8(img)
+            .write_to(&mut Cursor::new(&mut content), image::ImageFormat::Png)
+            .unwrap();
+
+        upload_stop_picture(
+            user_id,
+            view_sensitive,
+            format!("{shade}.png"),
+            storage,
+            pool,
+            &Bytes::from(content),
+            &[],
+        )
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test(fixtures(path = "../auth/fixtures", scripts("users")))]
+    async fn similar_pics_visibility(pool: PgPool) {
+        crate::settings::load();
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(dir.path());
+
+        let (private_pic, _) =
+            upload_variant(&storage, &pool, 2, false, 190).await;
+        // Others' private pictures stay hidden
+        let (others_pic, similar_pics) =
+            upload_variant(&storage, &pool, 1, false, 180).await;
+        assert!(similar_pics.is_empty());
+        // Whereas one's own show up
+        let (own_pic, similar_pics) =
+            upload_variant(&storage, &pool, 2, false, 170).await;
+        assert_eq!(similar_pics, vec![private_pic.id]);
+        // As do all of them for those who can see sensitive pictures
+        let (_, similar_pics) =
+            upload_variant(&storage, &pool, 1, true, 160).await;
+        assert_eq!(
+            similar_pics,
+            vec![private_pic.id, others_pic.id, own_pic.id]
+        );
+    }
+}
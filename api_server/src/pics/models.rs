@@ -16,9 +16,11 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::geo::logic::ring_contains;
+
 #[derive(Deserialize, Debug)]
 pub struct SimpleRichImg {
     pub id: Uuid,
@@ -32,12 +34,92 @@ pub struct ExternalNewsImg {
     pub has_copyright_issues: Option<bool>,
     pub transcript: Option<String>,
 }
+
+/// An area to blur out of a stop picture,
+/// in pixels of the picture once upright
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Redaction {
+    Rect {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    Polygon {
+        points: Vec<(f64, f64)>,
+    },
+}
+
+impl Redaction {
+    /// The (x, y, width, height) box around the area,
+    /// clipped to a `width` by `height` picture
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub(crate) fn bounds(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> (u32, u32, u32, u32) {
+        let (left, top, right, bottom) = match self {
+            Redaction::Rect {
+                x,
+                y,
+                width: w,
+                height: h,
+            } => (*x, *y, x.saturating_add(*w), y.saturating_add(*h)),
+            Redaction::Polygon { points } => {
+                let (mut left, mut top) = (f64::INFINITY, f64::INFINITY);
+                let (mut right, mut bottom) = (0.0f64, 0.0f64);
+                for &(x, y) in points {
+                    left = left.min(x);
+                    top = top.min(y);
+                    right = right.max(x);
+                    bottom = bottom.max(y);
+                }
+                // Float to int casts saturate
+                (
+                    left.floor() as u32,
+                    top.floor() as u32,
+                    right.ceil() as u32,
+                    bottom.ceil() as u32,
+                )
+            }
+        };
+        let (left, top) = (left.min(width), top.min(height));
+        (
+            left,
+            top,
+            right.min(width).saturating_sub(left),
+            bottom.min(height).saturating_sub(top),
+        )
+    }
+
+    pub(crate) fn contains(&self, x: f64, y: f64) -> bool {
+        match self {
+            Redaction::Rect {
+                x: left,
+                y: top,
+                width,
+                height,
+            } => {
+                x >= f64::from(*left)
+                    && y >= f64::from(*top)
+                    && x < f64::from(*left) + f64::from(*width)
+                    && y < f64::from(*top) + f64::from(*height)
+            }
+            Redaction::Polygon { points } => ring_contains(points, x, y),
+        }
+    }
+}
+
 pub(crate) mod requests {
     use serde::Deserialize;
 
     use commons::models::{history::pics as history, pics};
 
+    use super::Redaction;
     use crate::utils::canonicalize_optional_string;
+    use crate::Error;
 
     #[derive(Debug, Deserialize)]
     pub struct ChangeStopPic {
@@ -87,6 +169,62 @@ pub(crate) mod requests {
         }
     }
 
+    #[derive(Debug, Deserialize)]
+    pub struct ChangeStopPicRedactions {
+        pub redactions: Vec<Redaction>,
+    }
+
+    impl ChangeStopPicRedactions {
+        pub(crate) fn validate(
+            &self,
+            pic: &pics::StopPic,
+        ) -> Result<(), Error> {
+            let (width, height) = (f64::from(pic.width), f64::from(pic.height));
+
+            for redaction in &self.redactions {
+                match redaction {
+                    Redaction::Rect {
+                        x,
+                        y,
+                        width: w,
+                        height: h,
+                    } => {
+                        if *w == 0 || *h == 0 {
+                            return Err(Error::ValidationFailure(
+                                "Empty redaction area".to_string(),
+                            ));
+                        }
+                        if f64::from(*x) + f64::from(*w) > width
+                            || f64::from(*y) + f64::from(*h) > height
+                        {
+                            return Err(Error::ValidationFailure(
+                                "Redaction outside of the picture".to_string(),
+                            ));
+                        }
+                    }
+                    Redaction::Polygon { points } => {
+                        if points.len() < 3 {
+                            return Err(Error::ValidationFailure(
+                                "Redaction polygons need three points"
+                                    .to_string(),
+                            ));
+                        }
+                        if !points.iter().all(|&(x, y)| {
+                            (0.0..=width).contains(&x)
+                                && (0.0..=height).contains(&y)
+                        }) {
+                            return Err(Error::ValidationFailure(
+                                "Redaction outside of the picture".to_string(),
+                            ));
+                        }
+                    }
+                }
+            }
+
+            Ok(())
+        }
+    }
+
     #[derive(Debug, Deserialize)]
     pub struct ChangeRichImgMeta {
         pub transcript: Option<String>,
@@ -207,6 +345,15 @@ pub(crate) mod responses {
         pub url_thumb: String,
     }
 
+    #[derive(Debug, Serialize)]
+    pub struct StopPicUpload<T> {
+        #[serde(flatten)]
+        pub upload: T,
+        // Pictures that look alike this one, likely duplicates
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub similar_pics: Vec<i32>,
+    }
+
     #[derive(Debug, Serialize)]
     pub struct PublicStopPic {
         pub id: i32,
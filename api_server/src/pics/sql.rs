@@ -25,7 +25,7 @@ use uuid::Uuid;
 
 use commons::models::pics;
 
-use super::models::{requests, responses};
+use super::models::{requests, responses, Redaction};
 use crate::pics::{
     get_stop_pic_medium_path, get_stop_pic_ori_path, get_stop_pic_thumb_path,
 };
@@ -134,6 +134,201 @@ WHERE sha1 = $1
     })
 }
 
+/// The areas blurred out of a picture
+pub(crate) async fn fetch_stop_pic_redactions<'c, E>(
+    executor: E,
+    pic_id: i32,
+) -> Result<Option<Vec<Redaction>>>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    Ok(sqlx::query!(
+        r#"
+SELECT redactions as "redactions!: sqlx::types::Json<Vec<Redaction>>"
+FROM stop_pics
+WHERE id = $1
+"#,
+        pic_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), pic_id);
+        Error::DatabaseExecution
+    })?
+    .map(|row| row.redactions.0))
+}
+
+// Locks the picture until the transaction ends
+pub(crate) async fn fetch_stop_pic_redactions_for_update(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    pic_id: i32,
+) -> Result<Option<Vec<Redaction>>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT redactions as "redactions!: sqlx::types::Json<Vec<Redaction>>"
+FROM stop_pics
+WHERE id = $1
+FOR UPDATE
+"#,
+        pic_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), pic_id);
+        Error::DatabaseExecution
+    })?
+    .map(|row| row.redactions.0))
+}
+
+pub(crate) async fn update_stop_pic_redactions(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    pic_id: i32,
+    redactions: &[Redaction],
+) -> Result<()> {
+    let res = sqlx::query!(
+        r#"
+UPDATE stop_pics
+SET redactions = $2
+WHERE id = $1
+"#,
+        pic_id,
+        sqlx::types::Json(redactions) as _
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), pic_id, ?redactions);
+        Error::DatabaseExecution
+    })?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::NotFoundUpstream);
+    }
+
+    Ok(())
+}
+
+/// Pictures within `max_distance` bits of a perceptual hash
+/// that are visible to the user
+pub(crate) async fn fetch_similar_stop_pics(
+    pool: &PgPool,
+    phash: i64,
+    max_distance: u32,
+    view_sensitive: bool,
+    uid: i32,
+) -> Result<Vec<i32>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id
+FROM stop_pics
+WHERE phash IS NOT NULL AND bit_count((phash # $1)::bit(64)) <= $2
+    AND (uploader = $3 OR (public AND NOT sensitive) OR $4)
+ORDER BY id
+"#,
+        phash,
+        i64::from(max_distance),
+        uid,
+        view_sensitive
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            error = err.to_string(),
+            phash,
+            max_distance,
+            uid,
+            view_sensitive
+        );
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| row.id)
+    .collect())
+}
+
+/// The ID, hash and filename of the pictures without a perceptual hash
+pub(crate) async fn fetch_unhashed_stop_pics(
+    pool: &PgPool,
+) -> Result<Vec<(i32, String, String)>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, sha1, original_filename
+FROM stop_pics
+WHERE phash IS NULL
+ORDER BY id
+"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| (row.id, row.sha1, row.original_filename))
+    .collect())
+}
+
+pub(crate) async fn update_stop_pic_phash(
+    pool: &PgPool,
+    pic_id: i32,
+    phash: i64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE stop_pics
+SET phash = $2
+WHERE id = $1
+"#,
+        pic_id,
+        phash
+    )
+    .execute(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), pic_id, phash);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+/// Every picture that has a perceptual hash, along with it
+pub(crate) async fn fetch_hashed_stop_pics(
+    pool: &PgPool,
+) -> Result<Vec<(responses::MinimalStopPic, i64)>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, sha1, phash as "phash!"
+FROM stop_pics
+WHERE phash IS NOT NULL
+ORDER BY id
+"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|r| {
+        (
+            responses::MinimalStopPic {
+                id: r.id,
+                url_full: get_stop_pic_ori_path(&r.sha1),
+                url_medium: get_stop_pic_medium_path(&r.sha1),
+                url_thumb: get_stop_pic_thumb_path(&r.sha1),
+            },
+            r.phash,
+        )
+    })
+    .collect())
+}
+
 /// A specific picture and its stops
 pub(crate) async fn fetch_picture_with_stops(
     pool: &PgPool,
@@ -680,15 +875,16 @@ ORDER BY stop ASC
 pub(crate) async fn insert_stop_pic(
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     mut pic: pics::StopPic,
+    phash: i64,
     stops: &[i32],
 ) -> Result<pics::StopPic> {
     let res = sqlx::query!(
         r#"
 INSERT INTO stop_pics(
     original_filename, sha1, public, sensitive, tagged, uploader,
-    upload_date, capture_date, width, height, lat, lon, camera_ref
+    upload_date, capture_date, width, height, lat, lon, camera_ref, phash
 )
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
 RETURNING id
         "#,
         pic.original_filename,
@@ -703,7 +899,8 @@ RETURNING id
         pic.height,
         pic.dyn_meta.lat,
         pic.dyn_meta.lon,
-        pic.camera_ref
+        pic.camera_ref,
+        phash
     )
     .fetch_one(&mut **transaction)
     .await
@@ -131,6 +131,18 @@ pub(crate) async fn patch_route(
         return Ok(Json(route));
     }
 
+    // The claim was only checked against the current operator
+    if patch.operator_id.is_some()
+        && !auth::holds_operator_permission::<auth::perms::ModifyRouteBase>(
+            &state.pool,
+            &claims.permissions,
+            changes.operator_id,
+        )
+        .await?
+    {
+        return Err(Error::Forbidden);
+    }
+
     let original = route.clone().into();
     let mut patched = route.clone();
     patch.clone().apply(&mut patched);
@@ -279,7 +291,7 @@ pub(crate) async fn delete_subroute(
         .ok_or(Error::NotFoundUpstream)?;
 
     let stops =
-        sql::fetch_subroute_stops(&mut transaction, subroute_id).await?;
+        sql::fetch_subroute_stops(&mut *transaction, subroute_id).await?;
     let departures =
         sql::fetch_subroute_departures(&mut transaction, subroute_id).await?;
 
@@ -428,7 +440,9 @@ pub(crate) async fn get_subroute_stops(
 
 pub(crate) async fn patch_subroute_stops(
     State(state): State<AppState>,
-    auth::ScopedClaim(_, _): auth::ScopedClaim<auth::perms::ModifyRouteStops>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyRouteStops,
+    >,
     Path(subroute_id): Path<i32>,
     Json(request): Json<requests::ChangeSubrouteStops>,
 ) -> Result<(), Error> {
@@ -438,7 +452,7 @@ pub(crate) async fn patch_subroute_stops(
     })?;
 
     let sr_stops =
-        sql::fetch_subroute_stops(&mut transaction, subroute_id).await?;
+        sql::fetch_subroute_stops(&mut *transaction, subroute_id).await?;
 
     if sr_stops != request.from {
         return Err(Error::ValidationFailure("Check mismatch".to_string()));
@@ -447,7 +461,18 @@ pub(crate) async fn patch_subroute_stops(
     sql::update_subroute_stops(&mut transaction, subroute_id, &request.to)
         .await?;
 
-    // TODO log
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::SubrouteStopsUpdate {
+            subroute_id,
+            original: sr_stops,
+            stops: request.to,
+        }],
+        None,
+    )
+    .await?;
+
     transaction.commit().await.map_err(|err| {
         tracing::error!("Transaction failed to commit: {err}");
         Error::DatabaseExecution
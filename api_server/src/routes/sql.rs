@@ -27,10 +27,13 @@ use super::models::{requests, responses};
 
 type Result<T> = std::result::Result<T, Error>;
 
-pub(crate) async fn fetch_commons_route(
-    pool: &PgPool,
+pub(crate) async fn fetch_commons_route<'c, E>(
+    executor: E,
     route_id: i32,
-) -> Result<Option<routes::Route>> {
+) -> Result<Option<routes::Route>>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
     sqlx::query_as!(
         routes::Route,
         r#"
@@ -49,7 +52,7 @@ WHERE routes.id = $1
 "#,
         route_id
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await
     .map_err(|err| {
         tracing::error!(error = err.to_string(), route_id);
@@ -57,10 +60,13 @@ WHERE routes.id = $1
     })
 }
 
-pub(crate) async fn fetch_simple_subroute(
-    pool: &PgPool,
+pub(crate) async fn fetch_simple_subroute<'c, E>(
+    executor: E,
     subroute_id: i32,
-) -> Result<Option<routes::Subroute>> {
+) -> Result<Option<routes::Subroute>>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
     let res = sqlx::query!(
         r#"
 SELECT subroutes.id,
@@ -78,7 +84,7 @@ WHERE subroutes.id = $1
 "#,
         subroute_id
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await
     .map_err(|err| {
         tracing::error!(error = err.to_string(), subroute_id);
@@ -461,6 +467,38 @@ RETURNING id
     })
 }
 
+pub(crate) async fn restore_route(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    route: &routes::Route,
+) -> Result<()> {
+    let _res = sqlx::query!(
+        r#"
+INSERT INTO routes(
+    id, code, name, main_subroute, operator, circular, active, type,
+    badge_text_color, badge_bg_color)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    "#,
+        route.id,
+        route.code,
+        route.name,
+        route.main_subroute,
+        route.operator_id,
+        route.circular,
+        route.active,
+        route.type_id,
+        route.badge_text_color,
+        route.badge_bg_color
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), route = ?route);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
 pub(crate) async fn update_route(
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     route_id: i32,
@@ -597,6 +635,36 @@ RETURNING id
     })
 }
 
+pub(crate) async fn restore_subroute(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    subroute: &routes::Subroute,
+) -> Result<()> {
+    let _res = sqlx::query!(
+        r#"
+INSERT INTO subroutes(id, route, "group", flag, origin, destination, headsign, via, circular, polyline)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    "#,
+        subroute.id,
+        subroute.route_id,
+        subroute.group,
+        subroute.flag,
+        subroute.origin,
+        subroute.destination,
+        subroute.headsign,
+        sqlx::types::Json(&subroute.via) as _,
+        subroute.circular,
+        subroute.polyline
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error=err.to_string(), subroute=?subroute);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
 pub(crate) async fn update_subroute(
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     subroute_id: i32,
@@ -774,10 +842,13 @@ FROM unnest($2::int[]) WITH ORDINALITY AS t(stop_id, ordinality)
     Ok(())
 }
 
-pub(crate) async fn fetch_subroute_stops(
-    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+pub(crate) async fn fetch_subroute_stops<'c, E>(
+    executor: E,
     subroute_id: i32,
-) -> Result<Vec<i32>> {
+) -> Result<Vec<i32>>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
     Ok(sqlx::query!(
         r#"
 SELECT stop
@@ -787,7 +858,7 @@ ORDER BY idx ASC
 "#,
         subroute_id
     )
-    .fetch_all(&mut **transaction)
+    .fetch_all(executor)
     .await
     .map_err(|err| {
         tracing::error!(error = err.to_string(), subroute_id);
@@ -921,6 +992,30 @@ RETURNING id
     })
 }
 
+pub(crate) async fn restore_departure(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    departure: &routes::Departure,
+) -> Result<()> {
+    let _res = sqlx::query!(
+        r#"
+INSERT INTO departures(id, subroute, time, calendar_id)
+VALUES($1, $2, $3, $4)
+    "#,
+        departure.id,
+        departure.subroute_id,
+        departure.time,
+        departure.calendar_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), departure = ?departure);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
 pub(crate) async fn update_departure(
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     subroute_id: i32,
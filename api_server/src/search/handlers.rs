@@ -0,0 +1,83 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use axum::extract::{Query, State};
+use axum::Json;
+use std::collections::HashSet;
+
+use super::models::{requests, responses};
+use super::sql;
+use crate::{AppState, Error};
+
+pub(crate) async fn get_search(
+    State(state): State<AppState>,
+    Query(search): Query<requests::Search>,
+) -> Result<Json<Vec<responses::SearchHit>>, Error> {
+    search.validate()?;
+    let query = search.q.trim();
+    let limit = search.limit.unwrap_or(requests::DEFAULT_LIMIT);
+    let take = i64::from(limit);
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::set_similarity_threshold(&mut transaction).await?;
+    let mut hits =
+        sql::search_stops(&mut transaction, query, search.region, take).await?;
+    let cluster_hits =
+        sql::search_stop_clusters(&mut transaction, query, search.region, take)
+            .await?;
+    // Stops of a matched cluster are represented by it
+    let matched_clusters = cluster_hits
+        .iter()
+        .filter_map(|hit| match hit.entity {
+            responses::Entity::StopCluster { id, .. } => Some(id),
+            _ => None,
+        })
+        .collect::<HashSet<i32>>();
+    hits.retain(|hit| match hit.entity {
+        responses::Entity::Stop { cluster_id, .. } => {
+            !cluster_id.is_some_and(|id| matched_clusters.contains(&id))
+        }
+        _ => true,
+    });
+    hits.extend(cluster_hits);
+    hits.extend(
+        sql::search_routes(&mut transaction, query, search.region, take)
+            .await?,
+    );
+    hits.extend(
+        sql::search_operators(&mut transaction, query, search.region, take)
+            .await?,
+    );
+    hits.extend(
+        sql::search_news(&mut transaction, query, search.region, take).await?,
+    );
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit as usize);
+
+    Ok(Json(hits))
+}
@@ -0,0 +1,133 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+pub(crate) mod requests {
+    use serde::Deserialize;
+
+    use crate::Error;
+
+    pub(crate) const DEFAULT_LIMIT: u32 = 20;
+    const MAX_LIMIT: u32 = 100;
+
+    #[derive(Debug, Deserialize)]
+    pub struct Search {
+        pub q: String,
+        // Only matches entities within this region
+        #[serde(default)]
+        pub region: Option<i32>,
+        #[serde(default)]
+        pub limit: Option<u32>,
+    }
+
+    impl Search {
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            let len = self.q.trim().chars().count();
+            if len < 2 {
+                return Err(Error::ValidationFailure(
+                    "Search query too short".to_string(),
+                ));
+            }
+            if len > 100 {
+                return Err(Error::ValidationFailure(
+                    "Search query too long".to_string(),
+                ));
+            }
+            if self
+                .limit
+                .is_some_and(|limit| limit == 0 || limit > MAX_LIMIT)
+            {
+                return Err(Error::ValidationFailure(
+                    "Invalid result limit".to_string(),
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+pub(crate) mod responses {
+    use chrono::{DateTime, Utc};
+    use serde::Serialize;
+
+    #[derive(Debug, Serialize)]
+    pub struct SearchHit {
+        #[serde(flatten)]
+        pub entity: Entity,
+        // Between 0 and 1, higher is better
+        pub score: f32,
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename_all = "camelCase", tag = "type")]
+    pub enum Entity {
+        Stop {
+            id: i32,
+            name: String,
+            short_name: Option<String>,
+            locality: Option<String>,
+            street: Option<String>,
+            lat: f64,
+            lon: f64,
+            cluster_id: Option<i32>,
+        },
+        StopCluster {
+            id: i32,
+            local_name: Option<String>,
+            regional_name: Option<String>,
+            national_name: Option<String>,
+            is_terminal: bool,
+        },
+        Route {
+            id: i32,
+            code: Option<String>,
+            name: String,
+            operator_id: i32,
+        },
+        Operator {
+            id: i32,
+            name: String,
+            tag: String,
+        },
+        News {
+            id: i32,
+            title: String,
+            publish_datetime: DateTime<Utc>,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::requests::Search;
+
+    fn search(q: &str, limit: Option<u32>) -> Search {
+        Search {
+            q: q.to_string(),
+            region: None,
+            limit,
+        }
+    }
+
+    #[test]
+    fn validate_search() {
+        assert!(search("São", None).validate().is_ok());
+        assert!(search(" é ", None).validate().is_err());
+        assert!(search("Lisboa", Some(0)).validate().is_err());
+        assert!(search("Lisboa", Some(1000)).validate().is_err());
+    }
+}
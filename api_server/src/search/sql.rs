@@ -0,0 +1,277 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use super::models::responses;
+use crate::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+// The default (0.6) is too strict to forgive typos in short names
+const WORD_SIMILARITY_THRESHOLD: &str = "0.4";
+
+pub(crate) async fn set_similarity_threshold(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<()> {
+    sqlx::query!(
+        "SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)",
+        WORD_SIMILARITY_THRESHOLD
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })?;
+    Ok(())
+}
+
+pub(crate) async fn search_stops(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    query: &str,
+    region_id: Option<i32>,
+    take: i64,
+) -> Result<Vec<responses::SearchHit>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, name, short_name, locality, street, lat, lon, cluster,
+    GREATEST(
+        word_similarity(search_fold($1), search_fold(name)),
+        word_similarity(search_fold($1), search_fold(short_name)),
+        word_similarity(search_fold($1), search_fold(locality)) * 0.8,
+        word_similarity(search_fold($1), search_fold(street)) * 0.8
+    )::real as "score!"
+FROM stops
+WHERE NOT is_ghost
+    AND (search_fold($1) <% search_fold(name)
+        OR search_fold($1) <% search_fold(short_name)
+        OR search_fold($1) <% search_fold(locality)
+        OR search_fold($1) <% search_fold(street))
+    AND ($2::int IS NULL OR EXISTS (
+        SELECT 1 FROM region_stops
+        WHERE region_stops.stop_id = stops.id AND region_stops.region_id = $2))
+ORDER BY 9 DESC
+LIMIT $3
+"#,
+        query,
+        region_id,
+        take
+    )
+    .fetch_all(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), query, region_id);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| responses::SearchHit {
+        entity: responses::Entity::Stop {
+            id: row.id,
+            name: row.name,
+            short_name: row.short_name,
+            locality: row.locality,
+            street: row.street,
+            lat: row.lat,
+            lon: row.lon,
+            cluster_id: row.cluster,
+        },
+        score: row.score,
+    })
+    .collect())
+}
+
+pub(crate) async fn search_stop_clusters(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    query: &str,
+    region_id: Option<i32>,
+    take: i64,
+) -> Result<Vec<responses::SearchHit>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, local_name, regional_name, national_name, is_terminal,
+    GREATEST(
+        word_similarity(search_fold($1), search_fold(local_name)),
+        word_similarity(search_fold($1), search_fold(regional_name)),
+        word_similarity(search_fold($1), search_fold(national_name))
+    )::real as "score!"
+FROM stop_clusters
+WHERE (search_fold($1) <% search_fold(local_name)
+        OR search_fold($1) <% search_fold(regional_name)
+        OR search_fold($1) <% search_fold(national_name))
+    AND ($2::int IS NULL OR EXISTS (
+        SELECT 1 FROM stops
+        JOIN region_stops ON region_stops.stop_id = stops.id
+        WHERE stops.cluster = stop_clusters.id
+            AND region_stops.region_id = $2))
+ORDER BY 6 DESC
+LIMIT $3
+"#,
+        query,
+        region_id,
+        take
+    )
+    .fetch_all(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), query, region_id);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| responses::SearchHit {
+        entity: responses::Entity::StopCluster {
+            id: row.id,
+            local_name: row.local_name,
+            regional_name: row.regional_name,
+            national_name: row.national_name,
+            is_terminal: row.is_terminal,
+        },
+        score: row.score,
+    })
+    .collect())
+}
+
+pub(crate) async fn search_routes(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    query: &str,
+    region_id: Option<i32>,
+    take: i64,
+) -> Result<Vec<responses::SearchHit>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, code, name, operator as operator_id,
+    GREATEST(
+        word_similarity(search_fold($1), search_fold(code)),
+        word_similarity(search_fold($1), search_fold(name))
+    )::real as "score!"
+FROM routes
+WHERE (search_fold($1) <% search_fold(code)
+        OR search_fold($1) <% search_fold(name))
+    AND ($2::int IS NULL OR EXISTS (
+        SELECT 1 FROM region_routes
+        WHERE region_routes.route_id = routes.id AND region_routes.region_id = $2))
+ORDER BY 5 DESC, active DESC
+LIMIT $3
+"#,
+        query,
+        region_id,
+        take
+    )
+    .fetch_all(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), query, region_id);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| responses::SearchHit {
+        entity: responses::Entity::Route {
+            id: row.id,
+            code: row.code,
+            name: row.name,
+            operator_id: row.operator_id,
+        },
+        score: row.score,
+    })
+    .collect())
+}
+
+pub(crate) async fn search_operators(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    query: &str,
+    region_id: Option<i32>,
+    take: i64,
+) -> Result<Vec<responses::SearchHit>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, name, tag,
+    GREATEST(
+        word_similarity(search_fold($1), search_fold(name)),
+        word_similarity(search_fold($1), search_fold(tag))
+    )::real as "score!"
+FROM operators
+WHERE (search_fold($1) <% search_fold(name)
+        OR search_fold($1) <% search_fold(tag))
+    AND ($2::int IS NULL OR EXISTS (
+        SELECT 1 FROM region_operators
+        WHERE region_operators.operator_id = operators.id
+            AND region_operators.region_id = $2))
+ORDER BY 4 DESC
+LIMIT $3
+"#,
+        query,
+        region_id,
+        take
+    )
+    .fetch_all(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), query, region_id);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| responses::SearchHit {
+        entity: responses::Entity::Operator {
+            id: row.id,
+            name: row.name,
+            tag: row.tag,
+        },
+        score: row.score,
+    })
+    .collect())
+}
+
+pub(crate) async fn search_news(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    query: &str,
+    region_id: Option<i32>,
+    take: i64,
+) -> Result<Vec<responses::SearchHit>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, title, publish_datetime,
+    word_similarity(search_fold($1), search_fold(title))::real as "score!"
+FROM news_items
+WHERE is_visible
+    AND search_fold($1) <% search_fold(title)
+    AND ($2::int IS NULL OR EXISTS (
+        SELECT 1 FROM news_items_regions
+        WHERE news_items_regions.item_id = news_items.id
+            AND news_items_regions.region_id = $2))
+ORDER BY 4 DESC, publish_datetime DESC
+LIMIT $3
+"#,
+        query,
+        region_id,
+        take
+    )
+    .fetch_all(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), query, region_id);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| responses::SearchHit {
+        entity: responses::Entity::News {
+            id: row.id,
+            title: row.title,
+            publish_datetime: row.publish_datetime,
+        },
+        score: row.score,
+    })
+    .collect())
+}
@@ -27,10 +27,14 @@ pub(crate) struct Settings {
     #[serde(default)]
     pub(crate) storage: Storage,
     pub(crate) db: Database,
-    pub(crate) s3: S3Api,
+    #[serde(default)]
+    pub(crate) objects: ObjectStorage,
+    #[serde(default)]
+    pub(crate) s3: Option<S3Api>,
     pub(crate) jwt: Jwt,
     pub(crate) cookies: Cookies,
     pub(crate) images: Images,
+    pub(crate) mail: Mail,
 }
 
 fn default_data_root() -> String {
@@ -63,6 +67,19 @@ pub(crate) struct S3Api {
     pub(crate) secret_key: String,
     pub(crate) bucket_name: String,
 }
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub(crate) enum ObjectStorage {
+    // The bucket in the S3 settings
+    #[default]
+    S3,
+    // Files in a directory, which the server exposes under /v1/objects
+    Local {
+        dir: String,
+    },
+}
+
 #[derive(Deserialize, Debug)]
 pub(crate) struct Jwt {
     pub(crate) refresh_secret: String,
@@ -79,9 +96,67 @@ pub(crate) struct Cookies {
     pub(crate) secure: bool,
 }
 
+fn default_duplicate_distance() -> u32 {
+    6
+}
+
 #[derive(Deserialize, Debug)]
 pub(crate) struct Images {
     pub(crate) root: String,
+    // Stop pictures whose perceptual hashes differ in this many bits or less
+    // are considered near-duplicates
+    #[serde(default = "default_duplicate_distance")]
+    pub(crate) duplicate_distance: u32,
+    // The CDN in front of the root, which has to forget replaced pictures
+    #[serde(default)]
+    pub(crate) cdn_purge: Option<CdnPurge>,
+}
+
+// A Cloudflare zone, purged through its API
+#[derive(Deserialize, Debug)]
+pub(crate) struct CdnPurge {
+    pub(crate) zone_id: String,
+    pub(crate) api_token: String,
+}
+
+fn default_mail_sender() -> String {
+    "Intermodal <noreply@intermodal.pt>".to_string()
+}
+
+fn default_site_url() -> String {
+    "https://intermodal.pt".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct Mail {
+    #[serde(default = "default_mail_sender")]
+    pub(crate) sender: String,
+    // Prefix of the links that are mailed to users
+    #[serde(default = "default_site_url")]
+    pub(crate) site_url: String,
+    // Deliberately without a default, lest mail go undelivered unnoticed
+    pub(crate) transport: MailTransport,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub(crate) enum MailTransport {
+    Smtp {
+        host: String,
+        // Defaults to the port of the chosen TLS mode
+        #[serde(default)]
+        port: Option<u16>,
+        username: String,
+        password: String,
+        #[serde(default)]
+        starttls: bool,
+    },
+    // Writes every message into a directory
+    File {
+        dir: String,
+    },
+    // Only logs that messages would have been sent, for development
+    Log,
 }
 
 pub(crate) fn load() {
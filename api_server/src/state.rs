@@ -19,6 +19,7 @@
 use std::collections::HashMap;
 use std::io::Cursor;
 use std::ops::Deref;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 
@@ -30,6 +31,8 @@ use uuid::Uuid;
 
 use crate::errors::Error;
 use crate::gtfs;
+use crate::mail::Mailer;
+use crate::storage::{LocalStorage, ObjectStorage};
 
 const CAPTCHA_LIMIT: i64 = 5;
 const CAPTCHA_STORE_CLEANUP_TIME: i64 = 5;
@@ -47,42 +50,40 @@ impl Deref for AppState {
 }
 
 pub struct State {
-    pub bucket: s3::Bucket,
+    pub storage: Arc<dyn ObjectStorage>,
     pub pool: PgPool,
     pub cached: Cached,
     pub captchas: CaptchaStorage,
+    pub mailer: Mailer,
+    // Identifies this server among others sharing the database
+    pub instance: Uuid,
 }
 
 impl State {
-    pub fn new(bucket: s3::Bucket, pool: PgPool) -> Self {
+    pub fn new(
+        storage: Arc<dyn ObjectStorage>,
+        pool: PgPool,
+        mailer: Mailer,
+    ) -> Self {
         State {
-            bucket,
+            storage,
             pool,
             cached: Cached {
                 gtfs_stops: RwLock::new(HashMap::new()),
                 tml_routes: RwLock::new(HashMap::new()),
             },
             captchas: CaptchaStorage::new(),
+            mailer,
+            instance: Uuid::new_v4(),
         }
     }
 
-    // For integration tests
+    // For integration tests, which own (and clean up) the storage directory
     #[allow(unused)]
-    pub fn test_state(pool: PgPool) -> State {
-        let credentials =
-            s3::creds::Credentials::new(Some(""), Some(""), None, None, None)
-                .unwrap();
-        let bucket = s3::Bucket::new(
-            "",
-            s3::Region::R2 {
-                account_id: String::new(),
-            },
-            credentials,
-        )
-        .unwrap()
-        .with_path_style();
+    pub fn test_state(pool: PgPool, storage_dir: &Path) -> State {
+        let storage = Arc::new(LocalStorage::new(storage_dir));
 
-        State::new(bucket, pool)
+        State::new(storage, pool, Mailer::Log)
     }
 }
 
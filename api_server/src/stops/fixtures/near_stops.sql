@@ -0,0 +1,5 @@
+INSERT INTO stops (id, name, lat, lon, is_ghost, license)
+VALUES (1, 'Origin', 38.7, -9.1, false, 'CC0'),
+       (2, 'Nearby', 38.7009, -9.1, false, 'CC0'),
+       (3, 'Ghost', 38.70045, -9.1, true, 'CC0'),
+       (4, 'Far away', 38.8, -9.1, false, 'CC0');
@@ -16,15 +16,26 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use axum::extract::{Path, State};
+use std::collections::{hash_map, HashMap};
+
+use axum::extract::{Path, Query, State};
 use axum::Json;
+use chrono::{Local, NaiveDate};
+use futures::future;
+use serde::Deserialize;
 
+use commons::models::calendar::Calendar;
 use commons::models::{history, routes, stops};
 
 use super::models::{requests, responses};
-use super::sql;
+use super::{logic, sql};
 use crate::responses::IdReturn;
-use crate::{auth, contrib, AppState, Error};
+use crate::{auth, contrib, geo, AppState, Error};
+
+#[derive(Deserialize, Default)]
+pub(crate) struct DepartureBoardParams {
+    date: Option<NaiveDate>,
+}
 
 pub(crate) async fn get_region_stops(
     State(state): State<AppState>,
@@ -107,6 +118,15 @@ pub(crate) async fn post_stop(
     let stop = sql::insert_stop(&mut transaction, stop, claims.uid).await?;
     let id = stop.id;
 
+    geo::logic::assign_stop_divisions(
+        &state.pool,
+        &mut transaction,
+        id,
+        stop.lon,
+        stop.lat,
+    )
+    .await?;
+
     contrib::sql::insert_changeset_log(
         &mut transaction,
         claims.uid,
@@ -158,8 +178,18 @@ pub(crate) async fn patch_stop(
     )
     .await?;
 
+    let position = (changes.lon, changes.lat);
     sql::update_stop(&mut transaction, stop_id, changes, claims.uid).await?;
 
+    geo::logic::reassign_moved_stop_divisions(
+        &state.pool,
+        &mut transaction,
+        stop_id,
+        (stop.lon, stop.lat),
+        position,
+    )
+    .await?;
+
     // If this fails then proceed find a better suited job in a fast food chain.
     // The patch was just made, must be valid.
     assert!(patch.apply(&mut stop).is_ok());
@@ -197,6 +227,15 @@ pub(crate) async fn put_stop_position(
         return Err(Error::NotFoundUpstream);
     }
 
+    geo::logic::assign_stop_divisions(
+        &state.pool,
+        &mut transaction,
+        stop_id,
+        location.lon,
+        location.lat,
+    )
+    .await?;
+
     transaction.commit().await.map_err(|err| {
         tracing::error!("Transaction failed to commit: {err}");
         Error::DatabaseExecution
@@ -245,6 +284,20 @@ pub(crate) async fn get_bounded_stops(
     Ok(Json(sql::fetch_bounded_stops(&state.pool, boundary).await?))
 }
 
+pub(crate) async fn get_near_stops(
+    State(state): State<AppState>,
+    Query(near): Query<requests::NearStops>,
+) -> Result<Json<Vec<responses::NearStop>>, Error> {
+    near.validate()?;
+    let radius = near.radius.unwrap_or(requests::DEFAULT_NEAR_RADIUS);
+    let take = i64::from(near.limit.unwrap_or(requests::DEFAULT_NEAR_LIMIT));
+
+    Ok(Json(
+        sql::fetch_near_stops(&state.pool, (near.lat, near.lon), radius, take)
+            .await?,
+    ))
+}
+
 pub(crate) async fn get_operator_stops(
     State(state): State<AppState>,
     Path(operator_id): Path<i32>,
@@ -278,6 +331,72 @@ pub(crate) async fn get_stop_routes(
     Ok(Json(sql::fetch_stop_routes(&state.pool, stop_id).await?))
 }
 
+pub(crate) async fn get_stop_departures(
+    State(state): State<AppState>,
+    Path(stop_id): Path<i32>,
+    params: Query<DepartureBoardParams>,
+) -> Result<Json<Vec<responses::StopDeparture>>, Error> {
+    let date = params.date.unwrap_or_else(|| Local::now().date_naive());
+
+    let (regions, municipality, departures) = future::join3(
+        geo::sql::fetch_stop_regions(&state.pool, stop_id),
+        geo::sql::fetch_stop_municipality(&state.pool, stop_id),
+        sql::fetch_stop_departures(&state.pool, stop_id),
+    )
+    .await;
+    let almanac =
+        geo::sql::fetch_almanac(&state.pool, &regions?, municipality?).await?;
+
+    // Calendars are shared by many departures. Evaluate each only once
+    let mut calendar_matches: HashMap<i32, bool> = HashMap::new();
+    let mut board = vec![];
+    for departure in departures? {
+        let matches = match calendar_matches.entry(departure.calendar_id) {
+            hash_map::Entry::Occupied(entry) => *entry.get(),
+            hash_map::Entry::Vacant(entry) => {
+                let calendar: Calendar = serde_json::from_value(
+                    departure.calendar,
+                )
+                .map_err(|err| {
+                    tracing::error!("Error deserializing {err}");
+                    Error::DatabaseDeserialization
+                })?;
+                *entry.insert(calendar.includes(date, &almanac))
+            }
+        };
+        if !matches {
+            continue;
+        }
+
+        let origin_time = i32::from(departure.origin_time);
+        let time = if departure.offset_known {
+            // Offsets are in seconds, departure times in minutes
+            #[allow(clippy::cast_possible_truncation)]
+            let offset = (departure.time_offset / 60) as i32;
+            origin_time + offset
+        } else {
+            origin_time
+        };
+
+        board.push(responses::StopDeparture {
+            departure_id: departure.departure_id,
+            route_id: departure.route_id,
+            route_code: departure.route_code,
+            subroute_id: departure.subroute_id,
+            headsign: departure.headsign,
+            operator_id: departure.operator_id,
+            badge_text: departure.badge_text,
+            badge_bg: departure.badge_bg,
+            time,
+            approximate: !departure.offset_known,
+        });
+    }
+
+    board.sort_by_key(|departure| (departure.time, departure.departure_id));
+
+    Ok(Json(board))
+}
+
 pub(crate) async fn get_stop_spider(
     State(state): State<AppState>,
     Path(stop_id): Path<i32>,
@@ -292,6 +411,16 @@ pub(crate) async fn get_stops_spider(
     Ok(Json(sql::fetch_stop_spider(&state.pool, &stops).await?))
 }
 
+pub(crate) async fn get_stop_cluster_spider(
+    State(state): State<AppState>,
+    Path(cluster_id): Path<i32>,
+) -> Result<Json<responses::SpiderMap>, Error> {
+    let cluster = sql::fetch_stop_cluster(&state.pool, cluster_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+    get_stops_spider(State(state), Json(cluster.stop_ids)).await
+}
+
 pub(crate) async fn get_osm_paired_stop(
     State(state): State<AppState>,
     Path(osm_id): Path<i64>,
@@ -341,3 +470,214 @@ pub(crate) async fn put_stop_todo(
 
     Ok(())
 }
+
+pub(crate) async fn get_stop_clusters(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<responses::StopCluster>>, Error> {
+    Ok(Json(sql::fetch_stop_clusters(&state.pool).await?))
+}
+
+pub(crate) async fn get_stop_cluster(
+    State(state): State<AppState>,
+    Path(cluster_id): Path<i32>,
+) -> Result<Json<responses::FullStopCluster>, Error> {
+    let (cluster, stops, routes) = future::join3(
+        sql::fetch_stop_cluster(&state.pool, cluster_id),
+        sql::fetch_cluster_stops(&state.pool, cluster_id),
+        sql::fetch_cluster_routes(&state.pool, cluster_id),
+    )
+    .await;
+
+    let cluster = cluster?.ok_or(Error::NotFoundUpstream)?;
+
+    Ok(Json(responses::FullStopCluster {
+        id: cluster.id,
+        local_name: cluster.local_name,
+        regional_name: cluster.regional_name,
+        national_name: cluster.national_name,
+        is_terminal: cluster.is_terminal,
+        stops: stops?,
+        routes: routes?,
+    }))
+}
+
+pub(crate) async fn post_stop_cluster(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyStopAttrs,
+    >,
+    Json(mut cluster): Json<requests::ChangeStopCluster>,
+) -> Result<Json<IdReturn<i32>>, Error> {
+    cluster.tidy();
+    cluster.validate()?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    let id = sql::insert_stop_cluster(&mut transaction, &cluster).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::StopClusterCreation {
+            data: cluster.to_history(id),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(Json(IdReturn { id }))
+}
+
+pub(crate) async fn patch_stop_cluster(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyStopAttrs,
+    >,
+    Path(cluster_id): Path<i32>,
+    Json(mut cluster): Json<requests::ChangeStopCluster>,
+) -> Result<(), Error> {
+    cluster.tidy();
+    cluster.validate()?;
+
+    let original = sql::fetch_stop_cluster(&state.pool, cluster_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::update_stop_cluster(&mut transaction, cluster_id, &cluster).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::StopClusterUpdate {
+            original: original.into(),
+            cluster: cluster.to_history(cluster_id),
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn delete_stop_cluster(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyStopAttrs,
+    >,
+    Path(cluster_id): Path<i32>,
+) -> Result<(), Error> {
+    let mut cluster = sql::fetch_stop_cluster(&state.pool, cluster_id)
+        .await?
+        .ok_or(Error::NotFoundUpstream)?;
+    let stops = std::mem::take(&mut cluster.stop_ids);
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::delete_stop_cluster(&mut transaction, cluster_id).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::StopClusterDeletion {
+            data: cluster.into(),
+            stops,
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn put_stop_cluster_stops(
+    State(state): State<AppState>,
+    auth::ScopedClaim(claims, _): auth::ScopedClaim<
+        auth::perms::ModifyStopAttrs,
+    >,
+    Path(cluster_id): Path<i32>,
+    Json(stop_ids): Json<Vec<i32>>,
+) -> Result<(), Error> {
+    let (cluster, stops) = future::join(
+        sql::fetch_stop_cluster(&state.pool, cluster_id),
+        sql::fetch_stop_list(&state.pool, &stop_ids),
+    )
+    .await;
+
+    let cluster = cluster?.ok_or(Error::NotFoundUpstream)?;
+    let stops = stops?;
+    if stop_ids
+        .iter()
+        .any(|id| !stops.iter().any(|stop| stop.id == *id))
+    {
+        return Err(Error::ValidationFailure("Unknown stop".to_string()));
+    }
+
+    let mut transaction = state.pool.begin().await.map_err(|err| {
+        tracing::error!("Failed to open transaction: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    sql::update_cluster_stops(&mut transaction, cluster_id, &stop_ids).await?;
+
+    contrib::sql::insert_changeset_log(
+        &mut transaction,
+        claims.uid,
+        &[history::Change::StopClusterStopsUpdate {
+            cluster_id,
+            original: cluster.stop_ids,
+            stops: stop_ids,
+        }],
+        None,
+    )
+    .await?;
+
+    transaction.commit().await.map_err(|err| {
+        tracing::error!("Transaction failed to commit: {err}");
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_stop_cluster_suggestions(
+    State(state): State<AppState>,
+    Path(stop_id): Path<i32>,
+) -> Result<Json<Vec<responses::ClusterSuggestion>>, Error> {
+    let stop = sql::fetch_stop_list(&state.pool, &[stop_id])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(Error::NotFoundUpstream)?;
+
+    let bounds =
+        logic::bounding_box(stop.lat, stop.lon, logic::CLUSTER_MAX_DISTANCE);
+    let candidates = sql::fetch_cluster_candidates(&state.pool, bounds).await?;
+
+    Ok(Json(logic::suggest_cluster_members(&stop, candidates)))
+}
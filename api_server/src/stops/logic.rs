@@ -0,0 +1,159 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashSet;
+
+use commons::utils::geo::{distance, EARTH_RADIUS};
+
+use super::models::responses;
+
+// Stops further apart than this are never suggested as part of a cluster
+pub(crate) const CLUSTER_MAX_DISTANCE: f64 = 300.0;
+// Stops this close are suggested regardless of their names
+const CLUSTER_NAMELESS_DISTANCE: f64 = 50.0;
+const CLUSTER_MIN_SIMILARITY: f64 = 0.4;
+
+/// Bounding box `(x0, y0, x1, y1)` that contains every point within
+/// `radius` meters of the given coordinates
+pub(crate) fn bounding_box(
+    lat: f64,
+    lon: f64,
+    radius: f64,
+) -> (f64, f64, f64, f64) {
+    let d_lat = (radius / EARTH_RADIUS).to_degrees();
+    let d_lon = d_lat / lat.to_radians().cos();
+    (lon - d_lon, lat + d_lat, lon + d_lon, lat - d_lat)
+}
+
+fn fold_char(c: char) -> char {
+    match c {
+        'á' | 'à' | 'â' | 'ã' | 'ä' => 'a',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'í' | 'ì' | 'î' | 'ï' => 'i',
+        'ó' | 'ò' | 'ô' | 'õ' | 'ö' => 'o',
+        'ú' | 'ù' | 'û' | 'ü' => 'u',
+        'ç' => 'c',
+        _ => c,
+    }
+}
+
+fn name_tokens(name: &str) -> HashSet<String> {
+    name.to_lowercase()
+        .chars()
+        .map(fold_char)
+        .collect::<String>()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Share of words that two names have in common, ignoring case and accents
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn name_similarity(name0: &str, name1: &str) -> f64 {
+    let tokens0 = name_tokens(name0);
+    let tokens1 = name_tokens(name1);
+    let union = tokens0.union(&tokens1).count();
+    if union == 0 {
+        return 0.0;
+    }
+    tokens0.intersection(&tokens1).count() as f64 / union as f64
+}
+
+/// Nearby stops that are likely to be the same place as `stop`,
+/// best matches first
+pub(crate) fn suggest_cluster_members(
+    stop: &responses::SimpleStop,
+    candidates: Vec<(responses::SimpleStop, Option<i32>)>,
+) -> Vec<responses::ClusterSuggestion> {
+    let mut suggestions = candidates
+        .into_iter()
+        .filter(|(candidate, _)| candidate.id != stop.id)
+        .filter_map(|(candidate, cluster_id)| {
+            let distance =
+                distance(stop.lat, stop.lon, candidate.lat, candidate.lon);
+            let similarity = name_similarity(&stop.name, &candidate.name);
+
+            let is_match = distance <= CLUSTER_NAMELESS_DISTANCE
+                || (distance <= CLUSTER_MAX_DISTANCE
+                    && similarity >= CLUSTER_MIN_SIMILARITY);
+            is_match.then_some(responses::ClusterSuggestion {
+                stop: candidate,
+                cluster_id,
+                distance,
+                similarity,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    suggestions.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then(a.distance.total_cmp(&b.distance))
+    });
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(id: i32, name: &str, lat: f64, lon: f64) -> responses::SimpleStop {
+        responses::SimpleStop {
+            id,
+            name: name.to_string(),
+            short_name: None,
+            lat,
+            lon,
+        }
+    }
+
+    #[test]
+    fn similarity_ignores_case_and_accents() {
+        assert!(
+            (name_similarity("Estação Fluvial", "ESTACAO fluvial") - 1.0).abs()
+                < f64::EPSILON
+        );
+        assert!(name_similarity("Terminal", "Hospital") < f64::EPSILON);
+    }
+
+    #[test]
+    fn suggestions_filter_and_rank() {
+        let origin = stop(1, "Terminal Rodoviário", 38.7369, -9.1427);
+        let candidates = vec![
+            (origin.clone(), None),
+            // Same name, ~100m away
+            (
+                stop(2, "Terminal Rodoviário (Cais 2)", 38.7378, -9.1427),
+                None,
+            ),
+            // Unrelated name, ~30m away
+            (stop(3, "Rua Direita", 38.7372, -9.1427), Some(7)),
+            // Unrelated name, ~100m away
+            (stop(4, "Escola", 38.7378, -9.1427), None),
+            // Same name, too far away
+            (stop(5, "Terminal Rodoviário", 38.7469, -9.1427), None),
+        ];
+
+        let suggestions = suggest_cluster_members(&origin, candidates);
+        let ids = suggestions.iter().map(|s| s.stop.id).collect::<Vec<_>>();
+
+        assert_eq!(ids, vec![2, 3]);
+        assert_eq!(suggestions[1].cluster_id, Some(7));
+    }
+}
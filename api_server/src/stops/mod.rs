@@ -17,5 +17,6 @@
 */
 
 pub(crate) mod handlers;
+pub(crate) mod logic;
 pub(crate) mod models;
 pub(crate) mod sql;
@@ -16,12 +16,35 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+// A departure that passes through a stop, as stored
+pub(crate) struct StopDeparture {
+    pub(crate) departure_id: i32,
+    pub(crate) calendar_id: i32,
+    pub(crate) calendar: serde_json::Value,
+    // Minutes since midnight at which the departure leaves its origin
+    pub(crate) origin_time: i16,
+    // Seconds it takes from the origin to the stop
+    pub(crate) time_offset: i64,
+    // Whether every travel time from the origin to the stop is known
+    pub(crate) offset_known: bool,
+    pub(crate) subroute_id: i32,
+    pub(crate) headsign: String,
+    pub(crate) route_id: i32,
+    pub(crate) route_code: Option<String>,
+    pub(crate) operator_id: i32,
+    pub(crate) badge_text: String,
+    pub(crate) badge_bg: String,
+}
+
 pub(crate) mod requests {
     use chrono::NaiveDate;
     use serde::Deserialize;
 
     use commons::models::{history, stops};
 
+    use crate::utils::canonicalize_optional_string;
+    use crate::Error;
+
     #[derive(Debug, Deserialize)]
     pub struct NewStop {
         pub lon: f64,
@@ -282,6 +305,91 @@ pub(crate) mod requests {
         pub lon: f64,
         pub lat: f64,
     }
+
+    pub(crate) const DEFAULT_NEAR_RADIUS: f64 = 500.0;
+    pub(crate) const DEFAULT_NEAR_LIMIT: u32 = 20;
+    const MAX_NEAR_RADIUS: f64 = 20_000.0;
+    const MAX_NEAR_LIMIT: u32 = 200;
+
+    #[derive(Debug, Deserialize)]
+    pub struct NearStops {
+        pub lat: f64,
+        pub lon: f64,
+        // In metres
+        #[serde(default)]
+        pub radius: Option<f64>,
+        #[serde(default)]
+        pub limit: Option<u32>,
+    }
+
+    impl NearStops {
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            if !(-90.0..=90.0).contains(&self.lat)
+                || !(-180.0..=180.0).contains(&self.lon)
+            {
+                return Err(Error::ValidationFailure(
+                    "Invalid coordinates".to_string(),
+                ));
+            }
+            if self.radius.is_some_and(|radius| {
+                !(radius > 0.0 && radius <= MAX_NEAR_RADIUS)
+            }) {
+                return Err(Error::ValidationFailure(format!(
+                    "The radius must be within ]0, {MAX_NEAR_RADIUS}] metres"
+                )));
+            }
+            if self
+                .limit
+                .is_some_and(|limit| limit == 0 || limit > MAX_NEAR_LIMIT)
+            {
+                return Err(Error::ValidationFailure(
+                    "Invalid result limit".to_string(),
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ChangeStopCluster {
+        pub local_name: Option<String>,
+        pub regional_name: Option<String>,
+        pub national_name: Option<String>,
+        pub is_terminal: bool,
+    }
+
+    impl ChangeStopCluster {
+        pub(crate) fn tidy(&mut self) {
+            canonicalize_optional_string(&mut self.local_name);
+            canonicalize_optional_string(&mut self.regional_name);
+            canonicalize_optional_string(&mut self.national_name);
+        }
+
+        pub(crate) fn validate(&self) -> Result<(), Error> {
+            if self.local_name.is_none()
+                && self.regional_name.is_none()
+                && self.national_name.is_none()
+            {
+                return Err(Error::ValidationFailure(
+                    "Clusters need at least one name".to_string(),
+                ));
+            }
+            Ok(())
+        }
+
+        pub(crate) fn to_history(
+            &self,
+            id: i32,
+        ) -> history::stops::StopCluster {
+            history::stops::StopCluster {
+                id,
+                local_name: self.local_name.clone(),
+                regional_name: self.regional_name.clone(),
+                national_name: self.national_name.clone(),
+                is_terminal: self.is_terminal,
+            }
+        }
+    }
 }
 
 pub(crate) mod responses {
@@ -290,7 +398,7 @@ pub(crate) mod responses {
     use sqlx::types::Json;
     use std::collections::HashMap;
 
-    use commons::models::stops;
+    use commons::models::{history, routes, stops};
 
     #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
     pub struct OperatorStopRel {
@@ -311,6 +419,14 @@ pub(crate) mod responses {
         pub lon: f64,
     }
 
+    #[derive(Debug, Serialize)]
+    pub struct NearStop {
+        #[serde(flatten)]
+        pub stop: Stop,
+        // In metres
+        pub distance: f64,
+    }
+
     /// Meant to be an information-rich stop for the client
     #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
     pub struct Stop {
@@ -399,6 +515,8 @@ pub(crate) mod responses {
         pub name: String,
         pub lat: f64,
         pub lon: f64,
+        // Stops of the same cluster are to be drawn as a single place
+        pub cluster: Option<i32>,
     }
 
     #[derive(Serialize)]
@@ -416,4 +534,66 @@ pub(crate) mod responses {
         pub lon: f64,
         pub todo: Json<Vec<stops::StopTodo>>,
     }
+
+    #[derive(Debug, Serialize)]
+    pub struct StopDeparture {
+        pub departure_id: i32,
+        pub route_id: i32,
+        pub route_code: Option<String>,
+        pub subroute_id: i32,
+        pub headsign: String,
+        pub operator_id: i32,
+        pub badge_text: String,
+        pub badge_bg: String,
+        // Minutes since midnight at which the vehicle passes by the stop
+        pub time: i32,
+        // The travel times up to this stop are unknown.
+        // `time` is the departure time from the origin instead
+        pub approximate: bool,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct StopCluster {
+        pub id: i32,
+        pub local_name: Option<String>,
+        pub regional_name: Option<String>,
+        pub national_name: Option<String>,
+        pub is_terminal: bool,
+        pub stop_ids: Vec<i32>,
+    }
+
+    impl From<StopCluster> for history::stops::StopCluster {
+        fn from(cluster: StopCluster) -> Self {
+            history::stops::StopCluster {
+                id: cluster.id,
+                local_name: cluster.local_name,
+                regional_name: cluster.regional_name,
+                national_name: cluster.national_name,
+                is_terminal: cluster.is_terminal,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct FullStopCluster {
+        pub id: i32,
+        pub local_name: Option<String>,
+        pub regional_name: Option<String>,
+        pub national_name: Option<String>,
+        pub is_terminal: bool,
+        pub stops: Vec<SimpleStop>,
+        pub routes: Vec<routes::Route>,
+    }
+
+    /// A nearby stop that might belong together with another stop
+    #[derive(Debug, Serialize)]
+    pub struct ClusterSuggestion {
+        #[serde(flatten)]
+        pub stop: SimpleStop,
+        pub cluster_id: Option<i32>,
+        /// Distance in meters
+        pub distance: f64,
+        /// Name similarity from 0 to 1
+        pub similarity: f64,
+    }
 }
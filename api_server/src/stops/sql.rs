@@ -30,10 +30,13 @@ use crate::Error;
 
 type Result<T> = std::result::Result<T, Error>;
 
-pub(crate) async fn fetch_stop(
-    pool: &PgPool,
+pub(crate) async fn fetch_stop<'c, E>(
+    executor: E,
     stop_id: i32,
-) -> Result<Option<responses::Stop>> {
+) -> Result<Option<responses::Stop>>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
     sqlx::query_as!(
         responses::Stop,
 r#"SELECT id, name, short_name, locality, street, door, lat, lon, notes, parish,
@@ -44,7 +47,7 @@ FROM Stops
 WHERE id = $1"#,
         stop_id
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await
     .map_err(|err| {
         tracing::error!(error=err.to_string(), stop_id);
@@ -267,6 +270,65 @@ WHERE lon >= $1 AND lon <= $2 AND lat <= $3 AND lat >= $4
         })
 }
 
+/// Stops within `radius` metres of a position, the nearest first
+pub(crate) async fn fetch_near_stops(
+    pool: &PgPool,
+    (lat, lon): (f64, f64),
+    radius: f64,
+    take: i64,
+) -> Result<Vec<responses::NearStop>> {
+    Ok(sqlx::query!(
+        r#"
+SELECT id, name, short_name, locality, street, door, lat, lon, notes, parish,
+    tags, verification_level, service_check_date, infrastructure_check_date,
+    accessibility_meta as "a11y!: sqlx::types::Json<stops::A11yMeta>", osm_id,
+    is_ghost, license,
+    earth_distance(ll_to_earth($1, $2), ll_to_earth(lat, lon)) as "distance!"
+FROM stops
+WHERE NOT is_ghost
+    AND earth_box(ll_to_earth($1, $2), $3) @> ll_to_earth(lat, lon)
+    AND earth_distance(ll_to_earth($1, $2), ll_to_earth(lat, lon)) <= $3
+ORDER BY ll_to_earth(lat, lon) <-> ll_to_earth($1, $2)
+LIMIT $4
+"#,
+        lat,
+        lon,
+        radius,
+        take
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), lat, lon, radius, take);
+        Error::DatabaseExecution
+    })?
+    .into_iter()
+    .map(|row| responses::NearStop {
+        stop: responses::Stop {
+            id: row.id,
+            name: row.name,
+            short_name: row.short_name,
+            locality: row.locality,
+            street: row.street,
+            door: row.door,
+            parish: row.parish,
+            lat: row.lat,
+            lon: row.lon,
+            notes: row.notes,
+            tags: row.tags,
+            a11y: row.a11y,
+            verification_level: row.verification_level,
+            service_check_date: row.service_check_date,
+            infrastructure_check_date: row.infrastructure_check_date,
+            osm_id: row.osm_id,
+            license: row.license,
+            is_ghost: row.is_ghost,
+        },
+        distance: row.distance,
+    })
+    .collect())
+}
+
 pub(crate) async fn fetch_route_stops(
     pool: &PgPool,
     route_id: i32,
@@ -563,7 +625,8 @@ SELECT Routes.id as route_id,
     subroute_stops.stop as stop_id,
     stops.name as stop_name,
     stops.lon as lon,
-    stops.lat as lat
+    stops.lat as lat,
+    stops.cluster as cluster
 FROM routes
 JOIN subroutes ON routes.id = subroutes.route
 JOIN subroute_stops ON subroutes.id = subroute_stops.subroute
@@ -615,6 +678,7 @@ ORDER BY subroute_stops.idx"#,
                 name: row.stop_name,
                 lat: row.lat,
                 lon: row.lon,
+                cluster: row.cluster,
             });
         }
     }
@@ -868,3 +932,319 @@ impl FromRow<'_, sqlx::postgres::PgRow> for responses::FullStop {
         })
     }
 }
+
+/// Fetches every departure of an active route that leaves from the stop.
+/// Departures for which the stop is the last one are left out.
+pub(crate) async fn fetch_stop_departures(
+    pool: &PgPool,
+    stop_id: i32,
+) -> Result<Vec<models::StopDeparture>> {
+    sqlx::query_as!(
+        models::StopDeparture,
+        r#"
+WITH offsets AS (
+    SELECT subroute, stop,
+        COALESCE(SUM(time_to_next) OVER preceding, 0) as time_offset,
+        COALESCE(bool_and(time_to_next IS NOT NULL) OVER preceding, true)
+            as offset_known,
+        idx = MAX(idx) OVER (PARTITION BY subroute) as is_last
+    FROM subroute_stops
+    WHERE subroute IN (
+        SELECT subroute
+        FROM subroute_stops
+        WHERE stop = $1
+    )
+    WINDOW preceding AS (
+        PARTITION BY subroute
+        ORDER BY idx
+        ROWS BETWEEN UNBOUNDED PRECEDING AND 1 PRECEDING
+    )
+)
+SELECT departures.id as departure_id,
+    departures.calendar_id,
+    operator_calendars.calendar,
+    departures.time as origin_time,
+    offsets.time_offset as "time_offset!: i64",
+    offsets.offset_known as "offset_known!: bool",
+    subroutes.id as subroute_id,
+    subroutes.headsign,
+    routes.id as route_id,
+    routes.code as route_code,
+    routes.operator as operator_id,
+    COALESCE(routes.badge_text_color, route_types.badge_text_color) as "badge_text!: String",
+    COALESCE(routes.badge_bg_color, route_types.badge_bg_color) as "badge_bg!: String"
+FROM offsets
+JOIN departures ON departures.subroute = offsets.subroute
+JOIN operator_calendars ON operator_calendars.id = departures.calendar_id
+JOIN subroutes ON subroutes.id = offsets.subroute
+JOIN routes ON routes.id = subroutes.route
+JOIN route_types ON route_types.id = routes.type
+WHERE offsets.stop = $1 AND NOT offsets.is_last AND routes.active
+"#,
+        stop_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), stop_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_stop_clusters(
+    pool: &PgPool,
+) -> Result<Vec<responses::StopCluster>> {
+    sqlx::query_as!(
+        responses::StopCluster,
+        r#"
+SELECT stop_clusters.id, stop_clusters.local_name, stop_clusters.regional_name,
+    stop_clusters.national_name, stop_clusters.is_terminal,
+    array_remove(array_agg(stops.id ORDER BY stops.id), NULL) as "stop_ids!: Vec<i32>"
+FROM stop_clusters
+LEFT JOIN stops ON stops.cluster = stop_clusters.id
+GROUP BY stop_clusters.id
+ORDER BY stop_clusters.id
+"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string());
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_stop_cluster(
+    pool: &PgPool,
+    cluster_id: i32,
+) -> Result<Option<responses::StopCluster>> {
+    sqlx::query_as!(
+        responses::StopCluster,
+        r#"
+SELECT stop_clusters.id, stop_clusters.local_name, stop_clusters.regional_name,
+    stop_clusters.national_name, stop_clusters.is_terminal,
+    array_remove(array_agg(stops.id ORDER BY stops.id), NULL) as "stop_ids!: Vec<i32>"
+FROM stop_clusters
+LEFT JOIN stops ON stops.cluster = stop_clusters.id
+WHERE stop_clusters.id = $1
+GROUP BY stop_clusters.id
+"#,
+        cluster_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), cluster_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_cluster_stops(
+    pool: &PgPool,
+    cluster_id: i32,
+) -> Result<Vec<responses::SimpleStop>> {
+    sqlx::query_as!(
+        responses::SimpleStop,
+        r#"
+SELECT id, name, short_name, lat, lon
+FROM stops
+WHERE cluster = $1
+ORDER BY id
+"#,
+        cluster_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), cluster_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn fetch_cluster_routes(
+    pool: &PgPool,
+    cluster_id: i32,
+) -> Result<Vec<routes::Route>> {
+    sqlx::query_as!(
+        routes::Route,
+        r#"
+SELECT DISTINCT routes.id as id,
+    routes.type as type_id,
+    routes.operator as operator_id,
+    routes.code as code,
+    routes.name as name,
+    routes.circular as circular,
+    routes.main_subroute as main_subroute,
+    routes.active as active,
+    COALESCE(routes.badge_text_color, route_types.badge_text_color) as badge_text_color,
+    COALESCE(routes.badge_bg_color, route_types.badge_bg_color) as badge_bg_color
+FROM routes
+JOIN subroutes ON routes.id = subroutes.route
+JOIN subroute_stops ON subroutes.id = subroute_stops.subroute
+JOIN stops ON subroute_stops.stop = stops.id
+JOIN route_types ON routes.type = route_types.id
+WHERE stops.cluster = $1"#,
+        cluster_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), cluster_id);
+        Error::DatabaseExecution
+    })
+}
+
+pub(crate) async fn insert_stop_cluster(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    cluster: &requests::ChangeStopCluster,
+) -> Result<i32> {
+    let res = sqlx::query!(
+        r#"
+INSERT INTO stop_clusters(local_name, regional_name, national_name, is_terminal)
+VALUES ($1, $2, $3, $4)
+RETURNING id
+"#,
+        cluster.local_name,
+        cluster.regional_name,
+        cluster.national_name,
+        cluster.is_terminal
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), ?cluster);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res.id)
+}
+
+pub(crate) async fn update_stop_cluster(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    cluster_id: i32,
+    cluster: &requests::ChangeStopCluster,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE stop_clusters
+SET local_name = $2, regional_name = $3, national_name = $4, is_terminal = $5
+WHERE id = $1
+"#,
+        cluster_id,
+        cluster.local_name,
+        cluster.regional_name,
+        cluster.national_name,
+        cluster.is_terminal
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), cluster_id, ?cluster);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn delete_stop_cluster(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    cluster_id: i32,
+) -> Result<()> {
+    update_cluster_stops(transaction, cluster_id, &[]).await?;
+
+    sqlx::query!(
+        r#"
+DELETE FROM stop_clusters
+WHERE id = $1
+"#,
+        cluster_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), cluster_id);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn update_cluster_stops(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    cluster_id: i32,
+    stop_ids: &[i32],
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE stops
+SET cluster = CASE WHEN id = ANY($2) THEN $1 ELSE NULL END
+WHERE cluster = $1 OR id = ANY($2)
+"#,
+        cluster_id,
+        stop_ids
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), cluster_id, ?stop_ids);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(())
+}
+
+pub(crate) async fn fetch_cluster_candidates(
+    pool: &PgPool,
+    (x0, y0, x1, y1): (f64, f64, f64, f64),
+) -> Result<Vec<(responses::SimpleStop, Option<i32>)>> {
+    let res = sqlx::query!(
+        r#"
+SELECT id, name, short_name, lat, lon, cluster
+FROM stops
+WHERE lon >= $1 AND lon <= $2 AND lat <= $3 AND lat >= $4
+"#,
+        x0,
+        x1,
+        y0,
+        y1
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        tracing::error!(error = err.to_string(), x0, x1, y0, y1);
+        Error::DatabaseExecution
+    })?;
+
+    Ok(res
+        .into_iter()
+        .map(|row| {
+            (
+                responses::SimpleStop {
+                    id: row.id,
+                    name: row.name,
+                    short_name: row.short_name,
+                    lat: row.lat,
+                    lon: row.lon,
+                },
+                row.cluster,
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::fetch_near_stops;
+
+    #[sqlx::test(fixtures("near_stops"))]
+    async fn near_stops_skip_ghosts(pool: PgPool) {
+        let near = fetch_near_stops(&pool, (38.7, -9.1), 500.0, 10)
+            .await
+            .unwrap();
+
+        let ids = near.iter().map(|near| near.stop.id).collect::<Vec<_>>();
+        assert_eq!(ids, [1, 2]);
+    }
+}
@@ -0,0 +1,330 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use axum::async_trait;
+use bytes::Bytes;
+use s3::error::S3Error;
+
+use crate::errors::Error;
+use crate::settings::{self, Settings};
+
+/// Keeps the uploaded pictures and other objects.
+/// Those under `/private` are not to be exposed by whatever serves the rest.
+#[async_trait]
+pub trait ObjectStorage: Send + Sync {
+    async fn put(
+        &self,
+        key: &str,
+        content: &[u8],
+        content_type: Option<&str>,
+    ) -> Result<(), Error>;
+
+    async fn get(&self, key: &str) -> Result<Bytes, Error>;
+
+    async fn content_type(&self, key: &str) -> Result<Option<String>, Error>;
+
+    // Deleting a missing object is not an error, as with S3
+    async fn delete(&self, key: &str) -> Result<(), Error>;
+
+    // Whether this server is to serve the objects, as nothing else would
+    fn is_local(&self) -> bool {
+        false
+    }
+}
+
+pub(crate) fn from_settings(
+    settings: &Settings,
+) -> Result<Arc<dyn ObjectStorage>, Error> {
+    Ok(match &settings.objects {
+        settings::ObjectStorage::S3 => {
+            let Some(s3_settings) = &settings.s3 else {
+                tracing::error!("No S3 settings provided");
+                return Err(Error::IllegalState);
+            };
+
+            let credentials = s3::creds::Credentials::new(
+                Some(s3_settings.access_key.as_str()),
+                Some(s3_settings.secret_key.as_str()),
+                None,
+                None,
+                None,
+            )
+            .map_err(|err| {
+                tracing::error!("Invalid S3 credentials: {err}");
+                Error::IllegalState
+            })?;
+
+            let region = if let Some(endpoint) = &s3_settings.endpoint {
+                s3::Region::Custom {
+                    region: "minio".to_owned(),
+                    endpoint: endpoint.clone(),
+                }
+            } else if let Some(account_id) = &s3_settings.account_id {
+                s3::Region::R2 {
+                    account_id: account_id.clone(),
+                }
+            } else {
+                tracing::error!("No S3 endpoint or account ID provided");
+                return Err(Error::IllegalState);
+            };
+
+            let bucket_name = s3_settings.bucket_name.as_str();
+            let bucket = s3::Bucket::new(bucket_name, region, credentials)
+                .map_err(|err| {
+                    tracing::error!("Invalid S3 bucket: {err}");
+                    Error::IllegalState
+                })?
+                .with_path_style();
+            tracing::info!("Configured to use the {bucket_name} bucket");
+            if !(bucket_name.ends_with("dev") || bucket_name.ends_with("test"))
+            {
+                tracing::warn!("Using a production bucket");
+            }
+
+            Arc::new(S3Storage {
+                bucket: Box::new(bucket),
+            })
+        }
+        settings::ObjectStorage::Local { dir } => {
+            tracing::info!("Configured to store objects in {dir}");
+            Arc::new(LocalStorage::new(dir))
+        }
+    })
+}
+
+/// Objects within an S3 (or compatible) bucket
+pub struct S3Storage {
+    bucket: Box<s3::Bucket>,
+}
+
+#[async_trait]
+impl ObjectStorage for S3Storage {
+    async fn put(
+        &self,
+        key: &str,
+        content: &[u8],
+        content_type: Option<&str>,
+    ) -> Result<(), Error> {
+        // TODO handle status codes
+        let _status_code = if let Some(content_type) = content_type {
+            self.bucket
+                .put_object_with_content_type(key, content, content_type)
+                .await
+        } else {
+            self.bucket.put_object(key, content).await
+        }
+        .map_err(|err| {
+            tracing::error!(key, "Object storage failure: {err}");
+            Error::ObjectStorageFailure
+        })?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, Error> {
+        match self.bucket.get_object(key).await {
+            Ok(obj) if obj.status_code() == 404 => Err(Error::NotFoundUpstream),
+            Err(S3Error::HttpFailWithBody(404, _)) => {
+                Err(Error::NotFoundUpstream)
+            }
+            Ok(obj) => Ok(obj.bytes().clone()),
+            Err(err) => {
+                tracing::error!(key, "Object storage failure: {err}");
+                Err(Error::ObjectStorageFailure)
+            }
+        }
+    }
+
+    async fn content_type(&self, key: &str) -> Result<Option<String>, Error> {
+        match self.bucket.head_object(key).await {
+            Ok((_, 404)) | Err(S3Error::HttpFailWithBody(404, _)) => {
+                Err(Error::NotFoundUpstream)
+            }
+            Ok((head, _)) => Ok(head.content_type),
+            Err(err) => {
+                tracing::error!(key, "Object storage failure: {err}");
+                Err(Error::ObjectStorageFailure)
+            }
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.bucket.delete_object(key).await.map_err(|err| {
+            tracing::error!(key, "Object storage failure: {err}");
+            Error::ObjectStorageFailure
+        })?;
+        Ok(())
+    }
+}
+
+/// Files within a directory, each next to another with its content type
+pub struct LocalStorage {
+    dir: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        LocalStorage { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl ObjectStorage for LocalStorage {
+    async fn put(
+        &self,
+        key: &str,
+        content: &[u8],
+        content_type: Option<&str>,
+    ) -> Result<(), Error> {
+        let path = local_path(&self.dir, key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|err| {
+                tracing::error!(?path, "Object storage failure: {err}");
+                Error::ObjectStorageFailure
+            })?;
+        }
+        tokio::fs::write(&path, content).await.map_err(|err| {
+            tracing::error!(?path, "Object storage failure: {err}");
+            Error::ObjectStorageFailure
+        })?;
+
+        let type_path = content_type_path(&path);
+        if let Some(content_type) = content_type {
+            tokio::fs::write(&type_path, content_type).await
+        } else {
+            ignore_missing(tokio::fs::remove_file(&type_path).await)
+        }
+        .map_err(|err| {
+            tracing::error!(?path, "Object storage failure: {err}");
+            Error::ObjectStorageFailure
+        })
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, Error> {
+        let path = local_path(&self.dir, key)?;
+        match tokio::fs::read(&path).await {
+            Ok(content) => Ok(Bytes::from(content)),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                Err(Error::NotFoundUpstream)
+            }
+            Err(err) => {
+                tracing::error!(?path, "Object storage failure: {err}");
+                Err(Error::ObjectStorageFailure)
+            }
+        }
+    }
+
+    async fn content_type(&self, key: &str) -> Result<Option<String>, Error> {
+        let path = content_type_path(&local_path(&self.dir, key)?);
+        match tokio::fs::read_to_string(&path).await {
+            Ok(content_type) => Ok(Some(content_type)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => {
+                tracing::error!(?path, "Object storage failure: {err}");
+                Err(Error::ObjectStorageFailure)
+            }
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        let path = local_path(&self.dir, key)?;
+        ignore_missing(tokio::fs::remove_file(&path).await)
+            .and(ignore_missing(
+                tokio::fs::remove_file(content_type_path(&path)).await,
+            ))
+            .map_err(|err| {
+                tracing::error!(?path, "Object storage failure: {err}");
+                Error::ObjectStorageFailure
+            })
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+}
+
+// Keys are relative to the directory and cannot escape it
+fn local_path(dir: &Path, key: &str) -> Result<PathBuf, Error> {
+    let key = Path::new(key.trim_start_matches('/'));
+    if !key.components().all(|c| matches!(c, Component::Normal(_))) {
+        tracing::error!(?key, "Illegal object key");
+        return Err(Error::ObjectStorageFailure);
+    }
+    Ok(dir.join(key))
+}
+
+fn content_type_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".type");
+    PathBuf::from(path)
+}
+
+fn ignore_missing(res: std::io::Result<()>) -> std::io::Result<()> {
+    match res {
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        res => res,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LocalStorage, ObjectStorage};
+    use crate::errors::Error;
+
+    #[tokio::test]
+    async fn local_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(dir.path());
+
+        storage
+            .put("/content/1/ori", b"picture", Some("image/png"))
+            .await
+            .unwrap();
+        storage
+            .put("/content/1/thumb", b"preview", None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.get("/content/1/ori").await.unwrap(),
+            &b"picture"[..]
+        );
+        assert_eq!(
+            storage.content_type("/content/1/ori").await.unwrap(),
+            Some("image/png".to_string())
+        );
+        assert_eq!(
+            storage.content_type("/content/1/thumb").await.unwrap(),
+            None
+        );
+
+        storage.delete("/content/1/ori").await.unwrap();
+        storage.delete("/content/1/ori").await.unwrap();
+        assert_eq!(
+            storage.get("/content/1/ori").await,
+            Err(Error::NotFoundUpstream)
+        );
+        assert_eq!(
+            storage.get("/content/../secrets").await,
+            Err(Error::ObjectStorageFailure)
+        );
+    }
+}
@@ -3,7 +3,7 @@ use ipnetwork::IpNetwork;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-pub use perms::Permissions;
+pub use perms::{PermissionScope, Permissions};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct User {
@@ -12,9 +12,21 @@ pub struct User {
     pub password: String,
     pub email: String,
     pub is_superuser: bool,
+    pub is_suspended: bool,
+    // Suspensions without an expiration last until lifted
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub verification_level: i32,
     pub works_for: Option<i32>,
 }
 
+impl User {
+    #[must_use]
+    pub fn is_suspended_at(&self, time: DateTime<Utc>) -> bool {
+        self.is_suspended
+            && self.suspended_until.is_none_or(|until| until > time)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuditLogEntry {
     pub id: i64,
@@ -54,13 +66,37 @@ pub enum AuditLogAction {
         user_id: i32,
         assignment_id: i32,
         permissions: Box<Permissions>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scope: Option<PermissionScope>,
     },
     RevokePermissionAssignment {
         user_id: i32,
         assignment_id: i32,
         permissions: Box<Permissions>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scope: Option<PermissionScope>,
     },
     QueryManagementTokens,
+    UserSuspension {
+        user_id: i32,
+        reason: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        until: Option<DateTime<Utc>>,
+        revoked_sessions: u64,
+    },
+    UserUnsuspension {
+        user_id: i32,
+    },
+    EmailVerification {
+        email: String,
+    },
+    PasswordReset {
+        revoked_sessions: u64,
+    },
+    RegionDeletion {
+        region_id: i32,
+        name: String,
+    },
 }
 
 impl AuditLogAction {
@@ -88,21 +124,23 @@ impl AuditLogAction {
             AuditLogAction::RevokePermissionAssignment { .. } => {
                 "revokePermissionAssignment"
             }
+            AuditLogAction::UserSuspension { .. } => "userSuspension",
+            AuditLogAction::UserUnsuspension { .. } => "userUnsuspension",
+            AuditLogAction::EmailVerification { .. } => "emailVerification",
+            AuditLogAction::PasswordReset { .. } => "passwordReset",
+            AuditLogAction::RegionDeletion { .. } => "regionDeletion",
         }
     }
 }
 
 mod perms {
+    use std::collections::BTreeMap;
+
     use serde::{Deserialize, Serialize};
 
     #[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
     #[serde(rename_all = "camelCase")]
     pub struct Permissions {
-        // TODO find a way to implement these two
-        // #[serde(default, skip_serializing_if = "Option::is_none")]
-        // region: Option<RegionPerm>,
-        // #[serde(default, skip_serializing_if = "Option::is_none")]
-        // operator: Option<OperatorPerm>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
         pub regions: Option<subperm::Regions>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -123,24 +161,66 @@ mod perms {
         pub admin: Option<subperm::Admin>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
         pub misc: Option<subperm::Misc>,
+        // Permissions that only apply to the entities of an operator
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub operator_scoped: BTreeMap<i32, Permissions>,
+        // Permissions that only apply to the entities within a region
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub region_scoped: BTreeMap<i32, Permissions>,
     }
 
-    // TODO find a way to implement these two
-    // #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
-    // #[serde(rename_all = "camelCase", tag = "perm")]
-    // pub enum ExtraPermission {
-    //
-    //     Region {
-    //         region_id: i32,
-    //         permissions: Vec<Permission>,
-    //     },
-    //     Operator {
-    //         operator_id: i32,
-    //         permissions: Vec<Permission>,
-    //     },
-    // }
+    /// Limits a permission assignment to a set of operators or regions
+    #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase", tag = "scope")]
+    pub enum PermissionScope {
+        Operators { ids: Vec<i32> },
+        Regions { ids: Vec<i32> },
+    }
 
     impl Permissions {
+        /// Merges permissions that only apply within a scope.
+        /// Scopes cannot be nested, so any scoped permission
+        /// within `perms` is discarded.
+        pub fn merge_scoped(&mut self, scope: &PermissionScope, perms: Self) {
+            let (ids, scoped) = match scope {
+                PermissionScope::Operators { ids } => {
+                    (ids, &mut self.operator_scoped)
+                }
+                PermissionScope::Regions { ids } => {
+                    (ids, &mut self.region_scoped)
+                }
+            };
+
+            let perms = Self {
+                operator_scoped: BTreeMap::new(),
+                region_scoped: BTreeMap::new(),
+                ..perms
+            };
+            for id in ids {
+                scoped.entry(*id).or_default().merge(perms.clone());
+            }
+        }
+
+        #[must_use]
+        pub fn has_scoped(&self) -> bool {
+            !(self.operator_scoped.is_empty() && self.region_scoped.is_empty())
+        }
+
+        /// The scoped permissions that apply to an entity that
+        /// belongs to the given operators and regions
+        pub fn scoped_to<'a>(
+            &'a self,
+            operators: &'a [i32],
+            regions: &'a [i32],
+        ) -> impl Iterator<Item = &'a Permissions> {
+            operators
+                .iter()
+                .filter_map(|id| self.operator_scoped.get(id))
+                .chain(
+                    regions.iter().filter_map(|id| self.region_scoped.get(id)),
+                )
+        }
+
         pub fn merge(&mut self, perms: Self) {
             if let Some(regions) = perms.regions {
                 if let Some(ref mut r) = self.regions {
@@ -212,6 +292,12 @@ mod perms {
                     self.misc = Some(misc);
                 }
             }
+            for (id, scoped) in perms.operator_scoped {
+                self.operator_scoped.entry(id).or_default().merge(scoped);
+            }
+            for (id, scoped) in perms.region_scoped {
+                self.region_scoped.entry(id).or_default().merge(scoped);
+            }
         }
 
         #[must_use]
@@ -227,6 +313,8 @@ mod perms {
                 external_news: Some(subperm::ExternalNews::everything()),
                 admin: Some(subperm::Admin::everything()),
                 misc: Some(subperm::Misc::everything()),
+                operator_scoped: BTreeMap::new(),
+                region_scoped: BTreeMap::new(),
             }
         }
 
@@ -302,6 +390,10 @@ mod perms {
             #[serde(default, skip_serializing_if = "is_false")]
             pub modify_calendars: bool,
             #[serde(default, skip_serializing_if = "is_false")]
+            pub handle_tickets: bool,
+            #[serde(default, skip_serializing_if = "is_false")]
+            pub modify_vehicles: bool,
+            #[serde(default, skip_serializing_if = "is_false")]
             pub delete: bool,
         }
 
@@ -312,6 +404,10 @@ mod perms {
                 self.modify_stops = self.modify_stops || perms.modify_stops;
                 self.modify_calendars =
                     self.modify_calendars || perms.modify_calendars;
+                self.handle_tickets =
+                    self.handle_tickets || perms.handle_tickets;
+                self.modify_vehicles =
+                    self.modify_vehicles || perms.modify_vehicles;
                 self.delete = self.delete || perms.delete;
             }
 
@@ -321,6 +417,8 @@ mod perms {
                     modify_base: true,
                     modify_stops: true,
                     modify_calendars: true,
+                    handle_tickets: true,
+                    modify_vehicles: true,
                     delete: true,
                 }
             }
@@ -563,6 +661,8 @@ mod perms {
             pub change_permissions: bool,
             #[serde(default, skip_serializing_if = "is_false")]
             pub suspend_users: bool,
+            #[serde(default, skip_serializing_if = "is_false")]
+            pub manage_jobs: bool,
         }
 
         impl Admin {
@@ -576,6 +676,7 @@ mod perms {
                 self.change_permissions =
                     self.change_permissions || perms.change_permissions;
                 self.suspend_users = self.suspend_users || perms.suspend_users;
+                self.manage_jobs = self.manage_jobs || perms.manage_jobs;
             }
 
             pub fn everything() -> Self {
@@ -585,6 +686,7 @@ mod perms {
                     change_passwords: true,
                     change_permissions: true,
                     suspend_users: true,
+                    manage_jobs: true,
                 }
             }
         }
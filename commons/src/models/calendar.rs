@@ -23,7 +23,7 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
-use crate::utils::calendar::within_dates;
+use crate::utils::calendar::{easter_sunday, within_dates};
 
 pub static EVERY_DAY: [Weekday; 7] = [
     Weekday::Monday,
@@ -45,31 +45,114 @@ pub static BUSINESS_WEEKDAYS: [Weekday; 5] = [
 
 pub static WEEKEND: [Weekday; 2] = [Weekday::Saturday, Weekday::Sunday];
 
-pub static HOLIDAYS: [(u8, u8); 13] = [
-    (1, 1),
-    (4, 9),
-    (4, 10),
-    (4, 25),
-    (5, 1),
-    (6, 8),
-    (6, 10),
-    (8, 15),
-    (10, 5),
-    (11, 1),
-    (12, 1),
-    (12, 8),
-    (12, 25),
+/// Holidays that are observed nationwide
+pub static NATIONAL_HOLIDAYS: [HolidayRule; 13] = [
+    HolidayRule::Fixed { month: 1, day: 1 },
+    // Good Friday
+    HolidayRule::Easter { offset: -2 },
+    HolidayRule::Easter { offset: 0 },
+    HolidayRule::Fixed { month: 4, day: 25 },
+    HolidayRule::Fixed { month: 5, day: 1 },
+    // Corpus Christi
+    HolidayRule::Easter { offset: 60 },
+    HolidayRule::Fixed { month: 6, day: 10 },
+    HolidayRule::Fixed { month: 8, day: 15 },
+    HolidayRule::Fixed { month: 10, day: 5 },
+    HolidayRule::Fixed { month: 11, day: 1 },
+    HolidayRule::Fixed { month: 12, day: 1 },
+    HolidayRule::Fixed { month: 12, day: 8 },
+    HolidayRule::Fixed { month: 12, day: 25 },
 ];
 
 pub static SUMMER: [(u8, u8); 2] = [(6, 23), (9, 23)];
 
-// Made up FIXME
-pub static SCHOOL_PERIODS: [[(u8, u8); 2]; 3] = [
-    // From   To  [month, day]
-    [(1, 5), (3, 20)],
-    [(3, 27), (6, 10)],
-    [(9, 20), (12, 15)],
-];
+/// How a holiday falls in a given year
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(tag = "rule")]
+pub enum HolidayRule {
+    // Same day every year
+    Fixed { month: u8, day: u8 },
+    // Moves along with the Easter Sunday (eg. Good Friday is -2)
+    Easter { offset: i16 },
+    // Happens once (eg. an exceptional day off)
+    Once { date: NaiveDate },
+}
+
+impl HolidayRule {
+    /// The date in which this holiday falls in the given year, if any
+    #[must_use]
+    pub fn date_in(&self, year: i32) -> Option<NaiveDate> {
+        match self {
+            HolidayRule::Fixed { month, day } => NaiveDate::from_ymd_opt(
+                year,
+                u32::from(*month),
+                u32::from(*day),
+            ),
+            HolidayRule::Easter { offset } => easter_sunday(year)?
+                .checked_add_signed(chrono::Duration::days(i64::from(*offset))),
+            HolidayRule::Once { date } => {
+                (date.year() == year).then_some(*date)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub struct SchoolPeriod {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl SchoolPeriod {
+    #[must_use]
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+}
+
+/// The holidays and school periods that apply to a place.
+/// Calendars are only meaningful when evaluated against one of these.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Almanac {
+    pub holidays: Vec<HolidayRule>,
+    pub school_periods: Vec<SchoolPeriod>,
+}
+
+impl Almanac {
+    /// An almanac with the national holidays and without any school period
+    #[must_use]
+    pub fn national() -> Self {
+        Almanac {
+            holidays: NATIONAL_HOLIDAYS.to_vec(),
+            school_periods: vec![],
+        }
+    }
+
+    #[must_use]
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.holidays
+            .iter()
+            .any(|rule| rule.date_in(date.year()) == Some(date))
+    }
+
+    #[must_use]
+    pub fn is_school_day(&self, date: NaiveDate) -> bool {
+        self.school_periods
+            .iter()
+            .any(|period| period.contains(date))
+    }
+
+    /// Every holiday date in a given year, sorted and deduplicated
+    #[must_use]
+    pub fn holidays_in(&self, year: i32) -> Vec<NaiveDate> {
+        self.holidays
+            .iter()
+            .filter_map(|rule| rule.date_in(year))
+            .sorted()
+            .dedup()
+            .collect()
+    }
+}
 
 #[derive(
     Serialize_repr, Deserialize_repr, PartialEq, Eq, Clone, Copy, Debug,
@@ -125,17 +208,16 @@ pub struct Calendar {
 impl Calendar {
     #[allow(clippy::cast_possible_truncation)]
     #[must_use]
-    pub fn includes(&self, date: NaiveDate) -> bool {
+    pub fn includes(&self, date: NaiveDate, almanac: &Almanac) -> bool {
+        let is_holiday = almanac.is_holiday(date);
+        let is_school = almanac.is_school_day(date);
+
         let month = date.month() as u8;
         let day = date.day() as u8;
         let weekday = date.weekday().num_days_from_monday() as u8;
 
         let date = (month, day);
-        let is_holiday = HOLIDAYS.contains(&date);
         let is_summer = within_dates(date, SUMMER[0], SUMMER[1]);
-        let is_school = SCHOOL_PERIODS
-            .into_iter()
-            .any(|period| within_dates(date, period[0], period[1]));
 
         let condition_matches = |condition: &Condition| match condition {
             Condition::Holiday => is_holiday,
@@ -301,10 +383,107 @@ impl fmt::Display for Condition {
 #[cfg(test)]
 mod test {
     use super::{
-        Calendar, Condition, Weekday, BUSINESS_WEEKDAYS, EVERY_DAY, WEEKEND,
+        Almanac, Calendar, Condition, HolidayRule, SchoolPeriod, Weekday,
+        BUSINESS_WEEKDAYS, EVERY_DAY, WEEKEND,
     };
     use chrono::NaiveDate;
 
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn almanac() -> Almanac {
+        let mut almanac = Almanac::national();
+        almanac.school_periods = vec![
+            SchoolPeriod {
+                start: date(2022, 1, 5),
+                end: date(2022, 3, 20),
+            },
+            SchoolPeriod {
+                start: date(2022, 3, 27),
+                end: date(2022, 6, 10),
+            },
+            SchoolPeriod {
+                start: date(2022, 9, 20),
+                end: date(2022, 12, 15),
+            },
+        ];
+        almanac
+    }
+
+    fn holiday_only() -> Calendar {
+        Calendar {
+            weekdays: EVERY_DAY.to_vec(),
+            only_if: vec![Condition::Holiday],
+            also_if: vec![],
+            except_if: vec![],
+        }
+    }
+
+    #[test]
+    fn easter_sundays() {
+        assert_eq!(
+            crate::utils::calendar::easter_sunday(2023),
+            Some(date(2023, 4, 9))
+        );
+        assert_eq!(
+            crate::utils::calendar::easter_sunday(2024),
+            Some(date(2024, 3, 31))
+        );
+        assert_eq!(
+            crate::utils::calendar::easter_sunday(2025),
+            Some(date(2025, 4, 20))
+        );
+    }
+
+    #[test]
+    fn movable_holidays() {
+        let cal = holiday_only();
+        // Good Friday
+        assert!(cal.includes(date(2023, 4, 7), &almanac()));
+        assert!(cal.includes(date(2024, 3, 29), &almanac()));
+        assert!(!cal.includes(date(2024, 4, 7), &almanac()));
+        // Corpus Christi
+        assert!(cal.includes(date(2023, 6, 8), &almanac()));
+        assert!(cal.includes(date(2024, 5, 30), &almanac()));
+        assert!(!cal.includes(date(2024, 6, 8), &almanac()));
+    }
+
+    #[test]
+    fn municipal_holiday() {
+        let cal = holiday_only();
+        let mut almanac = almanac();
+        // Lisbon's Saint Anthony
+        assert!(!cal.includes(date(2024, 6, 13), &almanac));
+        almanac
+            .holidays
+            .push(HolidayRule::Fixed { month: 6, day: 13 });
+        assert!(cal.includes(date(2024, 6, 13), &almanac));
+    }
+
+    #[test]
+    fn one_off_holiday() {
+        let cal = holiday_only();
+        let mut almanac = almanac();
+        almanac.holidays.push(HolidayRule::Once {
+            date: date(2024, 2, 13),
+        });
+        assert!(cal.includes(date(2024, 2, 13), &almanac));
+        assert!(!cal.includes(date(2025, 2, 13), &almanac));
+    }
+
+    #[test]
+    fn school_period_is_yearly() {
+        let cal = Calendar {
+            weekdays: EVERY_DAY.to_vec(),
+            only_if: vec![Condition::School],
+            also_if: vec![],
+            except_if: vec![],
+        };
+        assert!(cal.includes(date(2022, 10, 3), &almanac()));
+        assert!(!cal.includes(date(2023, 10, 3), &almanac()));
+    }
+
     #[test]
     fn yes_holiday() {
         let date = NaiveDate::from_ymd_opt(2022, 12, 25).unwrap();
@@ -314,7 +493,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(cal.includes(date));
+        assert!(cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -326,7 +505,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(!cal.includes(date));
+        assert!(!cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -338,7 +517,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(!cal.includes(date));
+        assert!(!cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -350,7 +529,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(cal.includes(date));
+        assert!(cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -362,7 +541,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(cal.includes(date));
+        assert!(cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -374,7 +553,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(!cal.includes(date));
+        assert!(!cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -386,7 +565,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(!cal.includes(date));
+        assert!(!cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -398,7 +577,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(cal.includes(date));
+        assert!(cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -410,7 +589,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(cal.includes(date));
+        assert!(cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -422,7 +601,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(!cal.includes(date));
+        assert!(!cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -434,7 +613,7 @@ mod test {
             also_if: vec![Condition::Holiday],
             except_if: vec![],
         };
-        assert!(cal.includes(date));
+        assert!(cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -446,7 +625,7 @@ mod test {
             also_if: vec![Condition::Holiday],
             except_if: vec![Condition::Holiday],
         };
-        assert!(!cal.includes(date));
+        assert!(!cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -458,7 +637,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(cal.includes(date));
+        assert!(cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -470,7 +649,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(cal.includes(date));
+        assert!(cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -482,7 +661,7 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(!cal.includes(date));
+        assert!(!cal.includes(date, &almanac()));
     }
 
     #[test]
@@ -494,6 +673,6 @@ mod test {
             also_if: vec![],
             except_if: vec![],
         };
-        assert!(!cal.includes(date));
+        assert!(!cal.includes(date, &almanac()));
     }
 }
@@ -44,7 +44,7 @@ pub struct Geojson {
     // pub properties: HashMap<String, JsonValue>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "type")]
 pub enum GeojsonGeometry {
     Polygon {
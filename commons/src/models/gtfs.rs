@@ -24,6 +24,8 @@ pub type StopId = String;
 pub type TripId = String;
 pub type RouteId = String;
 pub type PatternId = String;
+pub type ServiceId = String;
+pub type ShapeId = String;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Stop {
@@ -38,6 +40,8 @@ pub struct StopTime {
     pub trip_id: TripId,
     pub stop_id: StopId,
     pub stop_sequence: usize,
+    pub arrival_time: Option<String>,
+    pub departure_time: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,10 +62,69 @@ pub struct Trip {
     pub service_id: String,
     pub pattern_id: Option<String>,
     pub trip_headsign: Option<String>,
+    pub shape_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Calendar {
+    pub service_id: ServiceId,
+    pub monday: u8,
+    pub tuesday: u8,
+    pub wednesday: u8,
+    pub thursday: u8,
+    pub friday: u8,
+    pub saturday: u8,
+    pub sunday: u8,
+    // YYYYMMDD
+    pub start_date: String,
+    pub end_date: String,
+}
+
+impl Calendar {
+    #[must_use]
+    pub fn has_weekdays(&self) -> bool {
+        [
+            self.monday,
+            self.tuesday,
+            self.wednesday,
+            self.thursday,
+            self.friday,
+            self.saturday,
+            self.sunday,
+        ]
+        .contains(&1)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CalendarDate {
+    pub service_id: ServiceId,
+    // YYYYMMDD
+    pub date: String,
+    // 1 - Service added, 2 - Service removed
+    pub exception_type: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShapePoint {
+    pub shape_id: ShapeId,
+    pub shape_pt_lat: f64,
+    pub shape_pt_lon: f64,
+    pub shape_pt_sequence: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeedInfo {
+    pub feed_publisher_name: String,
+    // YYYYMMDD
+    pub feed_start_date: Option<String>,
+    pub feed_end_date: Option<String>,
+    pub feed_version: Option<String>,
 }
 
 pub enum File {
     Agency,
+    Calendar,
     CalendarDates,
     Facilities,
     FareAttributes,
@@ -81,6 +144,7 @@ impl File {
     pub fn filename(&self) -> &'static str {
         match self {
             File::Agency => "agency.txt",
+            File::Calendar => "calendar.txt",
             File::CalendarDates => "calendar_dates.txt",
             File::Facilities => "facilities.txt",
             File::FareAttributes => "fare_attributes.txt",
@@ -128,7 +192,51 @@ pub struct RouteValidation {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OperatorValidation {
-    pub gtfs_lints: Vec<Lint>,
+    pub gtfs_lints: Vec<GtfsLint>,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredGtfsLint")]
+pub struct GtfsLint {
+    pub severity: LintSeverity,
+    #[serde(flatten)]
+    pub lint: Lint,
+}
+
+impl From<Lint> for GtfsLint {
+    fn from(lint: Lint) -> Self {
+        Self {
+            severity: lint.severity(),
+            lint,
+        }
+    }
+}
+
+// Validations stored before severities existed lack them
+#[derive(Deserialize)]
+struct StoredGtfsLint {
+    severity: Option<LintSeverity>,
+    #[serde(flatten)]
+    lint: Lint,
+}
+
+impl From<StoredGtfsLint> for GtfsLint {
+    fn from(stored: StoredGtfsLint) -> Self {
+        Self {
+            severity: stored.severity.unwrap_or_else(|| stored.lint.severity()),
+            lint: stored.lint,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -140,4 +248,93 @@ pub enum Lint {
     DuplicatedPattern(HashSet<PatternId>),
     UnusedStop(StopId),
     DanglingStopPointer(StopId),
+    // A stop time that does not come after the previous one
+    NonMonotonicStopTimes {
+        trip_id: TripId,
+        stop_sequence: usize,
+    },
+    // Vehicles would have to exceed a plausible speed (km/h)
+    ImpossibleSpeed {
+        trip_id: TripId,
+        from_stop_id: StopId,
+        to_stop_id: StopId,
+        speed: f64,
+    },
+    // A stop that is too far (meters) from the shape of a trip serving it
+    StopFarFromShape {
+        shape_id: ShapeId,
+        stop_id: StopId,
+        distance: f64,
+    },
+    // Distinct stops that are only a few meters apart
+    DuplicatedStop {
+        stop_ids: (StopId, StopId),
+        distance: f64,
+    },
+    DanglingShapePointer(ShapeId),
+    // A service that never runs
+    EmptyService(ServiceId),
+    // A service whose last day has already passed
+    ExpiredService(ServiceId),
+    // A trip whose service is neither in the calendar nor its exceptions
+    CalendarlessTrip(TripId),
+    MissingFeedInfo,
+    MissingFeedValidity,
+    ExpiredFeed,
+}
+
+impl Lint {
+    #[must_use]
+    pub fn severity(&self) -> LintSeverity {
+        match self {
+            Lint::ServicelessTrip(_)
+            | Lint::EmptyTrip(_)
+            | Lint::DanglingStopPointer(_)
+            | Lint::NonMonotonicStopTimes { .. }
+            | Lint::DanglingShapePointer(_)
+            | Lint::CalendarlessTrip(_)
+            | Lint::ExpiredFeed => LintSeverity::Error,
+            Lint::EmptyPattern(_)
+            | Lint::PatternlessRoute(_)
+            | Lint::ImpossibleSpeed { .. }
+            | Lint::StopFarFromShape { .. }
+            | Lint::DuplicatedStop { .. }
+            | Lint::EmptyService(_)
+            | Lint::MissingFeedValidity => LintSeverity::Warning,
+            Lint::DuplicatedPattern(_)
+            | Lint::UnusedStop(_)
+            | Lint::ExpiredService(_)
+            | Lint::MissingFeedInfo => LintSeverity::Info,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GtfsLint, Lint, LintSeverity};
+
+    #[test]
+    fn serialize_deserialize_lint() {
+        let lint = GtfsLint::from(Lint::NonMonotonicStopTimes {
+            trip_id: "1".to_string(),
+            stop_sequence: 3,
+        });
+        let json = serde_json::to_string(&lint).unwrap();
+        let lint: GtfsLint = serde_json::from_str(&json).unwrap();
+        assert_eq!(lint.severity, LintSeverity::Error);
+        assert!(matches!(
+            lint.lint,
+            Lint::NonMonotonicStopTimes {
+                stop_sequence: 3,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn deserialize_lint_without_severity() {
+        let lint: GtfsLint =
+            serde_json::from_str(r#"{"UnusedStop": "123"}"#).unwrap();
+        assert_eq!(lint.severity, LintSeverity::Info);
+    }
 }
@@ -19,7 +19,7 @@
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 
-use super::{operators, pics, routes, stops};
+use super::{fares, gtfs, operators, pics, routes, stops};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Contribution {
@@ -56,6 +56,22 @@ pub enum Change {
     StopDeletion {
         data: stops::Stop,
     },
+    StopClusterCreation {
+        data: stops::StopCluster,
+    },
+    StopClusterUpdate {
+        original: stops::StopCluster,
+        cluster: stops::StopCluster,
+    },
+    StopClusterDeletion {
+        data: stops::StopCluster,
+        stops: Vec<i32>,
+    },
+    StopClusterStopsUpdate {
+        cluster_id: i32,
+        original: Vec<i32>,
+        stops: Vec<i32>,
+    },
     RouteCreation {
         data: routes::Route,
     },
@@ -81,6 +97,11 @@ pub enum Change {
         // TODO drop the Option after history is rebuilt
         departures: Option<Vec<routes::Departure>>,
     },
+    SubrouteStopsUpdate {
+        subroute_id: i32,
+        original: Vec<i32>,
+        stops: Vec<i32>,
+    },
     DepartureCreation {
         data: routes::Departure,
     },
@@ -121,4 +142,237 @@ pub enum Change {
         original: operators::Abnormality,
         patch: operators::AbnormalityPatch,
     },
+    VehicleCreation {
+        data: operators::OperatorVehicle,
+    },
+    VehicleUpdate {
+        original: operators::OperatorVehicle,
+        patch: operators::OperatorVehiclePatch,
+    },
+    VehicleDeletion {
+        data: operators::OperatorVehicle,
+    },
+    RouteTypeVehiclesUpdate {
+        type_id: i32,
+        original: Vec<i32>,
+        vehicles: Vec<i32>,
+    },
+    FareProductCreation {
+        data: fares::FareProduct,
+    },
+    FareProductUpdate {
+        original: fares::FareProduct,
+        product: fares::FareProduct,
+    },
+    FareProductDeletion {
+        data: fares::FareProduct,
+    },
+    FareZoneCreation {
+        data: fares::FareZone,
+    },
+    FareZoneUpdate {
+        original: fares::FareZone,
+        zone: fares::FareZone,
+    },
+    FareZoneDeletion {
+        data: fares::FareZone,
+        stops: Vec<i32>,
+    },
+    FareZoneStopsUpdate {
+        zone_id: i32,
+        original: Vec<i32>,
+        stops: Vec<i32>,
+    },
+    FareTransferRuleCreation {
+        data: fares::FareTransferRule,
+    },
+    FareTransferRuleUpdate {
+        original: fares::FareTransferRule,
+        rule: fares::FareTransferRule,
+    },
+    FareTransferRuleDeletion {
+        data: fares::FareTransferRule,
+    },
+    GtfsSourceUpdate {
+        original: Option<gtfs::GtfsSource>,
+        source: gtfs::GtfsSource,
+    },
+    GtfsSourceDeletion {
+        data: gtfs::GtfsSource,
+    },
+}
+
+/// An entity that a change touches. Two changes conflict when they share one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "type", content = "id")]
+pub enum ChangeTarget {
+    Stop(i32),
+    StopCluster(i32),
+    StopClusterStops(i32),
+    Route(i32),
+    Subroute(i32),
+    SubrouteStops(i32),
+    Departure(i32),
+    StopPic(i32),
+    Issue(i32),
+    Abnormality(i32),
+    Vehicle(i32),
+    RouteTypeVehicles(i32),
+    FareProduct(i32),
+    FareZone(i32),
+    FareZoneStops(i32),
+    FareTransferRule(i32),
+    // Operators have a single source, hence the operator ID
+    GtfsSource(i32),
+}
+
+impl ChangeTarget {
+    #[must_use]
+    pub fn id(self) -> i32 {
+        match self {
+            ChangeTarget::Stop(id)
+            | ChangeTarget::StopCluster(id)
+            | ChangeTarget::StopClusterStops(id)
+            | ChangeTarget::Route(id)
+            | ChangeTarget::Subroute(id)
+            | ChangeTarget::SubrouteStops(id)
+            | ChangeTarget::Departure(id)
+            | ChangeTarget::StopPic(id)
+            | ChangeTarget::Issue(id)
+            | ChangeTarget::Abnormality(id)
+            | ChangeTarget::Vehicle(id)
+            | ChangeTarget::RouteTypeVehicles(id)
+            | ChangeTarget::FareProduct(id)
+            | ChangeTarget::FareZone(id)
+            | ChangeTarget::FareZoneStops(id)
+            | ChangeTarget::FareTransferRule(id)
+            | ChangeTarget::GtfsSource(id) => id,
+        }
+    }
+}
+
+impl Change {
+    #[must_use]
+    pub fn targets(&self) -> Vec<ChangeTarget> {
+        match self {
+            Change::StopCreation { data } | Change::StopDeletion { data } => {
+                vec![ChangeTarget::Stop(data.id)]
+            }
+            Change::StopUpdate { original, .. } => {
+                vec![ChangeTarget::Stop(original.id)]
+            }
+            Change::StopClusterCreation { data } => {
+                vec![ChangeTarget::StopCluster(data.id)]
+            }
+            Change::StopClusterUpdate { original, .. } => {
+                vec![ChangeTarget::StopCluster(original.id)]
+            }
+            Change::StopClusterDeletion { data, .. } => vec![
+                ChangeTarget::StopCluster(data.id),
+                ChangeTarget::StopClusterStops(data.id),
+            ],
+            Change::StopClusterStopsUpdate { cluster_id, .. } => {
+                vec![ChangeTarget::StopClusterStops(*cluster_id)]
+            }
+            Change::RouteCreation { data } | Change::RouteDeletion { data } => {
+                vec![ChangeTarget::Route(data.id)]
+            }
+            Change::RouteUpdate { original, .. } => {
+                vec![ChangeTarget::Route(original.id)]
+            }
+            Change::SubrouteCreation { data } => vec![
+                ChangeTarget::Subroute(data.id),
+                ChangeTarget::SubrouteStops(data.id),
+            ],
+            Change::SubrouteUpdate { original, .. } => {
+                vec![ChangeTarget::Subroute(original.id)]
+            }
+            Change::SubrouteDeletion {
+                subroute,
+                departures,
+                ..
+            } => {
+                let mut targets = vec![
+                    ChangeTarget::Subroute(subroute.id),
+                    ChangeTarget::SubrouteStops(subroute.id),
+                ];
+                if let Some(departures) = departures {
+                    targets.extend(departures.iter().map(|departure| {
+                        ChangeTarget::Departure(departure.id)
+                    }));
+                }
+                targets
+            }
+            Change::SubrouteStopsUpdate { subroute_id, .. } => {
+                vec![ChangeTarget::SubrouteStops(*subroute_id)]
+            }
+            Change::DepartureCreation { data }
+            | Change::DepartureDeletion { data } => {
+                vec![ChangeTarget::Departure(data.id)]
+            }
+            Change::DepartureUpdate { original, .. } => {
+                vec![ChangeTarget::Departure(original.id)]
+            }
+            Change::StopPicUpload { pic, .. }
+            | Change::StopPicDeletion { pic, .. } => {
+                vec![ChangeTarget::StopPic(pic.id)]
+            }
+            Change::StopPicMetaUpdate { pic_id, .. } => {
+                pic_id.map(ChangeTarget::StopPic).into_iter().collect()
+            }
+            Change::IssueCreation { data } => {
+                vec![ChangeTarget::Issue(data.id)]
+            }
+            Change::IssueUpdate { original, .. } => {
+                vec![ChangeTarget::Issue(original.id)]
+            }
+            Change::AbnormalityCreation { data } => {
+                vec![ChangeTarget::Abnormality(data.id)]
+            }
+            Change::AbnormalityUpdate { original, .. } => {
+                vec![ChangeTarget::Abnormality(original.id)]
+            }
+            Change::VehicleCreation { data }
+            | Change::VehicleDeletion { data } => {
+                vec![ChangeTarget::Vehicle(data.id)]
+            }
+            Change::VehicleUpdate { original, .. } => {
+                vec![ChangeTarget::Vehicle(original.id)]
+            }
+            Change::RouteTypeVehiclesUpdate { type_id, .. } => {
+                vec![ChangeTarget::RouteTypeVehicles(*type_id)]
+            }
+            Change::FareProductCreation { data }
+            | Change::FareProductDeletion { data } => {
+                vec![ChangeTarget::FareProduct(data.id)]
+            }
+            Change::FareProductUpdate { original, .. } => {
+                vec![ChangeTarget::FareProduct(original.id)]
+            }
+            Change::FareZoneCreation { data } => {
+                vec![ChangeTarget::FareZone(data.id)]
+            }
+            Change::FareZoneUpdate { original, .. } => {
+                vec![ChangeTarget::FareZone(original.id)]
+            }
+            Change::FareZoneDeletion { data, .. } => vec![
+                ChangeTarget::FareZone(data.id),
+                ChangeTarget::FareZoneStops(data.id),
+            ],
+            Change::FareZoneStopsUpdate { zone_id, .. } => {
+                vec![ChangeTarget::FareZoneStops(*zone_id)]
+            }
+            Change::FareTransferRuleCreation { data }
+            | Change::FareTransferRuleDeletion { data } => {
+                vec![ChangeTarget::FareTransferRule(data.id)]
+            }
+            Change::FareTransferRuleUpdate { original, .. } => {
+                vec![ChangeTarget::FareTransferRule(original.id)]
+            }
+            Change::GtfsSourceUpdate { source: data, .. }
+            | Change::GtfsSourceDeletion { data } => {
+                vec![ChangeTarget::GtfsSource(data.operator_id)]
+            }
+        }
+    }
 }
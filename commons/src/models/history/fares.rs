@@ -0,0 +1,55 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2023 - 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize_repr, Deserialize_repr)]
+pub enum FareProductKind {
+    Single = 0,
+    Pass = 1,
+    Zone = 2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FareProduct {
+    pub id: i32,
+    pub operator_id: i32,
+    pub name: String,
+    pub kind: FareProductKind,
+    pub price: i32,
+    pub zone_price: Option<i32>,
+    pub route_type_ids: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FareZone {
+    pub id: i32,
+    pub operator_id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FareTransferRule {
+    pub id: i32,
+    pub operator_id: i32,
+    pub from_route_type_id: i32,
+    pub to_route_type_id: i32,
+    pub cost: i32,
+}
@@ -25,6 +25,8 @@ pub type StopId = String;
 pub type TripId = String;
 pub type RouteId = String;
 pub type PatternId = String;
+pub type ServiceId = String;
+pub type ShapeId = String;
 
 // Validation structs
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,7 +87,7 @@ impl From<current::RouteValidation> for RouteValidation {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OperatorValidation {
-    pub gtfs_lints: Vec<Lint>,
+    pub gtfs_lints: Vec<GtfsLint>,
 }
 
 impl From<current::OperatorValidation> for OperatorValidation {
@@ -96,6 +98,40 @@ impl From<current::OperatorValidation> for OperatorValidation {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl From<current::LintSeverity> for LintSeverity {
+    fn from(severity: current::LintSeverity) -> Self {
+        match severity {
+            current::LintSeverity::Info => Self::Info,
+            current::LintSeverity::Warning => Self::Warning,
+            current::LintSeverity::Error => Self::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GtfsLint {
+    pub severity: LintSeverity,
+    #[serde(flatten)]
+    pub lint: Lint,
+}
+
+impl From<current::GtfsLint> for GtfsLint {
+    fn from(lint: current::GtfsLint) -> Self {
+        Self {
+            severity: lint.severity.into(),
+            lint: lint.lint.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Lint {
     ServicelessTrip(TripId),
@@ -105,6 +141,32 @@ pub enum Lint {
     DuplicatedPattern(HashSet<PatternId>),
     UnusedStop(StopId),
     DanglingStopPointer(StopId),
+    NonMonotonicStopTimes {
+        trip_id: TripId,
+        stop_sequence: usize,
+    },
+    ImpossibleSpeed {
+        trip_id: TripId,
+        from_stop_id: StopId,
+        to_stop_id: StopId,
+        speed: f64,
+    },
+    StopFarFromShape {
+        shape_id: ShapeId,
+        stop_id: StopId,
+        distance: f64,
+    },
+    DuplicatedStop {
+        stop_ids: (StopId, StopId),
+        distance: f64,
+    },
+    DanglingShapePointer(ShapeId),
+    EmptyService(ServiceId),
+    ExpiredService(ServiceId),
+    CalendarlessTrip(TripId),
+    MissingFeedInfo,
+    MissingFeedValidity,
+    ExpiredFeed,
 }
 
 impl From<current::Lint> for Lint {
@@ -127,6 +189,71 @@ impl From<current::Lint> for Lint {
             current::Lint::DanglingStopPointer(stop_id) => {
                 Self::DanglingStopPointer(stop_id)
             }
+            current::Lint::NonMonotonicStopTimes {
+                trip_id,
+                stop_sequence,
+            } => Self::NonMonotonicStopTimes {
+                trip_id,
+                stop_sequence,
+            },
+            current::Lint::ImpossibleSpeed {
+                trip_id,
+                from_stop_id,
+                to_stop_id,
+                speed,
+            } => Self::ImpossibleSpeed {
+                trip_id,
+                from_stop_id,
+                to_stop_id,
+                speed,
+            },
+            current::Lint::StopFarFromShape {
+                shape_id,
+                stop_id,
+                distance,
+            } => Self::StopFarFromShape {
+                shape_id,
+                stop_id,
+                distance,
+            },
+            current::Lint::DuplicatedStop { stop_ids, distance } => {
+                Self::DuplicatedStop { stop_ids, distance }
+            }
+            current::Lint::DanglingShapePointer(shape_id) => {
+                Self::DanglingShapePointer(shape_id)
+            }
+            current::Lint::EmptyService(service_id) => {
+                Self::EmptyService(service_id)
+            }
+            current::Lint::ExpiredService(service_id) => {
+                Self::ExpiredService(service_id)
+            }
+            current::Lint::CalendarlessTrip(trip_id) => {
+                Self::CalendarlessTrip(trip_id)
+            }
+            current::Lint::MissingFeedInfo => Self::MissingFeedInfo,
+            current::Lint::MissingFeedValidity => Self::MissingFeedValidity,
+            current::Lint::ExpiredFeed => Self::ExpiredFeed,
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GtfsSourceKind {
+    Zip,
+    Transporlis,
+    Upload,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GtfsSource {
+    pub id: i32,
+    pub operator_id: i32,
+    pub kind: GtfsSourceKind,
+    pub url: Option<String>,
+    pub transporlis_id: Option<i32>,
+    // Header values tend to be credentials, which have no place here
+    pub header_names: Vec<String>,
+    pub refresh_interval: Option<i32>,
+}
@@ -18,13 +18,14 @@
 
 pub mod calendar;
 pub mod changes;
+pub mod fares;
 pub mod gtfs;
 pub mod operators;
 pub mod pics;
 pub mod routes;
 pub mod stops;
 
-pub use changes::{Change, Changeset, Contribution};
+pub use changes::{Change, ChangeTarget, Changeset, Contribution};
 
 // Rust... cmon. Let's pretend I'm not going to use these a million times because they're not part of the stdlib
 #[must_use]
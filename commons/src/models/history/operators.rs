@@ -40,21 +40,40 @@ pub struct OperatorCalendar {
     pub calendar: Calendar,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct OperatorVehicle {
     pub id: i32,
+    pub operator_id: i32,
     pub name: String,
-    pub service_year: u16,
-    pub quantity: u16,
-    pub bench_seats: u16,
-    pub foot_seats: u16,
+    pub service_year: Option<i32>,
+    pub quantity: i32,
+    pub bench_seats: i32,
+    pub foot_seats: i32,
     pub has_ac: bool,
     pub has_usb_outlets: bool,
     pub has_wifi: bool,
     pub has_bicycle_rack: bool,
     pub has_wheelchair_ramp: bool,
-    // TODO complete
+}
+
+impl From<current::OperatorVehicle> for OperatorVehicle {
+    fn from(vehicle: current::OperatorVehicle) -> Self {
+        Self {
+            id: vehicle.id,
+            operator_id: vehicle.operator_id,
+            name: vehicle.name,
+            service_year: vehicle.service_year,
+            quantity: vehicle.quantity,
+            bench_seats: vehicle.bench_seats,
+            foot_seats: vehicle.foot_seats,
+            has_ac: vehicle.has_ac,
+            has_usb_outlets: vehicle.has_usb_outlets,
+            has_wifi: vehicle.has_wifi,
+            has_bicycle_rack: vehicle.has_bicycle_rack,
+            has_wheelchair_ramp: vehicle.has_wheelchair_ramp,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -595,3 +614,66 @@ impl AbnormalityPatch {
         Ok(())
     }
 }
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct OperatorVehiclePatch {
+    pub name: Option<String>,
+    pub service_year: Option<Option<i32>>,
+    pub quantity: Option<i32>,
+    pub bench_seats: Option<i32>,
+    pub foot_seats: Option<i32>,
+    pub has_ac: Option<bool>,
+    pub has_usb_outlets: Option<bool>,
+    pub has_wifi: Option<bool>,
+    pub has_bicycle_rack: Option<bool>,
+    pub has_wheelchair_ramp: Option<bool>,
+}
+
+impl OperatorVehiclePatch {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.service_year.is_none()
+            && self.quantity.is_none()
+            && self.bench_seats.is_none()
+            && self.foot_seats.is_none()
+            && self.has_ac.is_none()
+            && self.has_usb_outlets.is_none()
+            && self.has_wifi.is_none()
+            && self.has_bicycle_rack.is_none()
+            && self.has_wheelchair_ramp.is_none()
+    }
+
+    pub fn apply(self, vehicle: &mut current::OperatorVehicle) {
+        if let Some(name) = self.name {
+            vehicle.name = name;
+        }
+        if let Some(service_year) = self.service_year {
+            vehicle.service_year = service_year;
+        }
+        if let Some(quantity) = self.quantity {
+            vehicle.quantity = quantity;
+        }
+        if let Some(bench_seats) = self.bench_seats {
+            vehicle.bench_seats = bench_seats;
+        }
+        if let Some(foot_seats) = self.foot_seats {
+            vehicle.foot_seats = foot_seats;
+        }
+        if let Some(has_ac) = self.has_ac {
+            vehicle.has_ac = has_ac;
+        }
+        if let Some(has_usb_outlets) = self.has_usb_outlets {
+            vehicle.has_usb_outlets = has_usb_outlets;
+        }
+        if let Some(has_wifi) = self.has_wifi {
+            vehicle.has_wifi = has_wifi;
+        }
+        if let Some(has_bicycle_rack) = self.has_bicycle_rack {
+            vehicle.has_bicycle_rack = has_bicycle_rack;
+        }
+        if let Some(has_wheelchair_ramp) = self.has_wheelchair_ramp {
+            vehicle.has_wheelchair_ramp = has_wheelchair_ramp;
+        }
+    }
+}
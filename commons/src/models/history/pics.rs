@@ -166,6 +166,15 @@ impl From<current::StopAttrs> for StopAttrs {
     }
 }
 
+impl From<StopAttrs> for current::StopAttrs {
+    fn from(attrs: StopAttrs) -> Self {
+        Self {
+            id: attrs.id,
+            attrs: attrs.attrs,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PanoPic {
     pub id: i32,
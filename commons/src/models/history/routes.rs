@@ -32,6 +32,10 @@ pub struct Route {
     #[serde(default)]
     pub circular: Option<bool>,
     pub active: bool,
+    #[serde(default)]
+    pub badge_text_color: Option<String>,
+    #[serde(default)]
+    pub badge_bg_color: Option<String>,
 
     // --- TODO Maybe deprecate. Keep for historical data
     pub main_subroute: Option<i32>,
@@ -47,6 +51,25 @@ impl From<current::Route> for Route {
             name: route.name,
             circular: Some(route.circular),
             active: route.active,
+            badge_text_color: route.badge_text_color,
+            badge_bg_color: route.badge_bg_color,
+            main_subroute: route.main_subroute,
+        }
+    }
+}
+
+impl From<Route> for current::Route {
+    fn from(route: Route) -> Self {
+        Self {
+            id: route.id,
+            type_id: route.type_id,
+            operator_id: route.operator_id,
+            code: route.code,
+            name: route.name,
+            circular: route.circular.unwrap_or_default(),
+            active: route.active,
+            badge_text_color: route.badge_text_color,
+            badge_bg_color: route.badge_bg_color,
             main_subroute: route.main_subroute,
         }
     }
@@ -87,6 +110,28 @@ impl From<current::Subroute> for Subroute {
         }
     }
 }
+
+impl TryFrom<Subroute> for current::Subroute {
+    type Error = Error;
+
+    fn try_from(subroute: Subroute) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: subroute.id,
+            route_id: subroute.route_id,
+            group: subroute.group.ok_or(Error::Conversion)?,
+            origin: subroute.origin.ok_or(Error::Conversion)?,
+            destination: subroute.destination.ok_or(Error::Conversion)?,
+            headsign: subroute.headsign.ok_or(Error::Conversion)?,
+            via: super::vec_into_vec(subroute.via.ok_or(Error::Conversion)?),
+            circular: subroute.circular,
+            polyline: subroute.polyline,
+            // Validations are snapshotted, but cannot be restored
+            validation: None,
+            flag: subroute.flag.ok_or(Error::Conversion)?,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SubrouteVia {
     pub name: String,
@@ -200,6 +245,72 @@ impl RoutePatch {
             route.circular = circular;
         }
     }
+
+    /// Patch that undoes this one, restoring the touched fields to `original`
+    #[must_use]
+    pub fn invert(&self, original: &Route) -> Self {
+        let mut inverse = Self::default();
+        if self.type_id.is_some() {
+            inverse.type_id = Some(original.type_id);
+        }
+        if self.operator_id.is_some() {
+            inverse.operator_id = Some(original.operator_id);
+        }
+        if self.code.is_some() {
+            inverse.code = Some(original.code.clone());
+        }
+        if self.name.is_some() {
+            inverse.name = Some(original.name.clone());
+        }
+        if self.circular.is_some() {
+            inverse.circular = original.circular;
+        }
+        if self.main_subroute.is_some() {
+            inverse.main_subroute = Some(original.main_subroute);
+        }
+        if self.active.is_some() {
+            inverse.active = Some(original.active);
+        }
+        inverse
+    }
+
+    pub fn drop_noops(&mut self, route: &current::Route) {
+        if let Some(type_id) = self.type_id {
+            if type_id == route.type_id {
+                self.type_id = None;
+            }
+        }
+        if let Some(operator_id) = self.operator_id {
+            if operator_id == route.operator_id {
+                self.operator_id = None;
+            }
+        }
+        if let Some(code) = &self.code {
+            if code == &route.code {
+                self.code = None;
+            }
+        }
+        if let Some(name) = &self.name {
+            if name == &route.name {
+                self.name = None;
+            }
+        }
+        if let Some(circular) = self.circular {
+            if circular == route.circular {
+                self.circular = None;
+            }
+        }
+        if let Some(main_subroute) = self.main_subroute {
+            if main_subroute == route.main_subroute {
+                self.main_subroute = None;
+            }
+        }
+        if let Some(active) = self.active {
+            if active == route.active {
+                self.active = None;
+            }
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -253,6 +364,76 @@ impl SubroutePatch {
         }
         Ok(())
     }
+
+    #[must_use]
+    pub fn invert(&self, original: &Subroute) -> Self {
+        let mut inverse = Self::default();
+        if self.group.is_some() {
+            inverse.group = original.group;
+        }
+        if self.flag.is_some() {
+            inverse.flag.clone_from(&original.flag);
+        }
+        if self.headsign.is_some() {
+            inverse.headsign.clone_from(&original.headsign);
+        }
+        if self.origin.is_some() {
+            inverse.origin.clone_from(&original.origin);
+        }
+        if self.destination.is_some() {
+            inverse.destination.clone_from(&original.destination);
+        }
+        if self.via.is_some() {
+            inverse.via.clone_from(&original.via);
+        }
+        if self.circular.is_some() {
+            inverse.circular = Some(original.circular);
+        }
+        inverse
+    }
+
+    pub fn drop_noops(&mut self, subroute: &current::Subroute) {
+        if let Some(group) = self.group {
+            if group == subroute.group {
+                self.group = None;
+            }
+        }
+        if let Some(flag) = &self.flag {
+            if flag == &subroute.flag {
+                self.flag = None;
+            }
+        }
+        if let Some(headsign) = &self.headsign {
+            if headsign == &subroute.headsign {
+                self.headsign = None;
+            }
+        }
+        if let Some(origin) = &self.origin {
+            if origin == &subroute.origin {
+                self.origin = None;
+            }
+        }
+        if let Some(destination) = &self.destination {
+            if destination == &subroute.destination {
+                self.destination = None;
+            }
+        }
+        if let Some(via) = &self.via {
+            if via.len() == subroute.via.len()
+                && via
+                    .iter()
+                    .zip(subroute.via.iter())
+                    .all(|(a, b)| a.name == b.name && a.stops == b.stops)
+            {
+                self.via = None;
+            }
+        }
+        if let Some(circular) = self.circular {
+            if circular == subroute.circular {
+                self.circular = None;
+            }
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -282,4 +463,37 @@ impl DeparturePatch {
             departure.calendar_id = calendar_id;
         }
     }
+
+    #[must_use]
+    pub fn invert(&self, original: &Departure) -> Self {
+        let mut inverse = Self::default();
+        if self.time.is_some() {
+            inverse.time = Some(original.time);
+        }
+        if self.subroute_id.is_some() {
+            inverse.subroute_id = Some(original.subroute_id);
+        }
+        if self.calendar_id.is_some() {
+            inverse.calendar_id = Some(original.calendar_id);
+        }
+        inverse
+    }
+
+    pub fn drop_noops(&mut self, departure: &current::Departure) {
+        if let Some(time) = self.time {
+            if time == departure.time {
+                self.time = None;
+            }
+        }
+        if let Some(subroute_id) = self.subroute_id {
+            if subroute_id == departure.subroute_id {
+                self.subroute_id = None;
+            }
+        }
+        if let Some(calendar_id) = self.calendar_id {
+            if calendar_id == departure.calendar_id {
+                self.calendar_id = None;
+            }
+        }
+    }
 }
@@ -381,6 +381,15 @@ impl TryFrom<Stop> for current::Stop {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopCluster {
+    pub id: i32,
+    pub local_name: Option<String>,
+    pub regional_name: Option<String>,
+    pub national_name: Option<String>,
+    pub is_terminal: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct A11yMeta {
     pub schedules: Option<Vec<Schedule>>,
@@ -1046,6 +1055,142 @@ impl StopPatch {
         }
         Ok(())
     }
+
+    /// Patch that undoes this one, restoring the touched fields to `original`.
+    /// Fields that the original does not know about are left untouched.
+    #[allow(clippy::too_many_lines)]
+    #[must_use]
+    pub fn invert(&self, original: &Stop) -> Self {
+        let mut inverse = Self::default();
+        if self.name.is_some() {
+            inverse.name.clone_from(&original.name);
+        }
+        if self.short_name.is_some() {
+            inverse.short_name = Some(original.short_name.clone());
+        }
+        if self.locality.is_some() {
+            inverse.locality = Some(original.locality.clone());
+        }
+        if self.street.is_some() {
+            inverse.street = Some(original.street.clone());
+        }
+        if self.door.is_some() {
+            inverse.door = Some(original.door.clone());
+        }
+        if self.schedules.is_some() {
+            inverse.schedules = Some(original.a11y.schedules.clone());
+        }
+        if self.flags.is_some() {
+            inverse.flags = Some(original.a11y.flags.clone());
+        }
+        if self.has_sidewalk.is_some() {
+            inverse.has_sidewalk = Some(original.a11y.has_sidewalk);
+        }
+        if self.has_sidewalked_path.is_some() {
+            inverse.has_sidewalked_path =
+                Some(original.a11y.has_sidewalked_path);
+        }
+        if self.has_shelter.is_some() {
+            inverse.has_shelter = Some(original.a11y.has_shelter);
+        }
+        if self.has_cover.is_some() {
+            inverse.has_cover = Some(original.a11y.has_cover);
+        }
+        if self.has_bench.is_some() {
+            inverse.has_bench = Some(original.a11y.has_bench);
+        }
+        if self.has_trash_can.is_some() {
+            inverse.has_trash_can = Some(original.a11y.has_trash_can);
+        }
+        if self.has_waiting_times.is_some() {
+            inverse.has_waiting_times = Some(original.a11y.has_waiting_times);
+        }
+        if self.has_ticket_seller.is_some() {
+            inverse.has_ticket_seller = Some(original.a11y.has_ticket_seller);
+        }
+        if self.has_costumer_support.is_some() {
+            inverse.has_costumer_support =
+                Some(original.a11y.has_costumer_support);
+        }
+        if self.advertisement_qty.is_some() {
+            inverse.advertisement_qty = Some(original.a11y.advertisement_qty);
+        }
+        if self.has_crossing.is_some() {
+            inverse.has_crossing = Some(original.a11y.has_crossing);
+        }
+        if self.has_wide_access.is_some() {
+            inverse.has_wide_access = Some(original.a11y.has_wide_access);
+        }
+        if self.has_flat_access.is_some() {
+            inverse.has_flat_access = Some(original.a11y.has_flat_access);
+        }
+        if self.has_tactile_access.is_some() {
+            inverse.has_tactile_access = Some(original.a11y.has_tactile_access);
+        }
+        if self.illumination_strength.is_some() {
+            inverse.illumination_strength =
+                Some(original.a11y.illumination_strength);
+        }
+        if self.illumination_position.is_some() {
+            inverse.illumination_position =
+                Some(original.a11y.illumination_position);
+        }
+        if self.has_illuminated_path.is_some() {
+            inverse.has_illuminated_path =
+                Some(original.a11y.has_illuminated_path);
+        }
+        if self.has_visibility_from_within.is_some() {
+            inverse.has_visibility_from_within =
+                Some(original.a11y.has_visibility_from_within);
+        }
+        if self.has_visibility_from_area.is_some() {
+            inverse.has_visibility_from_area =
+                Some(original.a11y.has_visibility_from_area);
+        }
+        if self.is_visible_from_outside.is_some() {
+            inverse.is_visible_from_outside =
+                Some(original.a11y.is_visible_from_outside);
+        }
+        if self.parking_visibility_impairment.is_some() {
+            inverse.parking_visibility_impairment =
+                Some(original.a11y.parking_visibility_impairment);
+        }
+        if self.parking_local_access_impairment.is_some() {
+            inverse.parking_local_access_impairment =
+                Some(original.a11y.parking_local_access_impairment);
+        }
+        if self.parking_area_access_impairment.is_some() {
+            inverse.parking_area_access_impairment =
+                Some(original.a11y.parking_area_access_impairment);
+        }
+        if self.tmp_issues.is_some() {
+            inverse.tmp_issues = Some(original.a11y.tmp_issues.clone());
+        }
+        if self.tags.is_some() {
+            inverse.tags = Some(original.tags.clone());
+        }
+        if self.notes.is_some() {
+            inverse.notes = Some(original.notes.clone());
+        }
+        if self.service_check_date.is_some() {
+            inverse.service_check_date = Some(original.service_check_date);
+        }
+        if self.infrastructure_check_date.is_some() {
+            inverse.infrastructure_check_date =
+                Some(original.infrastructure_check_date);
+        }
+        if self.verification_level.is_some() {
+            inverse.verification_level = Some(original.verification_level);
+        }
+        if self.license.is_some() {
+            inverse.license.clone_from(&original.license);
+        }
+        if self.is_ghost.is_some() {
+            inverse.is_ghost = original.is_ghost;
+        }
+        inverse
+    }
+
     #[allow(clippy::too_many_lines)]
     pub fn drop_noops(&mut self, stop: &current::Stop) -> Result<(), Error> {
         if let Some(name) = &self.name {
@@ -19,10 +19,11 @@
 use std::fmt;
 
 use super::calendar::Calendar;
+use crate::errors::Error;
 use crate::models::content::RichContent;
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
-use serde_repr::Serialize_repr;
+use serde_repr::{Deserialize_repr, Serialize_repr};
 
 #[derive(Debug, Serialize)]
 pub struct Operator {
@@ -46,21 +47,21 @@ pub struct OperatorCalendar {
     pub calendar: Calendar,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct OperatorVehicle {
     pub id: i32,
+    pub operator_id: i32,
     pub name: String,
-    pub service_year: u16,
-    pub quantity: u16,
-    pub bench_seats: u16,
-    pub foot_seats: u16,
+    pub service_year: Option<i32>,
+    pub quantity: i32,
+    pub bench_seats: i32,
+    pub foot_seats: i32,
     pub has_ac: bool,
     pub has_usb_outlets: bool,
     pub has_wifi: bool,
     pub has_bicycle_rack: bool,
     pub has_wheelchair_ramp: bool,
-    // TODO complete
 }
 
 // Abnormalities are temporary changes to the network
@@ -145,32 +146,90 @@ impl fmt::Display for NewsItemType {
 }
 
 // Tickets are user submitted questions that might not meet quality standards
+#[derive(Debug, Serialize)]
 pub struct Ticket {
     pub id: i32,
     pub title: String,
     pub message: String,
+    pub reason: TicketReason,
     pub creation: DateTime<Local>,
-    pub operator: Option<i32>,
-    pub user: Option<i32>,
+    pub operator_id: i32,
+    pub user_id: i32,
     pub public: bool,
     pub status: TicketStatus,
 }
 
 #[repr(u8)]
-#[derive(Serialize_repr)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize_repr, Deserialize_repr)]
 pub enum TicketReason {
     Suggestion = 0,
     Complaint = 1,
     Other = 10,
 }
 
+impl TryFrom<i16> for TicketReason {
+    type Error = Error;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Suggestion),
+            1 => Ok(Self::Complaint),
+            10 => Ok(Self::Other),
+            _ => Err(Error::Conversion),
+        }
+    }
+}
+
+impl From<TicketReason> for i16 {
+    fn from(reason: TicketReason) -> Self {
+        i16::from(reason as u8)
+    }
+}
+
 #[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize_repr, Deserialize_repr)]
 pub enum TicketStatus {
     New = 0,
     Unanswered = 1,
     Answered = 2,
 }
 
+impl TicketStatus {
+    /// Tickets go from `New` to `Unanswered` once the operator acknowledges
+    /// them and bounce between `Unanswered` and `Answered` afterwards.
+    #[must_use]
+    pub fn can_transition_to(self, next: TicketStatus) -> bool {
+        matches!(
+            (self, next),
+            (
+                TicketStatus::New,
+                TicketStatus::Unanswered | TicketStatus::Answered
+            ) | (TicketStatus::Unanswered, TicketStatus::Answered)
+                | (TicketStatus::Answered, TicketStatus::Unanswered)
+        )
+    }
+}
+
+impl TryFrom<i16> for TicketStatus {
+    type Error = Error;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::New),
+            1 => Ok(Self::Unanswered),
+            2 => Ok(Self::Answered),
+            _ => Err(Error::Conversion),
+        }
+    }
+}
+
+impl From<TicketStatus> for i16 {
+    fn from(status: TicketStatus) -> Self {
+        i16::from(status as u8)
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct TicketComment {
     pub id: i32,
     pub ticket_id: i32,
@@ -16,6 +16,31 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use chrono::NaiveDate;
+
+/// Easter Sunday of a Gregorian year (anonymous Gregorian algorithm)
+#[allow(clippy::many_single_char_names)]
+#[must_use]
+pub fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    #[allow(clippy::cast_sign_loss)]
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
 #[must_use]
 pub fn within_dates(date: (u8, u8), start: (u8, u8), end: (u8, u8)) -> bool {
     let (from_month, from_day) = start;
@@ -0,0 +1,40 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2023  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use geo::{HaversineDistance, Point};
+
+/// Mean Earth radius in meters, the same that `geo` uses for its
+/// haversine distances
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Great-circle distance in meters between two coordinates
+#[must_use]
+pub fn distance(lat0: f64, lon0: f64, lat1: f64, lon1: f64) -> f64 {
+    Point::new(lon0, lat0).haversine_distance(&Point::new(lon1, lat1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::distance;
+
+    #[test]
+    fn distance_is_in_meters() {
+        let d = distance(38.7369, -9.1427, 38.7379, -9.1427);
+        assert!((d - 111.2).abs() < 1.0);
+    }
+}
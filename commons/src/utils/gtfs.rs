@@ -26,8 +26,9 @@ use itertools::Itertools;
 use crate::errors::Error;
 use crate::models::gtfs;
 
-const GTFS_FILES: [&str; 13] = [
+const GTFS_FILES: [&str; 14] = [
     "agency.txt",
+    "calendar.txt",
     "calendar_dates.txt",
     "facilities.txt",
     "fare_attributes.txt",
@@ -16,6 +16,7 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use std::collections::HashMap;
 use std::io::Cursor;
 use std::path::PathBuf;
 
@@ -62,3 +63,33 @@ pub async fn download_file(
         )))
     }
 }
+
+/// Downloads a resource into memory, sending along the provided headers
+#[allow(clippy::implicit_hasher)]
+pub async fn fetch_bytes(
+    url: &str,
+    headers: &HashMap<String, String>,
+) -> Result<Vec<u8>, Error> {
+    let mut request = reqwest::Client::new().get(url);
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| Error::Download(e.to_string()))?;
+
+    if !resp.status().is_success() {
+        return Err(Error::Download(format!(
+            "Unexpected return code: {}",
+            resp.status()
+        )));
+    }
+
+    Ok(resp
+        .bytes()
+        .await
+        .map_err(|e| Error::Download(e.to_string()))?
+        .to_vec())
+}
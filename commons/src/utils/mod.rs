@@ -18,5 +18,6 @@
 
 pub mod calendar;
 pub mod exif;
+pub mod geo;
 pub mod gtfs;
 pub mod http;
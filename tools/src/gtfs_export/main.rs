@@ -0,0 +1,101 @@
+/*
+    Intermodal, transportation information aggregator
+    Copyright (C) 2024  Cláudio Pereira
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::path::PathBuf;
+use std::process::exit;
+
+use chrono::NaiveDate;
+
+const API_URL: &str = "https://api.intermodal.pt";
+
+#[derive(Debug)]
+struct AppArgs {
+    operator: i32,
+    output: Option<PathBuf>,
+    from: Option<NaiveDate>,
+    days: Option<u16>,
+}
+
+fn parse_args() -> Result<AppArgs, pico_args::Error> {
+    let mut pargs = pico_args::Arguments::from_env();
+
+    let args = AppArgs {
+        operator: pargs.value_from_str("--op")?,
+        output: pargs.opt_value_from_str("--out")?,
+        from: pargs.opt_value_from_str("--from")?,
+        days: pargs.opt_value_from_str("--days")?,
+    };
+
+    let remaining = pargs.finish();
+    if !remaining.is_empty() {
+        eprintln!("Unknown args: {:?}.", remaining);
+        exit(1);
+    }
+
+    Ok(args)
+}
+
+#[tokio::main]
+async fn main() {
+    let args = match parse_args() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: {}.", e);
+            exit(1);
+        }
+    };
+
+    let output = args.output.unwrap_or_else(|| {
+        PathBuf::from(format!("./iml_{}_gtfs.zip", args.operator))
+    });
+
+    let mut query = vec![];
+    if let Some(from) = args.from {
+        query.push(("from", from.to_string()));
+    }
+    if let Some(days) = args.days {
+        query.push(("days", days.to_string()));
+    }
+
+    let res = reqwest::Client::new()
+        .get(format!(
+            "{API_URL}/v1/operators/{}/gtfs/export",
+            args.operator
+        ))
+        .query(&query)
+        .send()
+        .await
+        .unwrap();
+
+    if !res.status().is_success() {
+        eprintln!(
+            "Export failed with status {}: {}",
+            res.status(),
+            res.text().await.unwrap_or_default()
+        );
+        exit(1);
+    }
+
+    let feed = res.bytes().await.unwrap();
+    if let Err(err) = std::fs::write(&output, &feed) {
+        eprintln!("Unable to write {}: {err}", output.display());
+        exit(1);
+    }
+
+    println!("Exported {} bytes to {}", feed.len(), output.display());
+}
@@ -27,7 +27,9 @@ use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 pub(crate) use commons::models::gtfs::{
-    self, Lint, PatternId, Route, RouteId, Stop, StopId, StopTime, Trip, TripId,
+    self, Calendar, CalendarDate, FeedInfo, GtfsLint, Lint, PatternId, Route,
+    RouteId, ServiceId, ShapeId, ShapePoint, Stop, StopId, StopTime, Trip,
+    TripId,
 };
 
 use crate::error::Error;
@@ -40,6 +42,11 @@ pub(crate) struct Data {
     pub(crate) routes: HashMap<RouteId, Route>,
     pub(crate) trips: HashMap<TripId, Trip>,
     pub(crate) stop_times: Vec<StopTime>,
+    pub(crate) calendars: HashMap<ServiceId, Calendar>,
+    pub(crate) calendar_dates: HashMap<ServiceId, Vec<CalendarDate>>,
+    // Points sorted by their sequence
+    pub(crate) shapes: HashMap<ShapeId, Vec<ShapePoint>>,
+    pub(crate) feed_info: Option<FeedInfo>,
     // Calculated data
     pub(crate) trip_stops: HashMap<TripId, Vec<StopId>>,
     pub(crate) route_pattern_clusters: HashMap<RouteId, Vec<PatternCluster>>,
@@ -58,8 +65,16 @@ pub(crate) struct Overrides {
 }
 
 pub(crate) fn load_gtfs(root: &Path) -> Result<Data, Error> {
-    let (gtfs_stops, gtfs_routes, gtfs_trips, gtfs_times) =
-        load_gtfs_files(root)?;
+    let Files {
+        stops: gtfs_stops,
+        routes: gtfs_routes,
+        trips: gtfs_trips,
+        stop_times: gtfs_times,
+        calendars: gtfs_calendars,
+        calendar_dates: gtfs_calendar_dates,
+        shapes: gtfs_shapes,
+        feed_info: gtfs_feed_info,
+    } = load_gtfs_files(root)?;
 
     let trip_stops = gtfs_times
         .iter()
@@ -139,19 +154,52 @@ pub(crate) fn load_gtfs(root: &Path) -> Result<Data, Error> {
             .map(|trip| (trip.trip_id.clone(), trip))
             .collect(),
         stop_times: gtfs_times,
+        calendars: gtfs_calendars
+            .into_iter()
+            .map(|calendar| (calendar.service_id.clone(), calendar))
+            .collect(),
+        calendar_dates: gtfs_calendar_dates
+            .into_iter()
+            .into_group_map_by(|date| date.service_id.clone()),
+        shapes: gtfs_shapes
+            .into_iter()
+            .into_group_map_by(|point| point.shape_id.clone())
+            .into_iter()
+            .map(|(shape_id, mut points)| {
+                points.sort_by_key(|point| point.shape_pt_sequence);
+                (shape_id, points)
+            })
+            .collect(),
+        feed_info: gtfs_feed_info.into_iter().next(),
 
         trip_stops,
         route_pattern_clusters,
     };
     Ok(gtfs)
 }
-fn load_gtfs_files(
-    root: &Path,
-) -> Result<(Vec<Stop>, Vec<Route>, Vec<Trip>, Vec<StopTime>), Error> {
+struct Files {
+    stops: Vec<Stop>,
+    routes: Vec<Route>,
+    trips: Vec<Trip>,
+    stop_times: Vec<StopTime>,
+    calendars: Vec<Calendar>,
+    calendar_dates: Vec<CalendarDate>,
+    shapes: Vec<ShapePoint>,
+    feed_info: Vec<FeedInfo>,
+}
+
+fn load_gtfs_files(root: &Path) -> Result<Files, Error> {
     let gtfs_stops: OnceLock<Result<Vec<Stop>, Error>> = OnceLock::new();
     let gtfs_times: OnceLock<Result<Vec<StopTime>, Error>> = OnceLock::new();
     let gtfs_routes: OnceLock<Result<Vec<Route>, Error>> = OnceLock::new();
     let gtfs_trips: OnceLock<Result<Vec<Trip>, Error>> = OnceLock::new();
+    let gtfs_calendars: OnceLock<Result<Vec<Calendar>, Error>> =
+        OnceLock::new();
+    let gtfs_calendar_dates: OnceLock<Result<Vec<CalendarDate>, Error>> =
+        OnceLock::new();
+    let gtfs_shapes: OnceLock<Result<Vec<ShapePoint>, Error>> = OnceLock::new();
+    let gtfs_feed_info: OnceLock<Result<Vec<FeedInfo>, Error>> =
+        OnceLock::new();
 
     rayon::scope(|s| {
         s.spawn(|_| {
@@ -174,13 +222,49 @@ fn load_gtfs_files(
                 &gtfs::File::Trips.prepend_root(root),
             ));
         });
+        s.spawn(|_| {
+            let _ = gtfs_calendars.set(deserialize_optional_gtfs_entity(
+                &gtfs::File::Calendar.prepend_root(root),
+            ));
+        });
+        s.spawn(|_| {
+            let _ = gtfs_calendar_dates.set(deserialize_optional_gtfs_entity(
+                &gtfs::File::CalendarDates.prepend_root(root),
+            ));
+        });
+        s.spawn(|_| {
+            let _ = gtfs_shapes.set(deserialize_optional_gtfs_entity(
+                &gtfs::File::Shapes.prepend_root(root),
+            ));
+        });
+        s.spawn(|_| {
+            let _ = gtfs_feed_info.set(deserialize_optional_gtfs_entity(
+                &gtfs::File::FeedInfo.prepend_root(root),
+            ));
+        });
     });
-    let gtfs_stops = gtfs_stops.into_inner().unwrap()?;
-    let gtfs_times = gtfs_times.into_inner().unwrap()?;
-    let gtfs_routes = gtfs_routes.into_inner().unwrap()?;
-    let gtfs_trips = gtfs_trips.into_inner().unwrap()?;
 
-    Ok((gtfs_stops, gtfs_routes, gtfs_trips, gtfs_times))
+    Ok(Files {
+        stops: gtfs_stops.into_inner().unwrap()?,
+        routes: gtfs_routes.into_inner().unwrap()?,
+        trips: gtfs_trips.into_inner().unwrap()?,
+        stop_times: gtfs_times.into_inner().unwrap()?,
+        calendars: gtfs_calendars.into_inner().unwrap()?,
+        calendar_dates: gtfs_calendar_dates.into_inner().unwrap()?,
+        shapes: gtfs_shapes.into_inner().unwrap()?,
+        feed_info: gtfs_feed_info.into_inner().unwrap()?,
+    })
+}
+
+// Files that feeds are allowed to omit
+fn deserialize_optional_gtfs_entity<E: DeserializeOwned>(
+    path: &PathBuf,
+) -> Result<Vec<E>, Error> {
+    if path.exists() {
+        deserialize_gtfs_entity(path)
+    } else {
+        Ok(vec![])
+    }
 }
 
 fn deserialize_gtfs_entity<E: DeserializeOwned>(
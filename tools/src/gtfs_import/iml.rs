@@ -90,7 +90,7 @@ pub struct SubrouteStops {
 }
 #[derive(Serialize)]
 pub(crate) struct OperatorValidationData {
-    pub(crate) gtfs_lints: Vec<gtfs::Lint>,
+    pub(crate) gtfs_lints: Vec<gtfs::GtfsLint>,
 }
 
 #[derive(Serialize, Debug)]
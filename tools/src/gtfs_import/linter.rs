@@ -16,11 +16,34 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use std::collections::HashSet;
+use chrono::NaiveDate;
+use commons::utils::geo::distance;
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 
 use crate::gtfs;
+use crate::utils::{distance_to_polyline, parse_gtfs_time};
 
-pub(crate) fn lint_gtfs(data: &gtfs::Data) -> Vec<gtfs::Lint> {
+// Speed (km/h) above which a vehicle is unlikely to be going
+const MAX_ROAD_SPEED: f64 = 120.0;
+const MAX_RAIL_SPEED: f64 = 300.0;
+// GTFS route_type for intercity rail
+const RAIL_ROUTE_TYPE: &str = "2";
+// Times tend to be rounded to the minute, so shorter intervals are not
+// trusted for speed calculations
+const MIN_SPEED_INTERVAL: u32 = 60;
+// Distance (m) from which a stop is considered to be off its shape
+const MAX_STOP_SHAPE_DISTANCE: f64 = 100.0;
+// Distance (m) under which two stops are considered to be the same
+const DUPLICATED_STOP_DISTANCE: f64 = 5.0;
+// Size (degrees) of the grid cells used to find nearby stops
+const STOP_GRID_CELL: f64 = 0.001;
+
+pub(crate) fn lint_gtfs(
+    data: &gtfs::Data,
+    today: NaiveDate,
+) -> Vec<gtfs::GtfsLint> {
     let mut lints = vec![];
 
     let mut used_stops = HashSet::new();
@@ -80,5 +103,264 @@ pub(crate) fn lint_gtfs(data: &gtfs::Data) -> Vec<gtfs::Lint> {
             lints.push(gtfs::Lint::PatternlessRoute(route.route_id.clone()));
         }
     });
+
+    lint_stop_times(data, &mut lints);
+    lint_shapes(data, &mut lints);
+    lint_duplicated_stops(data, &mut lints);
+    lint_calendars(data, today, &mut lints);
+    lint_feed_info(data, today, &mut lints);
+
+    let mut lints = lints
+        .into_iter()
+        .map(gtfs::GtfsLint::from)
+        .collect::<Vec<_>>();
+    lints.sort_by_key(|lint| Reverse(lint.severity));
     lints
 }
+
+fn lint_stop_times(data: &gtfs::Data, lints: &mut Vec<gtfs::Lint>) {
+    let trip_times = data
+        .stop_times
+        .iter()
+        .into_group_map_by(|time| &time.trip_id);
+
+    for (trip_id, mut times) in trip_times {
+        times.sort_by_key(|time| time.stop_sequence);
+
+        let max_speed = data
+            .trips
+            .get(trip_id)
+            .and_then(|trip| data.routes.get(&trip.route_id))
+            .and_then(|route| route.route_type.as_deref())
+            .map_or(MAX_ROAD_SPEED, |route_type| {
+                if route_type == RAIL_ROUTE_TYPE {
+                    MAX_RAIL_SPEED
+                } else {
+                    MAX_ROAD_SPEED
+                }
+            });
+
+        let mut last: Option<(&gtfs::StopTime, u32)> = None;
+        for time in times {
+            let Some(arrival) = time
+                .arrival_time
+                .as_deref()
+                .or(time.departure_time.as_deref())
+                .and_then(parse_gtfs_time)
+            else {
+                continue;
+            };
+            let departure = time
+                .departure_time
+                .as_deref()
+                .and_then(parse_gtfs_time)
+                .unwrap_or(arrival);
+
+            if let Some((prev_time, prev_departure)) = last {
+                if arrival < prev_departure || departure < arrival {
+                    lints.push(gtfs::Lint::NonMonotonicStopTimes {
+                        trip_id: trip_id.clone(),
+                        stop_sequence: time.stop_sequence,
+                    });
+                } else if let (Some(from), Some(to)) = (
+                    data.stops.get(&prev_time.stop_id),
+                    data.stops.get(&time.stop_id),
+                ) {
+                    let meters = distance(
+                        from.stop_lat,
+                        from.stop_lon,
+                        to.stop_lat,
+                        to.stop_lon,
+                    );
+                    let seconds =
+                        (arrival - prev_departure).max(MIN_SPEED_INTERVAL);
+                    let speed = meters / f64::from(seconds) * 3.6;
+                    if speed > max_speed {
+                        lints.push(gtfs::Lint::ImpossibleSpeed {
+                            trip_id: trip_id.clone(),
+                            from_stop_id: prev_time.stop_id.clone(),
+                            to_stop_id: time.stop_id.clone(),
+                            speed,
+                        });
+                    }
+                }
+            }
+            last = Some((time, departure));
+        }
+    }
+}
+
+fn lint_shapes(data: &gtfs::Data, lints: &mut Vec<gtfs::Lint>) {
+    let mut dangling_shapes = HashSet::new();
+    let mut shape_stops = HashSet::new();
+
+    for trip in data.trips.values() {
+        let Some(shape_id) = &trip.shape_id else {
+            continue;
+        };
+        if !data.shapes.contains_key(shape_id) {
+            dangling_shapes.insert(shape_id);
+            continue;
+        }
+        if let Some(stops) = data.trip_stops.get(&trip.trip_id) {
+            for stop_id in stops {
+                shape_stops.insert((shape_id, stop_id));
+            }
+        }
+    }
+
+    lints.extend(
+        dangling_shapes
+            .into_iter()
+            .map(|shape_id| gtfs::Lint::DanglingShapePointer(shape_id.clone())),
+    );
+
+    let polylines = data
+        .shapes
+        .iter()
+        .map(|(shape_id, points)| {
+            let polyline = points
+                .iter()
+                .map(|point| (point.shape_pt_lat, point.shape_pt_lon))
+                .collect::<Vec<_>>();
+            (shape_id, polyline)
+        })
+        .collect::<HashMap<_, _>>();
+
+    for (shape_id, stop_id) in shape_stops {
+        let (Some(stop), Some(polyline)) =
+            (data.stops.get(stop_id), polylines.get(shape_id))
+        else {
+            continue;
+        };
+        if let Some(distance) =
+            distance_to_polyline(stop.stop_lat, stop.stop_lon, polyline)
+        {
+            if distance > MAX_STOP_SHAPE_DISTANCE {
+                lints.push(gtfs::Lint::StopFarFromShape {
+                    shape_id: shape_id.clone(),
+                    stop_id: stop_id.clone(),
+                    distance,
+                });
+            }
+        }
+    }
+}
+
+fn stop_grid_cell(stop: &gtfs::Stop) -> (i64, i64) {
+    (
+        (stop.stop_lat / STOP_GRID_CELL).floor() as i64,
+        (stop.stop_lon / STOP_GRID_CELL).floor() as i64,
+    )
+}
+
+fn lint_duplicated_stops(data: &gtfs::Data, lints: &mut Vec<gtfs::Lint>) {
+    let grid = data
+        .stops
+        .values()
+        .into_group_map_by(|stop| stop_grid_cell(stop));
+
+    for stop in data.stops.values() {
+        let (cell_lat, cell_lon) = stop_grid_cell(stop);
+        for d_lat in -1..=1 {
+            for d_lon in -1..=1 {
+                let Some(neighbours) =
+                    grid.get(&(cell_lat + d_lat, cell_lon + d_lon))
+                else {
+                    continue;
+                };
+                // Every pair is seen twice, only one of them is kept
+                for neighbour in neighbours
+                    .iter()
+                    .filter(|neighbour| stop.stop_id < neighbour.stop_id)
+                {
+                    let distance = distance(
+                        stop.stop_lat,
+                        stop.stop_lon,
+                        neighbour.stop_lat,
+                        neighbour.stop_lon,
+                    );
+                    if distance < DUPLICATED_STOP_DISTANCE {
+                        lints.push(gtfs::Lint::DuplicatedStop {
+                            stop_ids: (
+                                stop.stop_id.clone(),
+                                neighbour.stop_id.clone(),
+                            ),
+                            distance,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn parse_gtfs_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), "%Y%m%d").ok()
+}
+
+fn lint_calendars(
+    data: &gtfs::Data,
+    today: NaiveDate,
+    lints: &mut Vec<gtfs::Lint>,
+) {
+    let service_ids = data
+        .calendars
+        .keys()
+        .chain(data.calendar_dates.keys())
+        .collect::<HashSet<_>>();
+
+    for service_id in &service_ids {
+        let regular_end = data
+            .calendars
+            .get(*service_id)
+            .filter(|calendar| calendar.has_weekdays())
+            .and_then(|calendar| {
+                let start = parse_gtfs_date(&calendar.start_date)?;
+                let end = parse_gtfs_date(&calendar.end_date)?;
+                (start <= end).then_some(end)
+            });
+        // Exception type 1 adds the service to a date
+        let last_addition = data
+            .calendar_dates
+            .get(*service_id)
+            .into_iter()
+            .flatten()
+            .filter(|date| date.exception_type == 1)
+            .filter_map(|date| parse_gtfs_date(&date.date))
+            .max();
+
+        match regular_end.max(last_addition) {
+            None => {
+                lints.push(gtfs::Lint::EmptyService((*service_id).clone()));
+            }
+            Some(last_day) if last_day < today => {
+                lints.push(gtfs::Lint::ExpiredService((*service_id).clone()));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for trip in data.trips.values() {
+        if !service_ids.contains(&trip.service_id) {
+            lints.push(gtfs::Lint::CalendarlessTrip(trip.trip_id.clone()));
+        }
+    }
+}
+
+fn lint_feed_info(
+    data: &gtfs::Data,
+    today: NaiveDate,
+    lints: &mut Vec<gtfs::Lint>,
+) {
+    let Some(feed_info) = &data.feed_info else {
+        lints.push(gtfs::Lint::MissingFeedInfo);
+        return;
+    };
+
+    match feed_info.feed_end_date.as_deref().and_then(parse_gtfs_date) {
+        None => lints.push(gtfs::Lint::MissingFeedValidity),
+        Some(end) if end < today => lints.push(gtfs::Lint::ExpiredFeed),
+        Some(_) => {}
+    }
+}
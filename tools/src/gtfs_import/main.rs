@@ -102,7 +102,7 @@ async fn main() {
         args.operator
     )))
     .unwrap();
-    let lints = lint_gtfs(&gtfs);
+    let lints = lint_gtfs(&gtfs, chrono::Local::now().date_naive());
 
     iml::patch_operator_validation(
         args.operator,
@@ -0,0 +1,126 @@
+use chrono::NaiveDate;
+use commons::models::gtfs::LintSeverity;
+use std::collections::HashMap;
+
+use crate::gtfs::{self, Data, Lint};
+use crate::linter::lint_gtfs;
+use crate::utils::{distance_to_polyline, parse_gtfs_time};
+
+fn stop(id: &str, lat: f64, lon: f64) -> (gtfs::StopId, gtfs::Stop) {
+    (
+        id.to_string(),
+        gtfs::Stop {
+            stop_id: id.to_string(),
+            stop_name: id.to_string(),
+            stop_lat: lat,
+            stop_lon: lon,
+        },
+    )
+}
+
+fn stop_time(stop_id: &str, sequence: usize, time: &str) -> gtfs::StopTime {
+    gtfs::StopTime {
+        trip_id: "trip".to_string(),
+        stop_id: stop_id.to_string(),
+        stop_sequence: sequence,
+        arrival_time: Some(time.to_string()),
+        departure_time: Some(time.to_string()),
+    }
+}
+
+fn data(stop_times: Vec<gtfs::StopTime>) -> Data {
+    let trip = gtfs::Trip {
+        trip_id: "trip".to_string(),
+        route_id: "route".to_string(),
+        service_id: "service".to_string(),
+        pattern_id: None,
+        trip_headsign: None,
+        shape_id: None,
+    };
+    let trip_stops = HashMap::from([(
+        "trip".to_string(),
+        stop_times.iter().map(|time| time.stop_id.clone()).collect(),
+    )]);
+
+    Data {
+        stops: HashMap::from([
+            stop("a", 38.7000, -9.1400),
+            stop("b", 38.7100, -9.1400),
+            stop("c", 38.8000, -9.1400),
+            stop("d", 38.80002, -9.1400),
+        ]),
+        routes: HashMap::new(),
+        trips: HashMap::from([("trip".to_string(), trip)]),
+        stop_times,
+        calendars: HashMap::new(),
+        calendar_dates: HashMap::from([(
+            "service".to_string(),
+            vec![gtfs::CalendarDate {
+                service_id: "service".to_string(),
+                date: "20240101".to_string(),
+                exception_type: 1,
+            }],
+        )]),
+        shapes: HashMap::new(),
+        feed_info: None,
+        trip_stops,
+        route_pattern_clusters: HashMap::new(),
+    }
+}
+
+#[test]
+fn gtfs_times() {
+    assert_eq!(parse_gtfs_time("08:30:15"), Some(30615));
+    assert_eq!(parse_gtfs_time("25:00:00"), Some(90000));
+    assert_eq!(parse_gtfs_time("08:60:00"), None);
+    assert_eq!(parse_gtfs_time("08:30"), None);
+}
+
+#[test]
+fn polyline_distance() {
+    let polyline = [(38.70, -9.15), (38.70, -9.13)];
+    let on_line = distance_to_polyline(38.70, -9.14, &polyline).unwrap();
+    assert!(on_line < 1.0);
+    let off_line = distance_to_polyline(38.701, -9.14, &polyline).unwrap();
+    assert!((off_line - 111.2).abs() < 1.0);
+    assert!(distance_to_polyline(38.70, -9.14, &[]).is_none());
+}
+
+#[test]
+fn stop_time_lints() {
+    let data = data(vec![
+        stop_time("a", 1, "08:00:00"),
+        stop_time("b", 2, "08:02:00"),
+        // 10km in a minute
+        stop_time("c", 3, "08:03:00"),
+        stop_time("d", 4, "08:01:00"),
+    ]);
+    let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+    let lints = lint_gtfs(&data, today);
+
+    assert!(lints.iter().any(|lint| matches!(
+        &lint.lint,
+        Lint::NonMonotonicStopTimes {
+            stop_sequence: 4,
+            ..
+        }
+    )));
+    assert!(lints.iter().any(|lint| matches!(
+        &lint.lint,
+        Lint::ImpossibleSpeed { to_stop_id, .. } if to_stop_id == "c"
+    )));
+    assert!(lints.iter().any(|lint| matches!(
+        &lint.lint,
+        Lint::DuplicatedStop { stop_ids, .. }
+            if stop_ids == &("c".to_string(), "d".to_string())
+    )));
+    assert!(lints
+        .iter()
+        .any(|lint| matches!(&lint.lint, Lint::ExpiredService(_))));
+    assert!(lints
+        .iter()
+        .any(|lint| matches!(&lint.lint, Lint::MissingFeedInfo)));
+
+    // Most severe first
+    assert_eq!(lints[0].severity, LintSeverity::Error);
+}
@@ -1,2 +1,3 @@
+mod linter;
 mod pairing;
 mod stop_seq_error;
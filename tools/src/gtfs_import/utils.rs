@@ -16,6 +16,8 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use commons::utils::geo::{distance, EARTH_RADIUS};
+
 pub fn needleman_wunsch<'a, T: PartialEq + Clone>(
     seq1: &'a [T],
     seq2: &'a [T],
@@ -111,3 +113,53 @@ pub(crate) fn stop_seq_error<T: PartialEq + Clone>(
     }
     (matches, mismatches)
 }
+
+/// Distance in meters from a coordinate to the closest point of a polyline.
+/// Coordinates are projected onto a plane around the point, which is precise
+/// enough for the short distances at which stops and shapes get compared.
+pub(crate) fn distance_to_polyline(
+    lat: f64,
+    lon: f64,
+    polyline: &[(f64, f64)],
+) -> Option<f64> {
+    let lon_scale = lat.to_radians().cos();
+    let project = |(p_lat, p_lon): (f64, f64)| {
+        (
+            (p_lon - lon).to_radians() * lon_scale * EARTH_RADIUS,
+            (p_lat - lat).to_radians() * EARTH_RADIUS,
+        )
+    };
+
+    match polyline {
+        [] => None,
+        [point] => Some(distance(lat, lon, point.0, point.1)),
+        _ => polyline
+            .windows(2)
+            .map(|segment| {
+                let (x0, y0) = project(segment[0]);
+                let (x1, y1) = project(segment[1]);
+                let (dx, dy) = (x1 - x0, y1 - y0);
+                let length = dx * dx + dy * dy;
+                let t = if length == 0.0 {
+                    0.0
+                } else {
+                    (-(x0 * dx + y0 * dy) / length).clamp(0.0, 1.0)
+                };
+                (x0 + t * dx).hypot(y0 + t * dy)
+            })
+            .min_by(f64::total_cmp),
+    }
+}
+
+/// Seconds since the start of the service day of a GTFS `HH:MM:SS` time.
+/// Hours can go past 24 for trips that run after midnight.
+pub(crate) fn parse_gtfs_time(time: &str) -> Option<u32> {
+    let mut parts = time.trim().split(':');
+    let hours = parts.next()?.parse::<u32>().ok()?;
+    let minutes = parts.next()?.parse::<u32>().ok()?;
+    let seconds = parts.next()?.parse::<u32>().ok()?;
+    if parts.next().is_some() || minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+    Some(hours * 3600 + minutes * 60 + seconds)
+}